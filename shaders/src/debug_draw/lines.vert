@@ -0,0 +1,13 @@
+#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec4 line_color;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+out vec4 color;
+
+void main() {
+    color = line_color;
+    gl_Position = projection * view * vec4(pos, 1.0);
+}
@@ -0,0 +1,19 @@
+#version 330 core
+in vec3 view_position;
+in vec3 view_normal;
+in vec2 uv;
+
+layout (location = 0) out vec4 g_position;
+layout (location = 1) out vec4 g_normal;
+layout (location = 2) out vec4 g_albedo;
+
+uniform vec4 albedo;
+// strength of the highlights, stored in the alpha of the albedo target
+uniform float specular;
+
+void main() {
+    // w marks covered pixels, the cleared G-buffer has 0 there
+    g_position = vec4(view_position, 1.0);
+    g_normal = vec4(normalize(view_normal), 0.0);
+    g_albedo = vec4(albedo.rgb, specular);
+}
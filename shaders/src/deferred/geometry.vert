@@ -0,0 +1,22 @@
+#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec3 normal;
+layout (location = 2) in vec2 tex_coords;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+
+out vec3 view_position;
+out vec3 view_normal;
+out vec2 uv;
+
+void main() {
+    mat4 model_view = view * model;
+    vec4 position = model_view * vec4(pos, 1.0);
+
+    view_position = position.xyz;
+    view_normal = mat3(transpose(inverse(model_view))) * normal;
+    uv = tex_coords;
+    gl_Position = projection * position;
+}
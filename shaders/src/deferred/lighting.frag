@@ -0,0 +1,52 @@
+#version 330 core
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D g_position;
+uniform sampler2D g_normal;
+uniform sampler2D g_albedo;
+
+uniform vec3 ambient;
+uniform float shininess;
+
+// keep in sync with `deferred::MAX_LIGHTS`
+const int MAX_LIGHTS = 256;
+
+#include "../lighting/lights.glsl"
+
+// view space, like the G-buffer
+layout (std140) uniform Lights {
+    uvec4 count;
+    PointLight lights[MAX_LIGHTS];
+};
+
+void main() {
+    vec4 position = texture(g_position, uv);
+    if (position.w == 0.0) {
+        discard;
+    }
+
+    vec3 normal = normalize(texture(g_normal, uv).xyz);
+    vec4 albedo = texture(g_albedo, uv);
+    vec3 view_dir = normalize(-position.xyz);
+
+    vec3 color = ambient * albedo.rgb;
+    for (uint i = 0u; i < min(count.x, uint(MAX_LIGHTS)); i++) {
+        PointLight light = lights[i];
+        vec3 to_light = light.position - position.xyz;
+        float dist = length(to_light);
+        if (dist >= light.radius) {
+            continue;
+        }
+
+        vec3 light_dir = to_light / dist;
+        vec3 halfway = normalize(light_dir + view_dir);
+        float diffuse = max(dot(normal, light_dir), 0.0);
+        float highlight = pow(max(dot(normal, halfway), 0.0), shininess) * albedo.a;
+
+        vec3 radiance = light.color * light.intensity * light_attenuation(dist, light.radius);
+        color += (albedo.rgb * diffuse + highlight) * radiance;
+    }
+
+    FragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,23 @@
+#version 430 core
+#include "tiled_lights.glsl"
+
+in vec3 world_position;
+in vec3 world_normal;
+in vec2 uv;
+
+out vec4 FragColor;
+
+uniform vec3 camera_position;
+uniform vec4 albedo;
+uniform float specular;
+uniform float shininess;
+uniform vec3 ambient;
+
+void main() {
+    vec3 normal = normalize(world_normal);
+    vec3 view_dir = normalize(camera_position - world_position);
+
+    vec3 color = ambient * albedo.rgb
+        + tiled_point_lights(world_position, normal, view_dir, albedo.rgb, specular, shininess);
+    FragColor = vec4(color, albedo.a);
+}
@@ -0,0 +1,44 @@
+// Point lights binned into screen tiles by `ForwardPlus::cull`, in world space.
+
+#include "../lighting/lights.glsl"
+
+layout (std430, binding = 2) readonly buffer PointLights {
+    PointLight point_lights[];
+};
+
+// offset into light_indices and count, per tile, rows from the bottom of the screen
+layout (std430, binding = 3) readonly buffer LightTiles {
+    uvec2 light_tiles[];
+};
+
+layout (std430, binding = 4) readonly buffer LightIndices {
+    uint light_indices[];
+};
+
+uniform uint tile_size;
+uniform uint tiles_x;
+
+// Blinn-Phong sum of the lights of the tile the fragment lies in.
+vec3 tiled_point_lights(vec3 position, vec3 normal, vec3 view_dir, vec3 albedo, float specular, float shininess) {
+    uvec2 tile = uvec2(gl_FragCoord.xy) / tile_size;
+    uvec2 list = light_tiles[tile.y * tiles_x + tile.x];
+
+    vec3 color = vec3(0.0);
+    for (uint i = 0u; i < list.y; i++) {
+        PointLight light = point_lights[light_indices[list.x + i]];
+        vec3 to_light = light.position - position;
+        float dist = length(to_light);
+        if (dist >= light.radius) {
+            continue;
+        }
+
+        vec3 light_dir = to_light / dist;
+        vec3 halfway = normalize(light_dir + view_dir);
+        float diffuse = max(dot(normal, light_dir), 0.0);
+        float highlight = pow(max(dot(normal, halfway), 0.0), shininess) * specular;
+
+        vec3 radiance = light.color * light.intensity * light_attenuation(dist, light.radius);
+        color += (albedo * diffuse + highlight) * radiance;
+    }
+    return color;
+}
@@ -0,0 +1,68 @@
+#version 330 core
+in vec2 uv;
+
+out vec4 FragColor;
+
+uniform mat4 view_projection;
+uniform mat4 inverse_view_projection;
+uniform vec3 camera_position;
+// world units between minor lines, every `major_every`-th line is a major one
+uniform float spacing;
+uniform float major_every;
+uniform float fade_distance;
+uniform vec4 minor_color;
+uniform vec4 major_color;
+uniform vec4 x_axis_color;
+uniform vec4 z_axis_color;
+uniform int show_axes;
+
+vec3 unproject(vec2 ndc, float depth) {
+    vec4 world = inverse_view_projection * vec4(ndc, depth, 1.0);
+    return world.xyz / world.w;
+}
+
+// 1 on a line of the grid with cells of `cell`, falling to 0 a pixel away
+float grid_lines(vec2 coords, float cell) {
+    vec2 scaled = coords / cell;
+    vec2 width = fwidth(scaled);
+    vec2 lines = abs(fract(scaled - 0.5) - 0.5) / width;
+    // lines closer than a couple of pixels turn into noise, fade them instead
+    float density = 1.0 - smoothstep(0.25, 0.5, max(width.x, width.y));
+    return (1.0 - min(min(lines.x, lines.y), 1.0)) * density;
+}
+
+// 1 on the line where `coord` is 0
+float axis_line(float coord) {
+    return 1.0 - min(abs(coord) / fwidth(coord), 1.0);
+}
+
+void main() {
+    // the y = 0 plane where the ray through this pixel from the near to the far plane hits it
+    vec2 ndc = uv * 2.0 - 1.0;
+    vec3 near = unproject(ndc, -1.0);
+    vec3 far = unproject(ndc, 1.0);
+    float t = -near.y / (far.y - near.y);
+    if (t <= 0.0 || t > 1.0) {
+        discard;
+    }
+    vec3 position = mix(near, far, t);
+
+    vec4 clip = view_projection * vec4(position, 1.0);
+    gl_FragDepth = clip.z / clip.w * 0.5 + 0.5;
+
+    vec4 color = vec4(minor_color.rgb, minor_color.a * grid_lines(position.xz, spacing));
+    float major = grid_lines(position.xz, spacing * major_every);
+    color = mix(color, major_color, major);
+    if (show_axes != 0) {
+        // the x axis runs where z is 0 and the other way around
+        color = mix(color, x_axis_color, axis_line(position.z));
+        color = mix(color, z_axis_color, axis_line(position.x));
+    }
+
+    float fade = 1.0 - smoothstep(0.0, fade_distance, length(position.xz - camera_position.xz));
+    color.a *= fade;
+    if (color.a <= 0.001) {
+        discard;
+    }
+    FragColor = color;
+}
@@ -0,0 +1,42 @@
+#version 330 core
+#include "sampling.glsl"
+
+in vec2 uv;
+
+out vec2 FragColor;
+
+const uint SAMPLE_COUNT = 1024u;
+
+float geometry_schlick_ggx_ibl(float n_dot_x, float roughness) {
+    // k is remapped for image based lighting
+    float k = roughness * roughness / 2.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+// scale (x) and bias (y) to F0 of the split sum specular integral, for n·v along u and
+// roughness along v
+void main() {
+    float n_dot_v = max(uv.x, 1e-4);
+    float roughness = uv.y;
+    vec3 view_dir = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    vec3 normal = vec3(0.0, 0.0, 1.0);
+
+    float scale = 0.0;
+    float bias = 0.0;
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec3 halfway = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), normal, roughness);
+        vec3 light_dir = normalize(2.0 * dot(view_dir, halfway) * halfway - view_dir);
+        float n_dot_l = max(light_dir.z, 0.0);
+        float n_dot_h = max(halfway.z, 0.0);
+        float v_dot_h = max(dot(view_dir, halfway), 0.0);
+
+        if (n_dot_l > 0.0) {
+            float geometry = geometry_schlick_ggx_ibl(n_dot_v, roughness) * geometry_schlick_ggx_ibl(n_dot_l, roughness);
+            float visibility = geometry * v_dot_h / (n_dot_h * n_dot_v);
+            float fresnel = pow(1.0 - v_dot_h, 5.0);
+            scale += (1.0 - fresnel) * visibility;
+            bias += fresnel * visibility;
+        }
+    }
+    FragColor = vec2(scale, bias) / float(SAMPLE_COUNT);
+}
@@ -0,0 +1,30 @@
+#version 330 core
+in vec3 direction;
+
+out vec4 FragColor;
+
+uniform samplerCube environment;
+
+const float PI = 3.14159265359;
+// angle between samples, smaller is smoother and slower
+const float SAMPLE_DELTA = 0.025;
+
+// diffuse light arriving from the hemisphere around `direction`, cosine weighted
+void main() {
+    vec3 normal = normalize(direction);
+    vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+    vec3 right = normalize(cross(up, normal));
+    up = cross(normal, right);
+
+    vec3 irradiance = vec3(0.0);
+    float samples = 0.0;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += SAMPLE_DELTA) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += SAMPLE_DELTA) {
+            vec3 tangent_sample = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 sample_dir = tangent_sample.x * right + tangent_sample.y * up + tangent_sample.z * normal;
+            irradiance += texture(environment, sample_dir).rgb * cos(theta) * sin(theta);
+            samples += 1.0;
+        }
+    }
+    FragColor = vec4(PI * irradiance / samples, 1.0);
+}
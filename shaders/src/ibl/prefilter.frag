@@ -0,0 +1,41 @@
+#version 330 core
+#include "sampling.glsl"
+
+in vec3 direction;
+
+out vec4 FragColor;
+
+uniform samplerCube environment;
+// face size of the environment's level 0, to pick the mip matching a sample's footprint
+uniform float environment_size;
+uniform float roughness;
+
+const uint SAMPLE_COUNT = 1024u;
+
+// environment blurred by the GGX lobe of `roughness`, assuming view = normal = reflection
+void main() {
+    vec3 normal = normalize(direction);
+    vec3 view_dir = normal;
+
+    vec3 color = vec3(0.0);
+    float total_weight = 0.0;
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec3 halfway = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), normal, roughness);
+        vec3 light_dir = normalize(2.0 * dot(view_dir, halfway) * halfway - view_dir);
+        float n_dot_l = dot(normal, light_dir);
+        if (n_dot_l <= 0.0) {
+            continue;
+        }
+
+        // samples standing for a large solid angle read a blurrier mip, against bright speckles
+        float n_dot_h = max(dot(normal, halfway), 0.0);
+        float pdf = distribution_ggx(n_dot_h, roughness) * 0.25 + 1e-4;
+        float texel_angle = 4.0 * PI / (6.0 * environment_size * environment_size);
+        float sample_angle = 1.0 / (float(SAMPLE_COUNT) * pdf);
+        float mip = roughness == 0.0 ? 0.0 : 0.5 * log2(sample_angle / texel_angle);
+
+        color += textureLod(environment, light_dir, mip).rgb * n_dot_l;
+        total_weight += n_dot_l;
+    }
+    FragColor = vec4(color / max(total_weight, 1e-4), 1.0);
+}
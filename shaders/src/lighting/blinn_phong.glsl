@@ -0,0 +1,55 @@
+// Blinn-Phong over the `Lighting` block. Positions and directions are in world space.
+
+#include "lighting_block.glsl"
+#include "../shadow/shadow.glsl"
+
+vec3 shade(vec3 light_dir, vec3 radiance, vec3 normal, vec3 view_dir, vec3 albedo, float specular, float shininess) {
+    vec3 halfway = normalize(light_dir + view_dir);
+    float diffuse = max(dot(normal, light_dir), 0.0);
+    float highlight = diffuse > 0.0 ? pow(max(dot(normal, halfway), 0.0), shininess) * specular : 0.0;
+    return (albedo * diffuse + highlight) * radiance;
+}
+
+// Ambient plus every light of the block.
+vec3 blinn_phong(vec3 position, vec3 normal, vec3 view_dir, vec3 albedo, float specular, float shininess) {
+    vec3 color = ambient.rgb * albedo;
+
+    for (uint i = 0u; i < light_counts.x; i++) {
+        DirectionalLight light = directional_lights[i];
+        vec3 light_dir = -normalize(light.direction);
+        vec3 radiance = light.color * light.intensity;
+        // the shadow map belongs to the first directional light
+        if (i == 0u) {
+            radiance *= shadow_factor(position, normal, light_dir);
+        }
+        color += shade(light_dir, radiance, normal, view_dir, albedo, specular, shininess);
+    }
+
+    for (uint i = 0u; i < light_counts.y; i++) {
+        PointLight light = point_lights[i];
+        vec3 to_light = light.position - position;
+        float dist = length(to_light);
+        if (dist >= light.radius) {
+            continue;
+        }
+        vec3 radiance = light.color * light.intensity * light_attenuation(dist, light.radius);
+        radiance *= point_shadow_factor(int(i), position, normal);
+        color += shade(to_light / dist, radiance, normal, view_dir, albedo, specular, shininess);
+    }
+
+    for (uint i = 0u; i < light_counts.z; i++) {
+        SpotLight light = spot_lights[i];
+        vec3 to_light = light.position - position;
+        float dist = length(to_light);
+        if (dist >= light.radius) {
+            continue;
+        }
+        vec3 light_dir = to_light / dist;
+        // smooth edge between the inner and the outer cone
+        float cone = smoothstep(light.cos_outer, light.cos_inner, dot(-light_dir, normalize(light.direction)));
+        vec3 radiance = light.color * light.intensity * light_attenuation(dist, light.radius) * cone;
+        color += shade(light_dir, radiance, normal, view_dir, albedo, specular, shininess);
+    }
+
+    return color * shadow_debug_tint(position);
+}
@@ -0,0 +1,16 @@
+// The `Lighting` uniform block of `lighting::LightingUniforms`.
+
+#include "lights.glsl"
+
+// keep in sync with `lighting::MAX_*_LIGHTS`
+#define MAX_DIRECTIONAL_LIGHTS 4
+#define MAX_POINT_LIGHTS 32
+#define MAX_SPOT_LIGHTS 16
+
+layout (std140) uniform Lighting {
+    vec4 ambient;
+    uvec4 light_counts;
+    DirectionalLight directional_lights[MAX_DIRECTIONAL_LIGHTS];
+    PointLight point_lights[MAX_POINT_LIGHTS];
+    SpotLight spot_lights[MAX_SPOT_LIGHTS];
+};
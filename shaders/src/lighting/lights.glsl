@@ -0,0 +1,33 @@
+// Light structs and falloff shared by every lit shader, laid out like `lighting::PointLight`
+// and its siblings for std140 and std430 blocks alike.
+
+struct DirectionalLight {
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    float _padding;
+};
+
+struct PointLight {
+    vec3 position;
+    float radius;
+    vec3 color;
+    float intensity;
+};
+
+struct SpotLight {
+    vec3 position;
+    float radius;
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    float cos_inner;
+    float cos_outer;
+};
+
+// inverse square falloff windowed to reach exactly 0 at the radius
+float light_attenuation(float dist, float radius) {
+    float ratio = dist / radius;
+    float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window / (dist * dist + 1.0);
+}
@@ -0,0 +1,19 @@
+#version 330 core
+#include "blinn_phong.glsl"
+
+in vec3 world_position;
+in vec3 world_normal;
+in vec2 uv;
+
+out vec4 FragColor;
+
+uniform vec3 camera_position;
+uniform vec4 albedo;
+uniform float specular;
+uniform float shininess;
+
+void main() {
+    vec3 normal = normalize(world_normal);
+    vec3 view_dir = normalize(camera_position - world_position);
+    FragColor = vec4(blinn_phong(world_position, normal, view_dir, albedo.rgb, specular, shininess), albedo.a);
+}
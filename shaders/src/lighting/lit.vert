@@ -0,0 +1,24 @@
+#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec3 normal;
+layout (location = 2) in vec2 tex_coords;
+layout (location = 3) in vec4 tangent;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+
+out vec3 world_position;
+out vec3 world_normal;
+out vec2 uv;
+out vec4 world_tangent;
+
+void main() {
+    vec4 position = model * vec4(pos, 1.0);
+
+    world_position = position.xyz;
+    world_normal = mat3(transpose(inverse(model))) * normal;
+    uv = tex_coords;
+    world_tangent = vec4(mat3(model) * tangent.xyz, tangent.w);
+    gl_Position = projection * view * position;
+}
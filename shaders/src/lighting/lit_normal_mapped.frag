@@ -0,0 +1,26 @@
+#version 330 core
+#include "blinn_phong.glsl"
+#include "normal_mapping.glsl"
+
+in vec3 world_position;
+in vec3 world_normal;
+in vec2 uv;
+in vec4 world_tangent;
+
+out vec4 FragColor;
+
+uniform vec3 camera_position;
+uniform vec4 albedo;
+uniform float specular;
+uniform float shininess;
+
+// tangent space normals, linear
+uniform sampler2D normal_map;
+uniform float normal_scale;
+
+void main() {
+    vec3 normal = normalize(world_normal);
+    normal = perturb_normal(normal, world_tangent, world_position, uv, texture(normal_map, uv).xyz, normal_scale);
+    vec3 view_dir = normalize(camera_position - world_position);
+    FragColor = vec4(blinn_phong(world_position, normal, view_dir, albedo.rgb, specular, shininess), albedo.a);
+}
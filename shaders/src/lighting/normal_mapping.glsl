@@ -0,0 +1,33 @@
+// tangent frame from a vertex normal and tangent, w of the tangent is the bitangent sign
+mat3 tbn_matrix(vec3 normal, vec4 tangent) {
+    vec3 t = normalize(tangent.xyz - normal * dot(normal, tangent.xyz));
+    vec3 bitangent = cross(normal, t) * (tangent.w < 0.0 ? -1.0 : 1.0);
+    return mat3(t, bitangent, normal);
+}
+
+// tangent frame from screen-space derivatives, for meshes without tangents
+mat3 cotangent_frame(vec3 normal, vec3 position, vec2 coords) {
+    vec3 dp1 = dFdx(position);
+    vec3 dp2 = dFdy(position);
+    vec2 duv1 = dFdx(coords);
+    vec2 duv2 = dFdy(coords);
+
+    vec3 dp2perp = cross(dp2, normal);
+    vec3 dp1perp = cross(normal, dp1);
+    vec3 tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+
+    float scale = inversesqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-12));
+    return mat3(tangent * scale, bitangent * scale, normal);
+}
+
+// `normal` (normalized) bent by a tangent space normal map texel, through the vertex tangent
+// when the mesh has one and the derivative frame otherwise, `scale` as glTF's normal scale
+vec3 perturb_normal(vec3 normal, vec4 tangent, vec3 position, vec2 coords, vec3 texel, float scale) {
+    vec3 tangent_normal = texel * 2.0 - 1.0;
+    tangent_normal.xy *= scale;
+
+    bool has_tangent = dot(tangent.xyz, tangent.xyz) > 1e-8;
+    mat3 tbn = has_tangent ? tbn_matrix(normal, tangent) : cotangent_frame(normal, position, coords);
+    return normalize(tbn * tangent_normal);
+}
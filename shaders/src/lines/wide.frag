@@ -0,0 +1,19 @@
+#version 330 core
+in vec4 color;
+noperspective in float edge;
+noperspective in float half_width;
+
+out vec4 FragColor;
+
+uniform float feather;
+
+void main() {
+    // lines thinner than a pixel keep their brightness by fading instead of vanishing
+    float width = max(half_width, 0.5);
+    float coverage = 1.0 - smoothstep(width - feather * 0.5, width + feather * 0.5, abs(edge));
+    float alpha = color.a * coverage * min(half_width / 0.5, 1.0);
+    if (alpha <= 0.0) {
+        discard;
+    }
+    FragColor = vec4(color.rgb, alpha);
+}
@@ -0,0 +1,15 @@
+#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in float line_width;
+layout (location = 2) in vec4 line_color;
+
+uniform mat4 view_projection;
+
+out float v_width;
+out vec4 v_color;
+
+void main() {
+    v_width = line_width;
+    v_color = line_color;
+    gl_Position = view_projection * vec4(pos, 1.0);
+}
@@ -0,0 +1,170 @@
+#version 330 core
+#include "../lighting/lighting_block.glsl"
+#include "../shadow/shadow.glsl"
+#include "../lighting/normal_mapping.glsl"
+
+in vec3 world_position;
+in vec3 world_normal;
+in vec2 uv;
+in vec4 world_tangent;
+
+out vec4 FragColor;
+
+uniform vec3 camera_position;
+
+// glTF metallic-roughness material, factors multiply the maps which are present
+uniform vec4 albedo_factor;
+uniform float metallic_factor;
+uniform float roughness_factor;
+uniform vec3 emissive_factor;
+uniform float normal_scale;
+uniform float ao_strength;
+
+uniform sampler2D albedo_map;
+// roughness in g, metallic in b, as glTF packs them
+uniform sampler2D metallic_roughness_map;
+uniform sampler2D normal_map;
+// occlusion in r
+uniform sampler2D ao_map;
+uniform sampler2D emissive_map;
+
+// bits of `material::MaterialMaps`, which maps are bound
+uniform int maps;
+const int ALBEDO_MAP = 1;
+const int METALLIC_ROUGHNESS_MAP = 2;
+const int NORMAL_MAP = 4;
+const int AO_MAP = 8;
+const int EMISSIVE_MAP = 16;
+
+// image-based ambient light of `ibl::Ibl`, the flat `ambient` of the block when off
+uniform bool ibl_enabled;
+uniform samplerCube irradiance_map;
+uniform samplerCube prefiltered_map;
+uniform sampler2D brdf_lut;
+uniform float prefiltered_max_lod;
+
+const float PI = 3.14159265359;
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+float geometry_schlick_ggx(float n_dot_x, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Fresnel averaged over the rough microfacets, for light from the whole environment
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// diffuse and specular light from the environment, split sum approximated
+vec3 ambient_light(vec3 normal, vec3 view_dir, vec3 albedo, float metallic, float roughness) {
+    if (!ibl_enabled) {
+        return ambient.rgb * albedo;
+    }
+
+    float n_dot_v = max(dot(normal, view_dir), 0.0);
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+    vec3 fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * texture(irradiance_map, normal).rgb * albedo;
+
+    vec3 reflected = reflect(-view_dir, normal);
+    vec3 prefiltered = textureLod(prefiltered_map, reflected, roughness * prefiltered_max_lod).rgb;
+    vec2 scale_bias = texture(brdf_lut, vec2(n_dot_v, roughness)).rg;
+    vec3 specular = prefiltered * (fresnel * scale_bias.x + scale_bias.y);
+    return diffuse + specular;
+}
+
+// Cook-Torrance reflectance of one light arriving from `light_dir` with `radiance`
+vec3 brdf(vec3 light_dir, vec3 radiance, vec3 normal, vec3 view_dir, vec3 albedo, float metallic, float roughness) {
+    vec3 halfway = normalize(light_dir + view_dir);
+    float n_dot_l = max(dot(normal, light_dir), 0.0);
+    float n_dot_v = max(dot(normal, view_dir), 1e-4);
+    float n_dot_h = max(dot(normal, halfway), 0.0);
+
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+    vec3 fresnel = fresnel_schlick(max(dot(halfway, view_dir), 0.0), f0);
+    float geometry = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+    vec3 specular = distribution_ggx(n_dot_h, roughness) * geometry * fresnel / (4.0 * n_dot_v * n_dot_l + 1e-4);
+
+    // energy not reflected is refracted, metals absorb all of it
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * albedo / PI;
+    return (diffuse + specular) * radiance * n_dot_l;
+}
+
+void main() {
+    vec4 albedo = albedo_factor;
+    if ((maps & ALBEDO_MAP) != 0) {
+        albedo *= texture(albedo_map, uv);
+    }
+    float metallic = metallic_factor;
+    float roughness = roughness_factor;
+    if ((maps & METALLIC_ROUGHNESS_MAP) != 0) {
+        vec4 metal_rough = texture(metallic_roughness_map, uv);
+        roughness *= metal_rough.g;
+        metallic *= metal_rough.b;
+    }
+    roughness = clamp(roughness, 0.04, 1.0);
+
+    vec3 normal = normalize(world_normal);
+    if ((maps & NORMAL_MAP) != 0) {
+        normal = perturb_normal(normal, world_tangent, world_position, uv, texture(normal_map, uv).xyz, normal_scale);
+    }
+    float ao = 1.0;
+    if ((maps & AO_MAP) != 0) {
+        ao = mix(1.0, texture(ao_map, uv).r, ao_strength);
+    }
+    vec3 emissive = emissive_factor;
+    if ((maps & EMISSIVE_MAP) != 0) {
+        emissive *= texture(emissive_map, uv).rgb;
+    }
+
+    vec3 view_dir = normalize(camera_position - world_position);
+    vec3 color = ambient_light(normal, view_dir, albedo.rgb, metallic, roughness) * ao + emissive;
+
+    for (uint i = 0u; i < light_counts.x; i++) {
+        DirectionalLight light = directional_lights[i];
+        vec3 light_dir = -normalize(light.direction);
+        vec3 radiance = light.color * light.intensity;
+        if (i == 0u) {
+            radiance *= shadow_factor(world_position, normal, light_dir);
+        }
+        color += brdf(light_dir, radiance, normal, view_dir, albedo.rgb, metallic, roughness);
+    }
+
+    for (uint i = 0u; i < light_counts.y; i++) {
+        PointLight light = point_lights[i];
+        vec3 to_light = light.position - world_position;
+        float dist = length(to_light);
+        if (dist >= light.radius) {
+            continue;
+        }
+        vec3 radiance = light.color * light.intensity * light_attenuation(dist, light.radius);
+        radiance *= point_shadow_factor(int(i), world_position, normal);
+        color += brdf(to_light / dist, radiance, normal, view_dir, albedo.rgb, metallic, roughness);
+    }
+
+    for (uint i = 0u; i < light_counts.z; i++) {
+        SpotLight light = spot_lights[i];
+        vec3 to_light = light.position - world_position;
+        float dist = length(to_light);
+        if (dist >= light.radius) {
+            continue;
+        }
+        vec3 light_dir = to_light / dist;
+        float cone = smoothstep(light.cos_outer, light.cos_inner, dot(-light_dir, normalize(light.direction)));
+        vec3 radiance = light.color * light.intensity * light_attenuation(dist, light.radius) * cone;
+        color += brdf(light_dir, radiance, normal, view_dir, albedo.rgb, metallic, roughness);
+    }
+
+    FragColor = vec4(color * shadow_debug_tint(world_position), albedo.a);
+}
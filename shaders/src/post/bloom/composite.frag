@@ -0,0 +1,12 @@
+#version 330 core
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+uniform sampler2D bloom;
+uniform float intensity;
+
+void main() {
+    vec4 color = texture(screen, uv);
+    FragColor = vec4(color.rgb + texture(bloom, uv).rgb * intensity, color.a);
+}
@@ -0,0 +1,31 @@
+#version 330 core
+in vec2 uv;
+out vec4 FragColor;
+
+// the larger mip level
+uniform sampler2D screen;
+uniform vec2 texel;
+
+// 13 taps in overlapping 2x2 boxes, doesn't flicker on small bright spots like a single
+// bilinear tap does
+void main() {
+    vec3 a = texture(screen, uv + texel * vec2(-2.0, 2.0)).rgb;
+    vec3 b = texture(screen, uv + texel * vec2(0.0, 2.0)).rgb;
+    vec3 c = texture(screen, uv + texel * vec2(2.0, 2.0)).rgb;
+    vec3 d = texture(screen, uv + texel * vec2(-2.0, 0.0)).rgb;
+    vec3 e = texture(screen, uv).rgb;
+    vec3 f = texture(screen, uv + texel * vec2(2.0, 0.0)).rgb;
+    vec3 g = texture(screen, uv + texel * vec2(-2.0, -2.0)).rgb;
+    vec3 h = texture(screen, uv + texel * vec2(0.0, -2.0)).rgb;
+    vec3 i = texture(screen, uv + texel * vec2(2.0, -2.0)).rgb;
+    vec3 j = texture(screen, uv + texel * vec2(-1.0, 1.0)).rgb;
+    vec3 k = texture(screen, uv + texel * vec2(1.0, 1.0)).rgb;
+    vec3 l = texture(screen, uv + texel * vec2(-1.0, -1.0)).rgb;
+    vec3 m = texture(screen, uv + texel * vec2(1.0, -1.0)).rgb;
+
+    vec3 color = e * 0.125
+        + (a + c + g + i) * 0.03125
+        + (b + d + f + h) * 0.0625
+        + (j + k + l + m) * 0.125;
+    FragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,23 @@
+#version 330 core
+in vec2 uv;
+out vec4 FragColor;
+
+// the smaller mip level, added onto the bound larger one
+uniform sampler2D screen;
+uniform vec2 texel;
+uniform float radius;
+
+// 3x3 tent filter
+void main() {
+    vec2 offset = texel * radius;
+    vec3 color = texture(screen, uv).rgb * 4.0;
+    color += (texture(screen, uv + vec2(-offset.x, 0.0)).rgb
+        + texture(screen, uv + vec2(offset.x, 0.0)).rgb
+        + texture(screen, uv + vec2(0.0, -offset.y)).rgb
+        + texture(screen, uv + vec2(0.0, offset.y)).rgb) * 2.0;
+    color += texture(screen, uv + vec2(-offset.x, -offset.y)).rgb
+        + texture(screen, uv + vec2(offset.x, -offset.y)).rgb
+        + texture(screen, uv + vec2(-offset.x, offset.y)).rgb
+        + texture(screen, uv + vec2(offset.x, offset.y)).rgb;
+    FragColor = vec4(color / 16.0, 1.0);
+}
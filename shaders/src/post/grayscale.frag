@@ -0,0 +1,11 @@
+#version 330 core
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+
+void main() {
+    vec4 color = texture(screen, uv);
+    float luma = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    FragColor = vec4(vec3(luma), color.a);
+}
@@ -0,0 +1,10 @@
+#version 330 core
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+
+void main() {
+    vec4 color = texture(screen, uv);
+    FragColor = vec4(1.0 - color.rgb, color.a);
+}
@@ -0,0 +1,29 @@
+#version 330 core
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+uniform float exposure;
+// 0 Reinhard, 1 ACES
+uniform int curve;
+
+vec3 reinhard(vec3 color) {
+    return color / (1.0 + color);
+}
+
+// Narkowicz's fit of the ACES filmic curve
+vec3 aces(vec3 color) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+}
+
+void main() {
+    vec4 color = texture(screen, uv);
+    vec3 hdr = color.rgb * exposure;
+    vec3 mapped = curve == 1 ? aces(hdr) : reinhard(hdr);
+    FragColor = vec4(mapped, color.a);
+}
@@ -0,0 +1,13 @@
+#version 330 core
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+uniform float strength;
+
+void main() {
+    vec4 color = texture(screen, uv);
+    float dist = distance(uv, vec2(0.5));
+    float shade = 1.0 - smoothstep(0.3, 0.75, dist) * strength;
+    FragColor = vec4(color.rgb * shade, color.a);
+}
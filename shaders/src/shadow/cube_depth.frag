@@ -0,0 +1,10 @@
+#version 330 core
+in vec3 world_position;
+
+uniform vec3 light_position;
+uniform float far_plane;
+
+// distance to the light instead of the perspective depth, so all faces compare alike
+void main() {
+    gl_FragDepth = length(world_position - light_position) / far_plane;
+}
@@ -0,0 +1,11 @@
+#version 330 core
+layout (location = 0) in vec3 pos;
+
+uniform mat4 model;
+
+out vec3 world_position_geom;
+
+void main() {
+    world_position_geom = (model * vec4(pos, 1.0)).xyz;
+    gl_Position = vec4(world_position_geom, 1.0);
+}
@@ -0,0 +1,5 @@
+#version 330 core
+
+// depth is written by the rasterizer, no color attachments to fill
+void main() {
+}
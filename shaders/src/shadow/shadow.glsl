@@ -0,0 +1,140 @@
+// Shadow of a directional light rendered by `shadow::ShadowMap` or
+// `shadow::CascadedShadowMap` and of a point light rendered by `shadow::OmniShadowMap`, set up
+// by their `bind`. Positions and normals are in world space.
+
+const int SHADOWS_OFF = 0;
+const int SHADOWS_SINGLE = 1;
+const int SHADOWS_CASCADED = 2;
+
+// cascades side by side when cascaded
+uniform sampler2DShadow shadow_map;
+// SHADOWS_OFF until a shadow map is bound, everything is lit then
+uniform int shadow_mode;
+uniform mat4 light_space;
+// depth offset where the light grazes the surface, a tenth of it where it hits head on
+uniform float shadow_bias;
+// world space push along the normal where the light grazes the surface
+uniform float shadow_normal_bias;
+// taps reach this many texels to each side, 0 is a single (hardware filtered) tap
+uniform int shadow_pcf;
+
+// arrays are `shadow::MAX_CASCADES` long
+layout (std140) uniform ShadowCascades {
+    mat4 cascade_view;
+    mat4 cascade_matrices[4];
+    // view space distance where each cascade ends
+    vec4 cascade_splits;
+    // cascades used, 1 to tint by cascade
+    uvec4 cascade_info;
+};
+
+// first cascade reaching `position`, the cascade count beyond the last one
+int cascade_index(vec3 position) {
+    float depth = -(cascade_view * vec4(position, 1.0)).z;
+    int count = int(cascade_info.x);
+    for (int i = 0; i < count; i++) {
+        if (depth < cascade_splits[i]) {
+            return i;
+        }
+    }
+    return count;
+}
+
+// PCF around `coords` (0..1 in the map), kept inside [tile_min, tile_max]
+float filtered_shadow(vec3 coords, vec2 tile_min, vec2 tile_max) {
+    vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0));
+    float lit = 0.0;
+    for (int y = -shadow_pcf; y <= shadow_pcf; y++) {
+        for (int x = -shadow_pcf; x <= shadow_pcf; x++) {
+            vec2 tap = clamp(coords.xy + vec2(x, y) * texel, tile_min + texel * 0.5, tile_max - texel * 0.5);
+            lit += texture(shadow_map, vec3(tap, coords.z));
+        }
+    }
+    float taps = float(2 * shadow_pcf + 1);
+    return lit / (taps * taps);
+}
+
+// 1 where the light reaches `position`, 0 in full shadow, soft in between
+float shadow_factor(vec3 position, vec3 normal, vec3 light_dir) {
+    if (shadow_mode == SHADOWS_OFF) {
+        return 1.0;
+    }
+
+    mat4 matrix = light_space;
+    vec2 tile_min = vec2(0.0);
+    vec2 tile_max = vec2(1.0);
+    float bias_scale = 1.0;
+    if (shadow_mode == SHADOWS_CASCADED) {
+        int cascade = cascade_index(position);
+        if (cascade >= int(cascade_info.x)) {
+            return 1.0;
+        }
+        matrix = cascade_matrices[cascade];
+        float width = 1.0 / float(cascade_info.x);
+        tile_min = vec2(width * float(cascade), 0.0);
+        tile_max = vec2(width * float(cascade + 1), 1.0);
+        // further cascades cover more world per texel
+        bias_scale = float(cascade + 1);
+    }
+
+    float grazing = 1.0 - clamp(dot(normal, light_dir), 0.0, 1.0);
+    vec4 light_clip = matrix * vec4(position + normal * shadow_normal_bias * bias_scale * grazing, 1.0);
+    vec3 coords = light_clip.xyz / light_clip.w * 0.5 + 0.5;
+    // beyond the far plane of the light, nothing there was rendered into the map
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
+    coords.z -= max(shadow_bias * grazing, shadow_bias * 0.1);
+    coords.xy = mix(tile_min, tile_max, coords.xy);
+    return filtered_shadow(coords, tile_min, tile_max);
+}
+
+// color multiplied into lit output, tints every cascade differently when asked to
+vec3 shadow_debug_tint(vec3 position) {
+    if (shadow_mode != SHADOWS_CASCADED || cascade_info.y == 0u) {
+        return vec3(1.0);
+    }
+    const vec3 tints[5] = vec3[5](
+        vec3(1.0, 0.5, 0.5), vec3(0.5, 1.0, 0.5), vec3(0.5, 0.5, 1.0), vec3(1.0, 1.0, 0.5), vec3(1.0)
+    );
+    return tints[cascade_index(position)];
+}
+
+// distance to the light over `point_shadow_far` in every direction
+uniform samplerCubeShadow point_shadow_map;
+// index into `point_lights` of the light casting the cube shadow, -1 for none
+uniform int point_shadow_light;
+uniform vec3 point_shadow_position;
+uniform float point_shadow_far;
+// distance offset against acne, relative to `point_shadow_far`
+uniform float point_shadow_bias;
+// world space radius the taps spread over, at a distance of `point_shadow_far`
+uniform float point_shadow_softness;
+
+// directions spaced around the cube, for PCF without sampling the full 3D neighbourhood
+const vec3 point_shadow_taps[20] = vec3[20](
+    vec3(1, 1, 1), vec3(1, -1, 1), vec3(-1, -1, 1), vec3(-1, 1, 1),
+    vec3(1, 1, -1), vec3(1, -1, -1), vec3(-1, -1, -1), vec3(-1, 1, -1),
+    vec3(1, 1, 0), vec3(1, -1, 0), vec3(-1, -1, 0), vec3(-1, 1, 0),
+    vec3(1, 0, 1), vec3(-1, 0, 1), vec3(1, 0, -1), vec3(-1, 0, -1),
+    vec3(0, 1, 1), vec3(0, -1, 1), vec3(0, -1, -1), vec3(0, 1, -1)
+);
+
+// 1 where point light `light` reaches `position`, 0 in full shadow, 1 for lights without one
+float point_shadow_factor(int light, vec3 position, vec3 normal) {
+    if (light != point_shadow_light) {
+        return 1.0;
+    }
+
+    vec3 to_position = position - point_shadow_position;
+    float grazing = 1.0 - clamp(dot(normal, normalize(-to_position)), 0.0, 1.0);
+    float depth = length(to_position) / point_shadow_far - point_shadow_bias * (0.1 + grazing);
+    // further from the light the penumbra widens
+    float spread = point_shadow_softness * depth;
+
+    float lit = 0.0;
+    for (int i = 0; i < 20; i++) {
+        lit += texture(point_shadow_map, vec4(to_position + point_shadow_taps[i] * spread, depth));
+    }
+    return lit / 20.0;
+}
@@ -0,0 +1,15 @@
+#version 330 core
+in vec3 direction;
+
+out vec4 FragColor;
+
+// equirectangular panorama, longitude along u, latitude along v
+uniform sampler2D panorama;
+
+const float PI = 3.14159265359;
+
+void main() {
+    vec3 dir = normalize(direction);
+    vec2 uv = vec2(atan(dir.z, dir.x) / (2.0 * PI) + 0.5, asin(clamp(dir.y, -1.0, 1.0)) / PI + 0.5);
+    FragColor = vec4(texture(panorama, uv).rgb, 1.0);
+}
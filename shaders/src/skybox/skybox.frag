@@ -0,0 +1,12 @@
+#version 330 core
+in vec3 direction;
+
+out vec4 FragColor;
+
+uniform samplerCube sky;
+// scales HDR environments, 1 for LDR ones
+uniform float intensity;
+
+void main() {
+    FragColor = vec4(texture(sky, direction).rgb * intensity, 1.0);
+}
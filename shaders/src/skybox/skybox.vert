@@ -0,0 +1,17 @@
+#version 330 core
+
+uniform mat4 view;
+uniform mat4 projection;
+
+out vec3 direction;
+
+// unit cube as a 14 vertex triangle strip, no vertex buffer needed
+void main() {
+    int bit = 1 << gl_VertexID;
+    vec3 pos = vec3((0x287a & bit) != 0, (0x02af & bit) != 0, (0x31e3 & bit) != 0) * 2.0 - 1.0;
+    direction = pos;
+
+    // rotation only, the sky never gets closer; z = w puts it on the far plane
+    vec4 clip = projection * mat4(mat3(view)) * vec4(pos, 1.0);
+    gl_Position = clip.xyww;
+}
@@ -0,0 +1,11 @@
+#version 330 core
+in vec2 uv;
+in vec4 color;
+
+out vec4 FragColor;
+
+uniform sampler2D sprite;
+
+void main() {
+    FragColor = texture(sprite, uv) * color;
+}
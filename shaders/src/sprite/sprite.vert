@@ -0,0 +1,16 @@
+#version 330 core
+layout (location = 0) in vec2 pos;
+layout (location = 1) in vec2 tex_coords;
+layout (location = 2) in vec4 tint;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+out vec2 uv;
+out vec4 color;
+
+void main() {
+    uv = tex_coords;
+    color = tint;
+    gl_Position = projection * view * vec4(pos, 0.0, 1.0);
+}
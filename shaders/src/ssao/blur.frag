@@ -0,0 +1,17 @@
+#version 330 core
+in vec2 uv;
+out float FragColor;
+
+uniform sampler2D screen;
+uniform vec2 texel;
+
+// 4x4 box, the size of the noise tile, which hides its pattern
+void main() {
+    float sum = 0.0;
+    for (int x = -2; x < 2; x++) {
+        for (int y = -2; y < 2; y++) {
+            sum += texture(screen, uv + (vec2(x, y) + 0.5) * texel).r;
+        }
+    }
+    FragColor = sum / 16.0;
+}
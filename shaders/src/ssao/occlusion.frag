@@ -0,0 +1,56 @@
+#version 330 core
+in vec2 uv;
+out float FragColor;
+
+uniform sampler2D depth;
+// view space normals in xyz
+uniform sampler2D normals;
+uniform sampler2D noise;
+
+const int MAX_KERNEL = 64;
+uniform vec3 samples[MAX_KERNEL];
+uniform int kernel_size;
+
+uniform mat4 projection;
+uniform mat4 inverse_projection;
+// screen size over noise size, tiles the noise over the screen
+uniform vec2 noise_scale;
+uniform float radius;
+uniform float bias;
+
+vec3 view_position(vec2 coords) {
+    vec4 ndc = vec4(coords, texture(depth, coords).r, 1.0) * 2.0 - 1.0;
+    vec4 view = inverse_projection * ndc;
+    return view.xyz / view.w;
+}
+
+void main() {
+    if (texture(depth, uv).r >= 1.0) {
+        // background, nothing to occlude
+        FragColor = 1.0;
+        return;
+    }
+
+    vec3 position = view_position(uv);
+    vec3 normal = normalize(texture(normals, uv).xyz);
+    vec3 random = normalize(texture(noise, uv * noise_scale).xyz * 2.0 - 1.0);
+
+    // kernel rotated around the normal by the noise, Gram-Schmidt
+    vec3 tangent = normalize(random - normal * dot(random, normal));
+    mat3 tbn = mat3(tangent, cross(normal, tangent), normal);
+
+    float occlusion = 0.0;
+    for (int i = 0; i < kernel_size; i++) {
+        vec3 sample_position = position + tbn * samples[i] * radius;
+
+        vec4 offset = projection * vec4(sample_position, 1.0);
+        vec2 coords = offset.xy / offset.w * 0.5 + 0.5;
+        float scene_depth = view_position(coords).z;
+
+        // geometry far in front of the sample is a different surface and mustn't darken it
+        float range = smoothstep(0.0, 1.0, radius / abs(position.z - scene_depth));
+        occlusion += (scene_depth >= sample_position.z + bias ? 1.0 : 0.0) * range;
+    }
+
+    FragColor = 1.0 - occlusion / float(kernel_size);
+}
@@ -0,0 +1,47 @@
+#version 330 core
+in vec2 uv;
+in vec4 color;
+
+out vec4 FragColor;
+
+// distance to the glyph edge in alpha: 0.5 on the edge, 1 deep inside, 0 far outside
+uniform sampler2D sprite;
+// size of one atlas texel in uv
+uniform vec2 texel;
+// widths in distance units, 0.5 covering the whole spread
+uniform float outline_width;
+uniform vec4 outline_color;
+// in atlas texels, +y towards the top of the glyph
+uniform vec2 shadow_offset;
+uniform vec4 shadow_color;
+uniform float shadow_softness;
+uniform float softness;
+
+// `a` over `b`, straight alpha
+vec4 over(vec4 a, vec4 b) {
+    float alpha = a.a + b.a * (1.0 - a.a);
+    vec3 rgb = (a.rgb * a.a + b.rgb * b.a * (1.0 - a.a)) / max(alpha, 1e-5);
+    return vec4(rgb, alpha);
+}
+
+void main() {
+    float dist = texture(sprite, uv).a;
+    // half a screen pixel in distance units, keeps edges one pixel wide at any scale
+    float aa = max(fwidth(dist) * 0.5, 1e-4) + softness;
+
+    float fill = smoothstep(0.5 - aa, 0.5 + aa, dist);
+    float outline_edge = 0.5 - outline_width;
+    float outline = smoothstep(outline_edge - aa, outline_edge + aa, dist);
+
+    float shadow_dist = texture(sprite, uv - shadow_offset * texel).a;
+    float shadow_aa = aa + shadow_softness;
+    float shadow = smoothstep(outline_edge - shadow_aa, outline_edge + shadow_aa, shadow_dist);
+
+    vec4 result = vec4(color.rgb, color.a * fill);
+    result = over(result, vec4(outline_color.rgb, outline_color.a * outline));
+    result = over(result, vec4(shadow_color.rgb, shadow_color.a * shadow));
+    if (result.a <= 0.0) {
+        discard;
+    }
+    FragColor = result;
+}
@@ -0,0 +1,16 @@
+// Layer indexed sampling of sampler2DArray, layers count from 0. Built in, so any shader
+// going through `preprocess_file` can `#include "texture/array.glsl"`.
+
+// `layer` of the array, out of range layers clamp to the first or last one
+vec4 sample_layer(sampler2DArray tex, vec2 uv, int layer) {
+    return texture(tex, vec3(uv, float(layer)));
+}
+
+// fractional `layer` blends the two closest ones, e.g. between animation frames
+vec4 sample_layer_blend(sampler2DArray tex, vec2 uv, float layer) {
+    float last = float(textureSize(tex, 0).z - 1);
+    float first = clamp(floor(layer), 0.0, last);
+    vec4 a = texture(tex, vec3(uv, first));
+    vec4 b = texture(tex, vec3(uv, min(first + 1.0, last)));
+    return mix(a, b, clamp(layer - first, 0.0, 1.0));
+}
@@ -1,6 +1,8 @@
 #version 330 core
 out vec4 FragColor;
 
+uniform vec4 color;
+
 void main() {
-    FragColor = vec4(0.0f, 0.3f, 0.05f, 1.0f);
-}
\ No newline at end of file
+    FragColor = color;
+}
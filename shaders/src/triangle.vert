@@ -1,6 +1,10 @@
 #version 330 core
 layout (location = 0) in vec3 pos;
+// xy offset, w scale; attribute left disabled reads as (0, 0, 0, 1), a single untouched copy
+layout (location = 1) in vec4 instance;
+
+uniform mat4 model;
 
 void main() {
-    gl_Position = vec4(pos.x, pos.y, pos.z, 1.0);
-}
\ No newline at end of file
+    gl_Position = model * vec4(pos * instance.w + vec3(instance.xy, 0.0), 1.0);
+}
@@ -0,0 +1,43 @@
+use crate::backend::Backend;
+use crate::event::WindowEvent;
+use crate::time::Time;
+
+/// What `run_loop` drives on the `Backend` `B`, e.g. `impl App<Setup> for Game` for GLFW.
+/// Simulation state only changes in `update`, which always sees the same step, so a run
+/// replays the same way regardless of the frame rate.
+pub trait App<B: Backend> {
+    /// Every window event, before the frame's updates.
+    fn event(&mut self, _backend: &mut B, _event: WindowEvent) {}
+
+    /// Advances the simulation by exactly `dt` seconds, runs zero or more times per frame.
+    fn update(&mut self, backend: &mut B, dt: f32);
+
+    /// Draws a frame. `alpha` in `[0, 1)` is how far the frame lies between the previous and
+    /// the latest update, blend the two states by it for smooth motion.
+    fn render(&mut self, backend: &mut B, time: &Time, alpha: f32);
+}
+
+/// Runs `app` until the window is closed: events, then as many `update`s of `fixed_dt` seconds
+/// as real time has passed, then one `render`, then the buffer swap.
+/// A hitch is capped at `Time::MAX_DELTA`, so the updates can't fall into a death spiral.
+pub fn run_loop<B: Backend>(backend: &mut B, app: &mut impl App<B>, fixed_dt: f32) {
+    let mut time = Time::new();
+    let mut accumulator = 0.0;
+
+    while !backend.should_close() {
+        time.tick();
+
+        for event in backend.poll_events() {
+            app.event(backend, event);
+        }
+
+        accumulator += time.delta;
+        while accumulator >= fixed_dt {
+            app.update(backend, fixed_dt);
+            accumulator -= fixed_dt;
+        }
+
+        app.render(backend, &time, accumulator / fixed_dt);
+        backend.swap_buffers();
+    }
+}
@@ -0,0 +1,649 @@
+//! Registry of textures, shader programs and meshes loaded from disk. Loading the same file
+//! twice gives back the first handle, `unload` frees the GL object. Textures and meshes can
+//! be decoded on worker threads, `Assets::update` uploads them on the GL thread. With
+//! `Assets::watch` all of them are reloaded when their files change, keeping their handles.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::error::WrapperError;
+use crate::loader::obj;
+use crate::mesh::{Mesh, MeshData};
+use crate::shader_watcher::build_program;
+use crate::wrapper::{ColorSpace, ShaderProgram, ShaderType, Texture2D};
+
+/// Typed reference to an asset in `Assets`. Once the asset is unloaded the handle resolves
+/// to `None`, also after its slot got reused.
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    _asset: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> Debug for Handle<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handle<{}>({}v{})", std::any::type_name::<T>(), self.index, self.generation)
+    }
+}
+
+/// Where an asset loaded in the background is at.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadState {
+    /// decoding on a worker or waiting for `Assets::update` to upload it
+    Loading,
+    Loaded,
+    /// `Assets::load_error` tells why
+    Failed,
+    /// unloaded, or the handle belongs to another registry
+    Unloaded,
+}
+
+enum Entry<T> {
+    Loading,
+    Loaded(T),
+    Failed(WrapperError),
+}
+
+struct Slot<T, K> {
+    /// bumped on unload, so older handles stop matching
+    generation: u32,
+    /// `None` while the slot is free
+    entry: Option<Entry<T>>,
+    /// what the asset was loaded from, `None` for added ones
+    key: Option<K>,
+}
+
+/// Assets of one kind inside `Assets`, slots of unloaded ones are reused.
+pub struct Pool<T, K> {
+    slots: Vec<Slot<T, K>>,
+    free: Vec<u32>,
+    by_key: HashMap<K, Handle<T>>,
+}
+
+impl<T, K: Clone + Eq + Hash> Pool<T, K> {
+    fn new() -> Self {
+        Self { slots: Vec::new(), free: Vec::new(), by_key: HashMap::new() }
+    }
+
+    fn insert(&mut self, entry: Entry<T>, key: Option<K>) -> Handle<T> {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot { generation: 0, entry: None, key: None });
+                self.slots.len() as u32 - 1
+            }
+        };
+
+        let slot = &mut self.slots[index as usize];
+        slot.entry = Some(entry);
+        slot.key = key.clone();
+        let handle = Handle { index, generation: slot.generation, _asset: PhantomData };
+        if let Some(key) = key {
+            self.by_key.insert(key, handle);
+        }
+        handle
+    }
+
+    /// Handle of the asset loaded (or loading) from `key`, or of the one `load` makes.
+    fn get_or_load(&mut self, key: K, load: impl FnOnce() -> Result<T, WrapperError>) -> Result<Handle<T>, WrapperError> {
+        if let Some(&handle) = self.by_key.get(&key) {
+            return Ok(handle);
+        }
+        Ok(self.insert(Entry::Loaded(load()?), Some(key)))
+    }
+
+    /// Handle of the asset loaded (or loading) from `key`, otherwise a new loading one and
+    /// `true` to say the caller has to start loading it.
+    fn get_or_reserve(&mut self, key: K) -> (Handle<T>, bool) {
+        match self.by_key.get(&key) {
+            Some(&handle) => (handle, false),
+            None => (self.insert(Entry::Loading, Some(key)), true),
+        }
+    }
+
+    /// Result of a background load, dropped if the handle was unloaded meanwhile. A failed
+    /// reload keeps the loaded asset and gives back the error.
+    fn finish(&mut self, handle: Handle<T>, result: Result<T, WrapperError>) -> Option<WrapperError> {
+        let slot = self.slot_mut(handle)?;
+        match (result, &slot.entry) {
+            (Ok(asset), _) => slot.entry = Some(Entry::Loaded(asset)),
+            (Err(err), Some(Entry::Loaded(_))) => return Some(err),
+            (Err(err), _) => slot.entry = Some(Entry::Failed(err)),
+        }
+        None
+    }
+
+    fn slot(&self, handle: Handle<T>) -> Option<&Slot<T, K>> {
+        self.slots.get(handle.index as usize).filter(|slot| slot.generation == handle.generation)
+    }
+
+    fn slot_mut(&mut self, handle: Handle<T>) -> Option<&mut Slot<T, K>> {
+        self.slots.get_mut(handle.index as usize).filter(|slot| slot.generation == handle.generation)
+    }
+
+    fn get(&self, handle: Handle<T>) -> Option<&T> {
+        match self.slot(handle)?.entry.as_ref()? {
+            Entry::Loaded(asset) => Some(asset),
+            _ => None,
+        }
+    }
+
+    fn state(&self, handle: Handle<T>) -> LoadState {
+        match self.slot(handle).and_then(|slot| slot.entry.as_ref()) {
+            Some(Entry::Loading) => LoadState::Loading,
+            Some(Entry::Loaded(_)) => LoadState::Loaded,
+            Some(Entry::Failed(_)) => LoadState::Failed,
+            None => LoadState::Unloaded,
+        }
+    }
+
+    fn error(&self, handle: Handle<T>) -> Option<&WrapperError> {
+        match self.slot(handle)?.entry.as_ref()? {
+            Entry::Failed(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Frees the slot, giving back the asset if it was loaded.
+    fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let slot = self.slot_mut(handle)?;
+        let entry = slot.entry.take()?;
+        let key = slot.key.take();
+        slot.generation = slot.generation.wrapping_add(1);
+        if let Some(key) = key {
+            self.by_key.remove(&key);
+        }
+        self.free.push(handle.index);
+
+        match entry {
+            Entry::Loaded(asset) => Some(asset),
+            _ => None,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+}
+
+/// Kinds of assets `Assets` stores, `Key` identifies the files one was loaded from.
+pub trait Asset: Sized + 'static {
+    type Key: Clone + Eq + Hash + 'static;
+
+    fn pool(assets: &Assets) -> &Pool<Self, Self::Key>;
+    fn pool_mut(assets: &mut Assets) -> &mut Pool<Self, Self::Key>;
+}
+
+impl Asset for Texture2D {
+    type Key = (PathBuf, ColorSpace);
+
+    fn pool(assets: &Assets) -> &Pool<Self, Self::Key> {
+        &assets.textures
+    }
+
+    fn pool_mut(assets: &mut Assets) -> &mut Pool<Self, Self::Key> {
+        &mut assets.textures
+    }
+}
+
+impl Asset for ShaderProgram {
+    /// vertex and fragment shader
+    type Key = (PathBuf, PathBuf);
+
+    fn pool(assets: &Assets) -> &Pool<Self, Self::Key> {
+        &assets.shaders
+    }
+
+    fn pool_mut(assets: &mut Assets) -> &mut Pool<Self, Self::Key> {
+        &mut assets.shaders
+    }
+}
+
+impl Asset for Mesh {
+    type Key = PathBuf;
+
+    fn pool(assets: &Assets) -> &Pool<Self, Self::Key> {
+        &assets.meshes
+    }
+
+    fn pool_mut(assets: &mut Assets) -> &mut Pool<Self, Self::Key> {
+        &mut assets.meshes
+    }
+}
+
+/// File to decode on a worker.
+enum Job {
+    Texture(Handle<Texture2D>, PathBuf),
+    Mesh(Handle<Mesh>, PathBuf),
+}
+
+/// CPU side result of a `Job`, uploaded by `Assets::update`.
+enum Decoded {
+    Texture(Handle<Texture2D>, Result<image::RgbaImage, WrapperError>),
+    Mesh(Handle<Mesh>, Result<MeshData, WrapperError>),
+}
+
+impl Job {
+    fn run(self) -> Decoded {
+        match self {
+            Self::Texture(handle, path) => Decoded::Texture(handle, Texture2D::decode_file(path)),
+            Self::Mesh(handle, path) => Decoded::Mesh(handle, obj::load_data(path)),
+        }
+    }
+}
+
+/// Worker threads sharing one job queue. Dropping it closes the queue, the workers finish
+/// the file at hand and exit.
+struct Workers {
+    jobs: Sender<Job>,
+    decoded: Receiver<Decoded>,
+}
+
+impl Workers {
+    fn new(count: usize) -> Self {
+        let (jobs, queue) = channel::<Job>();
+        let (done, decoded) = channel();
+        let queue = Arc::new(Mutex::new(queue));
+
+        for _ in 0..count {
+            let (queue, done) = (Arc::clone(&queue), done.clone());
+            std::thread::spawn(move || loop {
+                // the lock is released before decoding, so the others can take jobs meanwhile
+                let job = match queue.lock() {
+                    Ok(queue) => queue.recv(),
+                    Err(_) => return,
+                };
+                let Ok(job) = job else {
+                    return;
+                };
+                if done.send(job.run()).is_err() {
+                    return;
+                }
+            });
+        }
+
+        Self { jobs, decoded }
+    }
+}
+
+/// Directories of loaded files watched for changes. Like `ShaderWatcher` it watches the
+/// directories, editors often replace a file on save, which ends a watch on the file itself.
+struct FileWatcher {
+    watcher: RecommendedWatcher,
+    changes: Receiver<notify::Result<Event>>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+    fn new() -> Result<Self, WrapperError> {
+        let (sender, changes) = channel();
+        let watcher = notify::recommended_watcher(sender)?;
+        Ok(Self { watcher, changes, dirs: HashSet::new() })
+    }
+
+    fn watch(&mut self, file: &Path) -> Result<(), WrapperError> {
+        if let Some(dir) = file.parent() {
+            if !self.dirs.contains(dir) {
+                self.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+                self.dirs.insert(dir.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+
+    /// Files created or modified since the last call, most of them not ours.
+    fn changed(&self) -> Result<HashSet<PathBuf>, WrapperError> {
+        let mut changed = HashSet::new();
+        for event in self.changes.try_iter() {
+            let event = event?;
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                changed.extend(event.paths);
+            }
+        }
+        Ok(changed)
+    }
+}
+
+/// Loads each file once and hands out `Handle`s to the GL objects, which live until
+/// `unload` or until the registry is dropped. Paths are canonicalized, so different
+/// spellings of the same file are deduplicated too.
+pub struct Assets {
+    textures: Pool<Texture2D, (PathBuf, ColorSpace)>,
+    shaders: Pool<ShaderProgram, (PathBuf, PathBuf)>,
+    meshes: Pool<Mesh, PathBuf>,
+    /// files every shader was built from, includes too
+    shader_files: HashMap<Handle<ShaderProgram>, Vec<PathBuf>>,
+    /// started by the first background load
+    workers: Option<Workers>,
+    /// started by `watch`
+    watcher: Option<FileWatcher>,
+}
+
+impl Default for Assets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[allow(dead_code)]
+impl Assets {
+    pub fn new() -> Self {
+        Self {
+            textures: Pool::new(), shaders: Pool::new(), meshes: Pool::new(), shader_files: HashMap::new(), workers: None,
+            watcher: None,
+        }
+    }
+
+    /// Image file as a texture, the same file in another color space is a separate texture.
+    pub fn load_texture(&mut self, path: impl AsRef<Path>, color_space: ColorSpace) -> Result<Handle<Texture2D>, WrapperError> {
+        let path = canonical(path.as_ref())?;
+        self.watch_file(&path)?;
+        self.textures.get_or_load((path.clone(), color_space), || Texture2D::from_file_as(&path, color_space))
+    }
+
+    pub fn load_shader(&mut self, vert_path: impl AsRef<Path>, frag_path: impl AsRef<Path>) -> Result<Handle<ShaderProgram>, WrapperError> {
+        let (vert, frag) = (canonical(vert_path.as_ref())?, canonical(frag_path.as_ref())?);
+        let mut files = Vec::new();
+        let handle = self.shaders.get_or_load((vert.clone(), frag.clone()), || {
+            let (program, built_from) = build_program(&[(ShaderType::Vertex, vert), (ShaderType::Fragment, frag)])?;
+            files = built_from;
+            Ok(program)
+        })?;
+
+        if !files.is_empty() {
+            for file in &files {
+                self.watch_file(file)?;
+            }
+            self.shader_files.insert(handle, files);
+        }
+        Ok(handle)
+    }
+
+    /// Wavefront .obj file, other formats are `Unsupported`.
+    pub fn load_mesh(&mut self, path: impl AsRef<Path>) -> Result<Handle<Mesh>, WrapperError> {
+        let path = canonical(path.as_ref())?;
+        if !path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("obj")) {
+            return Err(WrapperError::Unsupported("mesh format, only .obj files are loaded"));
+        }
+        self.watch_file(&path)?;
+        self.meshes.get_or_load(path.clone(), || obj::load(&path))
+    }
+
+    /// `load_texture` decoding the image on a worker thread. The handle is `Loading` until
+    /// an `update` after the worker is done uploads it.
+    pub fn load_texture_async(&mut self, path: impl AsRef<Path>, color_space: ColorSpace) -> Result<Handle<Texture2D>, WrapperError> {
+        let path = canonical(path.as_ref())?;
+        self.watch_file(&path)?;
+        let (handle, new) = self.textures.get_or_reserve((path.clone(), color_space));
+        if new {
+            self.send(Job::Texture(handle, path));
+        }
+        Ok(handle)
+    }
+
+    /// `load_mesh` parsing the file on a worker thread, `Loading` until an `update` uploads it.
+    pub fn load_mesh_async(&mut self, path: impl AsRef<Path>) -> Result<Handle<Mesh>, WrapperError> {
+        let path = canonical(path.as_ref())?;
+        if !path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("obj")) {
+            return Err(WrapperError::Unsupported("mesh format, only .obj files are loaded"));
+        }
+        self.watch_file(&path)?;
+        let (handle, new) = self.meshes.get_or_reserve(path.clone());
+        if new {
+            self.send(Job::Mesh(handle, path));
+        }
+        Ok(handle)
+    }
+
+    fn send(&mut self, job: Job) {
+        let workers = self.workers.get_or_insert_with(|| {
+            let count = std::thread::available_parallelism().map_or(2, |count| count.get().clamp(1, 4));
+            Workers::new(count)
+        });
+        let _ = workers.jobs.send(job);
+    }
+
+    /// Reloads assets whenever their files change from now on. Textures and meshes are
+    /// decoded on the workers like the `_async` loads, until `update` uploads the new version
+    /// the handle keeps giving the old one. Shaders are rebuilt by `update` itself, also when
+    /// a file they `#include` changes.
+    pub fn watch(&mut self) -> Result<(), WrapperError> {
+        if self.watcher.is_some() {
+            return Ok(());
+        }
+
+        let mut watcher = FileWatcher::new()?;
+        let textures = self.textures.by_key.keys().map(|(path, _)| path);
+        let shaders = self.shader_files.values().flatten();
+        for path in textures.chain(self.meshes.by_key.keys()).chain(shaders) {
+            watcher.watch(path)?;
+        }
+        self.watcher = Some(watcher);
+        Ok(())
+    }
+
+    pub fn is_watching(&self) -> bool {
+        self.watcher.is_some()
+    }
+
+    fn watch_file(&mut self, path: &Path) -> Result<(), WrapperError> {
+        match &mut self.watcher {
+            Some(watcher) => watcher.watch(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Starts reloads of changed files and uploads what the workers finished decoding since
+    /// the last call, call it once per frame on the GL thread. Returns why reloads failed,
+    /// the assets keep their previous version then.
+    pub fn update(&mut self) -> Vec<WrapperError> {
+        let mut errors = Vec::new();
+        let changed = match self.watcher.as_ref().map(FileWatcher::changed) {
+            Some(Ok(changed)) => changed,
+            Some(Err(err)) => {
+                errors.push(err);
+                HashSet::new()
+            }
+            None => HashSet::new(),
+        };
+
+        let mut reloads = Vec::new();
+        for ((path, _), &handle) in &self.textures.by_key {
+            // a file still loading is read again by its pending job anyway
+            if changed.contains(path) && self.textures.state(handle) != LoadState::Loading {
+                reloads.push(Job::Texture(handle, path.clone()));
+            }
+        }
+        for (path, &handle) in &self.meshes.by_key {
+            if changed.contains(path) && self.meshes.state(handle) != LoadState::Loading {
+                reloads.push(Job::Mesh(handle, path.clone()));
+            }
+        }
+        for job in reloads {
+            self.send(job);
+        }
+        errors.extend(self.reload_shaders(&changed));
+
+        let Some(workers) = &self.workers else {
+            return errors;
+        };
+        let decoded: Vec<Decoded> = workers.decoded.try_iter().collect();
+
+        for decoded in decoded {
+            let error = match decoded {
+                Decoded::Texture(handle, image) => {
+                    let Some(&(_, color_space)) = self.textures.slot(handle).and_then(|slot| slot.key.as_ref()) else {
+                        continue;
+                    };
+                    let texture = image.and_then(|image| {
+                        Texture2D::from_rgba_as(image.width(), image.height(), image.as_raw(), color_space)
+                    });
+                    self.textures.finish(handle, texture)
+                }
+                Decoded::Mesh(handle, data) => {
+                    // an unloaded handle doesn't need the GL buffers
+                    if self.meshes.state(handle) == LoadState::Unloaded {
+                        continue;
+                    }
+                    self.meshes.finish(handle, data.and_then(|data| data.upload()))
+                }
+            };
+            errors.extend(error);
+        }
+        errors
+    }
+
+    /// Rebuilds the shaders built from any of the `changed` files, a failed build keeps the
+    /// previous program.
+    fn reload_shaders(&mut self, changed: &HashSet<PathBuf>) -> Vec<WrapperError> {
+        let shaders = &self.shaders;
+        self.shader_files.retain(|&handle, _| shaders.state(handle) != LoadState::Unloaded);
+
+        let stale: Vec<Handle<ShaderProgram>> = self
+            .shader_files
+            .iter()
+            .filter(|(_, files)| files.iter().any(|file| changed.contains(file)))
+            .map(|(&handle, _)| handle)
+            .collect();
+
+        let mut errors = Vec::new();
+        for handle in stale {
+            let Some((vert, frag)) = self.shaders.slot(handle).and_then(|slot| slot.key.clone()) else {
+                continue;
+            };
+            let program = match build_program(&[(ShaderType::Vertex, vert), (ShaderType::Fragment, frag)]) {
+                Ok((program, files)) => {
+                    // includes may have been added
+                    for file in &files {
+                        if let Err(err) = self.watch_file(file) {
+                            errors.push(err);
+                        }
+                    }
+                    self.shader_files.insert(handle, files);
+                    Ok(program)
+                }
+                Err(err) => Err(err),
+            };
+            errors.extend(self.shaders.finish(handle, program));
+        }
+        errors
+    }
+
+    /// Takes over an asset made in code, e.g. a generated mesh. It's never deduplicated.
+    pub fn add<T: Asset>(&mut self, asset: T) -> Handle<T> {
+        T::pool_mut(self).insert(Entry::Loaded(asset), None)
+    }
+
+    /// The asset once it's loaded.
+    pub fn get<T: Asset>(&self, handle: Handle<T>) -> Option<&T> {
+        T::pool(self).get(handle)
+    }
+
+    pub fn load_state<T: Asset>(&self, handle: Handle<T>) -> LoadState {
+        T::pool(self).state(handle)
+    }
+
+    /// Why a background load `Failed`.
+    pub fn load_error<T: Asset>(&self, handle: Handle<T>) -> Option<&WrapperError> {
+        T::pool(self).error(handle)
+    }
+
+    pub fn contains<T: Asset>(&self, handle: Handle<T>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Removes the asset and gives it back, dropping it frees the GL object. `None` when
+    /// the handle was already unloaded or never finished loading.
+    pub fn unload<T: Asset>(&mut self, handle: Handle<T>) -> Option<T> {
+        T::pool_mut(self).remove(handle)
+    }
+
+    /// Number of assets of kind `T`, ones still loading or failed included.
+    pub fn count<T: Asset>(&self) -> usize {
+        T::pool(self).len()
+    }
+}
+
+fn canonical(path: &Path) -> Result<PathBuf, WrapperError> {
+    std::fs::canonicalize(path).map_err(|source| WrapperError::Io { path: path.to_path_buf(), source })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reused_slots_dont_match_old_handles() {
+        let mut pool: Pool<&str, PathBuf> = Pool::new();
+        let first = pool.get_or_load("a.obj".into(), || Ok("a")).unwrap();
+        assert_eq!(pool.get_or_load("a.obj".into(), || panic!("loaded twice")).unwrap(), first);
+
+        assert_eq!(pool.remove(first), Some("a"));
+        assert_eq!(pool.remove(first), None);
+        assert_eq!(pool.state(first), LoadState::Unloaded);
+
+        // the free slot is taken again, under a new generation
+        let second = pool.get_or_load("a.obj".into(), || Ok("b")).unwrap();
+        assert_eq!((second.index, second.generation), (first.index, first.generation + 1));
+        assert_ne!(second, first);
+        assert_eq!(pool.get(first), None);
+        assert_eq!(pool.get(second), Some(&"b"));
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn background_results_of_unloaded_handles_are_dropped() {
+        let mut pool: Pool<&str, PathBuf> = Pool::new();
+        let (handle, new) = pool.get_or_reserve("a.png".into());
+        assert!(new && !pool.get_or_reserve("a.png".into()).1);
+        assert_eq!(pool.state(handle), LoadState::Loading);
+
+        pool.remove(handle);
+        let other = pool.insert(Entry::Loaded("other"), None);
+        assert!(pool.finish(handle, Ok("late")).is_none());
+        assert_eq!(pool.get(other), Some(&"other"));
+    }
+
+    #[test]
+    fn failed_reload_keeps_the_asset() {
+        let mut pool: Pool<&str, PathBuf> = Pool::new();
+        let (handle, _) = pool.get_or_reserve("a.png".into());
+        assert!(pool.finish(handle, Err(WrapperError::Unsupported("first load"))).is_none());
+        assert_eq!(pool.state(handle), LoadState::Failed);
+
+        pool.finish(handle, Ok("a"));
+        assert!(pool.finish(handle, Err(WrapperError::Unsupported("reload"))).is_some());
+        assert_eq!(pool.get(handle), Some(&"a"));
+    }
+}
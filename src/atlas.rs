@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::WrapperError;
+use crate::wrapper::{ColorSpace, Texture2D, TextureFilter, TextureWrap};
+
+/// Part of a texture in 0..1 texture coordinates, `min` is the bottom left corner.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+#[allow(dead_code)]
+impl UvRect {
+    /// The whole texture.
+    pub const FULL: Self = Self { min: [0.0, 0.0], max: [1.0, 1.0] };
+
+    /// Pixel rectangle `x, y, width, height` (from the bottom left) of a texture of
+    /// `texture_width` x `texture_height`.
+    pub fn from_pixels(x: u32, y: u32, width: u32, height: u32, texture_width: u32, texture_height: u32) -> Self {
+        let (tw, th) = (texture_width as f32, texture_height as f32);
+        Self {
+            min: [x as f32 / tw, y as f32 / th],
+            max: [(x + width) as f32 / tw, (y + height) as f32 / th],
+        }
+    }
+
+    /// Mirrored left to right.
+    pub fn flip_x(self) -> Self {
+        Self { min: [self.max[0], self.min[1]], max: [self.min[0], self.max[1]] }
+    }
+
+    /// Mirrored top to bottom.
+    pub fn flip_y(self) -> Self {
+        Self { min: [self.min[0], self.max[1]], max: [self.max[0], self.min[1]] }
+    }
+}
+
+impl Default for UvRect {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+/// Where an image ended up in its atlas.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    /// pixels from the bottom left corner of the atlas
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub uv: UvRect,
+}
+
+/// Many small images packed into one texture at load time, so 2D scenes drawing them bind a
+/// single texture. Images are looked up by the name they were added with.
+pub struct Atlas {
+    texture: Texture2D,
+    width: u32,
+    height: u32,
+    regions: HashMap<String, AtlasRegion>,
+}
+
+#[allow(dead_code)]
+impl Atlas {
+    /// ```ignore
+    /// let atlas = Atlas::builder()
+    ///     .file("player", "assets/player.png")?
+    ///     .file("coin", "assets/coin.png")?
+    ///     .build()?;
+    /// let coin = atlas.uv("coin").unwrap();
+    /// ```
+    pub fn builder() -> AtlasBuilder {
+        AtlasBuilder {
+            images: Vec::new(),
+            padding: 1,
+            max_size: 4096,
+            color_space: ColorSpace::Linear,
+            filter: TextureFilter::Linear,
+        }
+    }
+
+    pub fn texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+
+    pub fn uv(&self, name: &str) -> Option<UvRect> {
+        self.region(name).map(|region| region.uv)
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.regions.keys().map(String::as_str)
+    }
+}
+
+struct AtlasImage {
+    name: String,
+    width: u32,
+    height: u32,
+    /// RGBA8, bottom row first
+    pixels: Vec<u8>,
+}
+
+pub struct AtlasBuilder {
+    images: Vec<AtlasImage>,
+    padding: u32,
+    max_size: u32,
+    color_space: ColorSpace,
+    filter: TextureFilter,
+}
+
+#[allow(dead_code)]
+impl AtlasBuilder {
+    /// Adds RGBA8 pixels, bottom row first like `Texture2D::from_rgba`. A later image with
+    /// the same name replaces the earlier one.
+    pub fn image(mut self, name: &str, width: u32, height: u32, pixels: &[u8]) -> Result<Self, WrapperError> {
+        if pixels.len() != (width * height * 4) as usize {
+            return Err(WrapperError::Unsupported("atlas images other than width * height RGBA8 pixels"));
+        }
+
+        self.images.retain(|image| image.name != name);
+        self.images.push(AtlasImage { name: name.to_string(), width, height, pixels: pixels.to_vec() });
+        Ok(self)
+    }
+
+    /// Loads a PNG/JPEG image, flipped like `Texture2D::from_file`.
+    pub fn file(self, name: &str, path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        let path = path.as_ref();
+        let img = image::open(path)
+            .map_err(|source| WrapperError::Image { path: path.to_path_buf(), source })?
+            .flipv()
+            .into_rgba8();
+
+        self.image(name, img.width(), img.height(), img.as_raw())
+    }
+
+    /// Transparent pixels between images, keeps filtering from bleeding neighbours in.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Largest width and height tried before giving up, the atlas grows in powers of two
+    /// up to it.
+    pub fn max_size(mut self, max_size: u32) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    /// `Nearest` keeps pixel art crisp.
+    pub fn filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn build(self) -> Result<Atlas, WrapperError> {
+        let sizes: Vec<(u32, u32)> = self.images.iter().map(|image| (image.width, image.height)).collect();
+        let (size, positions) = pack(&sizes, self.padding, self.max_size)
+            .ok_or(WrapperError::Unsupported("atlas images that don't fit into max_size"))?;
+        let (width, height) = (size, size);
+
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let mut regions = HashMap::with_capacity(self.images.len());
+        for (image, &(x, y)) in self.images.iter().zip(&positions) {
+            let row_len = (image.width * 4) as usize;
+            for row in 0..image.height {
+                let src = (row * image.width * 4) as usize;
+                let dst = (((y + row) * width + x) * 4) as usize;
+                pixels[dst..dst + row_len].copy_from_slice(&image.pixels[src..src + row_len]);
+            }
+
+            let uv = UvRect::from_pixels(x, y, image.width, image.height, width, height);
+            regions.insert(image.name.clone(), AtlasRegion { x, y, width: image.width, height: image.height, uv });
+        }
+
+        let texture = Texture2D::from_rgba_as(width, height, &pixels, self.color_space)?;
+        texture.set_filter(self.filter, self.filter);
+        texture.set_wrap(TextureWrap::ClampToEdge, TextureWrap::ClampToEdge);
+        texture.set_label("atlas");
+
+        Ok(Atlas { texture, width, height, regions })
+    }
+}
+
+/// Shelf packing: images sorted by height fill rows left to right, a new row starts above
+/// the tallest image of the last. Tries square power of two sizes from 64 (or `max_size` when
+/// that's smaller) up to `max_size`, returns the size and the bottom left corner of every
+/// image in input order.
+fn pack(sizes: &[(u32, u32)], padding: u32, max_size: u32) -> Option<(u32, Vec<(u32, u32)>)> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].1));
+
+    let mut size = 64.min(max_size);
+    loop {
+        if let Some(positions) = pack_into(sizes, &order, padding, size) {
+            return Some((size, positions));
+        }
+        if size >= max_size {
+            return None;
+        }
+        size = (size * 2).min(max_size);
+    }
+}
+
+fn pack_into(sizes: &[(u32, u32)], order: &[usize], padding: u32, size: u32) -> Option<Vec<(u32, u32)>> {
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut row_height) = (padding, padding, 0);
+
+    for &i in order {
+        let (width, height) = sizes[i];
+        if x + width + padding > size {
+            x = padding;
+            y += row_height + padding;
+            row_height = 0;
+        }
+        if x + width + padding > size || y + height + padding > size {
+            return None;
+        }
+
+        positions[i] = (x, y);
+        x += width + padding;
+        row_height = row_height.max(height);
+    }
+    Some(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlap(a: (u32, u32), a_size: (u32, u32), b: (u32, u32), b_size: (u32, u32)) -> bool {
+        a.0 < b.0 + b_size.0 && b.0 < a.0 + a_size.0 && a.1 < b.1 + b_size.1 && b.1 < a.1 + a_size.1
+    }
+
+    #[test]
+    fn images_dont_overlap_and_keep_padding() {
+        let sizes = [(30, 10), (20, 40), (50, 50), (10, 10), (60, 5)];
+        let (size, positions) = pack(&sizes, 1, 4096).unwrap();
+        assert_eq!(size, 128);
+        for (i, (&position, &image)) in positions.iter().zip(&sizes).enumerate() {
+            assert!(position.0 >= 1 && position.1 >= 1);
+            assert!(position.0 + image.0 < size && position.1 + image.1 < size);
+            for (&other, &other_size) in positions.iter().zip(&sizes).skip(i + 1) {
+                // grown by the padding, so touching counts as overlap
+                assert!(!overlap(position, (image.0 + 1, image.1 + 1), other, (other_size.0 + 1, other_size.1 + 1)));
+            }
+        }
+    }
+
+    #[test]
+    fn grows_in_powers_of_two() {
+        assert_eq!(pack(&[(10, 10)], 1, 4096).unwrap().0, 64);
+        assert_eq!(pack(&[(100, 100)], 1, 4096).unwrap().0, 128);
+        assert_eq!(pack(&[(100, 100)], 1, 120).unwrap().0, 120);
+        assert!(pack(&[(100, 100)], 1, 100).is_none());
+    }
+
+    #[test]
+    fn small_max_size() {
+        let (size, positions) = pack(&[(8, 8), (8, 8)], 1, 32).unwrap();
+        assert_eq!(size, 32);
+        assert_eq!(positions, [(1, 1), (10, 1)]);
+        assert!(pack(&[(40, 8)], 1, 32).is_none());
+    }
+}
@@ -0,0 +1,18 @@
+use crate::event::WindowEvent;
+
+/// Window with a GL context current on this thread, what `app::run_loop` drives. Every
+/// backend translates its events into `event::WindowEvent`, so input handling stays the same
+/// whichever window library is behind it.
+pub trait Backend {
+    fn should_close(&self) -> bool;
+
+    fn set_should_close(&mut self, close: bool);
+
+    /// Size of the default framebuffer in pixels.
+    fn framebuffer_size(&self) -> (i32, i32);
+
+    /// Events since the previous call, doesn't block.
+    fn poll_events(&mut self) -> Vec<WindowEvent>;
+
+    fn swap_buffers(&mut self);
+}
@@ -0,0 +1,134 @@
+use crate::error::WrapperError;
+use crate::framebuffer::Framebuffer;
+use crate::postprocess::{draw_fullscreen, FULLSCREEN_VERT, SCREEN_UNIT};
+use crate::wrapper::{BlendFactor, BlendEquation, BlendState, ShaderProgram, TextureFormat, VertexArray};
+
+const BRIGHT_FRAG: &str = include_str!("../shaders/src/post/bloom/bright.frag");
+const DOWNSAMPLE_FRAG: &str = include_str!("../shaders/src/post/bloom/downsample.frag");
+const UPSAMPLE_FRAG: &str = include_str!("../shaders/src/post/bloom/upsample.frag");
+const COMPOSITE_FRAG: &str = include_str!("../shaders/src/post/bloom/composite.frag");
+
+/// Texture unit of the blurred highlights in the composite shader.
+const BLOOM_UNIT: u32 = 1;
+
+/// Glow around bright parts of the image: these are cut out, blurred by downsampling them
+/// through a chain of ever smaller targets and upsampling back while adding the levels up,
+/// then added onto the image. Works best on an HDR scene, where only lights exceed 1.
+/// Set it on a `PostProcess`, which runs it before its passes.
+pub struct Bloom {
+    pub enabled: bool,
+    /// brightness where highlights start to glow
+    pub threshold: f32,
+    /// how far below `threshold` the glow fades in, 0 cuts off hard
+    pub knee: f32,
+    /// how much of the glow is added back
+    pub intensity: f32,
+    /// spread of the upsampling filter in texels of the smaller level
+    pub radius: f32,
+    /// half the scene size first, each next one half the previous
+    mips: Vec<Framebuffer>,
+    bright: ShaderProgram,
+    downsample: ShaderProgram,
+    upsample: ShaderProgram,
+    composite: ShaderProgram,
+}
+
+#[allow(dead_code)]
+impl Bloom {
+    pub const DEFAULT_LEVELS: usize = 6;
+
+    /// `width`/`height` is the size of the scene it's applied to.
+    pub fn new(width: u32, height: u32) -> Result<Self, WrapperError> {
+        Self::with_levels(width, height, Self::DEFAULT_LEVELS)
+    }
+
+    /// More levels spread the glow wider, at least 1. Levels are never smaller than a pixel.
+    pub fn with_levels(width: u32, height: u32, levels: usize) -> Result<Self, WrapperError> {
+        let program = |name: &str, frag_src: &str| -> Result<ShaderProgram, WrapperError> {
+            let program = ShaderProgram::from_vertex_fragment(FULLSCREEN_VERT, frag_src)?;
+            program.set_label(&format!("bloom.{}", name));
+            Ok(program)
+        };
+
+        let mut mips = Vec::with_capacity(levels);
+        for (i, (width, height)) in Self::mip_sizes(width, height, levels.max(1)).enumerate() {
+            let mip = Framebuffer::builder(width, height).color_texture(TextureFormat::R11fG11fB10f).build()?;
+            mip.set_label(&format!("bloom.mip{}", i));
+            mips.push(mip);
+        }
+
+        Ok(Self {
+            enabled: true,
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.8,
+            radius: 1.0,
+            mips,
+            bright: program("bright", BRIGHT_FRAG)?,
+            downsample: program("downsample", DOWNSAMPLE_FRAG)?,
+            upsample: program("upsample", UPSAMPLE_FRAG)?,
+            composite: program("composite", COMPOSITE_FRAG)?,
+        })
+    }
+
+    fn mip_sizes(width: u32, height: u32, levels: usize) -> impl Iterator<Item = (u32, u32)> {
+        (1..=levels.min(31) as u32).map(move |level| ((width >> level).max(1), (height >> level).max(1)))
+    }
+
+    pub fn levels(&self) -> usize {
+        self.mips.len()
+    }
+
+    /// Scene size changed.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let sizes = Self::mip_sizes(width, height, self.mips.len());
+        for (mip, (width, height)) in self.mips.iter_mut().zip(sizes) {
+            mip.resize(width, height);
+        }
+    }
+
+    /// Blurred highlights of the last `apply`, before `intensity`.
+    pub fn glow(&self) -> &Framebuffer {
+        &self.mips[0]
+    }
+
+    /// Writes `input` with the glow added into `output`. Expects depth testing and blending
+    /// to be off, leaves blending off.
+    pub fn apply(&self, vao: &VertexArray, input: &Framebuffer, output: &Framebuffer) {
+        let run = |program: &ShaderProgram, source: &Framebuffer, target: &Framebuffer| {
+            target.bind();
+            program.use_program();
+            if let Some(texture) = source.color_texture(0) {
+                texture.bind_to_unit(SCREEN_UNIT);
+            }
+            program.set_i32("screen", SCREEN_UNIT as i32);
+            program.set_vec2("texel", [1.0 / source.width() as f32, 1.0 / source.height() as f32]);
+            draw_fullscreen(vao);
+        };
+
+        self.bright.use_program();
+        self.bright.set_f32("threshold", self.threshold);
+        self.bright.set_f32("knee", self.knee);
+        run(&self.bright, input, &self.mips[0]);
+
+        for pair in self.mips.windows(2) {
+            run(&self.downsample, &pair[0], &pair[1]);
+        }
+
+        BlendState::new(BlendFactor::One, BlendFactor::One, BlendEquation::Add).apply();
+        self.upsample.use_program();
+        self.upsample.set_f32("radius", self.radius);
+        for pair in self.mips.windows(2).rev() {
+            run(&self.upsample, &pair[1], &pair[0]);
+        }
+        BlendState::disable();
+
+        self.composite.use_program();
+        self.composite.set_f32("intensity", self.intensity);
+        if let Some(texture) = self.mips[0].color_texture(0) {
+            texture.bind_to_unit(BLOOM_UNIT);
+        }
+        self.composite.set_i32("bloom", BLOOM_UNIT as i32);
+        run(&self.composite, input, output);
+    }
+}
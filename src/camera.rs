@@ -0,0 +1,451 @@
+use std::mem::offset_of;
+
+use glam::{Mat4, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::error::WrapperError;
+use crate::event::{Key, MouseButton};
+use crate::input::{Binding, InputMap};
+use crate::wrapper::{ShaderProgram, Std140, UniformBuffer, verify_std140};
+
+pub const VIEW_UNIFORM: &str = "view";
+pub const PROJECTION_UNIFORM: &str = "projection";
+pub const CAMERA_BLOCK: &str = "Camera";
+
+/// std140 mirror of
+/// ```glsl
+/// uniform Camera { mat4 view; mat4 projection; mat4 view_projection; vec4 position; };
+/// ```
+/// to share the camera between programs through a `UniformBuffer`.
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniforms {
+    pub view: [f32; 16],
+    pub projection: [f32; 16],
+    pub view_projection: [f32; 16],
+    /// w is unused
+    pub position: [f32; 4],
+}
+
+#[allow(dead_code)]
+impl CameraUniforms {
+    pub fn verify_layout() -> Result<(), WrapperError> {
+        verify_std140::<Self>(&[
+            ("view", offset_of!(Self, view), Std140::Mat4),
+            ("projection", offset_of!(Self, projection), Std140::Mat4),
+            ("view_projection", offset_of!(Self, view_projection), Std140::Mat4),
+            ("position", offset_of!(Self, position), Std140::Vec4),
+        ])
+    }
+
+    /// Points the program's `Camera` block at the buffer.
+    pub fn bind_block(program: &ShaderProgram, buffer: &UniformBuffer<Self>) {
+        program.bind_uniform_block(CAMERA_BLOCK, buffer.binding());
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Camera {
+    pub position: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    /// vertical field of view in degrees
+    pub fov: f32,
+    pub aspect: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+#[allow(dead_code)]
+impl Camera {
+    pub fn new(position: Vec3, target: Vec3, aspect: f32) -> Self {
+        Self {
+            position,
+            target,
+            up: Vec3::Y,
+            fov: 45.0,
+            aspect,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.position, self.target, self.up)
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        Mat4::perspective_rh_gl(self.fov.to_radians(), self.aspect, self.near, self.far)
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        (self.target - self.position).normalize_or_zero()
+    }
+
+    pub fn set_fov(&mut self, degrees: f32) {
+        self.fov = degrees.clamp(1.0, 179.0);
+    }
+
+    pub fn set_viewport(&mut self, width: i32, height: i32) {
+        if height > 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+
+    /// Moves both position and target, so the camera keeps looking in the same direction.
+    pub fn translate(&mut self, offset: Vec3) {
+        self.position += offset;
+        self.target += offset;
+    }
+
+    pub fn uniforms(&self) -> CameraUniforms {
+        CameraUniforms {
+            view: self.view_matrix().to_cols_array(),
+            projection: self.projection_matrix().to_cols_array(),
+            view_projection: self.view_projection().to_cols_array(),
+            position: self.position.extend(1.0).to_array(),
+        }
+    }
+
+    /// Sets `view` and `projection` mat4 uniforms of the program, which has to be in use.
+    pub fn upload(&self, program: &ShaderProgram) {
+        program.set_mat4(VIEW_UNIFORM, &self.view_matrix().to_cols_array());
+        program.set_mat4(PROJECTION_UNIFORM, &self.projection_matrix().to_cols_array());
+    }
+}
+
+/// Model viewer controls: dragging "orbit" rotates around `target`, dragging "pan" moves the
+/// target in the view plane, scrolling zooms. `bind_defaults` puts those on the left and
+/// middle mouse buttons.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct OrbitCamera {
+    pub target: Vec3,
+    pub distance: f32,
+    /// radians around the up axis, 0 looks from +z
+    pub yaw: f32,
+    /// radians above the target's horizontal plane
+    pub pitch: f32,
+    /// radians per screen unit of cursor motion
+    pub sensitivity: f32,
+    /// view plane units per screen unit and unit of distance
+    pub pan_speed: f32,
+    /// distance factor of one scroll wheel step
+    pub zoom_step: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+}
+
+#[allow(dead_code)]
+impl OrbitCamera {
+    pub const ORBIT_ACTION: &'static str = "orbit";
+    pub const PAN_ACTION: &'static str = "pan";
+    /// just short of straight up or down, where the view's up vector would flip
+    const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+    pub fn new(target: Vec3, distance: f32) -> Self {
+        Self {
+            target,
+            distance,
+            yaw: 0.0,
+            pitch: 0.0,
+            sensitivity: 0.005,
+            pan_speed: 0.0015,
+            zoom_step: 0.9,
+            min_distance: 0.1,
+            max_distance: 1000.0,
+        }
+    }
+
+    pub fn bind_defaults(input: &mut InputMap) {
+        input.bind(Self::ORBIT_ACTION, Binding::Mouse(MouseButton::Left));
+        input.bind(Self::PAN_ACTION, Binding::Mouse(MouseButton::Middle));
+    }
+
+    /// Applies this frame's cursor motion and scrolling.
+    pub fn update(&mut self, input: &InputMap) {
+        let [dx, dy] = input.cursor_delta().map(|delta| delta as f32);
+
+        if input.pressed(Self::ORBIT_ACTION) {
+            self.orbit(-dx * self.sensitivity, dy * self.sensitivity);
+        } else if input.pressed(Self::PAN_ACTION) {
+            let scale = self.pan_speed * self.distance;
+            self.pan(-dx * scale, dy * scale);
+        }
+
+        let [_, scroll] = input.scroll();
+        if scroll != 0.0 {
+            self.zoom(scroll as f32);
+        }
+    }
+
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch).clamp(-Self::MAX_PITCH, Self::MAX_PITCH);
+    }
+
+    /// Moves the target by `right`/`up` along the view plane.
+    pub fn pan(&mut self, right: f32, up: f32) {
+        let forward = (self.target - self.position()).normalize_or_zero();
+        let right_axis = forward.cross(Vec3::Y).normalize_or_zero();
+        let up_axis = right_axis.cross(forward);
+        self.target += right_axis * right + up_axis * up;
+    }
+
+    /// Moves closer by `steps` scroll wheel steps, away when negative.
+    pub fn zoom(&mut self, steps: f32) {
+        self.distance = (self.distance * self.zoom_step.powf(steps)).clamp(self.min_distance, self.max_distance);
+    }
+
+    pub fn position(&self) -> Vec3 {
+        let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+        let (pitch_sin, pitch_cos) = self.pitch.sin_cos();
+        self.target + Vec3::new(pitch_cos * yaw_sin, pitch_sin, pitch_cos * yaw_cos) * self.distance
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.position(), self.target, Vec3::Y)
+    }
+
+    /// Points `camera` from the orbit position at the target, the projection is left alone.
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.position = self.position();
+        camera.target = self.target;
+        camera.up = Vec3::Y;
+    }
+}
+
+/// Fly camera: the "fly_*" actions move relative to the view direction, cursor motion looks
+/// around. Capture the cursor with `Setup::capture_cursor` so it doesn't leave the window.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct FpsCamera {
+    pub position: Vec3,
+    /// radians around the up axis, 0 looks down -z
+    pub yaw: f32,
+    /// radians up from the horizon, clamped to `max_pitch`
+    pub pitch: f32,
+    /// units per second
+    pub speed: f32,
+    /// speed factor while "fly_fast" is held
+    pub fast_factor: f32,
+    /// radians per screen unit of cursor motion
+    pub sensitivity: f32,
+    pub max_pitch: f32,
+}
+
+#[allow(dead_code)]
+impl FpsCamera {
+    pub const FORWARD_ACTION: &'static str = "fly_forward";
+    pub const BACK_ACTION: &'static str = "fly_back";
+    pub const LEFT_ACTION: &'static str = "fly_left";
+    pub const RIGHT_ACTION: &'static str = "fly_right";
+    pub const UP_ACTION: &'static str = "fly_up";
+    pub const DOWN_ACTION: &'static str = "fly_down";
+    pub const FAST_ACTION: &'static str = "fly_fast";
+
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            yaw: 0.0,
+            pitch: 0.0,
+            speed: 3.0,
+            fast_factor: 4.0,
+            sensitivity: 0.002,
+            max_pitch: 89.0f32.to_radians(),
+        }
+    }
+
+    /// WASD, space and control for up and down, shift to go fast.
+    pub fn bind_defaults(input: &mut InputMap) {
+        input
+            .bind(Self::FORWARD_ACTION, Binding::Key(Key::W))
+            .bind(Self::BACK_ACTION, Binding::Key(Key::S))
+            .bind(Self::LEFT_ACTION, Binding::Key(Key::A))
+            .bind(Self::RIGHT_ACTION, Binding::Key(Key::D))
+            .bind(Self::UP_ACTION, Binding::Key(Key::Space))
+            .bind(Self::DOWN_ACTION, Binding::Key(Key::LeftControl))
+            .bind(Self::FAST_ACTION, Binding::Key(Key::LeftShift));
+    }
+
+    /// Looks by this frame's cursor motion and moves by the held actions for `delta` seconds.
+    pub fn update(&mut self, input: &InputMap, delta: f32) {
+        let [dx, dy] = input.cursor_delta().map(|delta| delta as f32);
+        self.look(-dx * self.sensitivity, -dy * self.sensitivity);
+
+        let forward = input.axis(Self::BACK_ACTION, Self::FORWARD_ACTION);
+        let right = input.axis(Self::LEFT_ACTION, Self::RIGHT_ACTION);
+        let up = input.axis(Self::DOWN_ACTION, Self::UP_ACTION);
+
+        // flat forward, looking down doesn't slow walking
+        let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+        let ahead = Vec3::new(-yaw_sin, 0.0, -yaw_cos);
+        let side = Vec3::new(yaw_cos, 0.0, -yaw_sin);
+        let direction = (ahead * forward + side * right + Vec3::Y * up).normalize_or_zero();
+
+        let speed = if input.pressed(Self::FAST_ACTION) { self.speed * self.fast_factor } else { self.speed };
+        self.position += direction * speed * delta;
+    }
+
+    pub fn look(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = (self.yaw + yaw).rem_euclid(std::f32::consts::TAU);
+        self.pitch = (self.pitch + pitch).clamp(-self.max_pitch, self.max_pitch);
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+        let (pitch_sin, pitch_cos) = self.pitch.sin_cos();
+        Vec3::new(-yaw_sin * pitch_cos, pitch_sin, -yaw_cos * pitch_cos)
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::look_to_rh(self.position, self.direction(), Vec3::Y)
+    }
+
+    /// Places `camera` at the position looking along `direction`, the projection is left alone.
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.position = self.position;
+        camera.target = self.position + self.direction();
+        camera.up = Vec3::Y;
+    }
+}
+
+/// Corner of the window at pixel (0, 0).
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Origin {
+    /// y grows downwards, like window and cursor coordinates
+    #[default]
+    TopLeft,
+    /// y grows upwards, like GL's window space
+    BottomLeft,
+}
+
+/// Projection for 2D drawing in pixels: vertices are given in framebuffer pixels from `origin`
+/// instead of the -1..1 range. z stays within -1..1.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ortho2D {
+    pub width: f32,
+    pub height: f32,
+    pub origin: Origin,
+}
+
+#[allow(dead_code)]
+impl Ortho2D {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width: width as f32, height: height as f32, origin: Origin::TopLeft }
+    }
+
+    pub fn with_origin(mut self, origin: Origin) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Call it on `FramebufferSize` so a pixel stays a pixel.
+    pub fn set_viewport(&mut self, width: i32, height: i32) {
+        self.width = width.max(1) as f32;
+        self.height = height.max(1) as f32;
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        match self.origin {
+            Origin::TopLeft => Mat4::orthographic_rh_gl(0.0, self.width, self.height, 0.0, -1.0, 1.0),
+            Origin::BottomLeft => Mat4::orthographic_rh_gl(0.0, self.width, 0.0, self.height, -1.0, 1.0),
+        }
+    }
+
+    /// Pixel position to normalized device coordinates.
+    pub fn pixel_to_ndc(&self, pixel: Vec2) -> Vec2 {
+        self.projection_matrix().transform_point3(pixel.extend(0.0)).truncate()
+    }
+
+    /// Sets `projection` to the pixel projection and `view` to identity, the program has to
+    /// be in use.
+    pub fn upload(&self, program: &ShaderProgram) {
+        program.set_mat4(VIEW_UNIFORM, &Mat4::IDENTITY.to_cols_array());
+        program.set_mat4(PROJECTION_UNIFORM, &self.projection_matrix().to_cols_array());
+    }
+}
+
+/// How a scene laid out for one aspect ratio fits a framebuffer of another.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AspectPolicy {
+    /// fills the framebuffer, shapes get distorted
+    Stretch,
+    /// keeps proportions with bars on the sides or top and bottom, see `AspectFit::content_rect`
+    #[default]
+    Letterbox,
+    /// keeps proportions and shows more of the scene in the extra space
+    Expand,
+}
+
+impl AspectPolicy {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Stretch => Self::Letterbox,
+            Self::Letterbox => Self::Expand,
+            Self::Expand => Self::Stretch,
+        }
+    }
+}
+
+/// Keeps a scene designed for `design` size in proportion when the framebuffer is resized:
+/// multiply vertices by `projection_matrix` and, for `Letterbox`, limit drawing to
+/// `content_rect` with `wrapper::scissor`. Call `resize` on `FramebufferSize`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AspectFit {
+    pub policy: AspectPolicy,
+    design: (u32, u32),
+    framebuffer: (u32, u32),
+}
+
+#[allow(dead_code)]
+impl AspectFit {
+    pub fn new(design_width: u32, design_height: u32, policy: AspectPolicy) -> Self {
+        let design = (design_width.max(1), design_height.max(1));
+        Self { policy, design, framebuffer: design }
+    }
+
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.framebuffer = (width.max(1) as u32, height.max(1) as u32);
+    }
+
+    /// x and y factors squeezing the -1..1 range into the part of the framebuffer matching
+    /// the design aspect ratio.
+    pub fn scale(&self) -> Vec2 {
+        if self.policy == AspectPolicy::Stretch {
+            return Vec2::ONE;
+        }
+
+        let design = self.design.0 as f32 / self.design.1 as f32;
+        let framebuffer = self.framebuffer.0 as f32 / self.framebuffer.1 as f32;
+        if framebuffer > design {
+            Vec2::new(design / framebuffer, 1.0)
+        } else {
+            Vec2::new(1.0, framebuffer / design)
+        }
+    }
+
+    /// Applied after the model matrix, identity for `Stretch`.
+    pub fn projection_matrix(&self) -> Mat4 {
+        Mat4::from_scale(self.scale().extend(1.0))
+    }
+
+    /// `[x, y, width, height]` in pixels of the framebuffer area the design fills.
+    pub fn content_rect(&self) -> [i32; 4] {
+        let scale = self.scale();
+        let (width, height) = (self.framebuffer.0 as f32 * scale.x, self.framebuffer.1 as f32 * scale.y);
+        let x = (self.framebuffer.0 as f32 - width) / 2.0;
+        let y = (self.framebuffer.1 as f32 - height) / 2.0;
+        [x.round() as i32, y.round() as i32, width.round() as i32, height.round() as i32]
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::Path;
+
+use gl::types::GLsizei;
+
+use crate::error::WrapperError;
+
+/// RGBA8 pixels of the given area of the bound read framebuffer, bottom row first.
+/// Blocks until everything drawn so far has finished rendering.
+pub fn read_pixels(x: i32, y: i32, width: u32, height: u32) -> Vec<u8> {
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    unsafe {
+        crate::checked!(gl::PixelStorei(gl::PACK_ALIGNMENT, 1));
+        crate::checked!(gl::ReadPixels(x, y, width as GLsizei, height as GLsizei, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr().cast()));
+    }
+    pixels
+}
+
+/// Writes bottom-up RGBA8 rows as read from GL to an image file, the format follows the
+/// extension. Alpha is forced to opaque, window contents rarely have meaningful alpha.
+pub fn save_pixels(path: impl AsRef<Path>, mut pixels: Vec<u8>, width: u32, height: u32) -> Result<(), WrapperError> {
+    let path = path.as_ref();
+
+    pixels.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 255);
+    let image = image::RgbaImage::from_raw(width, height, pixels).ok_or(WrapperError::Allocation("capture image"))?;
+
+    image::imageops::flip_vertical(&image)
+        .save(path)
+        .map_err(|source| WrapperError::Image { path: path.to_path_buf(), source })
+}
+
+/// Saves what was rendered to the window, `width`/`height` is the window framebuffer size.
+/// Call it after the frame was drawn and before swapping buffers.
+pub fn capture_frame(path: impl AsRef<Path>, width: u32, height: u32) -> Result<(), WrapperError> {
+    unsafe {
+        crate::checked!(gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0));
+        crate::checked!(gl::ReadBuffer(gl::BACK));
+    }
+
+    save_pixels(path, read_pixels(0, 0, width, height), width, height)
+}
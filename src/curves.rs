@@ -0,0 +1,308 @@
+use glam::Vec3;
+
+/// Deepest subdivision of one cubic, 2^16 segments at most however small the tolerance.
+const MAX_DEPTH: u32 = 16;
+
+/// Anything that turns into a polyline within `tolerance` world units of the true curve,
+/// with more points where it bends more.
+pub trait Curve {
+    /// Points along the curve from its start to its end, both included.
+    fn tessellate(&self, tolerance: f32) -> Vec<Vec3>;
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuadraticBezier {
+    pub start: Vec3,
+    pub control: Vec3,
+    pub end: Vec3,
+}
+
+#[allow(dead_code)]
+impl QuadraticBezier {
+    pub fn new(start: Vec3, control: Vec3, end: Vec3) -> Self {
+        Self { start, control, end }
+    }
+
+    pub fn point(&self, t: f32) -> Vec3 {
+        let s = 1.0 - t;
+        self.start * (s * s) + self.control * (2.0 * s * t) + self.end * (t * t)
+    }
+
+    /// Tangent at `t`, not normalized.
+    pub fn derivative(&self, t: f32) -> Vec3 {
+        (self.control - self.start) * (2.0 * (1.0 - t)) + (self.end - self.control) * (2.0 * t)
+    }
+
+    /// The same curve as a cubic, exactly.
+    pub fn to_cubic(self) -> CubicBezier {
+        CubicBezier {
+            start: self.start,
+            control1: self.start + (self.control - self.start) * (2.0 / 3.0),
+            control2: self.end + (self.control - self.end) * (2.0 / 3.0),
+            end: self.end,
+        }
+    }
+}
+
+impl Curve for QuadraticBezier {
+    fn tessellate(&self, tolerance: f32) -> Vec<Vec3> {
+        self.to_cubic().tessellate(tolerance)
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CubicBezier {
+    pub start: Vec3,
+    pub control1: Vec3,
+    pub control2: Vec3,
+    pub end: Vec3,
+}
+
+#[allow(dead_code)]
+impl CubicBezier {
+    pub fn new(start: Vec3, control1: Vec3, control2: Vec3, end: Vec3) -> Self {
+        Self { start, control1, control2, end }
+    }
+
+    pub fn point(&self, t: f32) -> Vec3 {
+        let s = 1.0 - t;
+        self.start * (s * s * s)
+            + self.control1 * (3.0 * s * s * t)
+            + self.control2 * (3.0 * s * t * t)
+            + self.end * (t * t * t)
+    }
+
+    /// Tangent at `t`, not normalized.
+    pub fn derivative(&self, t: f32) -> Vec3 {
+        let s = 1.0 - t;
+        (self.control1 - self.start) * (3.0 * s * s)
+            + (self.control2 - self.control1) * (6.0 * s * t)
+            + (self.end - self.control2) * (3.0 * t * t)
+    }
+
+    /// The parts before and after `t`, by de Casteljau.
+    pub fn split(&self, t: f32) -> (Self, Self) {
+        let a = self.start.lerp(self.control1, t);
+        let b = self.control1.lerp(self.control2, t);
+        let c = self.control2.lerp(self.end, t);
+        let ab = a.lerp(b, t);
+        let bc = b.lerp(c, t);
+        let middle = ab.lerp(bc, t);
+        (Self::new(self.start, a, ab, middle), Self::new(middle, bc, c, self.end))
+    }
+
+    /// How far the control points stray from the chord, an upper bound of how far the curve
+    /// does.
+    pub fn flatness(&self) -> f32 {
+        distance_to_segment(self.control1, self.start, self.end).max(distance_to_segment(self.control2, self.start, self.end))
+    }
+
+    /// Appends the points after `start` to `points`.
+    fn subdivide(&self, tolerance: f32, depth: u32, points: &mut Vec<Vec3>) {
+        if depth >= MAX_DEPTH || self.flatness() <= tolerance {
+            points.push(self.end);
+            return;
+        }
+        let (first, second) = self.split(0.5);
+        first.subdivide(tolerance, depth + 1, points);
+        second.subdivide(tolerance, depth + 1, points);
+    }
+}
+
+impl Curve for CubicBezier {
+    fn tessellate(&self, tolerance: f32) -> Vec<Vec3> {
+        let mut points = vec![self.start];
+        self.subdivide(tolerance.max(f32::EPSILON), 0, &mut points);
+        points
+    }
+}
+
+/// Smooth curve through all of `points`, each span between two points shaped by their
+/// neighbours. `alpha` picks the parameterization: 0 uniform, 0.5 centripetal, which never
+/// loops or cusps within a span, 1 chordal. An open spline runs from the first to the last
+/// point, a closed one also connects the last back to the first.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatmullRom {
+    pub points: Vec<Vec3>,
+    pub alpha: f32,
+    pub closed: bool,
+}
+
+#[allow(dead_code)]
+impl CatmullRom {
+    pub const CENTRIPETAL: f32 = 0.5;
+
+    /// Centripetal and open.
+    pub fn new(points: Vec<Vec3>) -> Self {
+        Self { points, alpha: Self::CENTRIPETAL, closed: false }
+    }
+
+    pub fn with_alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn closed(mut self, closed: bool) -> Self {
+        self.closed = closed;
+        self
+    }
+
+    /// Spans as exactly equivalent cubic Béziers, one per pair of neighbouring points.
+    pub fn segments(&self) -> Vec<CubicBezier> {
+        let count = self.points.len();
+        if count < 2 {
+            return Vec::new();
+        }
+
+        let point = |i: isize| -> Vec3 {
+            if self.closed {
+                return self.points[i.rem_euclid(count as isize) as usize];
+            }
+            // open ends continue straight on, mirroring the inner neighbour
+            match i {
+                -1 => self.points[0] * 2.0 - self.points[1],
+                i if i as usize == count => self.points[count - 1] * 2.0 - self.points[count - 2],
+                i => self.points[i as usize],
+            }
+        };
+
+        let spans = if self.closed { count } else { count - 1 };
+        (0..spans as isize)
+            .map(|i| catmull_rom_segment(point(i - 1), point(i), point(i + 1), point(i + 2), self.alpha))
+            .collect()
+    }
+}
+
+impl Curve for CatmullRom {
+    fn tessellate(&self, tolerance: f32) -> Vec<Vec3> {
+        let segments = self.segments();
+        let Some(first) = segments.first() else {
+            return self.points.clone();
+        };
+
+        let mut points = vec![first.start];
+        for segment in &segments {
+            segment.subdivide(tolerance.max(f32::EPSILON), 0, &mut points);
+        }
+        points
+    }
+}
+
+/// Bézier form of the span from `p1` to `p2`, with knot intervals of distance^alpha
+/// (Yuksel et al., "Parameterization and applications of Catmull-Rom curves").
+fn catmull_rom_segment(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, alpha: f32) -> CubicBezier {
+    const EPSILON: f32 = 1e-6;
+    let d2 = p1.distance(p2).powf(alpha);
+    if d2 < EPSILON {
+        return CubicBezier::new(p1, p1, p2, p2);
+    }
+    // a neighbour on top of its point has no direction to offer, borrow the span's interval
+    let d1 = match p0.distance(p1).powf(alpha) {
+        d if d < EPSILON => d2,
+        d => d,
+    };
+    let d3 = match p2.distance(p3).powf(alpha) {
+        d if d < EPSILON => d2,
+        d => d,
+    };
+
+    let control1 = (p2 * (d1 * d1) - p0 * (d2 * d2) + p1 * (2.0 * d1 * d1 + 3.0 * d1 * d2 + d2 * d2))
+        / (3.0 * d1 * (d1 + d2));
+    let control2 = (p1 * (d3 * d3) - p3 * (d2 * d2) + p2 * (2.0 * d3 * d3 + 3.0 * d3 * d2 + d2 * d2))
+        / (3.0 * d3 * (d3 + d2));
+    CubicBezier::new(p1, control1, control2, p2)
+}
+
+fn distance_to_segment(point: Vec3, a: Vec3, b: Vec3) -> f32 {
+    let ab = b - a;
+    let length_squared = ab.length_squared();
+    if length_squared <= f32::EPSILON {
+        return point.distance(a);
+    }
+    let t = ((point - a).dot(ab) / length_squared).clamp(0.0, 1.0);
+    point.distance(a + ab * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec3;
+
+    use super::*;
+
+    fn arch() -> CubicBezier {
+        CubicBezier::new(vec3(0.0, 0.0, 0.0), vec3(0.0, 2.0, 0.0), vec3(2.0, 2.0, 0.0), vec3(2.0, 0.0, 0.0))
+    }
+
+    /// Largest distance of the curve's points from the polyline.
+    fn deviation(curve: &CubicBezier, points: &[Vec3]) -> f32 {
+        (0..=200)
+            .map(|i| {
+                let point = curve.point(i as f32 / 200.0);
+                points.windows(2).map(|pair| distance_to_segment(point, pair[0], pair[1])).fold(f32::MAX, f32::min)
+            })
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn tessellation_stays_within_tolerance() {
+        let curve = arch();
+        for tolerance in [0.1, 0.01, 0.001] {
+            let points = curve.tessellate(tolerance);
+            assert_eq!(points.first(), Some(&curve.start));
+            assert_eq!(points.last(), Some(&curve.end));
+            assert!(deviation(&curve, &points) <= tolerance);
+        }
+        assert!(curve.tessellate(0.001).len() > curve.tessellate(0.1).len());
+    }
+
+    #[test]
+    fn straight_lines_need_no_subdivision() {
+        let line = CubicBezier::new(vec3(0.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0), vec3(2.0, 0.0, 0.0), vec3(3.0, 0.0, 0.0));
+        assert_eq!(line.tessellate(0.001), [line.start, line.end]);
+    }
+
+    #[test]
+    fn zero_tolerance_is_bounded_by_the_depth_limit() {
+        let points = arch().tessellate(0.0);
+        assert!(points.len() > 1000 && points.len() <= (1 << MAX_DEPTH) + 1);
+    }
+
+    #[test]
+    fn split_halves_meet_on_the_curve() {
+        let curve = arch();
+        let (first, second) = curve.split(0.3);
+        assert!(first.end.distance(curve.point(0.3)) < 1e-5);
+        assert_eq!(first.end, second.start);
+        assert!(first.point(0.5).distance(curve.point(0.15)) < 1e-5);
+        assert!(second.point(0.5).distance(curve.point(0.65)) < 1e-5);
+    }
+
+    #[test]
+    fn quadratic_to_cubic_is_exact() {
+        let quadratic = QuadraticBezier::new(vec3(0.0, 0.0, 0.0), vec3(1.0, 2.0, 0.0), vec3(2.0, 0.0, 1.0));
+        let cubic = quadratic.to_cubic();
+        for i in 0..=10 {
+            let t = i as f32 / 10.0;
+            assert!(quadratic.point(t).distance(cubic.point(t)) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_its_points() {
+        let points = vec![vec3(0.0, 0.0, 0.0), vec3(1.0, 1.0, 0.0), vec3(2.0, 0.0, 0.0), vec3(3.0, 1.0, 0.0)];
+        let open = CatmullRom::new(points.clone());
+        let segments = open.segments();
+        assert_eq!(segments.len(), 3);
+        for (segment, pair) in segments.iter().zip(points.windows(2)) {
+            assert_eq!((segment.start, segment.end), (pair[0], pair[1]));
+        }
+
+        let closed = CatmullRom::new(points.clone()).closed(true);
+        assert_eq!(closed.segments().len(), 4);
+        assert_eq!(closed.tessellate(0.01).last(), Some(&points[0]));
+        assert_eq!(CatmullRom::new(vec![points[0]]).tessellate(0.01), [points[0]]);
+    }
+}
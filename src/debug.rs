@@ -0,0 +1,131 @@
+use std::ffi::{c_void, CStr};
+
+use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+
+use crate::error::WrapperError;
+
+/// Severity of a KHR_debug message, ordered from the least to the most important.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugSeverity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+impl DebugSeverity {
+    const ALL: [Self; 4] = [Self::Notification, Self::Low, Self::Medium, Self::High];
+
+    fn gl_enum(self) -> GLenum {
+        match self {
+            Self::Notification => gl::DEBUG_SEVERITY_NOTIFICATION,
+            Self::Low => gl::DEBUG_SEVERITY_LOW,
+            Self::Medium => gl::DEBUG_SEVERITY_MEDIUM,
+            Self::High => gl::DEBUG_SEVERITY_HIGH,
+        }
+    }
+
+    fn from_gl(severity: GLenum) -> Self {
+        match severity {
+            gl::DEBUG_SEVERITY_HIGH => Self::High,
+            gl::DEBUG_SEVERITY_MEDIUM => Self::Medium,
+            gl::DEBUG_SEVERITY_LOW => Self::Low,
+            _ => Self::Notification,
+        }
+    }
+
+    fn log_level(self) -> log::Level {
+        match self {
+            Self::High => log::Level::Error,
+            Self::Medium => log::Level::Warn,
+            Self::Low => log::Level::Info,
+            Self::Notification => log::Level::Debug,
+        }
+    }
+}
+
+/// Installs the message callback on the current context, which should be a debug one
+/// (see `SetupBuilder::debug_severity`). Messages below `min_severity` are dropped by the driver.
+/// Needs GL 4.3 or the KHR_debug extension.
+pub fn install(min_severity: DebugSeverity) -> Result<(), WrapperError> {
+    if !gl::DebugMessageCallback::is_loaded() || !gl::DebugMessageControl::is_loaded() {
+        return Err(WrapperError::Unsupported("KHR_debug"));
+    }
+
+    unsafe {
+        crate::checked!(gl::Enable(gl::DEBUG_OUTPUT));
+        // report on the thread and in the call which caused the message
+        crate::checked!(gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS));
+        crate::checked!(gl::DebugMessageCallback(Some(callback), std::ptr::null()));
+    }
+    set_min_severity(min_severity);
+
+    Ok(())
+}
+
+/// Changes which messages reach the callback installed by `install`.
+pub fn set_min_severity(min_severity: DebugSeverity) {
+    for severity in DebugSeverity::ALL {
+        let enabled = if severity >= min_severity { gl::TRUE } else { gl::FALSE };
+        unsafe {
+            crate::checked!(gl::DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, severity.gl_enum(), 0, std::ptr::null(), enabled));
+        }
+    }
+}
+
+/// Stops debug output, the callback stays installed.
+#[allow(dead_code)]
+pub fn disable() {
+    unsafe { crate::checked!(gl::Disable(gl::DEBUG_OUTPUT)) }
+}
+
+extern "system" fn callback(
+    source: GLenum,
+    kind: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    _length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut c_void,
+) {
+    let message = if message.is_null() {
+        "".into()
+    } else {
+        unsafe { CStr::from_ptr(message) }.to_string_lossy()
+    };
+
+    // API errors are always worth seeing, whatever severity the driver gave them
+    let level = if kind == gl::DEBUG_TYPE_ERROR {
+        log::Level::Error
+    } else {
+        DebugSeverity::from_gl(severity).log_level()
+    };
+
+    log::log!(target: "gl", level, "[{} {} #{}] {}", source_name(source), type_name(kind), id, message.trim_end());
+}
+
+fn source_name(source: GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+fn type_name(kind: GLenum) -> &'static str {
+    match kind {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        gl::DEBUG_TYPE_PUSH_GROUP => "push group",
+        gl::DEBUG_TYPE_POP_GROUP => "pop group",
+        _ => "other",
+    }
+}
@@ -0,0 +1,205 @@
+use glam::{Mat4, Vec3};
+
+use crate::camera::Camera;
+use crate::curves::Curve;
+use crate::error::WrapperError;
+use crate::mesh::MeshVertex;
+use crate::wrapper::{
+    ArrayBuffer, Attribute, BlendState, BufferType, buffer_vertices, depth_test, depth_test_enabled, draw_arrays,
+    PrimitiveMode, ShaderProgram, VertexArray, VertexLike,
+};
+
+const LINES_VERT: &str = include_str!("../shaders/src/debug_draw/lines.vert");
+const LINES_FRAG: &str = include_str!("../shaders/src/debug_draw/lines.frag");
+
+/// segments of the circles making up `DebugDraw::sphere`
+const CIRCLE_SEGMENTS: usize = 32;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl VertexLike for DebugVertex {
+    const ATTRIBUTES: &'static [Attribute] = &[Attribute::vec3(0), Attribute::vec4(1)];
+}
+
+/// Gizmos for looking at what the code thinks: lines, boxes, spheres and arrows queued from
+/// anywhere during a frame and drawn together in one call by `draw`, which also forgets them.
+/// Nothing persists, whatever should stay visible is queued again every frame.
+///
+/// ```ignore
+/// debug.aabb(min, max, [0.0, 1.0, 0.0, 1.0]);
+/// debug.arrow(origin, origin + direction * 5.0, [1.0, 1.0, 0.0, 1.0]);
+/// debug.draw(&camera);
+/// ```
+pub struct DebugDraw {
+    /// hides lines behind geometry, off draws them over everything
+    pub depth_test: bool,
+    vertices: Vec<DebugVertex>,
+    program: ShaderProgram,
+    vao: VertexArray,
+    vbo: ArrayBuffer,
+}
+
+#[allow(dead_code)]
+impl DebugDraw {
+    pub fn new() -> Result<Self, WrapperError> {
+        let program = ShaderProgram::from_vertex_fragment(LINES_VERT, LINES_FRAG)?;
+        program.set_label("debug_draw");
+
+        let vao = VertexArray::new().ok_or(WrapperError::Allocation("vertex array"))?;
+        let vbo = ArrayBuffer::new().ok_or(WrapperError::Allocation("vertex buffer"))?;
+        vao.bind();
+        vbo.bind(BufferType::Array);
+        vao.set_vertex_layout::<DebugVertex>();
+        vao.set_label("debug_draw");
+
+        Ok(Self { depth_test: true, vertices: Vec::new(), program, vao, vbo })
+    }
+
+    pub fn line(&mut self, a: Vec3, b: Vec3, color: [f32; 4]) {
+        self.vertices.push(DebugVertex { position: a.to_array(), color });
+        self.vertices.push(DebugVertex { position: b.to_array(), color });
+    }
+
+    /// Connects consecutive points, and the last to the first when `closed`.
+    pub fn polyline(&mut self, points: &[Vec3], closed: bool, color: [f32; 4]) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], color);
+        }
+        if let (true, [first, .., last]) = (closed, points) {
+            self.line(*last, *first, color);
+        }
+    }
+
+    /// `curve` flattened to within `tolerance` world units, see `curves`.
+    pub fn curve(&mut self, curve: &impl Curve, tolerance: f32, color: [f32; 4]) {
+        self.polyline(&curve.tessellate(tolerance), false, color);
+    }
+
+    /// Box with edges along the axes between the corners `min` and `max`.
+    pub fn aabb(&mut self, min: Vec3, max: Vec3, color: [f32; 4]) {
+        self.cuboid(Mat4::IDENTITY, min, max, color);
+    }
+
+    /// Box between `min` and `max` in the space `transform` maps to the world, e.g. a mesh's
+    /// local bounds with its model matrix.
+    pub fn cuboid(&mut self, transform: Mat4, min: Vec3, max: Vec3, color: [f32; 4]) {
+        let corner = |i: usize| {
+            let pick = |bit: usize, axis: usize| if i & bit == 0 { min[axis] } else { max[axis] };
+            transform.transform_point3(Vec3::new(pick(1, 0), pick(2, 1), pick(4, 2)))
+        };
+        for i in 0..8 {
+            // to the neighbour along every axis once, from the corner with that bit unset
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    /// Circle of `radius` around `center` in the plane facing `normal`.
+    pub fn circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: [f32; 4]) {
+        let (u, v) = normal.normalize_or(Vec3::Z).any_orthonormal_pair();
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// Three circles around the axes, enough to read a sphere's size from any side.
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: [f32; 4]) {
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            self.circle(center, axis, radius, color);
+        }
+    }
+
+    /// Line from `from` to `to` with a head of four strokes, a fifth of its length.
+    pub fn arrow(&mut self, from: Vec3, to: Vec3, color: [f32; 4]) {
+        self.line(from, to, color);
+
+        let along = to - from;
+        let length = along.length();
+        if length <= f32::EPSILON {
+            return;
+        }
+        let direction = along / length;
+        let (u, v) = direction.any_orthonormal_pair();
+        let head = length * 0.2;
+        let base = to - direction * head;
+        for side in [u, -u, v, -v] {
+            self.line(to, base + side * head * 0.4, color);
+        }
+    }
+
+    /// Arrow along `direction` from `origin`, `length` long.
+    pub fn ray(&mut self, origin: Vec3, direction: Vec3, length: f32, color: [f32; 4]) {
+        self.arrow(origin, origin + direction.normalize_or_zero() * length, color);
+    }
+
+    /// Red, green and blue lines along the x, y and z axes of `transform`, `size` long.
+    pub fn axes(&mut self, transform: Mat4, size: f32) {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        for (axis, color) in [(Vec3::X, [1.0, 0.0, 0.0, 1.0]), (Vec3::Y, [0.0, 1.0, 0.0, 1.0]), (Vec3::Z, [0.0, 0.0, 1.0, 1.0])] {
+            self.line(origin, transform.transform_point3(axis * size), color);
+        }
+    }
+
+    /// Small three line star marking `point`.
+    pub fn cross(&mut self, point: Vec3, size: f32, color: [f32; 4]) {
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            self.line(point - axis * size * 0.5, point + axis * size * 0.5, color);
+        }
+    }
+
+    /// The normal of every vertex as a line of `length` from its position, after `model`.
+    pub fn normals(&mut self, vertices: &[MeshVertex], model: Mat4, length: f32, color: [f32; 4]) {
+        let normal_matrix = model.inverse().transpose();
+        for vertex in vertices {
+            let position = model.transform_point3(Vec3::from(vertex.position));
+            let normal = normal_matrix.transform_vector3(Vec3::from(vertex.normal)).normalize_or_zero();
+            self.line(position, position + normal * length, color);
+        }
+    }
+
+    /// Lines queued since the last `draw`.
+    pub fn line_count(&self) -> usize {
+        self.vertices.len() / 2
+    }
+
+    /// Forgets the queued lines without drawing them.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// Draws every queued line as seen by `camera` in one call and empties the queue. Alpha
+    /// blending is on while drawing.
+    pub fn draw(&mut self, camera: &Camera) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        self.vao.bind();
+        self.vbo.bind(BufferType::Array);
+        buffer_vertices(BufferType::Array, &self.vertices, gl::STREAM_DRAW);
+
+        let depth_enabled = depth_test_enabled();
+        depth_test(self.depth_test);
+        BlendState::alpha().apply();
+
+        self.program.use_program();
+        camera.upload(&self.program);
+        draw_arrays(PrimitiveMode::Lines, 0, self.vertices.len() as i32);
+
+        BlendState::disable();
+        depth_test(depth_enabled);
+        self.vertices.clear();
+    }
+}
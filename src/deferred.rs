@@ -0,0 +1,235 @@
+use std::mem::offset_of;
+
+use gl::types::GLuint;
+use glam::{Mat4, Vec3};
+
+use crate::camera::Camera;
+use crate::error::WrapperError;
+use crate::framebuffer::Framebuffer;
+use crate::lighting::PointLight;
+use crate::postprocess::{draw_fullscreen, FULLSCREEN_VERT};
+use crate::preprocessor::preprocess_builtin;
+use crate::wrapper::{
+    clear, depth_test, depth_test_enabled, Shader, ShaderProgram, ShaderType, Std140, Texture2D, TextureFormat,
+    UniformBuffer, verify_std140, VertexArray,
+};
+
+const GEOMETRY_VERT: &str = include_str!("../shaders/src/deferred/geometry.vert");
+const GEOMETRY_FRAG: &str = include_str!("../shaders/src/deferred/geometry.frag");
+
+/// Lights one lighting pass shades with, `MAX_LIGHTS` of the lighting shader.
+pub const MAX_LIGHTS: usize = 256;
+/// Uniform buffer binding point of the `Lights` block.
+pub const LIGHTS_BINDING: GLuint = 1;
+pub const LIGHTS_BLOCK: &str = "Lights";
+
+const POSITION: usize = 0;
+const NORMAL: usize = 1;
+const ALBEDO: usize = 2;
+
+/// std140 mirror of the `Lights` block.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightBlock {
+    /// x is the number of lights used, the rest pads to std140 array alignment
+    count: [u32; 4],
+    lights: [PointLight; MAX_LIGHTS],
+}
+
+const POINT_LIGHT_STD140: Std140 = Std140::Struct(&[Std140::Vec3, Std140::Float, Std140::Vec3, Std140::Float]);
+
+/// Scene attributes per pixel, everything the lighting pass needs: view space position
+/// (w is 1 where geometry was drawn), view space normal, albedo with the specular strength
+/// in alpha, and the depth as a texture, e.g. for `Ssao`. Depth is `Depth24Stencil8` like
+/// the usual scene framebuffers, so it can be copied into them.
+pub struct GBuffer {
+    framebuffer: Framebuffer,
+}
+
+#[allow(dead_code)]
+impl GBuffer {
+    pub fn new(width: u32, height: u32) -> Result<Self, WrapperError> {
+        let framebuffer = Framebuffer::builder(width, height)
+            .color_texture(TextureFormat::Rgba16F)
+            .color_texture(TextureFormat::Rgba16F)
+            .color_texture(TextureFormat::Rgba8)
+            .depth_texture(TextureFormat::Depth24Stencil8)
+            .build()?;
+        framebuffer.set_label("gbuffer");
+
+        Ok(Self { framebuffer })
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.framebuffer.resize(width, height);
+    }
+
+    /// Binds and clears the G-buffer.
+    pub fn begin(&self) {
+        self.framebuffer.bind();
+        clear(Some([0.0, 0.0, 0.0, 0.0]), Some(1.0));
+    }
+
+    pub fn position(&self) -> &Texture2D {
+        self.texture(POSITION)
+    }
+
+    pub fn normal(&self) -> &Texture2D {
+        self.texture(NORMAL)
+    }
+
+    pub fn albedo(&self) -> &Texture2D {
+        self.texture(ALBEDO)
+    }
+
+    pub fn depth(&self) -> &Texture2D {
+        self.framebuffer.depth_texture().expect("G-buffer has a depth texture")
+    }
+
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    fn texture(&self, index: usize) -> &Texture2D {
+        self.framebuffer.color_texture(index).expect("G-buffer attachments are textures")
+    }
+}
+
+/// Deferred shading: opaque geometry is drawn once into a `GBuffer`, then a single
+/// fullscreen pass lights every pixel with all point lights, so the cost of a light doesn't
+/// depend on the geometry it touches.
+///
+/// ```ignore
+/// deferred.begin_geometry(&camera);
+/// deferred.geometry_program().set_vec4("albedo", [0.8, 0.8, 0.8, 1.0]);
+/// for (mesh, transform) in &scene { transform.upload(deferred.geometry_program()); mesh.draw(); }
+/// deferred.set_lights(&lights, camera.view_matrix());
+/// post.begin();
+/// deferred.light();
+/// deferred.gbuffer().framebuffer().copy_depth(post.scene()); // forward drawn things on top
+/// ```
+pub struct Deferred {
+    /// light every lit pixel gets regardless of the point lights
+    pub ambient: [f32; 3],
+    /// Blinn-Phong exponent, higher is a smaller, sharper highlight
+    pub shininess: f32,
+    gbuffer: GBuffer,
+    geometry: ShaderProgram,
+    lighting: ShaderProgram,
+    lights: UniformBuffer<LightBlock>,
+    light_count: usize,
+    vao: VertexArray,
+}
+
+#[allow(dead_code)]
+impl Deferred {
+    pub fn new(width: u32, height: u32) -> Result<Self, WrapperError> {
+        PointLight::verify_layout()?;
+        verify_std140::<LightBlock>(&[
+            ("count", offset_of!(LightBlock, count), Std140::Vec4),
+            ("lights", offset_of!(LightBlock, lights), Std140::Array(&POINT_LIGHT_STD140, MAX_LIGHTS)),
+        ])?;
+
+        let geometry = ShaderProgram::from_vertex_fragment(GEOMETRY_VERT, GEOMETRY_FRAG)?;
+        geometry.set_label("deferred.geometry");
+        geometry.use_program();
+        geometry.set_vec4("albedo", [1.0, 1.0, 1.0, 1.0]);
+        geometry.set_f32("specular", 0.5);
+
+        let lighting = ShaderProgram::from_shaders([
+            Shader::from_source(ShaderType::Vertex, FULLSCREEN_VERT)?,
+            Shader::from_preprocessed(ShaderType::Fragment, &preprocess_builtin("deferred/lighting.frag")?)?,
+        ])?;
+        lighting.set_label("deferred.lighting");
+        lighting.bind_uniform_block(LIGHTS_BLOCK, LIGHTS_BINDING);
+
+        let lights = UniformBuffer::new(LIGHTS_BINDING, &bytemuck::Zeroable::zeroed())?;
+        lights.set_label("deferred.lights");
+
+        Ok(Self {
+            ambient: [0.05, 0.05, 0.05],
+            shininess: 32.0,
+            gbuffer: GBuffer::new(width, height)?,
+            geometry,
+            lighting,
+            lights,
+            light_count: 0,
+            vao: VertexArray::new().ok_or(WrapperError::Allocation("vertex array"))?,
+        })
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.gbuffer.resize(width, height);
+    }
+
+    pub fn gbuffer(&self) -> &GBuffer {
+        &self.gbuffer
+    }
+
+    /// Writes `model`, `view`, `projection` and the G-buffer outputs for vertices laid out
+    /// like `MeshVertex`. Set `albedo` (vec4) and `specular` (float) per object.
+    /// Custom geometry shaders have to write the same three outputs.
+    pub fn geometry_program(&self) -> &ShaderProgram {
+        &self.geometry
+    }
+
+    /// Binds and clears the G-buffer, turns depth testing on and uses the geometry program
+    /// with the camera's matrices.
+    pub fn begin_geometry(&self, camera: &Camera) {
+        self.gbuffer.begin();
+        depth_test(true);
+        self.geometry.use_program();
+        camera.upload(&self.geometry);
+    }
+
+    /// Uploads the lights for the next `light`, positions in world space get moved to view
+    /// space by `view`. Lights past `MAX_LIGHTS` are left out.
+    pub fn set_lights(&mut self, lights: &[PointLight], view: Mat4) {
+        let mut block: LightBlock = bytemuck::Zeroable::zeroed();
+        self.light_count = lights.len().min(MAX_LIGHTS);
+        block.count[0] = self.light_count as u32;
+
+        for (slot, light) in block.lights.iter_mut().zip(lights) {
+            let position = view.transform_point3(Vec3::from_array(light.position));
+            *slot = PointLight { position: position.to_array(), ..*light };
+        }
+        self.lights.update(&block);
+    }
+
+    pub fn light_count(&self) -> usize {
+        self.light_count
+    }
+
+    /// Shades the G-buffer into the bound framebuffer, pixels without geometry are left
+    /// as they were.
+    pub fn light(&self) {
+        let depth_enabled = depth_test_enabled();
+        depth_test(false);
+
+        self.lighting.use_program();
+        for (unit, (name, texture)) in
+            [("g_position", self.gbuffer.position()), ("g_normal", self.gbuffer.normal()), ("g_albedo", self.gbuffer.albedo())]
+                .into_iter()
+                .enumerate()
+        {
+            texture.bind_to_unit(unit as u32);
+            self.lighting.set_i32(name, unit as i32);
+        }
+        self.lighting.set_vec3("ambient", self.ambient);
+        self.lighting.set_f32("shininess", self.shininess);
+        draw_fullscreen(&self.vao);
+
+        depth_test(depth_enabled);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn light_count_matches_the_shader() {
+        let src = preprocess_builtin("deferred/lighting.frag").unwrap().source;
+        assert!(src.contains(&format!("const int MAX_LIGHTS = {};", MAX_LIGHTS)));
+    }
+}
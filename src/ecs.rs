@@ -0,0 +1,56 @@
+//! hecs integration: entities with a `Transform` and a `MeshHandle` are drawn by `render`,
+//! with the `PbrMaterial` of their `MaterialHandle` when they have one.
+
+use hecs::{Entity, World};
+
+use crate::material::PbrMaterial;
+use crate::mesh::Mesh;
+use crate::transform::{MODEL_UNIFORM, Transform};
+use crate::wrapper::ShaderProgram;
+
+/// Index into the meshes given to `render`. Components have to be `Send + Sync`, so entities
+/// refer to GL resources by index instead of holding them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MeshHandle(pub usize);
+
+/// Index into the materials given to `render`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MaterialHandle(pub usize);
+
+/// Spawns an entity `render` draws.
+#[allow(dead_code)]
+pub fn spawn_renderable(world: &mut World, transform: Transform, mesh: MeshHandle, material: Option<MaterialHandle>) -> Entity {
+    match material {
+        Some(material) => world.spawn((transform, mesh, material)),
+        None => world.spawn((transform, mesh)),
+    }
+}
+
+/// Draws every entity with a `Transform` and a `MeshHandle` with `program`, which has to be in
+/// use, setting its `model` uniform. Draws are sorted by material so each is bound once,
+/// entities without one come first and use whatever the program had. Handles past the end
+/// of `meshes` or `materials` are skipped.
+#[allow(dead_code)]
+pub fn render(world: &World, program: &ShaderProgram, meshes: &[Mesh], materials: &[PbrMaterial]) {
+    let mut query = world.query::<(&Transform, &MeshHandle, Option<&MaterialHandle>)>();
+    let mut draws: Vec<_> = query
+        .iter()
+        .map(|(_, (transform, mesh, material))| (material.copied(), *mesh, transform.matrix()))
+        .collect();
+    draws.sort_by_key(|&(material, mesh, _)| (material, mesh));
+
+    let mut bound = None;
+    for (material, mesh, model) in draws {
+        let Some(mesh) = meshes.get(mesh.0) else {
+            continue;
+        };
+        if material != bound {
+            if let Some(material) = material.and_then(|material| materials.get(material.0)) {
+                material.bind(program);
+            }
+            bound = material;
+        }
+        program.set_mat4(MODEL_UNIFORM, &model.to_cols_array());
+        mesh.draw();
+    }
+}
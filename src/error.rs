@@ -0,0 +1,124 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+use crate::wrapper::ShaderType;
+
+#[derive(Debug)]
+pub enum WrapperError {
+    /// `glCreate*`/`glGen*` returned 0 for the named kind of object
+    Allocation(&'static str),
+    /// `file` is known when the source was read from disk
+    ShaderCompile { stage: ShaderType, log: String, file: Option<PathBuf> },
+    ProgramLink(String),
+    ContextCreation(String),
+    /// `glCheckFramebufferStatus` result other than `FRAMEBUFFER_COMPLETE`
+    FramebufferIncomplete(gl::types::GLenum),
+    /// feature missing from the current context
+    Unsupported(&'static str),
+    Image { path: PathBuf, source: image::ImageError },
+    Io { path: PathBuf, source: std::io::Error },
+    Gltf(gltf::Error),
+    Watch(notify::Error),
+    /// malformed text asset, `line` is 1-based
+    Parse { line: usize, message: String },
+    /// struct which should mirror a uniform block has a field at the wrong place
+    Std140Layout { field: &'static str, expected: usize, actual: usize },
+    /// bad `#include` in a shader source
+    Preprocess { file: PathBuf, line: usize, message: String },
+    /// TrueType/OpenType data the font parser rejected
+    Font(&'static str),
+    /// scene description file that couldn't be read or written as RON/JSON
+    SceneFile { path: PathBuf, message: String },
+    /// KTX2/DDS container which is malformed or holds data that can't be uploaded
+    TextureFile { path: PathBuf, message: String },
+    /// uniform the program doesn't have, or a value of the wrong type for it
+    Uniform { name: String, message: String },
+    /// render graph whose passes can't be ordered or attached, e.g. a cycle or an unwritten read
+    RenderGraph(String),
+    /// scene hierarchy change which would make a node its own ancestor
+    SceneHierarchy(String),
+}
+
+impl Display for WrapperError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Allocation(object) => write!(f, "Could not allocate {}", object),
+            Self::ShaderCompile { stage, log, file: None } => write!(f, "{:?} Compile Error: {}", stage, log),
+            Self::ShaderCompile { stage, log, file: Some(file) } => {
+                write!(f, "{:?} Compile Error in '{}':\n{}", stage, file.display(), log)
+            }
+            Self::ProgramLink(log) => write!(f, "Program Link Error: {}", log),
+            Self::ContextCreation(msg) => write!(f, "Could not create GL context: {}", msg),
+            Self::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+            Self::FramebufferIncomplete(status) => write!(f, "Framebuffer is incomplete: {}", framebuffer_status_name(*status)),
+            Self::Image { path, source } => write!(f, "Could not load texture '{}': {}", path.display(), source),
+            Self::Io { path, source } => write!(f, "Could not read '{}': {}", path.display(), source),
+            Self::Gltf(err) => write!(f, "Could not import glTF: {}", err),
+            Self::Watch(err) => write!(f, "File watcher failed: {}", err),
+            Self::Parse { line, message } => write!(f, "Parse Error at line {}: {}", line, message),
+            Self::Std140Layout { field, expected, actual } => {
+                write!(f, "std140 mismatch for '{}': expected offset {}, found {}", field, expected, actual)
+            }
+            Self::Preprocess { file, line, message } => write!(f, "{}:{}: {}", file.display(), line, message),
+            Self::Font(msg) => write!(f, "Could not load font: {}", msg),
+            Self::SceneFile { path, message } => write!(f, "Scene file '{}': {}", path.display(), message),
+            Self::TextureFile { path, message } => write!(f, "Texture file '{}': {}", path.display(), message),
+            Self::Uniform { name, message } => write!(f, "Uniform '{}': {}", name, message),
+            Self::RenderGraph(msg) => write!(f, "Render graph: {}", msg),
+            Self::SceneHierarchy(msg) => write!(f, "Scene hierarchy: {}", msg),
+        }
+    }
+}
+
+fn framebuffer_status_name(status: gl::types::GLenum) -> &'static str {
+    match status {
+        gl::FRAMEBUFFER_UNDEFINED => "FRAMEBUFFER_UNDEFINED",
+        gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "FRAMEBUFFER_INCOMPLETE_ATTACHMENT",
+        gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT",
+        gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => "FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER",
+        gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => "FRAMEBUFFER_INCOMPLETE_READ_BUFFER",
+        gl::FRAMEBUFFER_UNSUPPORTED => "FRAMEBUFFER_UNSUPPORTED",
+        gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "FRAMEBUFFER_INCOMPLETE_MULTISAMPLE",
+        gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => "FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS",
+        _ => "unknown status",
+    }
+}
+
+/// Name of a `glGetError` code.
+pub fn gl_error_name(error: gl::types::GLenum) -> &'static str {
+    match error {
+        gl::INVALID_ENUM => "INVALID_ENUM",
+        gl::INVALID_VALUE => "INVALID_VALUE",
+        gl::INVALID_OPERATION => "INVALID_OPERATION",
+        gl::INVALID_FRAMEBUFFER_OPERATION => "INVALID_FRAMEBUFFER_OPERATION",
+        gl::OUT_OF_MEMORY => "OUT_OF_MEMORY",
+        gl::STACK_UNDERFLOW => "STACK_UNDERFLOW",
+        gl::STACK_OVERFLOW => "STACK_OVERFLOW",
+        _ => "unknown error",
+    }
+}
+
+impl Error for WrapperError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Image { source, .. } => Some(source),
+            Self::Io { source, .. } => Some(source),
+            Self::Gltf(err) => Some(err),
+            Self::Watch(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<gltf::Error> for WrapperError {
+    fn from(err: gltf::Error) -> Self {
+        Self::Gltf(err)
+    }
+}
+
+impl From<notify::Error> for WrapperError {
+    fn from(err: notify::Error) -> Self {
+        Self::Watch(err)
+    }
+}
@@ -0,0 +1,94 @@
+//! Window events as every `Backend` reports them, so `InputMap` and `App`s don't depend on
+//! the windowing library. Backends translate their own events into these.
+
+use std::ops::BitOr;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowEvent {
+    Close,
+    /// new size of the default framebuffer in pixels
+    FramebufferSize(i32, i32),
+    Focus(bool),
+    /// `scancode` is the platform's code of the physical key, 0 when the backend has none
+    Key { key: Key, scancode: i32, action: Action, modifiers: Modifiers },
+    MouseButton { button: MouseButton, action: Action, modifiers: Modifiers },
+    /// screen coordinates from the top left corner of the window
+    CursorPos(f64, f64),
+    /// in mouse wheel steps, y positive away from the user
+    Scroll(f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Press,
+    /// key held long enough for the system's key repeat
+    Repeat,
+    Release,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    /// side buttons, usually navigating back and forward
+    Back,
+    Forward,
+}
+
+/// Keys by their position on a US layout, whatever the active layout prints on them.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Num0, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Up, Down, Left, Right,
+    Space, Enter, Escape, Tab, Backspace, Insert, Delete, Home, End, PageUp, PageDown,
+    Minus, Equal, LeftBracket, RightBracket, Backslash, Semicolon, Apostrophe, GraveAccent, Comma, Period, Slash,
+    LeftShift, RightShift, LeftControl, RightControl, LeftAlt, RightAlt, LeftSuper, RightSuper,
+}
+
+/// Modifier keys held during a key or mouse button event, combined with `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers(u8);
+
+#[allow(dead_code)]
+impl Modifiers {
+    pub const SHIFT: Self = Self(1);
+    pub const CONTROL: Self = Self(1 << 1);
+    pub const ALT: Self = Self(1 << 2);
+    /// Windows or Command key
+    pub const SUPER: Self = Self(1 << 3);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether all of `other` are held, true for `empty()`.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn set(&mut self, other: Self, held: bool) {
+        if held {
+            self.0 |= other.0;
+        } else {
+            self.0 &= !other.0;
+        }
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
@@ -0,0 +1,249 @@
+use gl::types::GLuint;
+use glam::{vec2, Mat4, Vec2, Vec3, Vec4Swizzles};
+
+use crate::camera::Camera;
+use crate::error::WrapperError;
+use crate::lighting::{lit_program, PointLight};
+use crate::wrapper::{context_version, ShaderProgram, SsboBuffer};
+
+/// Storage buffer binding points, fixed by `layout (binding = ...)` in `tiled_lights.glsl`.
+pub const POINT_LIGHTS_BINDING: GLuint = 2;
+pub const LIGHT_TILES_BINDING: GLuint = 3;
+pub const LIGHT_INDICES_BINDING: GLuint = 4;
+
+/// Forward+ light culling: the screen is cut into square tiles and every point light is
+/// listed only in the tiles its sphere of influence covers, so a forward shader loops over
+/// the few lights near the pixel instead of all of them. Culling runs on the CPU against
+/// the projected bounds of each light, once per frame after the camera moved. Needs GL 4.3
+/// for the storage buffers.
+pub struct ForwardPlus {
+    /// light every lit pixel gets regardless of the point lights
+    pub ambient: [f32; 3],
+    /// Blinn-Phong exponent of the built-in lit program
+    pub shininess: f32,
+    tile_size: u32,
+    width: u32,
+    height: u32,
+    lights: SsboBuffer<PointLight>,
+    /// (offset into `indices`, count) per tile, row by row from the bottom
+    tiles: SsboBuffer<[u32; 2]>,
+    indices: SsboBuffer<u32>,
+    lit: ShaderProgram,
+    /// light count of every tile of the last `cull`
+    counts: Vec<u32>,
+}
+
+#[allow(dead_code)]
+impl ForwardPlus {
+    pub const DEFAULT_TILE_SIZE: u32 = 16;
+
+    /// `width`/`height` is the framebuffer size the lit geometry is drawn at.
+    pub fn new(width: u32, height: u32) -> Result<Self, WrapperError> {
+        Self::with_tile_size(width, height, Self::DEFAULT_TILE_SIZE)
+    }
+
+    /// Smaller tiles cull tighter but take more CPU time and list memory.
+    pub fn with_tile_size(width: u32, height: u32, tile_size: u32) -> Result<Self, WrapperError> {
+        if context_version() < (4, 3) {
+            return Err(WrapperError::Unsupported("Forward+ light lists need OpenGL 4.3"));
+        }
+
+        let lit = lit_program("forward_plus/lit.frag")?;
+        lit.set_label("forward_plus.lit");
+        lit.use_program();
+        lit.set_vec4("albedo", [1.0, 1.0, 1.0, 1.0]);
+        lit.set_f32("specular", 0.5);
+
+        // storage can't be empty, unused slots are never indexed
+        let lights = SsboBuffer::zeroed(POINT_LIGHTS_BINDING, 1, gl::DYNAMIC_DRAW)?;
+        lights.set_label("forward_plus.lights");
+        let tiles = SsboBuffer::zeroed(LIGHT_TILES_BINDING, 1, gl::DYNAMIC_DRAW)?;
+        tiles.set_label("forward_plus.tiles");
+        let indices = SsboBuffer::zeroed(LIGHT_INDICES_BINDING, 1, gl::DYNAMIC_DRAW)?;
+        indices.set_label("forward_plus.indices");
+
+        Ok(Self {
+            ambient: [0.05, 0.05, 0.05],
+            shininess: 32.0,
+            tile_size: tile_size.max(1),
+            width,
+            height,
+            lights,
+            tiles,
+            indices,
+            lit,
+            counts: Vec::new(),
+        })
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// Tiles across and up the screen.
+    pub fn tile_count(&self) -> (u32, u32) {
+        (self.width.max(1).div_ceil(self.tile_size), self.height.max(1).div_ceil(self.tile_size))
+    }
+
+    /// Lights listed in every tile by the last `cull`, rows from the bottom.
+    pub fn tile_light_counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    /// Bins `lights` (world space) into the tiles as seen by `camera` and uploads the lists.
+    pub fn cull(&mut self, lights: &[PointLight], camera: &Camera) {
+        let (view, projection) = (camera.view_matrix(), camera.projection_matrix());
+        let size = (self.width as f32, self.height as f32);
+        let bounds: Vec<_> = lights.iter().map(|light| screen_bounds(light, view, projection, camera.near, size)).collect();
+        let lists = bin_lights(&bounds, self.tile_size, self.tile_count());
+
+        let mut tiles = Vec::with_capacity(lists.len());
+        let mut indices = Vec::new();
+        for list in &lists {
+            tiles.push([indices.len() as u32, list.len() as u32]);
+            indices.extend_from_slice(list);
+        }
+        self.counts = lists.iter().map(|list| list.len() as u32).collect();
+
+        if indices.is_empty() {
+            indices.push(0);
+        }
+        let lights = if lights.is_empty() { &[bytemuck::Zeroable::zeroed()] } else { lights };
+        self.lights.upload(lights, gl::DYNAMIC_DRAW);
+        self.tiles.upload(&tiles, gl::DYNAMIC_DRAW);
+        self.indices.upload(&indices, gl::DYNAMIC_DRAW);
+    }
+
+    /// Built-in Blinn-Phong program for vertices laid out like `MeshVertex`: set `model`,
+    /// `albedo` (vec4) and `specular` (float) per object.
+    pub fn lit_program(&self) -> &ShaderProgram {
+        &self.lit
+    }
+
+    /// Uses the lit program with the camera and the lists of the last `cull`.
+    pub fn begin(&self, camera: &Camera) {
+        self.lit.use_program();
+        camera.upload(&self.lit);
+        self.lit.set_vec3("camera_position", camera.position.to_array());
+        self.lit.set_vec3("ambient", self.ambient);
+        self.lit.set_f32("shininess", self.shininess);
+        self.bind(&self.lit);
+    }
+
+    /// Sets the tile uniforms a program including `tiled_lights.glsl` needs, it has to be
+    /// in use. The storage buffers stay bound to their fixed binding points.
+    pub fn bind(&self, program: &ShaderProgram) {
+        program.set_u32("tile_size", self.tile_size);
+        program.set_u32("tiles_x", self.tile_count().0);
+    }
+}
+
+/// Pixel rectangle `[min_x, min_y, max_x, max_y]` the light's sphere covers on a screen of
+/// `size`, from the corners of its view space bounding box, `None` when it's off screen.
+fn screen_bounds(light: &PointLight, view: Mat4, projection: Mat4, near: f32, size: (f32, f32)) -> Option<[f32; 4]> {
+    let (width, height) = size;
+    let center = view.transform_point3(Vec3::from_array(light.position));
+    let radius = light.radius;
+
+    if center.z - radius > -near {
+        return None;
+    }
+    if center.z + radius > -near {
+        // reaches behind the near plane, where projecting corners breaks down
+        return Some([0.0, 0.0, width, height]);
+    }
+
+    let (mut min, mut max) = (Vec2::INFINITY, Vec2::NEG_INFINITY);
+    for corner in 0..8 {
+        let sign = |bit: u32| if corner & bit != 0 { 1.0 } else { -1.0 };
+        let point = center + Vec3::new(sign(1), sign(2), sign(4)) * radius;
+        let clip = projection * point.extend(1.0);
+        let ndc = clip.xy() / clip.w;
+        min = min.min(ndc);
+        max = max.max(ndc);
+    }
+
+    if max.x < -1.0 || max.y < -1.0 || min.x > 1.0 || min.y > 1.0 {
+        return None;
+    }
+    let to_pixels = |ndc: Vec2| (ndc.clamp(Vec2::NEG_ONE, Vec2::ONE) * 0.5 + 0.5) * vec2(width, height);
+    let (min, max) = (to_pixels(min), to_pixels(max));
+    Some([min.x, min.y, max.x, max.y])
+}
+
+/// Indices of the lights overlapping every tile, rows from the bottom, given the pixel
+/// bounds of each light from `screen_bounds`.
+fn bin_lights(bounds: &[Option<[f32; 4]>], tile_size: u32, (tiles_x, tiles_y): (u32, u32)) -> Vec<Vec<u32>> {
+    let mut lists: Vec<Vec<u32>> = vec![Vec::new(); (tiles_x * tiles_y) as usize];
+    // a bound on the far edge of the screen still falls in the last tile
+    let tile = |pixel: f32, tiles: u32| ((pixel.max(0.0) as u32) / tile_size).min(tiles - 1);
+
+    for (i, bounds) in bounds.iter().enumerate() {
+        let Some([min_x, min_y, max_x, max_y]) = *bounds else {
+            continue;
+        };
+        for y in tile(min_y, tiles_y)..=tile(max_y, tiles_y) {
+            for x in tile(min_x, tiles_x)..=tile(max_x, tiles_x) {
+                lists[(y * tiles_x + x) as usize].push(i as u32);
+            }
+        }
+    }
+    lists
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: (f32, f32) = (64.0, 32.0);
+
+    fn bounds_of(position: Vec3, radius: f32) -> Option<[f32; 4]> {
+        let projection = Mat4::perspective_rh_gl(90f32.to_radians(), SIZE.0 / SIZE.1, 0.1, 100.0);
+        screen_bounds(&PointLight::new(position, [1.0; 3], radius), Mat4::IDENTITY, projection, 0.1, SIZE)
+    }
+
+    #[test]
+    fn lights_in_front_are_bounded_around_their_center() {
+        let [min_x, min_y, max_x, max_y] = bounds_of(Vec3::new(0.0, 0.0, -10.0), 1.0).unwrap();
+        assert!(min_x > 0.0 && max_x < SIZE.0 && min_y > 0.0 && max_y < SIZE.1);
+        assert!(((min_x + max_x) / 2.0 - SIZE.0 / 2.0).abs() < 1e-3);
+        assert!(((min_y + max_y) / 2.0 - SIZE.1 / 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn lights_across_the_near_plane_cover_the_screen() {
+        assert_eq!(bounds_of(Vec3::new(0.0, 0.0, 0.5), 1.0), Some([0.0, 0.0, SIZE.0, SIZE.1]));
+        // conservative, even beside the camera
+        assert_eq!(bounds_of(Vec3::new(5.0, 0.0, 0.0), 1.0), Some([0.0, 0.0, SIZE.0, SIZE.1]));
+        // entirely behind it
+        assert_eq!(bounds_of(Vec3::new(0.0, 0.0, 2.0), 1.0), None);
+    }
+
+    #[test]
+    fn lights_off_screen_are_rejected() {
+        assert_eq!(bounds_of(Vec3::new(-100.0, 0.0, -10.0), 1.0), None);
+        assert_eq!(bounds_of(Vec3::new(0.0, 50.0, -10.0), 1.0), None);
+        // partly visible ones are clamped to the screen
+        let [min_x, _, max_x, _] = bounds_of(Vec3::new(-20.0, 0.0, -10.0), 1.5).unwrap();
+        assert_eq!(min_x, 0.0);
+        assert!(max_x > 0.0);
+    }
+
+    #[test]
+    fn bins_clamp_to_the_last_tile() {
+        let bounds = [Some([0.0, 0.0, 64.0, 32.0]), None, Some([-5.0, 17.0, 15.9, 31.0]), Some([63.0, 0.0, 64.0, 0.0])];
+        let lists = bin_lights(&bounds, 16, (4, 2));
+        assert_eq!(lists.len(), 8);
+        assert!(lists.iter().all(|list| list.first() == Some(&0)));
+        assert!(lists.iter().all(|list| !list.contains(&1)));
+        // rows from the bottom, so y 17..31 is the second row
+        let with = |light: u32| (0..8).filter(|&tile| lists[tile].contains(&light)).collect::<Vec<_>>();
+        assert_eq!(with(2), [4]);
+        assert_eq!(with(3), [3]);
+    }
+}
@@ -0,0 +1,407 @@
+use gl::types::{GLbitfield, GLenum, GLint, GLsizei, GLuint};
+
+use crate::capture;
+use crate::error::WrapperError;
+use crate::wrapper::{Cubemap, object_label, Texture2D, TextureFormat};
+
+pub struct Renderbuffer(pub GLuint);
+
+#[allow(dead_code)]
+impl Renderbuffer {
+    pub fn new() -> Option<Self> {
+        let mut rbo = 0;
+        unsafe {
+            crate::checked!(gl::GenRenderbuffers(1, &mut rbo));
+        }
+
+        if rbo != 0 {
+            Some(Self(rbo))
+        } else {
+            None
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe { crate::checked!(gl::BindRenderbuffer(gl::RENDERBUFFER, self.0)) }
+    }
+
+    pub fn storage(&self, width: u32, height: u32, format: TextureFormat) {
+        self.storage_multisample(width, height, format, 0);
+    }
+
+    /// `samples` of 0 allocates a regular single-sample renderbuffer.
+    pub fn storage_multisample(&self, width: u32, height: u32, format: TextureFormat, samples: u32) {
+        self.bind();
+        unsafe {
+            crate::checked!(gl::RenderbufferStorageMultisample(
+                gl::RENDERBUFFER,
+                samples as GLsizei,
+                format.internal_format(),
+                width.try_into().unwrap(),
+                height.try_into().unwrap(),
+            ));
+        }
+    }
+
+    pub fn set_label(&self, label: &str) {
+        object_label(gl::RENDERBUFFER, self.0, label);
+    }
+
+    /// Gives up ownership of the GL object: it won't be deleted on drop.
+    pub fn leak(self) -> GLuint {
+        let id = self.0;
+        std::mem::forget(self);
+        id
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteRenderbuffers(1, &self.0) }
+    }
+}
+
+/// Textures can be sampled after rendering, renderbuffers can't but may be faster to render into.
+/// Cubemaps are attached layered, a geometry shader picks the face with `gl_Layer`.
+pub enum Attachment {
+    Texture(Texture2D),
+    Renderbuffer(Renderbuffer),
+    Cubemap(Cubemap),
+}
+
+impl Attachment {
+    fn allocate(&self, width: u32, height: u32, format: TextureFormat, samples: u32) {
+        match self {
+            Self::Texture(texture) => texture.allocate(width, height, format),
+            Self::Renderbuffer(rbo) => rbo.storage_multisample(width, height, format, samples),
+            Self::Cubemap(cubemap) => cubemap.allocate(width, format),
+        }
+    }
+
+    fn set_label(&self, label: &str) {
+        match self {
+            Self::Texture(texture) => texture.set_label(label),
+            Self::Renderbuffer(rbo) => rbo.set_label(label),
+            Self::Cubemap(cubemap) => cubemap.set_label(label),
+        }
+    }
+
+    fn attach(&self, point: GLenum) {
+        unsafe {
+            match self {
+                Self::Texture(texture) => {
+                    crate::checked!(gl::FramebufferTexture2D(gl::FRAMEBUFFER, point, gl::TEXTURE_2D, texture.0, 0))
+                }
+                Self::Renderbuffer(rbo) => {
+                    crate::checked!(gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, point, gl::RENDERBUFFER, rbo.0))
+                }
+                Self::Cubemap(cubemap) => crate::checked!(gl::FramebufferTexture(gl::FRAMEBUFFER, point, cubemap.0, 0)),
+            }
+        }
+    }
+}
+
+/// Render target made of color attachments (`COLOR_ATTACHMENT0..`) in the order they were
+/// added to the builder and an optional depth (or depth-stencil) attachment.
+pub struct Framebuffer {
+    id: GLuint,
+    width: u32,
+    height: u32,
+    samples: u32,
+    colors: Vec<(TextureFormat, Attachment)>,
+    depth: Option<(TextureFormat, Attachment)>,
+}
+
+#[allow(dead_code)]
+impl Framebuffer {
+    /// ```ignore
+    /// let fbo = Framebuffer::builder(800, 600)
+    ///     .color_texture(TextureFormat::Rgba8)
+    ///     .depth_renderbuffer(TextureFormat::Depth24Stencil8)
+    ///     .build()?;
+    /// ```
+    pub fn builder(width: u32, height: u32) -> FramebufferBuilder {
+        FramebufferBuilder { width, height, samples: 0, colors: Vec::new(), depth: None }
+    }
+
+    /// Makes the framebuffer the render target and sets viewport to its size.
+    pub fn bind(&self) {
+        unsafe {
+            crate::checked!(gl::BindFramebuffer(gl::FRAMEBUFFER, self.id));
+            crate::checked!(gl::Viewport(0, 0, self.width as GLint, self.height as GLint));
+        }
+    }
+
+    /// Reallocates all attachments, their previous contents are lost.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == self.width && height == self.height {
+            return;
+        }
+
+        self.width = width;
+        self.height = height;
+
+        for (format, attachment) in self.colors.iter().chain(self.depth.iter()) {
+            attachment.allocate(width, height, *format, self.samples);
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Labels the framebuffer and its attachments as `label.color0`, `label.depth`, ...
+    pub fn set_label(&self, label: &str) {
+        object_label(gl::FRAMEBUFFER, self.id, label);
+
+        for (i, (_, attachment)) in self.colors.iter().enumerate() {
+            attachment.set_label(&format!("{}.color{}", label, i));
+        }
+        if let Some((_, attachment)) = &self.depth {
+            attachment.set_label(&format!("{}.depth", label));
+        }
+    }
+
+    pub fn color_texture(&self, index: usize) -> Option<&Texture2D> {
+        match self.colors.get(index) {
+            Some((_, Attachment::Texture(texture))) => Some(texture),
+            _ => None,
+        }
+    }
+
+    pub fn depth_texture(&self) -> Option<&Texture2D> {
+        match &self.depth {
+            Some((_, Attachment::Texture(texture))) => Some(texture),
+            _ => None,
+        }
+    }
+
+    pub fn depth_cubemap(&self) -> Option<&Cubemap> {
+        match &self.depth {
+            Some((_, Attachment::Cubemap(cubemap))) => Some(cubemap),
+            _ => None,
+        }
+    }
+
+    pub fn color_format(&self, index: usize) -> Option<TextureFormat> {
+        self.colors.get(index).map(|(format, _)| *format)
+    }
+
+    pub fn color_count(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// RGBA8 pixels of color attachment `index`, bottom row first. Multisampled framebuffers
+    /// have to be resolved into a single-sample one first.
+    pub fn read_pixels(&self, index: usize) -> Vec<u8> {
+        unsafe {
+            crate::checked!(gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.id));
+            crate::checked!(gl::ReadBuffer(gl::COLOR_ATTACHMENT0 + index as GLenum));
+        }
+        let pixels = capture::read_pixels(0, 0, self.width, self.height);
+        unsafe { crate::checked!(gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0)) };
+
+        pixels
+    }
+
+    /// Writes color attachment `index` to an image file, the format follows the extension.
+    pub fn save(&self, index: usize, path: impl AsRef<std::path::Path>) -> Result<(), WrapperError> {
+        capture::save_pixels(path, self.read_pixels(index), self.width, self.height)
+    }
+
+    /// 0 when the framebuffer isn't multisampled.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn is_multisampled(&self) -> bool {
+        self.samples > 1
+    }
+
+    /// Resolves (or just copies) the first color attachment, and depth when both framebuffers
+    /// have one, into `target`. Sizes may differ, depth is then left out as it can't be scaled.
+    pub fn resolve(&self, target: &Framebuffer) {
+        let mut mask = gl::COLOR_BUFFER_BIT;
+        let same_size = self.width == target.width && self.height == target.height;
+        if same_size && self.depth.is_some() && target.depth.is_some() {
+            mask |= gl::DEPTH_BUFFER_BIT;
+        }
+
+        self.blit(target.id, target.width as GLint, target.height as GLint, mask);
+    }
+
+    /// Copies the depth (and stencil) into `target`, e.g. so forward drawn geometry is tested
+    /// against a deferred scene. Size, depth format and sample count have to match.
+    pub fn copy_depth(&self, target: &Framebuffer) {
+        let mut mask = gl::DEPTH_BUFFER_BIT;
+        if self.depth.as_ref().is_some_and(|(format, _)| format.has_stencil()) {
+            mask |= gl::STENCIL_BUFFER_BIT;
+        }
+        self.blit(target.id, target.width as GLint, target.height as GLint, mask);
+    }
+
+    /// Resolves the first color attachment into the window, `width`/`height` is the
+    /// window framebuffer size.
+    pub fn resolve_to_default(&self, width: i32, height: i32) {
+        self.blit(0, width, height, gl::COLOR_BUFFER_BIT);
+    }
+
+    fn blit(&self, target: GLuint, width: GLint, height: GLint, mask: GLbitfield) {
+        let (src_width, src_height) = (self.width as GLint, self.height as GLint);
+        // multisample resolve needs matching sizes, depth can't be filtered
+        let filter = if (src_width, src_height) == (width, height) || mask & gl::DEPTH_BUFFER_BIT != 0 {
+            gl::NEAREST
+        } else {
+            gl::LINEAR
+        };
+
+        unsafe {
+            crate::checked!(gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.id));
+            crate::checked!(gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, target));
+            crate::checked!(gl::ReadBuffer(gl::COLOR_ATTACHMENT0));
+            crate::checked!(gl::BlitFramebuffer(0, 0, src_width, src_height, 0, 0, width, height, mask, filter));
+            crate::checked!(gl::BindFramebuffer(gl::FRAMEBUFFER, 0));
+        }
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteFramebuffers(1, &self.id) }
+    }
+}
+
+/// Renders to the window again, viewport is reset to the given window framebuffer size.
+#[allow(dead_code)]
+pub fn bind_default_framebuffer(width: i32, height: i32) {
+    unsafe {
+        crate::checked!(gl::BindFramebuffer(gl::FRAMEBUFFER, 0));
+        crate::checked!(gl::Viewport(0, 0, width, height));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttachmentKind {
+    Texture,
+    Renderbuffer,
+    Cubemap,
+}
+
+pub struct FramebufferBuilder {
+    width: u32,
+    height: u32,
+    samples: u32,
+    colors: Vec<(TextureFormat, AttachmentKind)>,
+    depth: Option<(TextureFormat, AttachmentKind)>,
+}
+
+#[allow(dead_code)]
+impl FramebufferBuilder {
+    /// Allocates every attachment with `samples` samples. Only renderbuffer attachments can be
+    /// multisampled, resolve into a texture framebuffer to sample the result.
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.samples = if samples > 1 { samples } else { 0 };
+        self
+    }
+
+    pub fn color_texture(mut self, format: TextureFormat) -> Self {
+        self.colors.push((format, AttachmentKind::Texture));
+        self
+    }
+
+    pub fn color_renderbuffer(mut self, format: TextureFormat) -> Self {
+        self.colors.push((format, AttachmentKind::Renderbuffer));
+        self
+    }
+
+    pub fn depth_texture(mut self, format: TextureFormat) -> Self {
+        self.depth = Some((format, AttachmentKind::Texture));
+        self
+    }
+
+    pub fn depth_renderbuffer(mut self, format: TextureFormat) -> Self {
+        self.depth = Some((format, AttachmentKind::Renderbuffer));
+        self
+    }
+
+    /// Layered depth cubemap, e.g. point light shadows. Width and height have to be equal.
+    pub fn depth_cubemap(mut self, format: TextureFormat) -> Self {
+        self.depth = Some((format, AttachmentKind::Cubemap));
+        self
+    }
+
+    pub fn build(self) -> Result<Framebuffer, WrapperError> {
+        let mut kinds = self.colors.iter().chain(self.depth.iter()).map(|&(_, kind)| kind);
+        if self.samples > 0 && kinds.any(|kind| kind != AttachmentKind::Renderbuffer) {
+            return Err(WrapperError::Unsupported("multisampled texture attachments"));
+        }
+        let has_cubemap = self.colors.iter().chain(self.depth.iter()).any(|&(_, kind)| kind == AttachmentKind::Cubemap);
+        if has_cubemap && self.width != self.height {
+            return Err(WrapperError::Unsupported("cubemap attachments with differing width and height"));
+        }
+
+        let make = |(format, kind): (TextureFormat, AttachmentKind)| -> Result<(TextureFormat, Attachment), WrapperError> {
+            let attachment = match kind {
+                AttachmentKind::Texture => Attachment::Texture(Texture2D::empty(self.width, self.height, format)?),
+                AttachmentKind::Renderbuffer => {
+                    let rbo = Renderbuffer::new().ok_or(WrapperError::Allocation("renderbuffer"))?;
+                    rbo.storage_multisample(self.width, self.height, format, self.samples);
+                    Attachment::Renderbuffer(rbo)
+                }
+                AttachmentKind::Cubemap => Attachment::Cubemap(Cubemap::empty(self.width, format)?),
+            };
+            Ok((format, attachment))
+        };
+
+        let colors = self.colors.iter().copied().map(make).collect::<Result<Vec<_>, _>>()?;
+        let depth = self.depth.map(make).transpose()?;
+
+        let mut id = 0;
+        unsafe { crate::checked!(gl::GenFramebuffers(1, &mut id)) };
+        if id == 0 {
+            return Err(WrapperError::Allocation("framebuffer"));
+        }
+
+        let framebuffer = Framebuffer { id, width: self.width, height: self.height, samples: self.samples, colors, depth };
+        unsafe { crate::checked!(gl::BindFramebuffer(gl::FRAMEBUFFER, id)) };
+
+        let mut draw_buffers = Vec::with_capacity(framebuffer.colors.len());
+        for (i, (_, attachment)) in framebuffer.colors.iter().enumerate() {
+            let point = gl::COLOR_ATTACHMENT0 + i as GLenum;
+            attachment.attach(point);
+            draw_buffers.push(point);
+        }
+
+        if let Some((format, attachment)) = &framebuffer.depth {
+            let point = if format.has_stencil() { gl::DEPTH_STENCIL_ATTACHMENT } else { gl::DEPTH_ATTACHMENT };
+            attachment.attach(point);
+        }
+
+        unsafe {
+            if draw_buffers.is_empty() {
+                // depth only, e.g. shadow map
+                crate::checked!(gl::DrawBuffer(gl::NONE));
+                crate::checked!(gl::ReadBuffer(gl::NONE));
+            } else {
+                crate::checked!(gl::DrawBuffers(draw_buffers.len() as GLint, draw_buffers.as_ptr()));
+            }
+        }
+
+        let status = unsafe { crate::checked!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER)) };
+        unsafe { crate::checked!(gl::BindFramebuffer(gl::FRAMEBUFFER, 0)) };
+
+        if status == gl::FRAMEBUFFER_COMPLETE {
+            Ok(framebuffer)
+        } else {
+            Err(WrapperError::FramebufferIncomplete(status))
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! GL 4.6 and extension entry points and enums missing from the 4.5 core bindings of the `gl`
+//! crate. Loaded next to them with `load_with`, using the same loader.
+
+#![allow(non_snake_case)]
+
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use gl::types::{GLchar, GLenum, GLuint};
+
+/// GL 4.6, ARB_gl_spirv
+pub const SHADER_BINARY_FORMAT_SPIR_V: GLenum = 0x9551;
+
+/// GL 4.6, EXT/ARB_texture_filter_anisotropic
+pub const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+pub const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+/// EXT_texture_compression_s3tc
+pub const COMPRESSED_RGB_S3TC_DXT1: GLenum = 0x83F0;
+pub const COMPRESSED_RGBA_S3TC_DXT1: GLenum = 0x83F1;
+pub const COMPRESSED_RGBA_S3TC_DXT3: GLenum = 0x83F2;
+pub const COMPRESSED_RGBA_S3TC_DXT5: GLenum = 0x83F3;
+/// EXT_texture_sRGB, EXT_texture_compression_s3tc_srgb
+pub const COMPRESSED_SRGB_S3TC_DXT1: GLenum = 0x8C4C;
+pub const COMPRESSED_SRGB_ALPHA_S3TC_DXT1: GLenum = 0x8C4D;
+pub const COMPRESSED_SRGB_ALPHA_S3TC_DXT3: GLenum = 0x8C4E;
+pub const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: GLenum = 0x8C4F;
+
+static SPECIALIZE_SHADER: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+
+/// Loads the entry points, for the current context like `gl::load_with`. Names are tried
+/// core first, then with their extension suffix.
+pub fn load_with(mut loadfn: impl FnMut(&'static str) -> *const c_void) {
+    let mut load = |names: &[&'static str]| {
+        names.iter().map(|&name| loadfn(name)).find(|ptr| !ptr.is_null()).unwrap_or(std::ptr::null())
+    };
+
+    SPECIALIZE_SHADER.store(load(&["glSpecializeShader", "glSpecializeShaderARB"]) as *mut _, Ordering::Relaxed);
+}
+
+pub mod SpecializeShader {
+    use std::sync::atomic::Ordering;
+
+    pub fn is_loaded() -> bool {
+        !super::SPECIALIZE_SHADER.load(Ordering::Relaxed).is_null()
+    }
+}
+
+/// # Safety
+/// Same as calling `glSpecializeShader` directly, `SpecializeShader::is_loaded` has to be true.
+pub unsafe fn SpecializeShader(
+    shader: GLuint,
+    entry_point: *const GLchar,
+    num_constants: GLuint,
+    constant_index: *const GLuint,
+    constant_value: *const GLuint,
+) {
+    let ptr = SPECIALIZE_SHADER.load(Ordering::Relaxed);
+    assert!(!ptr.is_null(), "glSpecializeShader is not loaded");
+
+    let function: extern "system" fn(GLuint, *const GLchar, GLuint, *const GLuint, *const GLuint) =
+        std::mem::transmute(ptr);
+    function(shader, entry_point, num_constants, constant_index, constant_value)
+}
@@ -0,0 +1,168 @@
+use glfw::Context;
+
+use crate::backend::Backend;
+use crate::event::{Action, Key, Modifiers, MouseButton, WindowEvent};
+use crate::wrapper::Setup;
+
+impl Backend for Setup {
+    fn should_close(&self) -> bool {
+        self.window.should_close()
+    }
+
+    fn set_should_close(&mut self, close: bool) {
+        self.window.set_should_close(close);
+    }
+
+    fn framebuffer_size(&self) -> (i32, i32) {
+        self.window.get_framebuffer_size()
+    }
+
+    fn poll_events(&mut self) -> Vec<WindowEvent> {
+        self.window.glfw.poll_events();
+        glfw::flush_messages(&self.events).filter_map(|(_, event)| event_from(event)).collect()
+    }
+
+    fn swap_buffers(&mut self) {
+        self.window.swap_buffers();
+    }
+}
+
+/// `None` for the GLFW events backends don't report, and for keys and buttons without a name.
+#[allow(dead_code)]
+pub fn event_from(event: glfw::WindowEvent) -> Option<WindowEvent> {
+    Some(match event {
+        glfw::WindowEvent::Close => WindowEvent::Close,
+        glfw::WindowEvent::FramebufferSize(width, height) => WindowEvent::FramebufferSize(width, height),
+        glfw::WindowEvent::Focus(focused) => WindowEvent::Focus(focused),
+        glfw::WindowEvent::Key(key, scancode, action, modifiers) => WindowEvent::Key {
+            key: key_from(key)?,
+            scancode,
+            action: action_from(action),
+            modifiers: modifiers_from(modifiers),
+        },
+        glfw::WindowEvent::MouseButton(button, action, modifiers) => WindowEvent::MouseButton {
+            button: mouse_button_from(button)?,
+            action: action_from(action),
+            modifiers: modifiers_from(modifiers),
+        },
+        glfw::WindowEvent::CursorPos(x, y) => WindowEvent::CursorPos(x, y),
+        glfw::WindowEvent::Scroll(x, y) => WindowEvent::Scroll(x, y),
+        _ => return None,
+    })
+}
+
+fn action_from(action: glfw::Action) -> Action {
+    match action {
+        glfw::Action::Press => Action::Press,
+        glfw::Action::Repeat => Action::Repeat,
+        glfw::Action::Release => Action::Release,
+    }
+}
+
+fn modifiers_from(glfw_modifiers: glfw::Modifiers) -> Modifiers {
+    let mut modifiers = Modifiers::empty();
+    modifiers.set(Modifiers::SHIFT, glfw_modifiers.contains(glfw::Modifiers::Shift));
+    modifiers.set(Modifiers::CONTROL, glfw_modifiers.contains(glfw::Modifiers::Control));
+    modifiers.set(Modifiers::ALT, glfw_modifiers.contains(glfw::Modifiers::Alt));
+    modifiers.set(Modifiers::SUPER, glfw_modifiers.contains(glfw::Modifiers::Super));
+    modifiers
+}
+
+fn mouse_button_from(button: glfw::MouseButton) -> Option<MouseButton> {
+    match button {
+        glfw::MouseButton::Button1 => Some(MouseButton::Left),
+        glfw::MouseButton::Button2 => Some(MouseButton::Right),
+        glfw::MouseButton::Button3 => Some(MouseButton::Middle),
+        glfw::MouseButton::Button4 => Some(MouseButton::Back),
+        glfw::MouseButton::Button5 => Some(MouseButton::Forward),
+        _ => None,
+    }
+}
+
+fn key_from(key: glfw::Key) -> Option<Key> {
+    Some(match key {
+        glfw::Key::A => Key::A,
+        glfw::Key::B => Key::B,
+        glfw::Key::C => Key::C,
+        glfw::Key::D => Key::D,
+        glfw::Key::E => Key::E,
+        glfw::Key::F => Key::F,
+        glfw::Key::G => Key::G,
+        glfw::Key::H => Key::H,
+        glfw::Key::I => Key::I,
+        glfw::Key::J => Key::J,
+        glfw::Key::K => Key::K,
+        glfw::Key::L => Key::L,
+        glfw::Key::M => Key::M,
+        glfw::Key::N => Key::N,
+        glfw::Key::O => Key::O,
+        glfw::Key::P => Key::P,
+        glfw::Key::Q => Key::Q,
+        glfw::Key::R => Key::R,
+        glfw::Key::S => Key::S,
+        glfw::Key::T => Key::T,
+        glfw::Key::U => Key::U,
+        glfw::Key::V => Key::V,
+        glfw::Key::W => Key::W,
+        glfw::Key::X => Key::X,
+        glfw::Key::Y => Key::Y,
+        glfw::Key::Z => Key::Z,
+        glfw::Key::Num0 => Key::Num0,
+        glfw::Key::Num1 => Key::Num1,
+        glfw::Key::Num2 => Key::Num2,
+        glfw::Key::Num3 => Key::Num3,
+        glfw::Key::Num4 => Key::Num4,
+        glfw::Key::Num5 => Key::Num5,
+        glfw::Key::Num6 => Key::Num6,
+        glfw::Key::Num7 => Key::Num7,
+        glfw::Key::Num8 => Key::Num8,
+        glfw::Key::Num9 => Key::Num9,
+        glfw::Key::F1 => Key::F1,
+        glfw::Key::F2 => Key::F2,
+        glfw::Key::F3 => Key::F3,
+        glfw::Key::F4 => Key::F4,
+        glfw::Key::F5 => Key::F5,
+        glfw::Key::F6 => Key::F6,
+        glfw::Key::F7 => Key::F7,
+        glfw::Key::F8 => Key::F8,
+        glfw::Key::F9 => Key::F9,
+        glfw::Key::F10 => Key::F10,
+        glfw::Key::F11 => Key::F11,
+        glfw::Key::F12 => Key::F12,
+        glfw::Key::Up => Key::Up,
+        glfw::Key::Down => Key::Down,
+        glfw::Key::Left => Key::Left,
+        glfw::Key::Right => Key::Right,
+        glfw::Key::Space => Key::Space,
+        glfw::Key::Enter => Key::Enter,
+        glfw::Key::Escape => Key::Escape,
+        glfw::Key::Tab => Key::Tab,
+        glfw::Key::Backspace => Key::Backspace,
+        glfw::Key::Insert => Key::Insert,
+        glfw::Key::Delete => Key::Delete,
+        glfw::Key::Home => Key::Home,
+        glfw::Key::End => Key::End,
+        glfw::Key::PageUp => Key::PageUp,
+        glfw::Key::PageDown => Key::PageDown,
+        glfw::Key::Minus => Key::Minus,
+        glfw::Key::Equal => Key::Equal,
+        glfw::Key::LeftBracket => Key::LeftBracket,
+        glfw::Key::RightBracket => Key::RightBracket,
+        glfw::Key::Backslash => Key::Backslash,
+        glfw::Key::Semicolon => Key::Semicolon,
+        glfw::Key::Apostrophe => Key::Apostrophe,
+        glfw::Key::GraveAccent => Key::GraveAccent,
+        glfw::Key::Comma => Key::Comma,
+        glfw::Key::Period => Key::Period,
+        glfw::Key::Slash => Key::Slash,
+        glfw::Key::LeftShift => Key::LeftShift,
+        glfw::Key::RightShift => Key::RightShift,
+        glfw::Key::LeftControl => Key::LeftControl,
+        glfw::Key::RightControl => Key::RightControl,
+        glfw::Key::LeftAlt => Key::LeftAlt,
+        glfw::Key::RightAlt => Key::RightAlt,
+        glfw::Key::LeftSuper => Key::LeftSuper,
+        glfw::Key::RightSuper => Key::RightSuper,
+        _ => return None,
+    })
+}
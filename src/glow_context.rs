@@ -0,0 +1,50 @@
+//! A `glow` context for the current GL context, for application code that has to run on
+//! OpenGL ES or WebGL as well. The wrapper types don't go through it, they call the `gl` crate.
+
+use std::ffi::c_void;
+
+use glow::HasContext;
+
+#[cfg(feature = "glfw")]
+use crate::wrapper::Setup;
+
+/// glow context of the current GL context.
+pub struct Gl {
+    context: glow::Context,
+}
+
+#[allow(dead_code)]
+impl Gl {
+    /// Loads the functions of the current context with `loadfn`, like `gl::load_with`, e.g.
+    /// from SDL's `gl_get_proc_address` or glutin's `get_proc_address`.
+    pub fn load_with(loadfn: impl FnMut(&str) -> *const c_void) -> Self {
+        let context = unsafe { glow::Context::from_loader_function(loadfn) };
+        Self { context }
+    }
+
+    /// Loads the functions of the context `setup` made current.
+    #[cfg(feature = "glfw")]
+    pub fn new(setup: &mut Setup) -> Self {
+        let window = &mut setup.window;
+        Self::load_with(|symbol| window.get_proc_address(symbol) as *const _)
+    }
+
+    /// Wraps a context created elsewhere, like a WebGL one.
+    pub fn from_context(context: glow::Context) -> Self {
+        Self { context }
+    }
+
+    pub fn context(&self) -> &glow::Context {
+        &self.context
+    }
+
+    pub fn version(&self) -> (u32, u32) {
+        let version = self.context.version();
+        (version.major, version.minor)
+    }
+
+    /// OpenGL ES or WebGL rather than desktop GL.
+    pub fn is_embedded(&self) -> bool {
+        self.context.version().is_embedded
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use crate::error::WrapperError;
+use crate::wrapper::Query;
+
+/// GPU time of the commands between `begin` and `end`, measured by `GL_TIME_ELAPSED` queries.
+/// Two queries take turns, so reading the last result waits on a frame the GPU has most
+/// likely finished already: `elapsed` lags one measurement behind.
+pub struct GpuTimer {
+    queries: [Query; 2],
+    /// query the next `begin` uses
+    current: usize,
+    pending: [bool; 2],
+    running: bool,
+    elapsed: Option<Duration>,
+}
+
+#[allow(dead_code)]
+impl GpuTimer {
+    pub fn new() -> Result<Self, WrapperError> {
+        let query = || Query::new().ok_or(WrapperError::Allocation("timer query"));
+        Ok(Self { queries: [query()?, query()?], current: 0, pending: [false; 2], running: false, elapsed: None })
+    }
+
+    /// Starts timing. Timers can't be nested or overlap each other, GL allows one active
+    /// `TIME_ELAPSED` query at a time.
+    pub fn begin(&mut self) {
+        debug_assert!(!self.running, "GpuTimer::begin called twice without end");
+        // the query is about to be reused, take its result first
+        if self.pending[self.current] {
+            self.elapsed = Some(Duration::from_nanos(self.queries[self.current].result()));
+            self.pending[self.current] = false;
+        }
+
+        self.queries[self.current].begin(gl::TIME_ELAPSED);
+        self.running = true;
+    }
+
+    pub fn end(&mut self) {
+        if !self.running {
+            return;
+        }
+        Query::end(gl::TIME_ELAPSED);
+        self.running = false;
+        self.pending[self.current] = true;
+        self.current = 1 - self.current;
+
+        // the older measurement is usually complete by now, pick it up without waiting
+        let other = self.current;
+        if self.pending[other] && self.queries[other].is_available() {
+            self.elapsed = Some(Duration::from_nanos(self.queries[other].result()));
+            self.pending[other] = false;
+        }
+    }
+
+    /// Latest completed measurement, `None` until one is.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.elapsed
+    }
+
+    /// `elapsed` in milliseconds, 0 until a measurement completed.
+    pub fn elapsed_ms(&self) -> f32 {
+        self.elapsed.map(|elapsed| elapsed.as_secs_f32() * 1000.0).unwrap_or(0.0)
+    }
+
+    pub fn set_label(&self, label: &str) {
+        for (i, query) in self.queries.iter().enumerate() {
+            query.set_label(&format!("{}[{}]", label, i));
+        }
+    }
+}
+
+/// Current GPU clock in nanoseconds, comparable to `Query::timestamp` results.
+/// Unlike those it's taken immediately, not once the queued commands completed.
+#[allow(dead_code)]
+pub fn gpu_timestamp() -> u64 {
+    let mut timestamp = 0;
+    unsafe { crate::checked!(gl::GetInteger64v(gl::TIMESTAMP, &mut timestamp)) };
+    timestamp as u64
+}
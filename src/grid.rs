@@ -0,0 +1,95 @@
+use glam::Mat4;
+
+use crate::camera::Camera;
+use crate::debug_draw::DebugDraw;
+use crate::error::WrapperError;
+use crate::postprocess::{draw_fullscreen, FULLSCREEN_VERT};
+use crate::wrapper::{BlendState, depth_write, ShaderProgram, VertexArray};
+
+const GRID_FRAG: &str = include_str!("../shaders/src/grid/grid.frag");
+
+/// Ground grid on the y = 0 plane reaching to the horizon, fading out with the distance to
+/// the camera, with the x and z axes drawn through it and a small XYZ axis marker at the
+/// origin. Drawn per pixel from a fullscreen triangle, so it costs the same at any spacing.
+/// Draw it after the opaque geometry: it depth tests against it without writing depth.
+pub struct Grid {
+    pub enabled: bool,
+    /// world units between the minor lines
+    pub spacing: f32,
+    /// minor cells per major line
+    pub major_every: u32,
+    /// distance from the camera at which the grid has faded out completely
+    pub fade_distance: f32,
+    pub minor_color: [f32; 4],
+    pub major_color: [f32; 4],
+    /// red x and blue z axis on the grid, plus the marker at the origin
+    pub show_axes: bool,
+    /// length of the marker's arms, 0 leaves it out
+    pub axis_length: f32,
+    program: ShaderProgram,
+    vao: VertexArray,
+    axes: DebugDraw,
+}
+
+#[allow(dead_code)]
+impl Grid {
+    pub const X_AXIS_COLOR: [f32; 4] = [0.9, 0.2, 0.2, 1.0];
+    pub const Z_AXIS_COLOR: [f32; 4] = [0.2, 0.4, 0.9, 1.0];
+
+    pub fn new() -> Result<Self, WrapperError> {
+        let program = ShaderProgram::from_vertex_fragment(FULLSCREEN_VERT, GRID_FRAG)?;
+        program.set_label("grid");
+
+        Ok(Self {
+            enabled: true,
+            spacing: 1.0,
+            major_every: 10,
+            fade_distance: 60.0,
+            minor_color: [0.5, 0.5, 0.5, 0.4],
+            major_color: [0.7, 0.7, 0.7, 0.7],
+            show_axes: true,
+            axis_length: 1.0,
+            program,
+            vao: VertexArray::new().ok_or(WrapperError::Allocation("vertex array"))?,
+            axes: DebugDraw::new()?,
+        })
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    /// Draws the grid and axes seen by `camera` unless disabled. Blending is on and depth
+    /// writes are off meanwhile.
+    pub fn draw(&mut self, camera: &Camera) {
+        if !self.enabled {
+            return;
+        }
+
+        let view_projection = camera.view_projection();
+        depth_write(false);
+        BlendState::alpha().apply();
+
+        self.program.use_program();
+        self.program.set_mat4("view_projection", &view_projection.to_cols_array());
+        self.program.set_mat4("inverse_view_projection", &view_projection.inverse().to_cols_array());
+        self.program.set_vec3("camera_position", camera.position.to_array());
+        self.program.set_f32("spacing", self.spacing.max(f32::EPSILON));
+        self.program.set_f32("major_every", self.major_every.max(1) as f32);
+        self.program.set_f32("fade_distance", self.fade_distance.max(f32::EPSILON));
+        self.program.set_vec4("minor_color", self.minor_color);
+        self.program.set_vec4("major_color", self.major_color);
+        self.program.set_vec4("x_axis_color", Self::X_AXIS_COLOR);
+        self.program.set_vec4("z_axis_color", Self::Z_AXIS_COLOR);
+        self.program.set_i32("show_axes", self.show_axes as i32);
+        draw_fullscreen(&self.vao);
+
+        BlendState::disable();
+        depth_write(true);
+
+        if self.show_axes && self.axis_length > 0.0 {
+            self.axes.axes(Mat4::IDENTITY, self.axis_length);
+            self.axes.draw(camera);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::error::WrapperError;
+use crate::framebuffer::Framebuffer;
+use crate::postprocess::{draw_fullscreen, FULLSCREEN_VERT};
+use crate::preprocessor::preprocess_builtin;
+use crate::skybox::{cube_layers_program, render_cubemap, render_into_cubemap};
+use crate::wrapper::{
+    Cubemap, depth_test, depth_test_enabled, Shader, ShaderProgram, ShaderType, Texture2D, TextureFormat, TextureWrap,
+    VertexArray,
+};
+
+const IRRADIANCE_FRAG: &str = include_str!("../shaders/src/ibl/irradiance.frag");
+
+/// Texture units the maps of an `Ibl` are bound to, after the shadow maps.
+pub const IRRADIANCE_UNIT: u32 = 7;
+pub const PREFILTERED_UNIT: u32 = 8;
+pub const BRDF_LUT_UNIT: u32 = 9;
+
+/// Image-based lighting from an environment cubemap, precomputed once at startup: the
+/// diffuse irradiance of every direction, the environment blurred for increasing roughness
+/// along the mips of a second cubemap, and the split sum lookup table scaling the specular
+/// part. `PbrProgram::set_environment` replaces the flat ambient term with it.
+pub struct Ibl {
+    irradiance: Cubemap,
+    prefiltered: Cubemap,
+    prefiltered_levels: u32,
+    brdf_lut: Framebuffer,
+}
+
+#[allow(dead_code)]
+impl Ibl {
+    pub const IRRADIANCE_SIZE: u32 = 32;
+    pub const PREFILTERED_SIZE: u32 = 128;
+    /// mips of the prefiltered cubemap, roughness 0 to 1
+    pub const PREFILTERED_LEVELS: u32 = 5;
+    pub const BRDF_LUT_SIZE: u32 = 512;
+
+    /// `environment_size` is the face size of `environment`'s level 0. Generates its mipmaps,
+    /// the prefilter pass reads them.
+    pub fn new(environment: &Cubemap, environment_size: u32) -> Result<Self, WrapperError> {
+        Self::with_sizes(environment, environment_size, Self::IRRADIANCE_SIZE, Self::PREFILTERED_SIZE)
+    }
+
+    pub fn with_sizes(
+        environment: &Cubemap,
+        environment_size: u32,
+        irradiance_size: u32,
+        prefiltered_size: u32,
+    ) -> Result<Self, WrapperError> {
+        // filter across face edges, visible as seams on the blurry levels otherwise
+        unsafe { crate::checked!(gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS)) };
+        environment.generate_mipmaps();
+
+        let irradiance = render_cubemap("ibl.irradiance", IRRADIANCE_FRAG, irradiance_size, TextureFormat::Rgba16F, |program| {
+            environment.bind_to_unit(0);
+            program.set_i32("environment", 0);
+        })?;
+
+        let prefiltered = Cubemap::empty(prefiltered_size, TextureFormat::Rgba16F)?;
+        prefiltered.set_label("ibl.prefiltered");
+        prefiltered.generate_mipmaps();
+        let prefiltered_levels = Self::PREFILTERED_LEVELS.min(prefiltered_size.max(1).ilog2() + 1);
+
+        let prefilter = cube_layers_program("ibl.prefilter", &preprocess_builtin("ibl/prefilter.frag")?.source)?;
+        for level in 0..prefiltered_levels {
+            let roughness = level as f32 / (prefiltered_levels - 1).max(1) as f32;
+            render_into_cubemap(&prefiltered, level, (prefiltered_size >> level).max(1), &prefilter, |program| {
+                environment.bind_to_unit(0);
+                program.set_i32("environment", 0);
+                program.set_f32("environment_size", environment_size as f32);
+                program.set_f32("roughness", roughness);
+            })?;
+        }
+
+        Ok(Self { irradiance, prefiltered, prefiltered_levels, brdf_lut: brdf_lut(Self::BRDF_LUT_SIZE)? })
+    }
+
+    pub fn irradiance(&self) -> &Cubemap {
+        &self.irradiance
+    }
+
+    pub fn prefiltered(&self) -> &Cubemap {
+        &self.prefiltered
+    }
+
+    /// Mips of `prefiltered` holding data, the last one is roughness 1.
+    pub fn prefiltered_levels(&self) -> u32 {
+        self.prefiltered_levels
+    }
+
+    /// Scale and bias to F0 in rg, n·v along u and roughness along v.
+    pub fn brdf_lut(&self) -> &Texture2D {
+        self.brdf_lut.color_texture(0).expect("BRDF LUT target has a color texture")
+    }
+
+    /// Binds the maps to their units and sets the uniforms of the PBR shader, `program` has
+    /// to be in use.
+    pub fn bind(&self, program: &ShaderProgram) {
+        self.irradiance.bind_to_unit(IRRADIANCE_UNIT);
+        self.prefiltered.bind_to_unit(PREFILTERED_UNIT);
+        self.brdf_lut().bind_to_unit(BRDF_LUT_UNIT);
+        program.set_i32("irradiance_map", IRRADIANCE_UNIT as i32);
+        program.set_i32("prefiltered_map", PREFILTERED_UNIT as i32);
+        program.set_i32("brdf_lut", BRDF_LUT_UNIT as i32);
+        program.set_f32("prefiltered_max_lod", (self.prefiltered_levels - 1) as f32);
+        program.set_i32("ibl_enabled", 1);
+    }
+
+    /// Back to the flat ambient term, `program` has to be in use.
+    pub fn unbind(program: &ShaderProgram) {
+        program.set_i32("ibl_enabled", 0);
+    }
+}
+
+/// Points the IBL samplers of the PBR shader at their units, so they never share one with a
+/// regular sampler while no `Ibl` is bound. `program` has to be in use.
+pub fn init_ibl_uniforms(program: &ShaderProgram) {
+    program.set_i32("irradiance_map", IRRADIANCE_UNIT as i32);
+    program.set_i32("prefiltered_map", PREFILTERED_UNIT as i32);
+    program.set_i32("brdf_lut", BRDF_LUT_UNIT as i32);
+    program.set_i32("ibl_enabled", 0);
+}
+
+/// Integrates the split sum BRDF into a `size`² RG16F texture, independent of the environment.
+fn brdf_lut(size: u32) -> Result<Framebuffer, WrapperError> {
+    let program = ShaderProgram::from_shaders([
+        Shader::from_source(ShaderType::Vertex, FULLSCREEN_VERT)?,
+        Shader::from_preprocessed(ShaderType::Fragment, &preprocess_builtin("ibl/brdf_lut.frag")?)?,
+    ])?;
+    program.set_label("ibl.brdf_lut");
+
+    let target = Framebuffer::builder(size, size).color_texture(TextureFormat::Rg16F).build()?;
+    target.set_label("ibl.brdf_lut");
+    if let Some(texture) = target.color_texture(0) {
+        texture.set_wrap(TextureWrap::ClampToEdge, TextureWrap::ClampToEdge);
+    }
+    let vao = VertexArray::new().ok_or(WrapperError::Allocation("vertex array"))?;
+
+    let depth_enabled = depth_test_enabled();
+    depth_test(false);
+    target.bind();
+    program.use_program();
+    draw_fullscreen(&vao);
+    depth_test(depth_enabled);
+    unsafe { crate::checked!(gl::BindFramebuffer(gl::FRAMEBUFFER, 0)) };
+
+    Ok(target)
+}
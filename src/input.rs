@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "glfw")]
+use glfw::{GamepadAxis, GamepadButton, GamepadState, Glfw, Joystick, JoystickEvent, JoystickId};
+
+use crate::event::{Action, Key, Modifiers, MouseButton, WindowEvent};
+
+/// Physical input an action can be bound to.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(Key),
+    /// the key only counts while all of the modifiers are held
+    KeyWith(Key, Modifiers),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    fn button(self) -> Button {
+        match self {
+            Self::Key(key) | Self::KeyWith(key, _) => Button::Key(key),
+            Self::Mouse(button) => Button::Mouse(button),
+        }
+    }
+
+    fn modifiers(self) -> Modifiers {
+        match self {
+            Self::KeyWith(_, modifiers) => modifiers,
+            _ => Modifiers::empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Button {
+    Key(Key),
+    Mouse(MouseButton),
+}
+
+/// Named actions ("move_up", "screenshot") bound to keys and buttons, rebindable at runtime.
+/// Feed it every window event with `handle_event` and call `end_frame` once the frame's input
+/// was looked at: the `just_*` queries report the changes since the previous `end_frame`, so
+/// a tap shorter than a frame isn't lost.
+#[derive(Debug, Clone)]
+pub struct InputMap {
+    bindings: HashMap<String, Vec<Binding>>,
+    /// buttons with the modifiers that were held when they went down, so a binding with
+    /// modifiers still counts when those are let go first
+    down: HashMap<Button, Modifiers>,
+    pressed: HashMap<Button, Modifiers>,
+    released: HashMap<Button, Modifiers>,
+    /// screen coordinates from the top left corner, `None` until the cursor moved
+    cursor: Option<[f64; 2]>,
+    cursor_delta: [f64; 2],
+    scroll: [f64; 2],
+}
+
+#[allow(dead_code)]
+impl InputMap {
+    pub fn new() -> Self {
+        Self {
+            bindings: HashMap::new(), down: HashMap::new(), pressed: HashMap::new(), released: HashMap::new(),
+            cursor: None, cursor_delta: [0.0, 0.0], scroll: [0.0, 0.0]
+        }
+    }
+
+    /// Adds `binding` to `action`, an action can have any number of them.
+    pub fn bind(&mut self, action: &str, binding: Binding) -> &mut Self {
+        let bindings = self.bindings.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        self
+    }
+
+    /// `bind` for building a map in one expression.
+    pub fn with(mut self, action: &str, binding: Binding) -> Self {
+        self.bind(action, binding);
+        self
+    }
+
+    /// Replaces all bindings of `action`.
+    pub fn rebind(&mut self, action: &str, bindings: &[Binding]) {
+        self.bindings.insert(action.to_string(), bindings.to_vec());
+    }
+
+    pub fn unbind(&mut self, action: &str) {
+        self.bindings.remove(action);
+    }
+
+    /// Empty for unknown actions.
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.bindings.get(action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(String::as_str)
+    }
+
+    /// Updates the state from key, mouse button, cursor and scroll events, others are ignored.
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        let (button, action, modifiers) = match *event {
+            WindowEvent::Key { key, action, modifiers, .. } => (Button::Key(key), action, modifiers),
+            WindowEvent::MouseButton { button, action, modifiers } => (Button::Mouse(button), action, modifiers),
+            WindowEvent::CursorPos(x, y) => {
+                if let Some([last_x, last_y]) = self.cursor {
+                    self.cursor_delta[0] += x - last_x;
+                    self.cursor_delta[1] += y - last_y;
+                }
+                self.cursor = Some([x, y]);
+                return;
+            }
+            WindowEvent::Scroll(x, y) => {
+                self.scroll[0] += x;
+                self.scroll[1] += y;
+                return;
+            }
+            _ => return,
+        };
+
+        match action {
+            Action::Press => {
+                self.down.insert(button, modifiers);
+                self.pressed.insert(button, modifiers);
+            }
+            Action::Release => {
+                let modifiers = self.down.remove(&button).unwrap_or(modifiers);
+                self.released.insert(button, modifiers);
+            }
+            Action::Repeat => {}
+        }
+    }
+
+    /// Ends the frame for the `just_*` queries.
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.cursor_delta = [0.0, 0.0];
+        self.scroll = [0.0, 0.0];
+    }
+
+    /// Last cursor position in screen coordinates, origin at the top left of the window.
+    pub fn cursor(&self) -> Option<[f64; 2]> {
+        self.cursor
+    }
+
+    /// Forgets the last cursor position, so the next motion isn't reported as a delta.
+    /// Call it when the cursor mode changes, GLFW jumps the position then.
+    pub fn reset_cursor(&mut self) {
+        self.cursor = None;
+        self.cursor_delta = [0.0, 0.0];
+    }
+
+    /// How far the cursor moved this frame, in screen coordinates with y pointing down.
+    pub fn cursor_delta(&self) -> [f64; 2] {
+        self.cursor_delta
+    }
+
+    /// Whether any binding of `action` is held down.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.any(action, &self.down)
+    }
+
+    /// Whether a binding of `action` went down this frame.
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.any(action, &self.pressed)
+    }
+
+    /// Whether a binding of `action` was let go this frame.
+    pub fn just_released(&self, action: &str) -> bool {
+        self.any(action, &self.released)
+    }
+
+    /// Scrolled this frame, y is the usual mouse wheel, positive away from the user.
+    pub fn scroll(&self) -> [f64; 2] {
+        self.scroll
+    }
+
+    /// -1, 0 or 1 from a pair of opposite actions, 0 when both are held.
+    pub fn axis(&self, negative: &str, positive: &str) -> f32 {
+        self.pressed(positive) as i32 as f32 - self.pressed(negative) as i32 as f32
+    }
+
+    fn any(&self, action: &str, buttons: &HashMap<Button, Modifiers>) -> bool {
+        self.bindings(action).iter().any(|binding| {
+            buttons.get(&binding.button()).is_some_and(|held| held.contains(binding.modifiers()))
+        })
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Joystick with a GLFW gamepad mapping (Xbox layout), `poll` it once per update.
+/// Buttons and axes read as released and centered while it's disconnected.
+#[cfg(feature = "glfw")]
+pub struct Gamepad {
+    joystick: Joystick,
+    state: Option<GamepadState>,
+    previous: Option<GamepadState>,
+    /// stick deflection below this reads as centered, worn sticks rarely rest at exactly 0
+    pub dead_zone: f32,
+}
+
+#[cfg(feature = "glfw")]
+#[allow(dead_code)]
+impl Gamepad {
+    pub const DEFAULT_DEAD_ZONE: f32 = 0.15;
+
+    pub fn new(glfw: &Glfw, id: JoystickId) -> Self {
+        Self { joystick: glfw.get_joystick(id), state: None, previous: None, dead_zone: Self::DEFAULT_DEAD_ZONE }
+    }
+
+    /// Reads the current state, returns `Connected`/`Disconnected` when that changed since
+    /// the previous poll.
+    pub fn poll(&mut self) -> Option<JoystickEvent> {
+        let was_connected = self.state.is_some();
+        self.previous = self.state;
+        self.state = if self.joystick.is_gamepad() { self.joystick.get_gamepad_state() } else { None };
+
+        match (was_connected, self.state.is_some()) {
+            (false, true) => Some(JoystickEvent::Connected),
+            (true, false) => Some(JoystickEvent::Disconnected),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> JoystickId {
+        self.joystick.id
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Name from the gamepad mapping, `None` while disconnected.
+    pub fn name(&self) -> Option<String> {
+        self.joystick.get_gamepad_name()
+    }
+
+    /// Raw axis value, -1..1 for sticks and triggers, without the dead zone.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.state.map(|state| state.get_axis(axis)).unwrap_or(0.0)
+    }
+
+    /// Stick position with the dead zone cut out and the rest rescaled to still reach 1.
+    /// y points down, as GLFW reports it.
+    pub fn stick(&self, x: GamepadAxis, y: GamepadAxis) -> [f32; 2] {
+        let (x, y) = (self.axis(x), self.axis(y));
+        let length = (x * x + y * y).sqrt();
+        if length <= self.dead_zone {
+            return [0.0, 0.0];
+        }
+
+        let scale = ((length - self.dead_zone) / (1.0 - self.dead_zone)).min(1.0) / length;
+        [x * scale, y * scale]
+    }
+
+    pub fn left_stick(&self) -> [f32; 2] {
+        self.stick(GamepadAxis::AxisLeftX, GamepadAxis::AxisLeftY)
+    }
+
+    pub fn right_stick(&self) -> [f32; 2] {
+        self.stick(GamepadAxis::AxisRightX, GamepadAxis::AxisRightY)
+    }
+
+    pub fn pressed(&self, button: GamepadButton) -> bool {
+        Self::is_down(self.state, button)
+    }
+
+    /// Whether `button` went down between the last two polls.
+    pub fn just_pressed(&self, button: GamepadButton) -> bool {
+        Self::is_down(self.state, button) && !Self::is_down(self.previous, button)
+    }
+
+    pub fn just_released(&self, button: GamepadButton) -> bool {
+        !Self::is_down(self.state, button) && Self::is_down(self.previous, button)
+    }
+
+    fn is_down(state: Option<GamepadState>, button: GamepadButton) -> bool {
+        state.is_some_and(|state| state.get_button_state(button) == glfw::Action::Press)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: Key, action: Action, modifiers: Modifiers) -> WindowEvent {
+        WindowEvent::Key { key, scancode: 0, action, modifiers }
+    }
+
+    #[test]
+    fn modifiers_released_first_still_count() {
+        let mut input = InputMap::new().with("save", Binding::KeyWith(Key::S, Modifiers::CONTROL));
+        input.handle_event(&key(Key::LeftControl, Action::Press, Modifiers::empty()));
+        input.handle_event(&key(Key::S, Action::Press, Modifiers::CONTROL));
+        input.handle_event(&key(Key::LeftControl, Action::Release, Modifiers::CONTROL));
+        assert!(input.just_pressed("save"));
+        assert!(input.pressed("save"));
+
+        input.handle_event(&key(Key::S, Action::Release, Modifiers::empty()));
+        assert!(input.just_released("save"));
+        assert!(!input.pressed("save"));
+    }
+
+    #[test]
+    fn binding_without_modifiers() {
+        let mut input = InputMap::new().with("save", Binding::KeyWith(Key::S, Modifiers::CONTROL));
+        input.bind("jump", Binding::Key(Key::Space));
+        input.handle_event(&key(Key::S, Action::Press, Modifiers::empty()));
+        input.handle_event(&key(Key::Space, Action::Press, Modifiers::SHIFT));
+        assert!(!input.pressed("save"));
+        assert!(input.pressed("jump"));
+    }
+
+    #[test]
+    fn end_frame_clears_changes() {
+        let mut input = InputMap::new().with("fire", Binding::Mouse(MouseButton::Left));
+        let click = WindowEvent::MouseButton { button: MouseButton::Left, action: Action::Press, modifiers: Modifiers::empty() };
+        input.handle_event(&click);
+        input.handle_event(&WindowEvent::CursorPos(1.0, 2.0));
+        input.handle_event(&WindowEvent::CursorPos(4.0, 6.0));
+        assert!(input.just_pressed("fire"));
+        assert_eq!(input.cursor_delta(), [3.0, 4.0]);
+
+        input.end_frame();
+        assert!(!input.just_pressed("fire"));
+        assert!(input.pressed("fire"));
+        assert_eq!(input.cursor_delta(), [0.0, 0.0]);
+    }
+}
@@ -0,0 +1,327 @@
+use std::mem::offset_of;
+
+use gl::types::GLuint;
+use glam::Vec3;
+
+use crate::camera::Camera;
+use crate::error::WrapperError;
+use crate::preprocessor::preprocess_builtin;
+use crate::shadow::init_shadow_uniforms;
+use crate::wrapper::{Shader, ShaderProgram, ShaderType, Std140, Texture2D, UniformBuffer, verify_std140};
+
+const LIT_VERT: &str = include_str!("../shaders/src/lighting/lit.vert");
+
+/// Array sizes of the `Lighting` block, `lighting_block.glsl` defines the same.
+pub const MAX_DIRECTIONAL_LIGHTS: usize = 4;
+pub const MAX_POINT_LIGHTS: usize = 32;
+pub const MAX_SPOT_LIGHTS: usize = 16;
+
+pub const LIGHTING_BLOCK: &str = "Lighting";
+/// Uniform buffer binding point `Lighting` uses unless told otherwise.
+pub const LIGHTING_BINDING: GLuint = 2;
+/// Texture unit of the normal map of `Lighting::normal_mapped_program`.
+pub const NORMAL_MAP_UNIT: u32 = 0;
+
+/// Sun-like light from infinitely far away, the same everywhere. std140 mirror of the
+/// shader's `DirectionalLight`.
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DirectionalLight {
+    /// where the light travels, from the light towards the scene
+    pub direction: [f32; 3],
+    pub intensity: f32,
+    pub color: [f32; 3],
+    _padding: f32,
+}
+
+#[allow(dead_code)]
+impl DirectionalLight {
+    pub fn new(direction: Vec3, color: [f32; 3]) -> Self {
+        Self { direction: direction.normalize_or_zero().to_array(), intensity: 1.0, color, _padding: 0.0 }
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+}
+
+/// Light shining in all directions from a point, fading out until `radius`. std140 (and
+/// std430) mirror of the shader's `PointLight`.
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointLight {
+    pub position: [f32; 3],
+    /// distance where the light has faded out completely, pixels further away skip it
+    pub radius: f32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+#[allow(dead_code)]
+impl PointLight {
+    pub fn new(position: Vec3, color: [f32; 3], radius: f32) -> Self {
+        Self { position: position.to_array(), radius, color, intensity: 1.0 }
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn verify_layout() -> Result<(), WrapperError> {
+        verify_std140::<Self>(&[
+            ("position", offset_of!(Self, position), Std140::Vec3),
+            ("radius", offset_of!(Self, radius), Std140::Float),
+            ("color", offset_of!(Self, color), Std140::Vec3),
+            ("intensity", offset_of!(Self, intensity), Std140::Float),
+        ])
+    }
+}
+
+/// Point light limited to a cone, full inside `cos_inner` and fading out to `cos_outer`.
+/// std140 mirror of the shader's `SpotLight`.
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpotLight {
+    pub position: [f32; 3],
+    pub radius: f32,
+    /// axis of the cone, pointing away from the light
+    pub direction: [f32; 3],
+    pub intensity: f32,
+    pub color: [f32; 3],
+    /// cosine of the half angle of the fully lit cone
+    pub cos_inner: f32,
+    pub cos_outer: f32,
+    _padding: [f32; 3],
+}
+
+#[allow(dead_code)]
+impl SpotLight {
+    /// Cone half angles in degrees, `inner` is clamped to `outer`.
+    pub fn new(position: Vec3, direction: Vec3, color: [f32; 3], radius: f32, inner: f32, outer: f32) -> Self {
+        Self {
+            position: position.to_array(),
+            radius,
+            direction: direction.normalize_or_zero().to_array(),
+            intensity: 1.0,
+            color,
+            cos_inner: inner.min(outer).to_radians().cos(),
+            cos_outer: outer.to_radians().cos(),
+            _padding: [0.0; 3],
+        }
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+}
+
+const DIRECTIONAL_STD140: Std140 = Std140::Struct(&[Std140::Vec3, Std140::Float, Std140::Vec3, Std140::Float]);
+const POINT_STD140: Std140 = Std140::Struct(&[Std140::Vec3, Std140::Float, Std140::Vec3, Std140::Float]);
+const SPOT_STD140: Std140 = Std140::Struct(&[
+    Std140::Vec3, Std140::Float, Std140::Vec3, Std140::Float, Std140::Vec3, Std140::Float, Std140::Float,
+]);
+
+/// std140 mirror of the `Lighting` block declared by `lighting_block.glsl`.
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightingUniforms {
+    /// w is unused
+    pub ambient: [f32; 4],
+    /// directional, point and spot lights used, w is unused
+    pub counts: [u32; 4],
+    pub directional: [DirectionalLight; MAX_DIRECTIONAL_LIGHTS],
+    pub point: [PointLight; MAX_POINT_LIGHTS],
+    pub spot: [SpotLight; MAX_SPOT_LIGHTS],
+}
+
+#[allow(dead_code)]
+impl LightingUniforms {
+    pub fn verify_layout() -> Result<(), WrapperError> {
+        PointLight::verify_layout()?;
+        verify_std140::<Self>(&[
+            ("ambient", offset_of!(Self, ambient), Std140::Vec4),
+            ("counts", offset_of!(Self, counts), Std140::Vec4),
+            ("directional", offset_of!(Self, directional), Std140::Array(&DIRECTIONAL_STD140, MAX_DIRECTIONAL_LIGHTS)),
+            ("point", offset_of!(Self, point), Std140::Array(&POINT_STD140, MAX_POINT_LIGHTS)),
+            ("spot", offset_of!(Self, spot), Std140::Array(&SPOT_STD140, MAX_SPOT_LIGHTS)),
+        ])
+    }
+
+    /// Points the program's `Lighting` block at the buffer.
+    pub fn bind_block(program: &ShaderProgram, buffer: &UniformBuffer<Self>) {
+        program.bind_uniform_block(LIGHTING_BLOCK, buffer.binding());
+    }
+}
+
+/// Lights of a scene on the CPU side, any number of each kind. Only the first
+/// `MAX_*_LIGHTS` of every kind reach the shader.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct Lights {
+    pub ambient: [f32; 3],
+    pub directional: Vec<DirectionalLight>,
+    pub point: Vec<PointLight>,
+    pub spot: Vec<SpotLight>,
+}
+
+#[allow(dead_code)]
+impl Lights {
+    pub fn uniforms(&self) -> LightingUniforms {
+        let mut uniforms: LightingUniforms = bytemuck::Zeroable::zeroed();
+        let [r, g, b] = self.ambient;
+        uniforms.ambient = [r, g, b, 0.0];
+
+        uniforms.counts[0] = copy(&mut uniforms.directional, &self.directional);
+        uniforms.counts[1] = copy(&mut uniforms.point, &self.point);
+        uniforms.counts[2] = copy(&mut uniforms.spot, &self.spot);
+        uniforms
+    }
+}
+
+/// Copies as many lights as fit, returns how many that were.
+fn copy<T: Copy>(target: &mut [T], source: &[T]) -> u32 {
+    let len = source.len().min(target.len());
+    target[..len].copy_from_slice(&source[..len]);
+    len as u32
+}
+
+/// `lit.vert` with the built-in fragment shader `frag_path`, see `preprocess_builtin`. The
+/// vertex shader takes vertices laid out like `MeshVertex` and passes on world space
+/// `world_position`, `world_normal`, `world_tangent` and `uv`.
+pub fn lit_program(frag_path: &str) -> Result<ShaderProgram, WrapperError> {
+    let vertex = Shader::from_source(ShaderType::Vertex, LIT_VERT)?;
+    let fragment = Shader::from_preprocessed(ShaderType::Fragment, &preprocess_builtin(frag_path)?)?;
+    ShaderProgram::from_shaders([vertex, fragment])
+}
+
+/// Lit rendering without writing GLSL: a Blinn-Phong program for vertices laid out like
+/// `MeshVertex` and the uniform buffer with the lights it reads.
+///
+/// ```ignore
+/// let mut lighting = Lighting::new()?;
+/// lighting.update(&lights);
+/// lighting.begin(&camera);
+/// lighting.program().set_vec4("albedo", [0.8, 0.2, 0.2, 1.0]);
+/// transform.upload(lighting.program());
+/// mesh.draw();
+/// ```
+pub struct Lighting {
+    /// Blinn-Phong exponent, higher is a smaller, sharper highlight
+    pub shininess: f32,
+    buffer: UniformBuffer<LightingUniforms>,
+    program: ShaderProgram,
+    normal_mapped: ShaderProgram,
+}
+
+#[allow(dead_code)]
+impl Lighting {
+    pub fn new() -> Result<Self, WrapperError> {
+        Self::with_binding(LIGHTING_BINDING)
+    }
+
+    pub fn with_binding(binding: GLuint) -> Result<Self, WrapperError> {
+        LightingUniforms::verify_layout()?;
+
+        let buffer = UniformBuffer::new(binding, &bytemuck::Zeroable::zeroed())?;
+        buffer.set_label("lighting");
+
+        let program = lit_program("lighting/lit.frag")?;
+        program.set_label("lighting.lit");
+        let normal_mapped = lit_program("lighting/lit_normal_mapped.frag")?;
+        normal_mapped.set_label("lighting.lit_normal_mapped");
+
+        for program in [&program, &normal_mapped] {
+            LightingUniforms::bind_block(program, &buffer);
+            program.use_program();
+            init_shadow_uniforms(program);
+            program.set_vec4("albedo", [1.0, 1.0, 1.0, 1.0]);
+            program.set_f32("specular", 0.5);
+        }
+        normal_mapped.set_i32("normal_map", NORMAL_MAP_UNIT as i32);
+        normal_mapped.set_f32("normal_scale", 1.0);
+
+        Ok(Self { shininess: 32.0, buffer, program, normal_mapped })
+    }
+
+    /// Uploads the lights, every program bound to the block sees them.
+    pub fn update(&self, lights: &Lights) {
+        self.buffer.update(&lights.uniforms());
+    }
+
+    pub fn buffer(&self) -> &UniformBuffer<LightingUniforms> {
+        &self.buffer
+    }
+
+    /// Points a program including `lighting_block.glsl` at the lights and leaves it in use
+    /// with shadows off.
+    pub fn bind_block(&self, program: &ShaderProgram) {
+        LightingUniforms::bind_block(program, &self.buffer);
+        program.use_program();
+        init_shadow_uniforms(program);
+    }
+
+    /// Built-in lit program: set `model`, `albedo` (vec4) and `specular` (float) per object.
+    pub fn program(&self) -> &ShaderProgram {
+        &self.program
+    }
+
+    /// Variant of `program` bending the normals by a tangent space normal map on unit
+    /// `NORMAL_MAP_UNIT`, uses the vertex tangents where the mesh has them. Set
+    /// `normal_scale` (float) to flatten or deepen the map.
+    pub fn normal_mapped_program(&self) -> &ShaderProgram {
+        &self.normal_mapped
+    }
+
+    /// Uses the built-in program with the camera's matrices and position.
+    pub fn begin(&self, camera: &Camera) {
+        self.start(&self.program, camera);
+    }
+
+    /// Uses the normal-mapped program like `begin` and binds `normal_map`.
+    pub fn begin_normal_mapped(&self, camera: &Camera, normal_map: &Texture2D) {
+        self.start(&self.normal_mapped, camera);
+        normal_map.bind_to_unit(NORMAL_MAP_UNIT);
+    }
+
+    fn start(&self, program: &ShaderProgram, camera: &Camera) {
+        program.use_program();
+        camera.upload(program);
+        program.set_vec3("camera_position", camera.position.to_array());
+        program.set_f32("shininess", self.shininess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn define(src: &str, name: &str) -> Option<usize> {
+        src.lines().find_map(|line| line.trim().strip_prefix("#define ")?.strip_prefix(name)?.trim().parse().ok())
+    }
+
+    #[test]
+    fn block_sizes_match_the_shader() {
+        let src = preprocess_builtin("lighting/lighting_block.glsl").unwrap().source;
+        assert_eq!(define(&src, "MAX_DIRECTIONAL_LIGHTS"), Some(MAX_DIRECTIONAL_LIGHTS));
+        assert_eq!(define(&src, "MAX_POINT_LIGHTS"), Some(MAX_POINT_LIGHTS));
+        assert_eq!(define(&src, "MAX_SPOT_LIGHTS"), Some(MAX_SPOT_LIGHTS));
+        assert!(src.contains(&format!("uniform {} {{", LIGHTING_BLOCK)));
+    }
+
+    #[test]
+    fn lit_shaders_declare_the_lights_once() {
+        for path in ["lighting/lit.frag", "lighting/lit_normal_mapped.frag", "material/pbr.frag", "forward_plus/lit.frag", "deferred/lighting.frag"] {
+            let src = preprocess_builtin(path).unwrap().source;
+            assert!(src.starts_with("#version "), "{}", path);
+            assert_eq!(src.matches("struct PointLight {").count(), 1, "{}", path);
+            assert_eq!(src.matches("float light_attenuation(").count(), 1, "{}", path);
+        }
+    }
+}
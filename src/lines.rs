@@ -0,0 +1,220 @@
+use glam::{Mat4, Vec3};
+
+use crate::curves::Curve;
+use crate::error::WrapperError;
+use crate::wrapper::{
+    ArrayBuffer, Attribute, BlendState, BufferType, buffer_data, buffer_vertices, draw_elements, PrimitiveMode,
+    ShaderProgram, VertexArray, VertexLike,
+};
+
+const WIDE_VERT: &str = include_str!("../shaders/src/lines/wide.vert");
+const WIDE_GEOM: &str = include_str!("../shaders/src/lines/wide.geom");
+const WIDE_FRAG: &str = include_str!("../shaders/src/lines/wide.frag");
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    /// pixels across
+    pub width: f32,
+    pub color: [f32; 4],
+}
+
+impl VertexLike for LineVertex {
+    const ATTRIBUTES: &'static [Attribute] = &[Attribute::vec3(0), Attribute::float(1), Attribute::vec4(2)];
+}
+
+/// Lines of any width in pixels with smooth edges, expanded into screen space quads by a
+/// geometry shader, for when `glLineWidth` isn't enough: core profiles may clamp it to 1.
+/// Consecutive segments of a polyline are mitered into each other, so strips and loops get
+/// clean corners. Like `DebugDraw` the lines are queued and drawn together by `draw`.
+///
+/// ```ignore
+/// lines.polyline(&points, false, 3.0, [0.0, 0.0, 0.0, 1.0]);
+/// lines.draw(camera.view_projection(), fb_width, fb_height);
+/// ```
+pub struct LineRenderer {
+    /// pixels of the anti-aliased edge
+    pub feather: f32,
+    /// sharpest join, as how many half widths its miter may stick out, before squaring it off
+    pub miter_limit: f32,
+    vertices: Vec<LineVertex>,
+    /// four per segment: the vertex before, its ends and the vertex after
+    indices: Vec<u32>,
+    program: ShaderProgram,
+    vao: VertexArray,
+    vbo: ArrayBuffer,
+    ebo: ArrayBuffer,
+}
+
+#[allow(dead_code)]
+impl LineRenderer {
+    pub fn new() -> Result<Self, WrapperError> {
+        let program = ShaderProgram::from_stages()
+            .vertex(WIDE_VERT)
+            .geometry(WIDE_GEOM)
+            .fragment(WIDE_FRAG)
+            .build()?;
+        program.set_label("wide_lines");
+
+        let vao = VertexArray::new().ok_or(WrapperError::Allocation("vertex array"))?;
+        let vbo = ArrayBuffer::new().ok_or(WrapperError::Allocation("vertex buffer"))?;
+        let ebo = ArrayBuffer::new().ok_or(WrapperError::Allocation("element buffer"))?;
+        vao.bind();
+        vbo.bind(BufferType::Array);
+        vao.set_vertex_layout::<LineVertex>();
+        ebo.bind(BufferType::ElementArray);
+        vao.set_label("wide_lines");
+
+        Ok(Self {
+            feather: 1.0,
+            miter_limit: 4.0,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            program,
+            vao,
+            vbo,
+            ebo,
+        })
+    }
+
+    /// A single segment with square ends.
+    pub fn line(&mut self, a: Vec3, b: Vec3, width: f32, color: [f32; 4]) {
+        let first = self.push(&[a, b], width, color);
+        self.indices.extend_from_slice(&[first, first, first + 1, first + 1]);
+    }
+
+    /// Segments between consecutive points, joined at every inner point, and from the last
+    /// back to the first when `closed`.
+    pub fn polyline(&mut self, points: &[Vec3], closed: bool, width: f32, color: [f32; 4]) {
+        let count = points.len() as u32;
+        if count < 2 {
+            return;
+        }
+        let first = self.push(points, width, color);
+
+        // neighbours wrap around a loop and repeat the end points of an open strip
+        let at = |i: i64| -> u32 {
+            let i = if closed { i.rem_euclid(count as i64) } else { i.clamp(0, count as i64 - 1) };
+            first + i as u32
+        };
+        let segments = if closed { count } else { count - 1 };
+        for i in 0..segments as i64 {
+            self.indices.extend_from_slice(&[at(i - 1), at(i), at(i + 1), at(i + 2)]);
+        }
+    }
+
+    /// `curve` flattened to within `tolerance` world units, see `curves`.
+    pub fn curve(&mut self, curve: &impl Curve, tolerance: f32, width: f32, color: [f32; 4]) {
+        self.polyline(&curve.tessellate(tolerance), false, width, color);
+    }
+
+    /// Segments between pairs of `points`, as in a `Lines` index buffer. Where exactly two
+    /// segments meet they're joined like in a polyline, other ends are square. Nothing is
+    /// queued when a pair refers past the end of `points`.
+    pub fn segments(
+        &mut self,
+        points: &[Vec3],
+        pairs: &[[u32; 2]],
+        width: f32,
+        color: [f32; 4],
+    ) -> Result<(), WrapperError> {
+        let adjacency = segment_adjacency(points.len(), pairs)?;
+        let first = self.push(points, width, color);
+        self.indices.extend(adjacency.as_flattened().iter().map(|&i| first + i));
+        Ok(())
+    }
+
+    /// Segments queued since the last `draw`.
+    pub fn segment_count(&self) -> usize {
+        self.indices.len() / 4
+    }
+
+    /// Forgets the queued lines without drawing them.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+
+    /// Draws the queued lines through `view_projection` into a framebuffer of `width` x
+    /// `height` pixels, then empties the queue. Alpha blending is on while drawing, depth
+    /// testing is left as it is.
+    pub fn draw(&mut self, view_projection: Mat4, width: i32, height: i32) {
+        if self.indices.is_empty() {
+            return;
+        }
+
+        self.vao.bind();
+        self.vbo.bind(BufferType::Array);
+        buffer_vertices(BufferType::Array, &self.vertices, gl::STREAM_DRAW);
+        self.ebo.bind(BufferType::ElementArray);
+        buffer_data(BufferType::ElementArray, bytemuck::cast_slice(&self.indices), gl::STREAM_DRAW);
+
+        BlendState::alpha().apply();
+        self.program.use_program();
+        self.program.set_mat4("view_projection", &view_projection.to_cols_array());
+        self.program.set_vec2("viewport", [width.max(1) as f32, height.max(1) as f32]);
+        self.program.set_f32("feather", self.feather.max(f32::EPSILON));
+        self.program.set_f32("miter_limit", self.miter_limit.max(1.0));
+        draw_elements(PrimitiveMode::LinesAdjacency, self.indices.len() as i32);
+        BlendState::disable();
+
+        self.clear();
+    }
+
+    fn push(&mut self, points: &[Vec3], width: f32, color: [f32; 4]) -> u32 {
+        let first = self.vertices.len() as u32;
+        self.vertices.extend(points.iter().map(|point| LineVertex { position: point.to_array(), width, color }));
+        first
+    }
+}
+
+/// `LinesAdjacency` indices of `pairs`: the vertex before each segment, its ends and the
+/// vertex after, which is the end itself where other than two segments meet.
+fn segment_adjacency(point_count: usize, pairs: &[[u32; 2]]) -> Result<Vec<[u32; 4]>, WrapperError> {
+    if pairs.as_flattened().iter().any(|&i| i as usize >= point_count) {
+        return Err(WrapperError::Unsupported("line segments with indices past the end of the points"));
+    }
+
+    let mut degree = vec![0u32; point_count];
+    for &[a, b] in pairs {
+        degree[a as usize] += 1;
+        degree[b as usize] += 1;
+    }
+    // the far end of the other segment at `end`, or `end` itself
+    let neighbour = |end: u32, skip: usize| -> u32 {
+        if degree[end as usize] != 2 {
+            return end;
+        }
+        pairs
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != skip)
+            .find_map(|(_, &[a, b])| match (a == end, b == end) {
+                (true, _) => Some(b),
+                (_, true) => Some(a),
+                _ => None,
+            })
+            .unwrap_or(end)
+    };
+
+    Ok(pairs.iter().enumerate().map(|(i, &[a, b])| [neighbour(a, i), a, b, neighbour(b, i)]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments_join_where_two_meet() {
+        // a triangle with a spur off its first corner
+        let adjacency = segment_adjacency(4, &[[0, 1], [1, 2], [2, 0], [0, 3]]).unwrap();
+        assert_eq!(adjacency, [[0, 0, 1, 2], [0, 1, 2, 0], [1, 2, 0, 0], [0, 0, 3, 3]]);
+    }
+
+    #[test]
+    fn segments_reject_indices_past_the_points() {
+        assert!(segment_adjacency(3, &[[0, 1], [1, 3]]).is_err());
+        assert!(segment_adjacency(0, &[]).unwrap().is_empty());
+    }
+}
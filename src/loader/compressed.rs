@@ -0,0 +1,431 @@
+//! Block compressed textures from KTX2 and DDS containers, uploaded as they are with
+//! `glCompressedTexImage2D`, including their mip chains. They take a quarter to an eighth of
+//! the memory of RGBA8 and stay compressed on the GPU.
+//!
+//! Compressed blocks can't be flipped on upload, so unlike `Texture2D::from_file` the first
+//! row is the top one: flip V when exporting or in the shader.
+
+use std::path::Path;
+
+use gl::types::{GLenum, GLint, GLsizei};
+
+use crate::error::WrapperError;
+use crate::gl_ext;
+use crate::wrapper::{Extensions, Texture2D};
+
+const KTX2_MAGIC: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+
+/// Block formats, all made of 4x4 texel blocks.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressedFormat {
+    /// DXT1 without alpha
+    Bc1Rgb,
+    /// DXT1 with 1 bit alpha
+    Bc1Rgba,
+    /// DXT3, explicit 4 bit alpha
+    Bc2,
+    /// DXT5, interpolated alpha
+    Bc3,
+    /// single channel
+    Bc4,
+    /// two channels, e.g. normal maps
+    Bc5,
+    /// unsigned HDR color
+    Bc6h,
+    /// high quality color and alpha
+    Bc7,
+    Etc2Rgb,
+    /// ETC2 with 1 bit alpha
+    Etc2RgbA1,
+    /// ETC2 color with EAC alpha
+    Etc2Rgba,
+    EacR11,
+    EacRg11,
+}
+
+#[allow(dead_code)]
+impl CompressedFormat {
+    /// Bytes per 4x4 block.
+    pub fn block_size(self) -> usize {
+        match self {
+            Self::Bc1Rgb | Self::Bc1Rgba | Self::Bc4 | Self::Etc2Rgb | Self::Etc2RgbA1 | Self::EacR11 => 8,
+            _ => 16,
+        }
+    }
+
+    /// Bytes of a `width` x `height` level.
+    pub fn level_size(self, width: u32, height: u32) -> usize {
+        width.div_ceil(4).max(1) as usize * height.div_ceil(4).max(1) as usize * self.block_size()
+    }
+
+    pub fn has_srgb(self) -> bool {
+        !matches!(self, Self::Bc4 | Self::Bc5 | Self::Bc6h | Self::EacR11 | Self::EacRg11)
+    }
+
+    /// Internal format for `glCompressedTexImage2D`, `None` for sRGB of a format without it.
+    pub fn gl_format(self, srgb: bool) -> Option<GLenum> {
+        if srgb && !self.has_srgb() {
+            return None;
+        }
+
+        Some(match (self, srgb) {
+            (Self::Bc1Rgb, false) => gl_ext::COMPRESSED_RGB_S3TC_DXT1,
+            (Self::Bc1Rgb, true) => gl_ext::COMPRESSED_SRGB_S3TC_DXT1,
+            (Self::Bc1Rgba, false) => gl_ext::COMPRESSED_RGBA_S3TC_DXT1,
+            (Self::Bc1Rgba, true) => gl_ext::COMPRESSED_SRGB_ALPHA_S3TC_DXT1,
+            (Self::Bc2, false) => gl_ext::COMPRESSED_RGBA_S3TC_DXT3,
+            (Self::Bc2, true) => gl_ext::COMPRESSED_SRGB_ALPHA_S3TC_DXT3,
+            (Self::Bc3, false) => gl_ext::COMPRESSED_RGBA_S3TC_DXT5,
+            (Self::Bc3, true) => gl_ext::COMPRESSED_SRGB_ALPHA_S3TC_DXT5,
+            (Self::Bc4, _) => gl::COMPRESSED_RED_RGTC1,
+            (Self::Bc5, _) => gl::COMPRESSED_RG_RGTC2,
+            (Self::Bc6h, _) => gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+            (Self::Bc7, false) => gl::COMPRESSED_RGBA_BPTC_UNORM,
+            (Self::Bc7, true) => gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+            (Self::Etc2Rgb, false) => gl::COMPRESSED_RGB8_ETC2,
+            (Self::Etc2Rgb, true) => gl::COMPRESSED_SRGB8_ETC2,
+            (Self::Etc2RgbA1, false) => gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            (Self::Etc2RgbA1, true) => gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            (Self::Etc2Rgba, false) => gl::COMPRESSED_RGBA8_ETC2_EAC,
+            (Self::Etc2Rgba, true) => gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+            (Self::EacR11, _) => gl::COMPRESSED_R11_EAC,
+            (Self::EacRg11, _) => gl::COMPRESSED_RG11_EAC,
+        })
+    }
+
+    /// Whether a context with `extensions` can sample the format. BC4/BC5 are core in 3.0.
+    pub fn is_supported(self, srgb: bool, extensions: &Extensions) -> bool {
+        match self {
+            Self::Bc1Rgb | Self::Bc1Rgba | Self::Bc2 | Self::Bc3 if srgb => extensions.texture_compression_s3tc_srgb(),
+            Self::Bc1Rgb | Self::Bc1Rgba | Self::Bc2 | Self::Bc3 => extensions.texture_compression_s3tc(),
+            Self::Bc4 | Self::Bc5 => true,
+            Self::Bc6h | Self::Bc7 => extensions.texture_compression_bptc(),
+            Self::Etc2Rgb | Self::Etc2RgbA1 | Self::Etc2Rgba | Self::EacR11 | Self::EacRg11 => {
+                extensions.texture_compression_etc2()
+            }
+        }
+    }
+}
+
+/// Compressed 2D texture read from a container, levels from the full size one down.
+#[derive(Debug, Clone)]
+pub struct CompressedImage {
+    pub format: CompressedFormat,
+    /// whether the color is sRGB encoded; legacy DDS files don't say, set it for color maps
+    pub srgb: bool,
+    pub width: u32,
+    pub height: u32,
+    pub levels: Vec<Vec<u8>>,
+}
+
+#[allow(dead_code)]
+impl CompressedImage {
+    /// Reads a KTX2 or DDS file, told apart by their magic bytes.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| WrapperError::Io { path: path.to_path_buf(), source })?;
+
+        let parsed = if bytes.starts_with(&KTX2_MAGIC) {
+            parse_ktx2(&bytes)
+        } else if bytes.starts_with(DDS_MAGIC) {
+            parse_dds(&bytes)
+        } else {
+            Err("neither a KTX2 nor a DDS file".to_string())
+        };
+        parsed.map_err(|message| WrapperError::TextureFile { path: path.to_path_buf(), message })
+    }
+
+    /// Uploads every level, sampling trilinearly when there's more than one. Fails with
+    /// `Unsupported` when the context can't sample the format.
+    pub fn upload(&self) -> Result<Texture2D, WrapperError> {
+        if !self.format.is_supported(self.srgb, &Extensions::query()) {
+            return Err(WrapperError::Unsupported("compressed texture format missing from the context"));
+        }
+        let internal_format = self
+            .format
+            .gl_format(self.srgb)
+            .ok_or(WrapperError::Unsupported("sRGB variant of a compressed format without one"))?;
+
+        check_size(self.width, self.height, self.levels.len()).map_err(|_| {
+            WrapperError::Unsupported("compressed images without a size or with more levels than a full mip chain")
+        })?;
+
+        let texture = Texture2D::new().ok_or(WrapperError::Allocation("texture"))?;
+        texture.bind();
+        for (level, data) in self.levels.iter().enumerate() {
+            let (width, height) = (level_extent(self.width, level), level_extent(self.height, level));
+            unsafe {
+                crate::checked!(gl::CompressedTexImage2D(
+                    gl::TEXTURE_2D,
+                    level as GLint,
+                    internal_format,
+                    width as GLsizei,
+                    height as GLsizei,
+                    0,
+                    data.len() as GLsizei,
+                    data.as_ptr().cast(),
+                ));
+            }
+        }
+
+        let min_filter = if self.levels.len() > 1 { gl::LINEAR_MIPMAP_LINEAR } else { gl::LINEAR };
+        unsafe {
+            crate::checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, self.levels.len() as GLint - 1));
+            crate::checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter as GLint));
+            crate::checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint));
+            crate::checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as GLint));
+            crate::checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as GLint));
+        }
+
+        Ok(texture)
+    }
+}
+
+/// Reads a compressed texture file and uploads it.
+#[allow(dead_code)]
+pub fn load(path: impl AsRef<Path>) -> Result<Texture2D, WrapperError> {
+    CompressedImage::load(path)?.upload()
+}
+
+fn parse_ktx2(bytes: &[u8]) -> Result<CompressedImage, String> {
+    let u32_at = |offset: usize| read_u32(bytes, offset).ok_or("truncated header");
+    let u64_at = |offset: usize| read_u64(bytes, offset).ok_or("truncated header");
+
+    let (format, srgb) = vk_format(u32_at(12)?)?;
+    let (width, height, depth) = (u32_at(20)?, u32_at(24)?, u32_at(28)?);
+    let (layers, faces, level_count, supercompression) = (u32_at(32)?, u32_at(36)?, u32_at(40)?, u32_at(44)?);
+
+    if depth > 0 || layers > 1 || faces != 1 {
+        return Err("only plain 2D textures are supported, not arrays, cubemaps or volumes".to_string());
+    }
+    if supercompression != 0 {
+        return Err(format!("supercompression scheme {} isn't supported", supercompression));
+    }
+    // 0 levels asks for mipmaps to be generated, only the full size one is stored then
+    let level_count = level_count.max(1) as usize;
+    check_size(width, height, level_count)?;
+
+    // level index after the 80 byte header, 24 bytes per level: offset, length, uncompressed length
+    let levels = (0..level_count)
+        .map(|level| {
+            let (offset, length) = (u64_at(80 + level * 24)? as usize, u64_at(88 + level * 24)? as usize);
+            let expected = format.level_size(level_extent(width, level), level_extent(height, level));
+            if length != expected {
+                return Err(format!("level {} holds {} bytes, expected {}", level, length, expected));
+            }
+            level_bytes(bytes, offset, length, level)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(CompressedImage { format, srgb, width, height, levels })
+}
+
+/// `VkFormat` values of the block formats.
+fn vk_format(format: u32) -> Result<(CompressedFormat, bool), String> {
+    use CompressedFormat::*;
+
+    Ok(match format {
+        131 => (Bc1Rgb, false),
+        132 => (Bc1Rgb, true),
+        133 => (Bc1Rgba, false),
+        134 => (Bc1Rgba, true),
+        135 => (Bc2, false),
+        136 => (Bc2, true),
+        137 => (Bc3, false),
+        138 => (Bc3, true),
+        139 => (Bc4, false),
+        141 => (Bc5, false),
+        143 => (Bc6h, false),
+        145 => (Bc7, false),
+        146 => (Bc7, true),
+        147 => (Etc2Rgb, false),
+        148 => (Etc2Rgb, true),
+        149 => (Etc2RgbA1, false),
+        150 => (Etc2RgbA1, true),
+        151 => (Etc2Rgba, false),
+        152 => (Etc2Rgba, true),
+        153 => (EacR11, false),
+        155 => (EacRg11, false),
+        0 => return Err("uncompressed or Basis Universal data isn't supported".to_string()),
+        other => return Err(format!("VkFormat {} isn't a supported block format", other)),
+    })
+}
+
+fn parse_dds(bytes: &[u8]) -> Result<CompressedImage, String> {
+    const MIPMAP_COUNT_FLAG: u32 = 0x2_0000;
+    const CUBEMAP_CAPS: u32 = 0x200;
+    const VOLUME_CAPS: u32 = 0x20_0000;
+
+    let u32_at = |offset: usize| read_u32(bytes, offset).ok_or("truncated header");
+
+    let (flags, height, width, mip_count) = (u32_at(8)?, u32_at(12)?, u32_at(16)?, u32_at(28)?);
+    let four_cc = bytes.get(84..88).ok_or("truncated header")?;
+    if u32_at(112)? & (CUBEMAP_CAPS | VOLUME_CAPS) != 0 {
+        return Err("only plain 2D textures are supported, not cubemaps or volumes".to_string());
+    }
+
+    let (format, srgb, mut offset) = match four_cc {
+        b"DXT1" => (CompressedFormat::Bc1Rgba, false, 128),
+        b"DXT3" => (CompressedFormat::Bc2, false, 128),
+        b"DXT5" => (CompressedFormat::Bc3, false, 128),
+        b"ATI1" | b"BC4U" => (CompressedFormat::Bc4, false, 128),
+        b"ATI2" | b"BC5U" => (CompressedFormat::Bc5, false, 128),
+        b"DX10" => {
+            if u32_at(140)? > 1 {
+                return Err("texture arrays aren't supported".to_string());
+            }
+            let (format, srgb) = dxgi_format(u32_at(128)?)?;
+            (format, srgb, 148)
+        }
+        other => return Err(format!("pixel format '{}' isn't a supported block format", String::from_utf8_lossy(other))),
+    };
+
+    let level_count = if flags & MIPMAP_COUNT_FLAG != 0 { mip_count.max(1) as usize } else { 1 };
+    check_size(width, height, level_count)?;
+    let levels = (0..level_count)
+        .map(|level| {
+            let length = format.level_size(level_extent(width, level), level_extent(height, level));
+            let data = level_bytes(bytes, offset, length, level)?;
+            offset += length;
+            Ok(data)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(CompressedImage { format, srgb, width, height, levels })
+}
+
+/// `DXGI_FORMAT` values of the block formats.
+fn dxgi_format(format: u32) -> Result<(CompressedFormat, bool), String> {
+    use CompressedFormat::*;
+
+    Ok(match format {
+        71 => (Bc1Rgba, false),
+        72 => (Bc1Rgba, true),
+        74 => (Bc2, false),
+        75 => (Bc2, true),
+        77 => (Bc3, false),
+        78 => (Bc3, true),
+        80 => (Bc4, false),
+        83 => (Bc5, false),
+        95 => (Bc6h, false),
+        98 => (Bc7, false),
+        99 => (Bc7, true),
+        other => return Err(format!("DXGI format {} isn't a supported block format", other)),
+    })
+}
+
+/// Rejects empty images and mip chains longer than the one down to 1x1.
+fn check_size(width: u32, height: u32, level_count: usize) -> Result<(), String> {
+    if width == 0 || height == 0 {
+        return Err(format!("{}x{} texture has no texels", width, height));
+    }
+    let full_chain = 32 - width.max(height).leading_zeros() as usize;
+    if level_count > full_chain {
+        return Err(format!("{} levels for {}x{}, a full mip chain has {}", level_count, width, height, full_chain));
+    }
+    Ok(())
+}
+
+/// Width or height of mip `level`, at least 1.
+fn level_extent(size: u32, level: usize) -> u32 {
+    u32::try_from(level).ok().and_then(|level| size.checked_shr(level)).unwrap_or(0).max(1)
+}
+
+fn level_bytes(bytes: &[u8], offset: usize, length: usize, level: usize) -> Result<Vec<u8>, String> {
+    offset
+        .checked_add(length)
+        .and_then(|end| bytes.get(offset..end))
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| format!("level {} runs past the end of the file", level))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(bytes.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// KTX2 file of BC1 levels with the given byte lengths, the data filled with the level number.
+    fn ktx2(width: u32, height: u32, level_count: u32, lengths: &[usize]) -> Vec<u8> {
+        let mut bytes = KTX2_MAGIC.to_vec();
+        for value in [131, 1, width, height, 0, 0, 1, level_count, 0] {
+            bytes.extend_from_slice(&u32::to_le_bytes(value));
+        }
+        bytes.resize(80, 0);
+
+        let mut offset = 80 + lengths.len() * 24;
+        for &length in lengths {
+            for value in [offset, length, length] {
+                bytes.extend_from_slice(&(value as u64).to_le_bytes());
+            }
+            offset += length;
+        }
+        for (level, &length) in lengths.iter().enumerate() {
+            bytes.extend(std::iter::repeat_n(level as u8, length));
+        }
+        bytes
+    }
+
+    /// DDS file with a DXT5 mip chain of `mip_count` levels, `data` bytes after the header.
+    fn dds(width: u32, height: u32, mip_count: u32, data: usize) -> Vec<u8> {
+        let mut bytes = DDS_MAGIC.to_vec();
+        bytes.resize(128 + data, 0);
+        bytes[8..12].copy_from_slice(&0x2_1007u32.to_le_bytes());
+        bytes[12..16].copy_from_slice(&height.to_le_bytes());
+        bytes[16..20].copy_from_slice(&width.to_le_bytes());
+        bytes[28..32].copy_from_slice(&mip_count.to_le_bytes());
+        bytes[84..88].copy_from_slice(b"DXT5");
+        bytes
+    }
+
+    #[test]
+    fn ktx2_mip_chain() {
+        // 16x8 BC1: 4x2, 2x1, 1x1 and 1x1 blocks of 8 bytes
+        let image = parse_ktx2(&ktx2(16, 8, 5, &[64, 16, 8, 8, 8])).unwrap();
+        assert_eq!((image.format, image.srgb, image.width, image.height), (CompressedFormat::Bc1Rgb, false, 16, 8));
+        assert_eq!(image.levels.iter().map(Vec::len).collect::<Vec<_>>(), [64, 16, 8, 8, 8]);
+        assert!(image.levels[1].iter().all(|&byte| byte == 1));
+    }
+
+    #[test]
+    fn ktx2_rejects_bad_sizes() {
+        assert!(parse_ktx2(&ktx2(16, 8, 1, &[48])).unwrap_err().contains("expected 64"));
+        assert!(parse_ktx2(&ktx2(16, 8, 6, &[64, 16, 8, 8, 8, 8])).unwrap_err().contains("full mip chain has 5"));
+        assert!(parse_ktx2(&ktx2(0, 8, 1, &[8])).unwrap_err().contains("no texels"));
+        assert!(parse_ktx2(&ktx2(16, 8, 2, &[64, 16])[..150]).unwrap_err().contains("past the end"));
+        // a level count that would shift the size out of range or index far past the file
+        assert!(parse_ktx2(&ktx2(1, 1, u32::MAX, &[8])).is_err());
+    }
+
+    #[test]
+    fn dds_mip_chain() {
+        // 8x8 DXT5: 2x2, 1x1 and 1x1 blocks of 16 bytes
+        let image = parse_dds(&dds(8, 8, 4, 64 + 16 + 16 + 16)).unwrap();
+        assert_eq!((image.format, image.width, image.height), (CompressedFormat::Bc3, 8, 8));
+        assert_eq!(image.levels.iter().map(Vec::len).collect::<Vec<_>>(), [64, 16, 16, 16]);
+    }
+
+    #[test]
+    fn dds_rejects_bad_sizes() {
+        assert!(parse_dds(&dds(8, 8, 5, 1024)).unwrap_err().contains("full mip chain has 4"));
+        assert!(parse_dds(&dds(8, 0, 1, 64)).unwrap_err().contains("no texels"));
+        assert!(parse_dds(&dds(8, 8, 2, 70)).unwrap_err().contains("level 1 runs past"));
+        assert!(parse_dds(&dds(1 << 20, 1, u32::MAX, 16)).is_err());
+    }
+
+    #[test]
+    fn level_extents() {
+        assert_eq!((level_extent(16, 0), level_extent(16, 3), level_extent(16, 5)), (16, 2, 1));
+        assert_eq!((level_extent(u32::MAX, 31), level_extent(u32::MAX, 32), level_extent(3, 1000)), (1, 1, 1));
+        assert!(check_size(u32::MAX, 1, 32).is_ok());
+        assert!(check_size(u32::MAX, 1, 33).is_err());
+    }
+}
@@ -0,0 +1,281 @@
+use std::path::Path;
+
+use ::gltf::image::Format;
+use ::gltf::mesh::Mode;
+use ::gltf::texture::{MagFilter, MinFilter, WrappingMode};
+use glam::{Quat, Vec3};
+
+use crate::error::WrapperError;
+use crate::mesh::{MeshData, MeshVertex};
+use crate::transform::Transform;
+use crate::wrapper::{TextureFilter, TextureWrap};
+
+/// Everything useful pulled out of a .gltf/.glb file. Meshes, materials, textures, images and
+/// nodes reference each other by index in the corresponding vector, same as in glTF itself.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct GltfScene {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    pub textures: Vec<GltfTexture>,
+    pub images: Vec<GltfImage>,
+    pub nodes: Vec<GltfNode>,
+    /// top level nodes of the default (or first) scene
+    pub roots: Vec<usize>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct GltfPrimitive {
+    pub data: MeshData,
+    pub material: Option<usize>,
+}
+
+/// Metallic-roughness material parameters, texture fields are indices into `GltfScene::textures`.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct GltfMaterial {
+    pub name: Option<String>,
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    pub base_color_texture: Option<usize>,
+    pub metallic_roughness_texture: Option<usize>,
+    pub normal_texture: Option<usize>,
+    pub occlusion_texture: Option<usize>,
+    pub emissive_texture: Option<usize>,
+}
+
+/// Image of the scene with the sampler settings to use it with.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub struct GltfTexture {
+    /// index into `GltfScene::images`
+    pub image: usize,
+    pub sampler: GltfSampler,
+}
+
+/// Filtering and wrapping of a glTF sampler. Filters the file leaves out are up to the
+/// renderer, glTF suggests mipmapped filtering then.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GltfSampler {
+    pub mag_filter: Option<TextureFilter>,
+    /// filter within a level and, for mipmapped filtering, between levels
+    pub min_filter: Option<(TextureFilter, Option<TextureFilter>)>,
+    pub wrap_s: TextureWrap,
+    pub wrap_t: TextureWrap,
+}
+
+/// Decoded image converted to RGBA8, ready for `Texture2D::from_rgba`. Rows are stored
+/// top to bottom, which matches glTF texture coordinates, so no flipping is needed.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct GltfImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct GltfNode {
+    pub name: Option<String>,
+    /// relative to the parent node
+    pub transform: Transform,
+    pub mesh: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+#[allow(dead_code)]
+pub fn load(path: impl AsRef<Path>) -> Result<GltfScene, WrapperError> {
+    let (document, buffers, images) = ::gltf::import(path)?;
+
+    let meshes = document
+        .meshes()
+        .map(|mesh| GltfMesh {
+            name: mesh.name().map(str::to_string),
+            primitives: mesh
+                .primitives()
+                .filter(|primitive| primitive.mode() == Mode::Triangles)
+                .map(|primitive| {
+                    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+                    let mut data = MeshData::default();
+
+                    if let Some(positions) = reader.read_positions() {
+                        data.vertices = positions
+                            .map(|position| MeshVertex { position, ..MeshVertex::default() })
+                            .collect();
+                    }
+                    let normals = reader.read_normals();
+                    let uvs = reader.read_tex_coords(0);
+                    let needs_tangents = normals.is_some() && uvs.is_some();
+
+                    if let Some(normals) = normals {
+                        data.vertices.iter_mut().zip(normals).for_each(|(v, n)| v.normal = n);
+                    }
+                    if let Some(uvs) = uvs {
+                        data.vertices.iter_mut().zip(uvs.into_f32()).for_each(|(v, uv)| v.uv = uv);
+                    }
+                    let has_tangents = match reader.read_tangents() {
+                        Some(tangents) => {
+                            data.vertices.iter_mut().zip(tangents).for_each(|(v, t)| v.tangent = t);
+                            true
+                        }
+                        None => false,
+                    };
+
+                    data.indices = match reader.read_indices() {
+                        Some(indices) => indices.into_u32().collect(),
+                        None => (0..data.vertices.len() as u32).collect(),
+                    };
+
+                    // the spec asks for MikkTSpace tangents when a normal-mapped primitive has none
+                    if needs_tangents && !has_tangents {
+                        data.generate_tangents();
+                    }
+
+                    GltfPrimitive { data, material: primitive.material().index() }
+                })
+                .collect(),
+        })
+        .collect();
+
+    let textures = document
+        .textures()
+        .map(|texture| GltfTexture { image: texture.source().index(), sampler: sampler(&texture.sampler()) })
+        .collect();
+
+    let texture_index = |info: Option<::gltf::Texture>| info.map(|texture| texture.index());
+
+    let materials = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+
+            GltfMaterial {
+                name: material.name().map(str::to_string),
+                base_color: pbr.base_color_factor(),
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                emissive: material.emissive_factor(),
+                base_color_texture: texture_index(pbr.base_color_texture().map(|info| info.texture())),
+                metallic_roughness_texture: texture_index(pbr.metallic_roughness_texture().map(|info| info.texture())),
+                normal_texture: texture_index(material.normal_texture().map(|info| info.texture())),
+                occlusion_texture: texture_index(material.occlusion_texture().map(|info| info.texture())),
+                emissive_texture: texture_index(material.emissive_texture().map(|info| info.texture())),
+            }
+        })
+        .collect();
+
+    let nodes = document
+        .nodes()
+        .map(|node| {
+            let (translation, rotation, scale) = node.transform().decomposed();
+
+            GltfNode {
+                name: node.name().map(str::to_string),
+                transform: Transform {
+                    translation: Vec3::from(translation),
+                    rotation: Quat::from_array(rotation),
+                    scale: Vec3::from(scale),
+                },
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                children: node.children().map(|child| child.index()).collect(),
+            }
+        })
+        .collect();
+
+    let roots = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .map(|scene| scene.nodes().map(|node| node.index()).collect())
+        .unwrap_or_default();
+
+    Ok(GltfScene {
+        meshes,
+        materials,
+        textures,
+        images: images.into_iter().map(to_rgba8).collect(),
+        nodes,
+        roots,
+    })
+}
+
+fn sampler(sampler: &::gltf::texture::Sampler) -> GltfSampler {
+    use TextureFilter::{Linear, Nearest};
+
+    let wrap = |mode| match mode {
+        WrappingMode::ClampToEdge => TextureWrap::ClampToEdge,
+        WrappingMode::MirroredRepeat => TextureWrap::MirroredRepeat,
+        WrappingMode::Repeat => TextureWrap::Repeat,
+    };
+
+    GltfSampler {
+        mag_filter: sampler.mag_filter().map(|filter| match filter {
+            MagFilter::Nearest => Nearest,
+            MagFilter::Linear => Linear,
+        }),
+        min_filter: sampler.min_filter().map(|filter| match filter {
+            MinFilter::Nearest => (Nearest, None),
+            MinFilter::Linear => (Linear, None),
+            MinFilter::NearestMipmapNearest => (Nearest, Some(Nearest)),
+            MinFilter::LinearMipmapNearest => (Linear, Some(Nearest)),
+            MinFilter::NearestMipmapLinear => (Nearest, Some(Linear)),
+            MinFilter::LinearMipmapLinear => (Linear, Some(Linear)),
+        }),
+        wrap_s: wrap(sampler.wrap_s()),
+        wrap_t: wrap(sampler.wrap_t()),
+    }
+}
+
+fn to_rgba8(image: ::gltf::image::Data) -> GltfImage {
+    let (channels, channel_size) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+
+    let channel = |bytes: &[u8]| -> u8 {
+        match channel_size {
+            1 => bytes[0],
+            // little endian, so the most significant byte is the last one
+            2 => bytes[1],
+            _ => (f32::from_le_bytes(bytes.try_into().unwrap()).clamp(0.0, 1.0) * 255.0).round() as u8,
+        }
+    };
+
+    let pixels = image
+        .pixels
+        .chunks_exact(channels * channel_size)
+        .flat_map(|pixel| {
+            let mut rgba = [0, 0, 0, 255];
+            for (i, value) in pixel.chunks_exact(channel_size).enumerate() {
+                rgba[i] = channel(value);
+            }
+            if channels == 1 {
+                // grayscale
+                rgba[1] = rgba[0];
+                rgba[2] = rgba[0];
+            }
+            rgba
+        })
+        .collect();
+
+    GltfImage { width: image.width, height: image.height, pixels }
+}
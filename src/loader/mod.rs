@@ -0,0 +1,3 @@
+pub mod compressed;
+pub mod gltf;
+pub mod obj;
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::WrapperError;
+use crate::mesh::{Mesh, MeshData, MeshVertex, Normals};
+
+/// Reads Wavefront .obj file into a single triangle mesh.
+#[allow(dead_code)]
+pub fn load(path: impl AsRef<Path>) -> Result<Mesh, WrapperError> {
+    load_data(path)?.upload()
+}
+
+#[allow(dead_code)]
+pub fn load_data(path: impl AsRef<Path>) -> Result<MeshData, WrapperError> {
+    let path = path.as_ref();
+    let src = std::fs::read_to_string(path)
+        .map_err(|source| WrapperError::Io { path: path.to_path_buf(), source })?;
+
+    parse(&src)
+}
+
+/// Understands `v`, `vt`, `vn` and `f` statements, everything else (groups, materials,
+/// smoothing) is skipped. Polygons are triangulated as fans, and each unique
+/// position/uv/normal combination becomes one vertex. When a face vertex has no normal,
+/// smooth normals are computed for the whole mesh. Tangents are generated when the file has UVs.
+pub fn parse(src: &str) -> Result<MeshData, WrapperError> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+
+    let mut data = MeshData::default();
+    let mut unique: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+    let mut missing_normals = false;
+
+    for (i, line) in src.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut parts = line.split_whitespace();
+
+        match parts.next() {
+            Some("v") => positions.push(parse_floats(parts, line_no)?),
+            Some("vt") => {
+                // third (w) texture coordinate is optional and unused
+                let [u, v, _]: [f32; 3] = parse_floats(parts.chain(std::iter::once("0")).take(3), line_no)?;
+                uvs.push([u, v]);
+            }
+            Some("vn") => normals.push(parse_floats(parts, line_no)?),
+            Some("f") => {
+                let mut face = Vec::new();
+
+                for corner in parts {
+                    let key = parse_corner(corner, positions.len(), uvs.len(), normals.len(), line_no)?;
+                    missing_normals |= key.2.is_none();
+
+                    let index = *unique.entry(key).or_insert_with(|| {
+                        let (p, t, n) = key;
+                        data.vertices.push(MeshVertex {
+                            position: positions[p],
+                            normal: n.map_or([0.0; 3], |n| normals[n]),
+                            uv: t.map_or([0.0; 2], |t| uvs[t]),
+                            tangent: [0.0; 4],
+                        });
+                        (data.vertices.len() - 1) as u32
+                    });
+                    face.push(index);
+                }
+
+                if face.len() < 3 {
+                    return Err(parse_error(line_no, "face needs at least 3 vertices"));
+                }
+
+                for k in 1..face.len() - 1 {
+                    data.indices.extend_from_slice(&[face[0], face[k], face[k + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if missing_normals {
+        data.compute_normals(Normals::Smooth);
+    }
+    if !uvs.is_empty() {
+        data.generate_tangents();
+    }
+
+    Ok(data)
+}
+
+fn parse_floats<'a, const N: usize>(parts: impl Iterator<Item = &'a str>, line: usize) -> Result<[f32; N], WrapperError> {
+    let mut values = [0.0; N];
+    let mut count = 0;
+
+    for part in parts.take(N) {
+        values[count] = part.parse().map_err(|_| parse_error(line, &format!("'{}' is not a number", part)))?;
+        count += 1;
+    }
+
+    if count == N {
+        Ok(values)
+    } else {
+        Err(parse_error(line, &format!("expected {} numbers, found {}", N, count)))
+    }
+}
+
+/// Turns `v`, `v/vt`, `v//vn` or `v/vt/vn` into zero-based indices.
+fn parse_corner(
+    corner: &str,
+    positions: usize,
+    uvs: usize,
+    normals: usize,
+    line: usize,
+) -> Result<(usize, Option<usize>, Option<usize>), WrapperError> {
+    let mut refs = corner.split('/');
+
+    let position = resolve_index(refs.next(), positions, line)?
+        .ok_or_else(|| parse_error(line, "face vertex has no position"))?;
+    let uv = resolve_index(refs.next(), uvs, line)?;
+    let normal = resolve_index(refs.next(), normals, line)?;
+
+    Ok((position, uv, normal))
+}
+
+/// OBJ indices are 1-based, negative ones count back from the last element defined so far.
+fn resolve_index(value: Option<&str>, len: usize, line: usize) -> Result<Option<usize>, WrapperError> {
+    let value = match value {
+        Some(value) if !value.is_empty() => value,
+        _ => return Ok(None),
+    };
+
+    let index: i64 = value.parse().map_err(|_| parse_error(line, &format!("'{}' is not an index", value)))?;
+    let resolved = if index < 0 { len as i64 + index } else { index - 1 };
+
+    if (0..len as i64).contains(&resolved) {
+        Ok(Some(resolved as usize))
+    } else {
+        Err(parse_error(line, &format!("index {} is out of range", index)))
+    }
+}
+
+fn parse_error(line: usize, message: &str) -> WrapperError {
+    WrapperError::Parse { line, message: message.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD: &str = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vn 0 0 1
+f 1//1 2//1 3//1 4//1
+";
+
+    #[test]
+    fn quads_become_two_triangles() {
+        let data = parse(QUAD).unwrap();
+        assert_eq!(data.indices, [0, 1, 2, 0, 2, 3]);
+        assert_eq!(data.vertices.len(), 4);
+        assert!(data.vertices.iter().all(|vertex| vertex.normal == [0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn negative_indices_count_back() {
+        let relative = parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nvn 0 0 1\nf -3//-1 -2//-1 -1//-1\n").unwrap();
+        let absolute = parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\n").unwrap();
+        assert_eq!(relative.indices, absolute.indices);
+        assert_eq!(relative.vertices, absolute.vertices);
+    }
+
+    #[test]
+    fn shared_corners_are_one_vertex() {
+        let data = parse(&format!("{}f 1//1 3//1 4//1\n", QUAD)).unwrap();
+        assert_eq!(data.vertices.len(), 4);
+        assert_eq!(data.indices.len(), 9);
+    }
+
+    #[test]
+    fn missing_normals_are_computed() {
+        let data = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        assert!(data.vertices.iter().all(|vertex| (vertex.normal[2] - 1.0).abs() < 1e-6));
+    }
+
+    #[test]
+    fn out_of_range_indices_name_their_line() {
+        let err = parse("v 0 0 0\nv 1 0 0\n# comment\nf 1 2 3\n").unwrap_err();
+        assert!(matches!(err, WrapperError::Parse { line: 4, .. }), "{}", err);
+
+        let err = parse("v 0 0 0\nf 1 -2 1\n").unwrap_err();
+        assert!(matches!(err, WrapperError::Parse { line: 2, .. }), "{}", err);
+        assert!(matches!(parse("f 0 0 0\n"), Err(WrapperError::Parse { line: 1, .. })));
+    }
+
+    #[test]
+    fn texture_coordinates_take_an_optional_w() {
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0.25 0.5\nvt 0.5 0.75 0.1\nvt 1 1\nf 1/1 2/2 3/3\n";
+        let data = parse(src).unwrap();
+        let uvs: Vec<[f32; 2]> = data.vertices.iter().map(|vertex| vertex.uv).collect();
+        assert_eq!(uvs, [[0.25, 0.5], [0.5, 0.75], [1.0, 1.0]]);
+
+        assert!(matches!(parse("vt 0.5\n"), Err(WrapperError::Parse { line: 1, .. })));
+    }
+}
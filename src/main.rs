@@ -1,251 +1,674 @@
-use std::mem::size_of;
-
-use glfw::{Action, Context, Key, WindowEvent};
+// the demo is built on GLFW, without it only the library modules are compiled
+#![cfg_attr(not(feature = "glfw"), allow(dead_code, unused_imports))]
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::app::App;
+use crate::assets::{Assets, Handle};
+use crate::backend::Backend;
+use crate::bloom::Bloom;
+use crate::camera::{AspectFit, AspectPolicy, Ortho2D};
+use crate::error::WrapperError;
+use crate::event::{Key, Modifiers, MouseButton, WindowEvent};
+use crate::framebuffer::Framebuffer;
+use crate::gpu_timer::GpuTimer;
+use crate::input::{Binding, InputMap};
+#[cfg(feature = "glfw")]
+use crate::input::Gamepad;
+use crate::lines::LineRenderer;
+use crate::mesh::Mesh;
+use crate::postprocess::{PostPass, PostProcess, Tonemap};
+use crate::recorder::{Recorder, RecorderOutput};
+use crate::scene_file::{MeshSource, SceneFile};
+use crate::time::{FrameStats, Time};
+use crate::transform::{MODEL_UNIFORM, Transform};
+use crate::streaming::{DynamicBuffer, StreamStrategy};
+use crate::text::TextRenderer;
+use crate::wrapper::{
+    Attribute, BiIndices, BufferType, clear, clear_array_binding, CompareFunc, FullscreenMode, PrimitiveMode, Settings,
+    ShaderProgram, TextureFormat, TriIndices, UniformValue, Vertex,
+};
+
+mod app;
+mod assets;
+mod atlas;
+mod backend;
+mod bloom;
+mod camera;
+mod capture;
+mod curves;
+#[cfg(feature = "debug")]
+mod debug;
+mod debug_draw;
+mod deferred;
+#[cfg(feature = "ecs")]
+mod ecs;
+mod error;
+mod event;
+mod forward_plus;
+mod framebuffer;
+mod gl_ext;
+#[cfg(feature = "glfw")]
+mod glfw_backend;
+#[cfg(feature = "glow")]
+mod glow_context;
+mod gpu_timer;
+mod grid;
+mod ibl;
+mod input;
+mod lighting;
+mod lines;
+mod loader;
+mod material;
+mod mesh;
+mod postprocess;
+mod preprocessor;
+mod program_cache;
+mod recorder;
+mod reflection;
+mod render_graph;
+mod scene;
+mod scene_file;
+#[cfg(feature = "sdl2")]
+mod sdl_backend;
+mod shader_variants;
+mod shader_watcher;
+mod shadow;
+mod shapes;
+mod skybox;
+mod sprite;
+mod ssao;
+mod streaming;
+mod text;
+mod time;
+mod transform;
+#[cfg(feature = "winit")]
+mod winit_backend;
+mod wrapper;
 
-use crate::wrapper::{ArrayBuffer, BiIndices, BufferType, clear_array_binding, clear_mask, draw_lines, draw_triangles, EBO_LOAD_ERROR, Settings, ShaderProgram, TriIndices, VAO_LOAD_ERROR, VBO_LOAD_ERROR, Vertex, VertexArray};
+const TITLE: &str = "Rust is safe C";
+const MSAA_SAMPLES: u32 = 4;
+/// frames the statistics in the window title are taken over
+const STATS_FRAMES: usize = 120;
+/// seconds simulated by every update, independent of the frame rate
+const UPDATE_STEP: f32 = 1.0 / 60.0;
+/// copies per row and column of the instanced grid
+const GRID_SIZE: usize = 32;
+/// pixels across the shape's outline
+const OUTLINE_WIDTH: f32 = 3.0;
+/// frames recorded with R keep every n-th frame
+const RECORD_EVERY: u32 = 2;
+/// objects, colors and shaders of the demo
+const SCENE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/demo.ron");
+/// fonts tried for the overlay, the first one found is used
+const FONT_PATHS: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+/// pixel size of the overlay text
+const OVERLAY_TEXT_SIZE: f32 = 16.0;
+const HELP_TEXT: &str = "WASD move, drag or C to capture the cursor, scroll to zoom\n\
+    1-4 grayscale/invert/vignette/tonemap, T tonemap curve, B bloom\n\
+    M msaa, I instanced, Tab polygon mode, V aspect policy\n\
+    F12 screenshot, R record, P GPU times, Alt+Enter fullscreen, Alt+Esc quit\n\
+    H toggles this help";
+
+/// Shape of the demo with its colors and shader, as described in `scenes/demo.ron`.
+struct Figure {
+    vertices: Vec<Vertex>,
+    triangles: Vec<TriIndices>,
+    lines: Vec<BiIndices>,
+    fill: [f32; 4],
+    outline: [f32; 4],
+    clear_color: [f32; 4],
+    vertex_shader: PathBuf,
+    fragment_shader: PathBuf,
+}
 
-mod wrapper;
+/// Reads the "figure" object of the demo scene, its inline mesh and shader, the fill color of
+/// its material and the color of the "outline" material.
+fn load_figure() -> Result<Figure, WrapperError> {
+    let scene = SceneFile::load(SCENE_PATH)?;
+    let missing = |what: &str| WrapperError::SceneFile { path: SCENE_PATH.into(), message: format!("no {}", what) };
 
-macro_rules! match_all_movement_keys {
-    ($action: pat) => {
-            WindowEvent::Key(Key::W, _, $action, _) | WindowEvent::Key(Key::S, _, $action, _) |
-            WindowEvent::Key(Key::A, _, $action, _) | WindowEvent::Key(Key::D, _, $action, _)
+    let figure = scene.find("figure").ok_or_else(|| missing("\"figure\" object"))?;
+    let Some(MeshSource::Inline { positions, triangles, lines }) = &figure.mesh else {
+        return Err(missing("inline mesh in \"figure\""));
+    };
+    let shader = figure.shader.as_ref().ok_or_else(|| missing("shader for \"figure\""))?;
+    let color = |material: Option<&str>| {
+        material.and_then(|name| scene.materials.get(name)).map_or([1.0; 4], |material| material.albedo)
     };
-}
 
-fn get_vertices(landslide: &[f32; 2]) -> [Vertex; 17] {
-    let mut vertices = [
-        [-0.81, 0.12, 0.0],
-        [-0.81, 0.468, 0.0],
-        [-0.632, 0.291, 0.0],
-        [-0.557, 0.364, 0.0],
-        [-0.557, 0.12, 0.0],
-        [-0.557, -0.141, 0.0],
-        [-0.81, -0.141, 0.0],
-        [-0.557, -0.011, 0.0],
-        [-0.04, -0.011, 0.0],
-        [0.477, -0.011, 0.0],
-        [-0.3, -0.27, 0.0],
-        [0.22, -0.27, 0.0],
-        [-0.557, -0.526, 0.0],
-        [0.477, -0.526, 0.0],
-        [0.544, 0.236, 0.0],
-        [0.85, 0.408, 0.0],
-        [0.792, 0.168, 0.0],
-    ];
-
-    vertices.iter_mut().for_each(|vertex| {
-        vertex[0] += landslide[0];
-        vertex[1] += landslide[1];
-    });
+    Ok(Figure {
+        vertices: positions.clone(),
+        triangles: triangles.clone(),
+        lines: lines.clone(),
+        fill: color(figure.material.as_deref()),
+        outline: color(Some("outline")),
+        clear_color: scene.clear_color,
+        vertex_shader: scene.resolve(&shader.vertex),
+        fragment_shader: scene.resolve(&shader.fragment),
+    })
+}
 
-    vertices
+/// Actions of the demo and their default keys.
+fn get_input_map() -> InputMap {
+    InputMap::new()
+        .with("move_up", Binding::Key(Key::W))
+        .with("move_down", Binding::Key(Key::S))
+        .with("move_left", Binding::Key(Key::A))
+        .with("move_right", Binding::Key(Key::D))
+        .with("drag", Binding::Mouse(MouseButton::Left))
+        .with("toggle_grayscale", Binding::Key(Key::Num1))
+        .with("toggle_invert", Binding::Key(Key::Num2))
+        .with("toggle_vignette", Binding::Key(Key::Num3))
+        .with("toggle_tonemap", Binding::Key(Key::Num4))
+        .with("tonemap_curve", Binding::Key(Key::T))
+        .with("toggle_bloom", Binding::Key(Key::B))
+        .with("toggle_msaa", Binding::Key(Key::M))
+        .with("toggle_instanced", Binding::Key(Key::I))
+        .with("screenshot", Binding::Key(Key::F12))
+        .with("record", Binding::Key(Key::R))
+        .with("gpu_times", Binding::Key(Key::P))
+        .with("capture_cursor", Binding::Key(Key::C))
+        .with("aspect_policy", Binding::Key(Key::V))
+        .with("fullscreen", Binding::KeyWith(Key::Enter, Modifiers::ALT))
+        .with("polygon_mode", Binding::Key(Key::Tab))
+        .with("toggle_help", Binding::Key(Key::H))
+        .with("quit", Binding::KeyWith(Key::Escape, Modifiers::ALT))
 }
 
-fn get_triangles_indices() -> [TriIndices; 9] {
-    [
-        [0, 1, 2],
-        [0, 3, 4],
-        [0, 4, 6],
-        [4, 5, 6],
-        [7, 8, 12],
-        [8, 10, 11],
-        [8, 9, 13],
-        [9, 14, 16],
-        [14, 15, 16]
-    ]
+/// xy offset and scale (in w) of every copy, filling the screen with a grid of small shapes
+/// whose rows sway with `time`.
+fn get_instances(time: f32) -> Vec<[f32; 4]> {
+    let cell = 2.0 / GRID_SIZE as f32;
+    (0..GRID_SIZE * GRID_SIZE)
+        .map(|i| {
+            let (col, row) = ((i % GRID_SIZE) as f32, (i / GRID_SIZE) as f32);
+            let sway = (time * 2.0 + row * 0.4).sin() * cell * 0.25;
+            [-1.0 + (col + 0.5) * cell + sway, -1.0 + (row + 0.5) * cell, 0.0, cell / 2.0]
+        })
+        .collect()
 }
 
-fn get_lines_indices() -> [BiIndices; 19] {
-    [
-        [0, 1],
-        [1, 2],
-        [0, 3],
-        [3, 4],
-        [0, 4],
-        [0, 6],
-        [4, 7],
-        [5, 6],
-        [7, 12],
-        [7, 8],
-        [8, 9],
-        [8, 12],
-        [8, 13],
-        [10, 11],
-        [9, 13],
-        [9, 14],
-        [9, 16],
-        [14, 15],
-        [15, 16]
-    ]
+fn unix_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0)
 }
 
-fn process_events(setup: &mut wrapper::Setup, settings: &mut Settings) {
-    for (_, event) in glfw::flush_messages(&setup.events) {
-        settings.move_img(&setup.window);
+/// Everything the windowed demo keeps between frames.
+#[cfg(feature = "glfw")]
+struct Demo {
+    settings: Settings,
+    /// landslide and clock as of the update before the latest, `render` blends from them
+    previous: ([f32; 2], f32),
+    /// simulated seconds, drives the sway of the instanced grid
+    clock: f32,
+    figure: Figure,
+    /// meshes and the shader, which is rebuilt when its files change
+    assets: Assets,
+    shader: Handle<ShaderProgram>,
+    shape: Handle<Mesh>,
+    /// the edges of the shape or of every copy in screen space pixels wide, `glLineWidth` may
+    /// be clamped to 1
+    outline: LineRenderer,
+    crowd: Handle<Mesh>,
+    instances: DynamicBuffer<[f32; 4]>,
+    post: PostProcess,
+    tonemap: Tonemap,
+    recorder: Option<Recorder>,
+    aspect: AspectFit,
+    input: InputMap,
+    gamepad: Gamepad,
+    stats: FrameStats,
+    /// GPU time of the triangle pass and of the line pass
+    timers: [GpuTimer; 2],
+    /// FPS counter and key help on top of the frame, `None` without a font
+    text: Option<TextRenderer>,
+    overlay: Ortho2D,
+    show_help: bool,
+}
 
-        match event {
-            WindowEvent::FramebufferSize(width, height) => {
-                unsafe {
-                    gl::Viewport(0, 0, width, height);
-                }
+#[cfg(feature = "glfw")]
+impl Demo {
+    /// Reacts to the actions triggered since the previous frame.
+    fn handle_actions(&mut self, setup: &mut wrapper::Setup) {
+        let (input, settings, post) = (&mut self.input, &mut self.settings, &mut self.post);
+
+        for (action, pass) in [
+            ("toggle_grayscale", "grayscale"), ("toggle_invert", "invert"), ("toggle_vignette", "vignette"),
+            ("toggle_tonemap", "tonemap"),
+        ] {
+            if input.just_pressed(action) {
+                post.toggle(pass);
             }
-            WindowEvent::Key(Key::Escape, _, Action::Press, glfw::Modifiers::Alt) => {
-                setup.window.set_should_close(true);
+        }
+        if input.just_pressed("toggle_bloom") {
+            post.toggle_bloom();
+        }
+        if input.just_pressed("tonemap_curve") {
+            self.tonemap = self.tonemap.next();
+            if let Some(pass) = post.pass_mut("tonemap") {
+                pass.set_uniform("curve", UniformValue::I32(self.tonemap as i32));
             }
-            match_all_movement_keys!(Action::Release) => {
-                settings.reset_params();
+            println!("Tonemap: {:?}", self.tonemap);
+        }
+        if input.just_pressed("toggle_msaa") {
+            settings.msaa = !settings.msaa;
+            wrapper::multisample(settings.msaa);
+        }
+        if input.just_pressed("toggle_instanced") {
+            settings.instanced = !settings.instanced;
+        }
+        if input.just_pressed("screenshot") {
+            settings.screenshot = true;
+        }
+        if input.just_pressed("record") {
+            settings.recording = !settings.recording;
+        }
+        if input.just_pressed("gpu_times") {
+            let [triangles, lines] = &self.timers;
+            println!("GPU: triangles {:.3} ms, lines {:.3} ms", triangles.elapsed_ms(), lines.elapsed_ms());
+        }
+        if input.just_pressed("polygon_mode") {
+            settings.polygon_mode = settings.polygon_mode.next();
+            wrapper::polygon_mode(settings.polygon_mode);
+        }
+        if input.just_pressed("capture_cursor") {
+            // moving the mouse drags the shape without a button while captured
+            let capture = !setup.is_cursor_captured();
+            setup.capture_cursor(capture);
+            input.reset_cursor();
+        }
+        if input.just_pressed("aspect_policy") {
+            self.aspect.policy = self.aspect.policy.next();
+            println!("Aspect policy: {:?}", self.aspect.policy);
+        }
+        if input.just_pressed("fullscreen") {
+            let mode = match setup.fullscreen() {
+                FullscreenMode::Windowed => FullscreenMode::Borderless(0),
+                _ => FullscreenMode::Windowed,
+            };
+            if let Err(err) = setup.set_fullscreen(mode) {
+                eprintln!("Fullscreen failed: {}", err);
             }
-            _ => {}
+        }
+        if input.just_pressed("toggle_help") {
+            self.show_help = !self.show_help;
+        }
+        if input.just_pressed("quit") {
+            setup.set_should_close(true);
+        }
+        if input.pressed("drag") || setup.is_cursor_captured() {
+            // screen coordinates to the -1..1 range the shape moves in, y flipped and undoing the
+            // aspect fit
+            let (width, height) = setup.window.get_size();
+            let [dx, dy] = input.cursor_delta();
+            let scale = self.aspect.scale();
+            let offset = [
+                dx as f32 * 2.0 / width.max(1) as f32 / scale.x,
+                -dy as f32 * 2.0 / height.max(1) as f32 / scale.y,
+            ];
+            settings.drag(offset);
+            // no interpolation lag, the shape sticks to the cursor
+            self.previous.0[0] += offset[0];
+            self.previous.0[1] += offset[1];
+        }
+        let [_, scroll] = input.scroll();
+        if scroll != 0.0 {
+            settings.zoom_by(scroll as f32);
+        }
+        if ["move_up", "move_down", "move_left", "move_right"].iter().any(|action| input.just_released(action)) {
+            settings.reset_params();
         }
     }
 
+    /// Frame statistics and, unless hidden, the key help in the top left corner.
+    fn draw_overlay(&mut self) {
+        let Some(text) = self.text.as_mut() else {
+            return;
+        };
+
+        let margin = OVERLAY_TEXT_SIZE * 0.5;
+        let line = text.font().line_height(OVERLAY_TEXT_SIZE);
+        text.begin(&self.overlay);
+        text.draw_text(&self.stats.to_string(), margin, margin, OVERLAY_TEXT_SIZE, [1.0, 1.0, 1.0, 1.0]);
+        if self.show_help {
+            text.draw_text(HELP_TEXT, margin, margin + line * 1.5, OVERLAY_TEXT_SIZE, [1.0, 1.0, 1.0, 0.8]);
+        }
+        text.end();
+    }
 }
 
-fn main() {
-    let mut setup = wrapper::Setup::new(800, 600, "Rust is safe C");
+#[cfg(feature = "glfw")]
+impl App<wrapper::Setup> for Demo {
+    fn event(&mut self, _setup: &mut wrapper::Setup, event: WindowEvent) {
+        self.input.handle_event(&event);
 
-    let mut settings = Settings::new();
+        if let WindowEvent::FramebufferSize(width, height) = event {
+            unsafe {
+                crate::checked!(gl::Viewport(0, 0, width, height));
+            }
+            self.post.resize(width.max(1) as u32, height.max(1) as u32);
+            self.aspect.resize(width, height);
+            self.overlay.set_viewport(width, height);
+        }
+    }
 
-    let vertices = get_vertices(&settings.landslide);
-    let triangles = get_triangles_indices();
-    let lines = get_lines_indices();
+    fn update(&mut self, _setup: &mut wrapper::Setup, dt: f32) {
+        match self.gamepad.poll() {
+            Some(glfw::JoystickEvent::Connected) => {
+                println!("Gamepad connected: {}", self.gamepad.name().unwrap_or_default());
+            }
+            Some(glfw::JoystickEvent::Disconnected) => println!("Gamepad disconnected"),
+            None => {}
+        }
 
-    let vbo = ArrayBuffer::new().expect(VBO_LOAD_ERROR);
-    vbo.bind(BufferType::Array);
-    wrapper::buffer_data(
-        BufferType::Array,
-        bytemuck::cast_slice(&vertices),
-        gl::STATIC_DRAW,
-    );
+        self.previous = (self.settings.landslide, self.clock);
+        self.settings.move_img(&self.input, self.gamepad.left_stick(), dt);
+        self.clock += dt;
+    }
 
-    let vao1 = VertexArray::new().expect(VAO_LOAD_ERROR);
-    vao1.bind();
+    fn render(&mut self, setup: &mut wrapper::Setup, time: &Time, alpha: f32) {
+        self.handle_actions(setup);
+        self.input.end_frame();
 
-    let ebo1 = ArrayBuffer::new().expect(EBO_LOAD_ERROR);
-    ebo1.bind(BufferType::ElementArray);
-    wrapper::buffer_data(
-        BufferType::ElementArray,
-        bytemuck::cast_slice(&triangles),
-        gl::STATIC_DRAW,
-    );
+        self.stats.record(time.delta);
+        self.stats.update_title(|title| setup.window.set_title(title));
+
+        for err in self.assets.update() {
+            eprintln!("Reload failed, keeping the previous version: {}", err);
+        }
+        let (Some(shader), Some(shape), Some(crowd)) =
+            (self.assets.get(self.shader), self.assets.get(self.shape), self.assets.get(self.crowd))
+        else {
+            return;
+        };
+
+        let (landslide, clock) = self.previous;
+        let landslide = Vec2::from(landslide).lerp(Vec2::from(self.settings.landslide), alpha);
+        let clock = clock + (self.clock - clock) * alpha;
+        let transform = Transform::from_translation(landslide.extend(0.0)).with_scale(Vec3::splat(self.settings.zoom));
+
+        self.post.begin();
+        if self.aspect.policy == AspectPolicy::Letterbox {
+            clear(Some([0.0, 0.0, 0.0, 1.0]), Some(1.0));
+            wrapper::scissor(Some(self.aspect.content_rect()));
+        }
+        clear(Some(self.figure.clear_color), Some(1.0));
+
+        shader.use_program();
+        let model = self.aspect.projection_matrix() * transform.matrix();
+        shader.set_mat4(MODEL_UNIFORM, &model.to_cols_array());
+
+        shader.set_vec4("color", self.figure.fill);
+        self.timers[0].begin();
+        let copies = if self.settings.instanced { get_instances(clock) } else { vec![[0.0, 0.0, 0.0, 1.0]] };
+        if self.settings.instanced {
+            self.instances.upload(&copies);
+            crowd.draw_instanced(self.instances.len() as i32);
+        } else {
+            shape.draw();
+        }
+        self.timers[0].end();
+
+        let (fb_width, fb_height) = setup.framebuffer_size();
+        self.timers[1].begin();
+        // the outlines of the copies are placed on the CPU, the way the vertex shader places the shapes
+        for [x, y, _, scale] in copies {
+            let offset = Vec3::new(x, y, 0.0);
+            let points: Vec<Vec3> = self.figure.vertices.iter().map(|&vertex| Vec3::from(vertex) * scale + offset).collect();
+            if let Err(err) = self.outline.segments(&points, &self.figure.lines, OUTLINE_WIDTH, self.figure.outline) {
+                eprintln!("Outline skipped: {}", err);
+                break;
+            }
+        }
+        self.outline.draw(model, fb_width, fb_height);
+        self.timers[1].end();
+        wrapper::scissor(None);
+
+        self.post.finish(fb_width, fb_height);
+        self.draw_overlay();
+
+        if self.settings.screenshot {
+            self.settings.screenshot = false;
+            let path = format!("screenshot-{}.png", unix_secs());
+            match capture::capture_frame(&path, fb_width as u32, fb_height as u32) {
+                Ok(()) => println!("Saved {}", path),
+                Err(err) => eprintln!("Screenshot failed: {}", err),
+            }
+        }
+
+        match (self.settings.recording, self.recorder.as_mut()) {
+            (true, Some(recorder)) => recorder.record(fb_width as u32, fb_height as u32),
+            (true, None) => {
+                let dir = format!("recording-{}", unix_secs());
+                match Recorder::new(RecorderOutput::PngSequence(dir.clone().into()), RECORD_EVERY) {
+                    Ok(new) => {
+                        println!("Recording to {}/", dir);
+                        self.recorder = Some(new);
+                    }
+                    Err(err) => {
+                        eprintln!("Recording failed: {}", err);
+                        self.settings.recording = false;
+                    }
+                }
+            }
+            (false, Some(_)) => {
+                match self.recorder.take().map(Recorder::finish) {
+                    Some(Err(err)) => eprintln!("Recording failed: {}", err),
+                    _ => println!("Recording stopped"),
+                }
+            }
+            (false, None) => {}
+        }
 
-    unsafe {
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            size_of::<Vertex>().try_into().unwrap(),
-            0 as *const _,
-        );
-        gl::EnableVertexAttribArray(0);
+        clear_array_binding();
     }
+}
 
-    let vao2 = VertexArray::new().expect(VAO_LOAD_ERROR);
-    vao2.bind();
+/// Draws one frame of the shape into an offscreen framebuffer and saves it, no window shown.
+#[cfg(feature = "glfw")]
+fn render_headless(path: &str) {
+    let (width, height) = (800, 600);
+    let _setup = match wrapper::Setup::new_headless(width, height) {
+        Ok(setup) => setup,
+        Err(err) => panic!("{}", err)
+    };
 
-    let ebo2 = ArrayBuffer::new().expect(EBO_LOAD_ERROR);
-    ebo2.bind(BufferType::ElementArray);
-    wrapper::buffer_data(
-        BufferType::ElementArray,
-        bytemuck::cast_slice(&lines),
-        gl::STATIC_DRAW,
-    );
+    let figure = match load_figure() {
+        Ok(figure) => figure,
+        Err(err) => panic!("{}", err)
+    };
+    let mut assets = Assets::new();
+    let shape = match Mesh::new(&figure.vertices, figure.triangles.as_flattened(), PrimitiveMode::Triangles) {
+        Ok(mesh) => assets.add(mesh),
+        Err(err) => panic!("{}", err)
+    };
+    let mut outline = match LineRenderer::new() {
+        Ok(lines) => lines,
+        Err(err) => panic!("{}", err)
+    };
+    let points: Vec<Vec3> = figure.vertices.iter().copied().map(Vec3::from).collect();
+    if let Err(err) = outline.segments(&points, &figure.lines, OUTLINE_WIDTH, figure.outline) {
+        panic!("{}", err)
+    }
+
+    let shader = match assets.load_shader(&figure.vertex_shader, &figure.fragment_shader) {
+        Ok(handle) => handle,
+        Err(err) => panic!("{}", err)
+    };
+    // just loaded and added, so both resolve
+    let (Some(shader), Some(shape)) = (assets.get(shader), assets.get(shape)) else {
+        return;
+    };
+
+    let target = match Framebuffer::builder(width, height)
+        .color_texture(TextureFormat::Rgba8)
+        .depth_renderbuffer(TextureFormat::Depth24Stencil8)
+        .build() {
+        Ok(target) => target,
+        Err(err) => panic!("{}", err)
+    };
 
-    unsafe {
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            size_of::<Vertex>().try_into().unwrap(),
-            0 as *const _,
-        );
-        gl::EnableVertexAttribArray(0);
-
-        gl::LineWidth(3.0);
+    target.bind();
+    wrapper::depth_test(true);
+    wrapper::depth_func(CompareFunc::LessEqual);
+    clear(Some(figure.clear_color), Some(1.0));
+
+    shader.use_program();
+    Transform::IDENTITY.upload(shader);
+    shader.set_vec4("color", figure.fill);
+    shape.draw();
+    outline.draw(Mat4::IDENTITY, width as i32, height as i32);
+
+    match target.save(0, path) {
+        Ok(()) => println!("Saved {}", path),
+        Err(err) => panic!("{}", err)
     }
+}
 
-    clear_array_binding();
+#[cfg(not(feature = "glfw"))]
+fn main() {
+    eprintln!("The demo needs the `glfw` feature, the winit and SDL backends are for applications of their own.");
+}
 
-    let vert_src = r#"
-            #version 330 core
-            layout (location = 0) in vec3 pos;
+#[cfg(feature = "glfw")]
+fn main() {
+    #[cfg(feature = "debug")]
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // `--headless out.png` renders a single frame to a file instead of opening the demo
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--headless") {
+        render_headless(args.get(i + 1).map(String::as_str).unwrap_or("headless.png"));
+        return;
+    }
 
-            void main() {
-                gl_Position = vec4(pos.x, pos.y, pos.z, 1.0);
-            }
-        "#;
-    let frag_triangle_src = r#"
-            #version 330 core
-            out vec4 FragColor;
+    let mut setup = match wrapper::Setup::builder(800, 600, TITLE).samples(MSAA_SAMPLES).build() {
+        Ok(setup) => setup,
+        Err(err) => panic!("{}", err)
+    };
+    println!(
+        "OpenGL {}.{} {:?}, {} extensions, DSA: {}, debug output: {}, sRGB: {}",
+        setup.gl_version.0, setup.gl_version.1, setup.profile, setup.extensions.len(),
+        setup.extensions.direct_state_access(), setup.extensions.debug(), setup.srgb
+    );
 
-            void main() {
-                FragColor = vec4(0.0f, 0.5f, 0.00f, 1.0f);
-            }
-        "#;
-    let frag_line_src = r#"
-            #version 330 core
-            out vec4 FragColor;
+    let settings = Settings::new();
 
-            void main() {
-                FragColor = vec4(0.0f, 0.0f, 0.00f, 1.0f);
-            }
-        "#;
+    let figure = match load_figure() {
+        Ok(figure) => figure,
+        Err(err) => panic!("{}", err)
+    };
 
-    let shader_triangle = match ShaderProgram::from_vertex_fragment(vert_src, frag_triangle_src) {
-        Ok(program) => program,
+    // the shader is rebuilt by `Assets::update` when it or one of its includes is saved
+    let mut assets = Assets::new();
+    if let Err(err) = assets.watch() {
+        panic!("{}", err)
+    }
+    let shader = match assets.load_shader(&figure.vertex_shader, &figure.fragment_shader) {
+        Ok(handle) => handle,
         Err(err) => panic!("{}", err)
     };
 
-    let shader_line = match ShaderProgram::from_vertex_fragment(vert_src, frag_line_src) {
-        Ok(program) => program,
+    let shape = match Mesh::new(&figure.vertices, figure.triangles.as_flattened(), PrimitiveMode::Triangles) {
+        Ok(mesh) => {
+            mesh.set_label("shape");
+            assets.add(mesh)
+        }
         Err(err) => panic!("{}", err)
     };
 
-    wrapper::clear_color(0.8, 0.4, 0.0, 1.0);
+    let outline = match LineRenderer::new() {
+        Ok(lines) => lines,
+        Err(err) => panic!("{}", err)
+    };
 
-    while !setup.window.should_close() {
-        let prev_set = settings.clone();
-        process_events(&mut setup, &mut settings);
+    // rewritten every frame, orphaning keeps the single buffer the vertex arrays point at
+    let mut instances = match DynamicBuffer::new(BufferType::Array, GRID_SIZE * GRID_SIZE, StreamStrategy::Orphan) {
+        Ok(buffer) => buffer,
+        Err(err) => panic!("{}", err)
+    };
+    instances.upload(&get_instances(0.0));
+    instances.set_label("instances");
+
+    // same geometry with the per-instance attribute attached, the mesh above keeps reading
+    // the attribute's default and stays a single full size copy
+    let crowd = match Mesh::new(&figure.vertices, figure.triangles.as_flattened(), PrimitiveMode::Triangles) {
+        Ok(mesh) => {
+            instances.attach(mesh.vao(), &[Attribute::vec4(1).per_instance()]);
+            mesh.set_label("crowd");
+            assets.add(mesh)
+        }
+        Err(err) => panic!("{}", err)
+    };
+    clear_array_binding();
 
-        let mut settings_has_changed = false;
-        for i in 0..prev_set.landslide.len() {
-            if prev_set.landslide[i] != settings.landslide[i] {
-                settings_has_changed = true;
+    let (fb_width, fb_height) = setup.window.get_framebuffer_size();
+    let samples = MSAA_SAMPLES.min(wrapper::max_samples());
+    let mut post = match PostProcess::with_format(fb_width as u32, fb_height as u32, samples, TextureFormat::Rgba16F) {
+        Ok(post) => post,
+        Err(err) => panic!("{}", err)
+    };
+    let tonemap = Tonemap::default();
+    for pass in [PostPass::tonemap(tonemap, 1.0), PostPass::grayscale(), PostPass::invert(), PostPass::vignette(0.6)] {
+        match pass {
+            Ok(mut pass) => {
+                pass.enabled = false;
+                post.push(pass);
             }
+            Err(err) => panic!("{}", err)
         }
-
-        if settings_has_changed {
-            let vertices = get_vertices(&settings.landslide);
-            wrapper::update_buffer_data(
-                BufferType::Array,
-                bytemuck::cast_slice(&vertices),
-            );
+    }
+    match Bloom::new(fb_width as u32, fb_height as u32) {
+        Ok(mut bloom) => {
+            bloom.enabled = false;
+            post.set_bloom(Some(bloom));
         }
+        Err(err) => panic!("{}", err)
+    }
 
-        clear_mask(gl::COLOR_BUFFER_BIT);
-
-        let v_count = vertices.len() as i32 * 3;
+    wrapper::multisample(settings.msaa);
 
-        vao1.bind();
-        shader_triangle.use_program();
-        draw_triangles(v_count);
+    let timers = ["gpu.triangles", "gpu.lines"].map(|label| match GpuTimer::new() {
+        Ok(timer) => {
+            timer.set_label(label);
+            timer
+        }
+        Err(err) => panic!("{}", err)
+    });
 
-        vao2.bind();
-        shader_line.use_program();
-        draw_lines(v_count);
+    wrapper::depth_test(true);
+    // the outline lies in the same plane as the shape and is drawn after it
+    wrapper::depth_func(CompareFunc::LessEqual);
 
-        clear_array_binding();
+    let mut aspect = AspectFit::new(800, 600, AspectPolicy::default());
+    aspect.resize(fb_width, fb_height);
 
-        setup.window.glfw.poll_events();
-        setup.window.swap_buffers();
+    let text = FONT_PATHS.iter().find(|path| Path::new(path).exists()).and_then(|path| {
+        match TextRenderer::from_file(path) {
+            Ok(text) => Some(text),
+            Err(err) => {
+                eprintln!("Overlay font failed: {}", err);
+                None
+            }
+        }
+    });
+    if text.is_none() {
+        println!("No overlay font found, statistics stay in the window title");
     }
+    let overlay = Ortho2D::new(fb_width as u32, fb_height as u32);
 
-    vbo.delete();
-    ebo1.delete();
-    ebo2.delete();
-    vao1.delete();
-    vao2.delete();
+    let mut demo = Demo {
+        settings, previous: ([0.0, 0.0], 0.0), clock: 0.0, figure, assets, shader, shape, outline, crowd, instances, post, tonemap,
+        recorder: None, aspect, input: get_input_map(), gamepad: Gamepad::new(&setup.window.glfw, glfw::JoystickId::Joystick1),
+        stats: FrameStats::new(STATS_FRAMES).with_title(TITLE), timers, text, overlay, show_help: true,
+    };
+    app::run_loop(&mut setup, &mut demo, UPDATE_STEP);
 }
@@ -0,0 +1,222 @@
+use std::rc::Rc;
+
+use crate::camera::Camera;
+use crate::error::WrapperError;
+use crate::ibl::{init_ibl_uniforms, Ibl};
+use crate::lighting::{lit_program, Lighting};
+use crate::loader::gltf::{GltfMaterial, GltfScene};
+use crate::wrapper::{ColorSpace, ShaderProgram, Texture2D, TextureFilter};
+
+/// Texture units the maps of a `PbrMaterial` are bound to.
+pub const ALBEDO_UNIT: u32 = 0;
+pub const METALLIC_ROUGHNESS_UNIT: u32 = 1;
+pub const NORMAL_UNIT: u32 = 2;
+pub const AO_UNIT: u32 = 3;
+pub const EMISSIVE_UNIT: u32 = 4;
+
+/// Bits of the `maps` uniform of the PBR shader, which of the maps are bound.
+#[allow(dead_code)]
+pub struct MaterialMaps;
+
+#[allow(dead_code)]
+impl MaterialMaps {
+    pub const ALBEDO: i32 = 1;
+    pub const METALLIC_ROUGHNESS: i32 = 2;
+    pub const NORMAL: i32 = 4;
+    pub const AO: i32 = 8;
+    pub const EMISSIVE: i32 = 16;
+}
+
+/// Metallic-roughness material as glTF defines it. Factors multiply the maps, a missing map
+/// counts as white. Maps are shared, so many materials can use one texture.
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct PbrMaterial {
+    /// base color, linear, alpha is written out as is
+    pub albedo: [f32; 4],
+    /// 0 is a dielectric, 1 a metal
+    pub metallic: f32,
+    /// 0 is a perfect mirror, 1 fully rough
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    /// sRGB base color
+    pub albedo_map: Option<Rc<Texture2D>>,
+    /// linear, roughness in g and metallic in b
+    pub metallic_roughness_map: Option<Rc<Texture2D>>,
+    /// tangent space normals, linear
+    pub normal_map: Option<Rc<Texture2D>>,
+    /// how much `normal_map` bends the normals sideways
+    pub normal_scale: f32,
+    /// ambient occlusion in r, linear
+    pub ao_map: Option<Rc<Texture2D>>,
+    /// 0 ignores `ao_map`, 1 applies it fully
+    pub ao_strength: f32,
+    /// sRGB
+    pub emissive_map: Option<Rc<Texture2D>>,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self {
+            albedo: [1.0, 1.0, 1.0, 1.0],
+            metallic: 0.0,
+            roughness: 0.5,
+            emissive: [0.0, 0.0, 0.0],
+            albedo_map: None,
+            metallic_roughness_map: None,
+            normal_map: None,
+            normal_scale: 1.0,
+            ao_map: None,
+            ao_strength: 1.0,
+            emissive_map: None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl PbrMaterial {
+    pub fn new(albedo: [f32; 4], metallic: f32, roughness: f32) -> Self {
+        Self { albedo, metallic, roughness, ..Self::default() }
+    }
+
+    /// Material of a glTF file, `textures` are the scene's textures uploaded by `gltf_textures`.
+    pub fn from_gltf(material: &GltfMaterial, textures: &[Rc<Texture2D>]) -> Self {
+        let map = |texture: Option<usize>| texture.and_then(|texture| textures.get(texture).cloned());
+
+        Self {
+            albedo: material.base_color,
+            metallic: material.metallic,
+            roughness: material.roughness,
+            emissive: material.emissive,
+            albedo_map: map(material.base_color_texture),
+            metallic_roughness_map: map(material.metallic_roughness_texture),
+            normal_map: map(material.normal_texture),
+            ao_map: map(material.occlusion_texture),
+            emissive_map: map(material.emissive_texture),
+            ..Self::default()
+        }
+    }
+
+    /// `MaterialMaps` bits of the maps present.
+    pub fn maps(&self) -> i32 {
+        [
+            (&self.albedo_map, MaterialMaps::ALBEDO),
+            (&self.metallic_roughness_map, MaterialMaps::METALLIC_ROUGHNESS),
+            (&self.normal_map, MaterialMaps::NORMAL),
+            (&self.ao_map, MaterialMaps::AO),
+            (&self.emissive_map, MaterialMaps::EMISSIVE),
+        ]
+        .into_iter()
+        .filter(|(map, _)| map.is_some())
+        .fold(0, |maps, (_, bit)| maps | bit)
+    }
+
+    /// Sets the factors and binds the maps to their units, `program` has to be in use.
+    pub fn bind(&self, program: &ShaderProgram) {
+        program.set_vec4("albedo_factor", self.albedo);
+        program.set_f32("metallic_factor", self.metallic);
+        program.set_f32("roughness_factor", self.roughness);
+        program.set_vec3("emissive_factor", self.emissive);
+        program.set_f32("normal_scale", self.normal_scale);
+        program.set_f32("ao_strength", self.ao_strength);
+        program.set_i32("maps", self.maps());
+
+        let maps = [
+            ("albedo_map", ALBEDO_UNIT, &self.albedo_map),
+            ("metallic_roughness_map", METALLIC_ROUGHNESS_UNIT, &self.metallic_roughness_map),
+            ("normal_map", NORMAL_UNIT, &self.normal_map),
+            ("ao_map", AO_UNIT, &self.ao_map),
+            ("emissive_map", EMISSIVE_UNIT, &self.emissive_map),
+        ];
+        for (name, unit, map) in maps {
+            program.set_i32(name, unit as i32);
+            if let Some(texture) = map {
+                texture.bind_to_unit(unit);
+            }
+        }
+    }
+}
+
+/// Uploads the textures of a glTF scene, in the order materials refer to them. Textures used
+/// as base color or emissive map are sRGB, all others linear, as glTF specifies. Filters and
+/// wrapping come from the texture's sampler, mipmaps are only generated when its min filter
+/// uses them. Filters the file leaves out are trilinear with up to 16x anisotropy.
+#[allow(dead_code)]
+pub fn gltf_textures(scene: &GltfScene) -> Result<Vec<Rc<Texture2D>>, WrapperError> {
+    let is_color = |texture: usize| {
+        scene.materials.iter().any(|material| {
+            material.base_color_texture == Some(texture) || material.emissive_texture == Some(texture)
+        })
+    };
+
+    scene
+        .textures
+        .iter()
+        .enumerate()
+        .map(|(i, gltf_texture)| {
+            let image = &scene.images[gltf_texture.image];
+            let sampler = gltf_texture.sampler;
+            let color_space = if is_color(i) { ColorSpace::Srgb } else { ColorSpace::Linear };
+            let texture = Texture2D::from_rgba_as(image.width, image.height, &image.pixels, color_space)?;
+
+            let (min, mip) = sampler.min_filter.unwrap_or((TextureFilter::Linear, Some(TextureFilter::Linear)));
+            let mag = sampler.mag_filter.unwrap_or(TextureFilter::Linear);
+            match mip {
+                Some(mip) => {
+                    texture.generate_mipmaps();
+                    texture.set_mipmap_filter(min, mip, mag);
+                }
+                None => texture.set_filter(min, mag),
+            }
+            if sampler.min_filter.is_none() {
+                texture.set_anisotropy(16.0);
+            }
+            texture.set_wrap(sampler.wrap_s, sampler.wrap_t);
+            Ok(Rc::new(texture))
+        })
+        .collect()
+}
+
+/// Physically based shading of `PbrMaterial`s lit by the `Lighting` block, for vertices laid
+/// out like `MeshVertex`. Normal maps go through the vertex tangents when the mesh has them.
+/// Outputs linear HDR colors, follow it with a tonemap pass.
+pub struct PbrProgram {
+    program: ShaderProgram,
+}
+
+#[allow(dead_code)]
+impl PbrProgram {
+    pub fn new(lighting: &Lighting) -> Result<Self, WrapperError> {
+        let program = lit_program("material/pbr.frag")?;
+        program.set_label("material.pbr");
+        lighting.bind_block(&program);
+        init_ibl_uniforms(&program);
+
+        Ok(Self { program })
+    }
+
+    pub fn program(&self) -> &ShaderProgram {
+        &self.program
+    }
+
+    /// Uses the program with the camera's matrices and position.
+    pub fn begin(&self, camera: &Camera) {
+        self.program.use_program();
+        camera.upload(&self.program);
+        self.program.set_vec3("camera_position", camera.position.to_array());
+    }
+
+    /// Lights with `ibl` instead of the flat ambient color of the `Lighting` block, `None`
+    /// goes back to it. The program has to be in use.
+    pub fn set_environment(&self, ibl: Option<&Ibl>) {
+        match ibl {
+            Some(ibl) => ibl.bind(&self.program),
+            None => Ibl::unbind(&self.program),
+        }
+    }
+
+    /// Binds `material` for the next draws, set `model` per object as usual.
+    pub fn set_material(&self, material: &PbrMaterial) {
+        material.bind(&self.program);
+    }
+}
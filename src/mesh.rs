@@ -0,0 +1,510 @@
+use std::collections::HashMap;
+
+use gl::types::GLsizei;
+use glam::{Mat3, Vec2, Vec3, Vec4, Vec4Swizzles};
+
+use crate::error::WrapperError;
+use crate::wrapper::{
+    ArrayBuffer, Attribute, BufferType, buffer_data, buffer_vertices, clear_array_binding, draw_elements,
+    draw_elements_instanced, DrawElementsIndirectCommand, IndirectBuffer, MapRead, multi_draw_elements_indirect,
+    PrimitiveMode, VertexArray, VertexLike,
+};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    /// direction of increasing u along the surface, w is +1 or -1 for which way the
+    /// bitangent points, all zeros when the mesh has no tangents
+    pub tangent: [f32; 4],
+}
+
+impl VertexLike for MeshVertex {
+    const ATTRIBUTES: &'static [Attribute] =
+        &[Attribute::vec3(0), Attribute::vec3(1), Attribute::vec2(2), Attribute::vec4(3)];
+}
+
+#[allow(dead_code)]
+impl MeshVertex {
+    pub fn has_tangent(&self) -> bool {
+        self.tangent[..3] != [0.0; 3]
+    }
+
+    /// Tangent space of the vertex, columns tangent, bitangent and normal, the way the
+    /// normal-mapped shaders build it. `None` without a tangent.
+    pub fn tbn(&self) -> Option<Mat3> {
+        let normal = Vec3::from_array(self.normal).try_normalize()?;
+        tbn(normal, Vec4::from_array(self.tangent))
+    }
+}
+
+/// Tangent frame from a normal and a glTF style tangent (w is the bitangent sign), the
+/// tangent is made orthogonal to the normal first. `None` when the tangent is zero or
+/// parallel to the normal. Same as `tbn_matrix` of `normal_mapping.glsl`.
+#[allow(dead_code)]
+pub fn tbn(normal: Vec3, tangent: Vec4) -> Option<Mat3> {
+    let handedness = if tangent.w < 0.0 { -1.0 } else { 1.0 };
+    let tangent = (tangent.xyz() - normal * normal.dot(tangent.xyz())).try_normalize()?;
+    Some(Mat3::from_cols(tangent, normal.cross(tangent) * handedness, normal))
+}
+
+/// How `MeshData::compute_normals` shades the surface.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normals {
+    /// Normals of the triangles around a position averaged, weighted by their angle at it.
+    /// Vertices at the same position share the normal, so UV seams don't show.
+    Smooth,
+    /// Every triangle gets its own three vertices with its face normal.
+    Flat,
+}
+
+/// CPU side copy of triangle geometry, e.g. loaded from a model file, before it gets uploaded.
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+}
+
+#[allow(dead_code)]
+impl MeshData {
+    pub fn upload(&self) -> Result<Mesh, WrapperError> {
+        Mesh::new(&self.vertices, &self.indices, PrimitiveMode::Triangles)
+    }
+
+    /// Replaces the normals of all vertices with ones computed from the triangles.
+    /// Degenerate triangles don't contribute, vertices only used by them get a zero normal.
+    pub fn compute_normals(&mut self, normals: Normals) {
+        match normals {
+            Normals::Smooth => self.smooth_normals(),
+            Normals::Flat => self.flat_normals(),
+        }
+    }
+
+    /// Computes tangents from the normals and UVs the way MikkTSpace does, so normal maps
+    /// baked against MikkTSpace tangents (the glTF default) shade without seams. Vertices
+    /// with the same position, normal and UV are welded for it, and a vertex is split when
+    /// its triangles mirror the UVs differently, as the two sides need opposite w.
+    /// Vertices without a normal get a zero tangent.
+    pub fn generate_tangents(&mut self) {
+        // MikkTSpace treats corners with identical attributes as one vertex
+        let key = |vertex: &MeshVertex| {
+            let mut key = [0; 8];
+            for (bits, value) in key.iter_mut().zip(vertex.position.iter().chain(&vertex.normal).chain(&vertex.uv)) {
+                *bits = value.to_bits();
+            }
+            key
+        };
+        let mut welded: HashMap<[u32; 8], usize> = HashMap::new();
+        let weld: Vec<usize> = self
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let next = welded.len();
+                *welded.entry(key(vertex)).or_insert(next)
+            })
+            .collect();
+
+        // tangent sums of the welded vertices, apart for triangles with mirrored UVs
+        let mut sums: HashMap<(usize, bool), Vec3> = HashMap::new();
+        // whether the UVs keep the triangle's orientation, `None` without a UV mapping
+        let mut orientations = Vec::with_capacity(self.indices.len() / 3);
+
+        for triangle in self.indices.chunks_exact(3) {
+            let corners = [0, 1, 2].map(|i| self.vertices[triangle[i] as usize]);
+            let positions = corners.map(|corner| Vec3::from_array(corner.position));
+            let uvs = corners.map(|corner| Vec2::from_array(corner.uv));
+
+            let (edge1, edge2) = (positions[1] - positions[0], positions[2] - positions[0]);
+            let (duv1, duv2) = (uvs[1] - uvs[0], uvs[2] - uvs[0]);
+            let det = duv1.perp_dot(duv2);
+            let tangent = ((edge1 * duv2.y - edge2 * duv1.y) * det.signum()).try_normalize();
+            let degenerate = det.abs() < f32::EPSILON || edge1.cross(edge2) == Vec3::ZERO;
+            let Some(tangent) = tangent.filter(|_| !degenerate) else {
+                orientations.push(None);
+                continue;
+            };
+            let preserving = det > 0.0;
+            orientations.push(Some(preserving));
+
+            for i in 0..3 {
+                let Some(normal) = Vec3::from_array(corners[i].normal).try_normalize() else {
+                    continue;
+                };
+                let project = |v: Vec3| (v - normal * normal.dot(v)).normalize_or_zero();
+                // the angle at the corner as seen along the normal weighs the triangle in
+                let to_next = project(positions[(i + 1) % 3] - positions[i]);
+                let to_previous = project(positions[(i + 2) % 3] - positions[i]);
+                let angle = to_next.dot(to_previous).clamp(-1.0, 1.0).acos();
+                *sums.entry((weld[triangle[i] as usize], preserving)).or_default() += project(tangent) * angle;
+            }
+        }
+
+        // the first orientation a vertex is used with keeps it, the other gets a copy
+        let mut splits: HashMap<(u32, bool), u32> = HashMap::new();
+        let mut used = vec![false; self.vertices.len()];
+        for (triangle, orientation) in self.indices.chunks_exact_mut(3).zip(orientations) {
+            for index in triangle {
+                let welded = weld[*index as usize];
+                // triangles without a UV mapping follow the vertex's other triangles
+                let preserving = orientation
+                    .unwrap_or_else(|| sums.contains_key(&(welded, true)) || !sums.contains_key(&(welded, false)));
+                let vertex = *splits.entry((*index, preserving)).or_insert_with(|| {
+                    if std::mem::replace(&mut used[*index as usize], true) {
+                        self.vertices.push(self.vertices[*index as usize]);
+                        self.vertices.len() as u32 - 1
+                    } else {
+                        *index
+                    }
+                });
+                *index = vertex;
+
+                let target = &mut self.vertices[vertex as usize];
+                let Some(normal) = Vec3::from_array(target.normal).try_normalize() else {
+                    target.tangent = [0.0; 4];
+                    continue;
+                };
+                // no usable UVs around the vertex, any tangent keeps the frame valid
+                let tangent = sums
+                    .get(&(welded, preserving))
+                    .and_then(|sum| sum.try_normalize())
+                    .unwrap_or_else(|| normal.any_orthonormal_vector());
+                target.tangent = tangent.extend(if preserving { 1.0 } else { -1.0 }).to_array();
+            }
+        }
+    }
+
+    fn smooth_normals(&mut self) {
+        let key = |vertex: &MeshVertex| vertex.position.map(f32::to_bits);
+        let mut sums: HashMap<[u32; 3], Vec3> = HashMap::new();
+
+        for triangle in self.indices.chunks_exact(3) {
+            let corners = [0, 1, 2].map(|i| Vec3::from_array(self.vertices[triangle[i] as usize].position));
+            let Some(normal) = (corners[1] - corners[0]).cross(corners[2] - corners[0]).try_normalize() else {
+                continue;
+            };
+
+            for i in 0..3 {
+                let to_next = corners[(i + 1) % 3] - corners[i];
+                let to_previous = corners[(i + 2) % 3] - corners[i];
+                let angle = to_next.angle_between(to_previous);
+                *sums.entry(key(&self.vertices[triangle[i] as usize])).or_default() += normal * angle;
+            }
+        }
+
+        for vertex in &mut self.vertices {
+            let sum = sums.get(&key(vertex)).copied().unwrap_or_default();
+            vertex.normal = sum.normalize_or_zero().to_array();
+        }
+    }
+
+    fn flat_normals(&mut self) {
+        let mut vertices = Vec::with_capacity(self.indices.len());
+
+        for triangle in self.indices.chunks_exact(3) {
+            let mut corners = [0, 1, 2].map(|i| self.vertices[triangle[i] as usize]);
+            let [a, b, c] = corners.map(|corner| Vec3::from_array(corner.position));
+            let normal = (b - a).cross(c - a).normalize_or_zero().to_array();
+
+            for corner in &mut corners {
+                corner.normal = normal;
+            }
+            vertices.extend_from_slice(&corners);
+        }
+
+        self.indices = (0..vertices.len() as u32).collect();
+        self.vertices = vertices;
+    }
+}
+
+/// Indexed geometry with its own GPU buffers, ready to be drawn with a single call.
+pub struct Mesh {
+    vao: VertexArray,
+    _vbo: ArrayBuffer,
+    _ebo: ArrayBuffer,
+    index_count: GLsizei,
+    mode: PrimitiveMode,
+    /// attributes of the vertex type it was made from
+    layout: &'static [Attribute],
+}
+
+#[allow(dead_code)]
+impl Mesh {
+    pub fn new<V: VertexLike>(vertices: &[V], indices: &[u32], mode: PrimitiveMode) -> Result<Self, WrapperError> {
+        let vao = VertexArray::new().ok_or(WrapperError::Allocation("vertex array"))?;
+        vao.bind();
+
+        let vbo = ArrayBuffer::new().ok_or(WrapperError::Allocation("vertex buffer"))?;
+        vbo.bind(BufferType::Array);
+        buffer_vertices(BufferType::Array, vertices, gl::STATIC_DRAW);
+
+        let ebo = ArrayBuffer::new().ok_or(WrapperError::Allocation("element buffer"))?;
+        ebo.bind(BufferType::ElementArray);
+        buffer_data(BufferType::ElementArray, bytemuck::cast_slice(indices), gl::STATIC_DRAW);
+
+        vao.set_vertex_layout::<V>();
+        clear_array_binding();
+
+        Ok(Self {
+            vao,
+            _vbo: vbo,
+            _ebo: ebo,
+            index_count: indices.len().try_into().unwrap(),
+            mode,
+            layout: V::ATTRIBUTES,
+        })
+    }
+
+    pub fn draw(&self) {
+        self.vao.bind();
+        draw_elements(self.mode, self.index_count);
+    }
+
+    /// `MeshData::generate_tangents` for a mesh already on the GPU, e.g. a model loaded
+    /// without tangents that gets a normal map. Reads the buffers back and uploads them
+    /// again, with more vertices where some had to be split. Only for `MeshVertex` triangles.
+    pub fn generate_tangents(&mut self) -> Result<(), WrapperError> {
+        if self.layout != MeshVertex::ATTRIBUTES || self.mode != PrimitiveMode::Triangles {
+            return Err(WrapperError::Unsupported("tangents for meshes other than MeshVertex triangles"));
+        }
+
+        // the element buffer binding belongs to the vertex array
+        self.vao.bind();
+        let mut data = MeshData {
+            vertices: self._vbo.map(BufferType::Array, MapRead)?.to_vec(),
+            indices: self._ebo.map(BufferType::ElementArray, MapRead)?.to_vec(),
+        };
+        data.generate_tangents();
+
+        self._vbo.bind(BufferType::Array);
+        buffer_vertices(BufferType::Array, &data.vertices, gl::STATIC_DRAW);
+        self._ebo.bind(BufferType::ElementArray);
+        buffer_data(BufferType::ElementArray, bytemuck::cast_slice(&data.indices), gl::STATIC_DRAW);
+        clear_array_binding();
+        Ok(())
+    }
+
+    /// Per-instance attributes have to be attached to `vao()` first.
+    pub fn draw_instanced(&self, instances: GLsizei) {
+        self.vao.bind();
+        draw_elements_instanced(self.mode, self.index_count, instances);
+    }
+
+    /// Labels the vertex array and buffers as `label`, `label.vertices`, `label.indices`.
+    pub fn set_label(&self, label: &str) {
+        self.vao.set_label(label);
+        self._vbo.set_label(&format!("{}.vertices", label));
+        self._ebo.set_label(&format!("{}.indices", label));
+    }
+
+    pub fn index_count(&self) -> GLsizei {
+        self.index_count
+    }
+
+    pub fn mode(&self) -> PrimitiveMode {
+        self.mode
+    }
+
+    pub fn vao(&self) -> &VertexArray {
+        &self.vao
+    }
+}
+
+/// Several meshes packed into shared vertex and index buffers, drawn together with one
+/// `multi_draw_elements_indirect` call. Needs GL 4.3+ to draw.
+pub struct MeshBatch {
+    mesh: Mesh,
+    commands: Vec<DrawElementsIndirectCommand>,
+    indirect: IndirectBuffer,
+}
+
+#[allow(dead_code)]
+impl MeshBatch {
+    pub fn new(meshes: &[MeshData]) -> Result<Self, WrapperError> {
+        let mut data = MeshData::default();
+        let mut commands = Vec::with_capacity(meshes.len());
+
+        for mesh in meshes {
+            commands.push(DrawElementsIndirectCommand::new(
+                mesh.indices.len() as u32,
+                data.indices.len() as u32,
+                data.vertices.len() as i32,
+            ));
+            data.vertices.extend_from_slice(&mesh.vertices);
+            data.indices.extend_from_slice(&mesh.indices);
+        }
+
+        let indirect = IndirectBuffer::new(&commands, gl::DYNAMIC_DRAW)?;
+        Ok(Self { mesh: data.upload()?, commands, indirect })
+    }
+
+    /// Draw command of the `index`th mesh, e.g. to set its instance count.
+    pub fn command_mut(&mut self, index: usize) -> Option<&mut DrawElementsIndirectCommand> {
+        self.commands.get_mut(index)
+    }
+
+    /// Sends commands changed through `command_mut` to the GPU.
+    pub fn update_commands(&mut self) {
+        self.indirect.upload(&self.commands, gl::DYNAMIC_DRAW);
+    }
+
+    pub fn draw(&self) -> Result<(), WrapperError> {
+        self.mesh.vao.bind();
+        multi_draw_elements_indirect(self.mesh.mode, &self.indirect)
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(position: [f32; 3], uv: [f32; 2]) -> MeshVertex {
+        MeshVertex { position, uv, ..Default::default() }
+    }
+
+    /// Two triangles folded 90° along the x axis, the shared edge doubled for a UV seam.
+    fn fold() -> MeshData {
+        MeshData {
+            vertices: vec![
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([1.0, 0.0, 0.0], [1.0, 0.0]),
+                vertex([0.0, 0.0, -1.0], [0.0, 1.0]),
+                vertex([0.0, 0.0, 0.0], [0.5, 0.0]),
+                vertex([0.0, -1.0, 0.0], [0.5, 1.0]),
+                vertex([1.0, 0.0, 0.0], [1.5, 0.0]),
+            ],
+            indices: vec![0, 1, 2, 3, 4, 5],
+        }
+    }
+
+    #[test]
+    fn smooth_normals_weld_across_seams() {
+        let mut data = fold();
+        data.compute_normals(Normals::Smooth);
+
+        // the edge vertices average +y and +z, the others keep their face normal
+        let edge = Vec3::new(0.0, 1.0, 1.0).normalize().to_array();
+        for i in [0, 1, 3, 5] {
+            assert!(Vec3::from_array(data.vertices[i].normal).abs_diff_eq(Vec3::from_array(edge), 1e-5), "{}", i);
+        }
+        assert_eq!(data.vertices[2].normal, [0.0, 1.0, 0.0]);
+        assert_eq!(data.vertices[4].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(data.indices, [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn flat_normals_split_vertices() {
+        let mut data = MeshData {
+            vertices: vec![
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([1.0, 0.0, 0.0], [1.0, 0.0]),
+                vertex([0.0, 0.0, -1.0], [0.0, 1.0]),
+                vertex([0.0, -1.0, 0.0], [0.0, 1.0]),
+            ],
+            indices: vec![0, 1, 2, 0, 3, 1],
+        };
+        data.compute_normals(Normals::Flat);
+
+        assert_eq!(data.vertices.len(), 6);
+        assert_eq!(data.indices, [0, 1, 2, 3, 4, 5]);
+        assert!(data.vertices[..3].iter().all(|vertex| vertex.normal == [0.0, 1.0, 0.0]));
+        assert!(data.vertices[3..].iter().all(|vertex| vertex.normal == [0.0, 0.0, 1.0]));
+        assert_eq!(data.vertices[4].uv, [0.0, 1.0]);
+    }
+
+    fn with_normal(position: [f32; 3], uv: [f32; 2]) -> MeshVertex {
+        MeshVertex { position, normal: [0.0, 0.0, 1.0], uv, ..Default::default() }
+    }
+
+    #[test]
+    fn tangents_of_flat_faces_follow_u() {
+        for mut data in [crate::shapes::cube(1.0), crate::shapes::plane(2.0, 1.0, 3)] {
+            let expected: Vec<[f32; 4]> = data.vertices.iter().map(|vertex| vertex.tangent).collect();
+            data.generate_tangents();
+            assert_eq!(data.vertices.len(), expected.len());
+            for (vertex, expected) in data.vertices.iter().zip(expected) {
+                assert!(Vec4::from_array(vertex.tangent).abs_diff_eq(Vec4::from_array(expected), 1e-5), "{:?}", vertex);
+            }
+        }
+    }
+
+    #[test]
+    fn mirrored_uvs_split_vertices() {
+        // the right half mirrors the left one, the middle edge is shared
+        let mut data = MeshData {
+            vertices: vec![
+                with_normal([-1.0, 0.0, 0.0], [0.0, 0.0]),
+                with_normal([0.0, 0.0, 0.0], [1.0, 0.0]),
+                with_normal([0.0, 1.0, 0.0], [1.0, 1.0]),
+                with_normal([1.0, 0.0, 0.0], [0.0, 0.0]),
+            ],
+            indices: vec![0, 1, 2, 1, 3, 2],
+        };
+        data.generate_tangents();
+
+        assert_eq!(data.vertices.len(), 6);
+        assert_eq!(data.indices.len(), 6);
+        for (corner, &index) in data.indices.iter().enumerate() {
+            let vertex = data.vertices[index as usize];
+            let expected = if corner < 3 { [1.0, 0.0, 0.0, 1.0] } else { [-1.0, 0.0, 0.0, -1.0] };
+            assert!(Vec4::from_array(vertex.tangent).abs_diff_eq(Vec4::from_array(expected), 1e-5), "{}", corner);
+        }
+    }
+
+    #[test]
+    fn identical_vertices_are_welded() {
+        // a fan around the center whose triangles skew u differently, once with shared
+        // vertices and once with every corner on its own
+        let mut shared = MeshData {
+            vertices: vec![
+                with_normal([0.0, 0.0, 0.0], [0.5, 0.5]),
+                with_normal([1.0, 0.0, 0.0], [1.0, 0.6]),
+                with_normal([0.0, 1.0, 0.0], [0.4, 1.0]),
+                with_normal([-1.0, 0.0, 0.0], [0.0, 0.3]),
+                with_normal([0.0, -1.0, 0.0], [0.6, 0.0]),
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 1],
+        };
+        let mut split = MeshData {
+            vertices: shared.indices.iter().map(|&i| shared.vertices[i as usize]).collect(),
+            indices: (0..shared.indices.len() as u32).collect(),
+        };
+        shared.generate_tangents();
+        split.generate_tangents();
+
+        for (&a, &b) in shared.indices.iter().zip(&split.indices) {
+            let (a, b) = (shared.vertices[a as usize].tangent, split.vertices[b as usize].tangent);
+            assert!(Vec4::from_array(a).abs_diff_eq(Vec4::from_array(b), 1e-6));
+        }
+        let center = Vec4::from_array(shared.vertices[0].tangent);
+        assert!((center.truncate().length() - 1.0).abs() < 1e-5 && center.z == 0.0 && center.w == 1.0);
+    }
+
+    #[test]
+    fn degenerate_triangles_are_skipped() {
+        let mut data = MeshData {
+            vertices: vec![
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([1.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([2.0, 0.0, 0.0], [0.0, 0.0]),
+            ],
+            indices: vec![0, 1, 2],
+        };
+        data.compute_normals(Normals::Smooth);
+        assert!(data.vertices.iter().all(|vertex| vertex.normal == [0.0; 3]));
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use crate::bloom::Bloom;
+use crate::error::WrapperError;
+use crate::framebuffer::{bind_default_framebuffer, Framebuffer};
+use crate::wrapper::{
+    current_polygon_mode, depth_test, depth_test_enabled, polygon_mode, PolygonMode, ShaderProgram, TextureFormat,
+    UniformValue, VertexArray,
+};
+
+pub const FULLSCREEN_VERT: &str = include_str!("../shaders/src/post/fullscreen.vert");
+const COPY_FRAG: &str = include_str!("../shaders/src/post/copy.frag");
+const GRAYSCALE_FRAG: &str = include_str!("../shaders/src/post/grayscale.frag");
+const INVERT_FRAG: &str = include_str!("../shaders/src/post/invert.frag");
+const VIGNETTE_FRAG: &str = include_str!("../shaders/src/post/vignette.frag");
+const TONEMAP_FRAG: &str = include_str!("../shaders/src/post/tonemap.frag");
+
+/// Texture unit the previous pass result is bound to, available as `uniform sampler2D screen`.
+pub const SCREEN_UNIT: u32 = 0;
+
+/// Draws the fullscreen triangle of `FULLSCREEN_VERT`, which needs no vertex data, only some VAO bound.
+pub fn draw_fullscreen(vao: &VertexArray) {
+    vao.bind();
+    unsafe { crate::checked!(gl::DrawArrays(gl::TRIANGLES, 0, 3)) };
+}
+
+/// Curve compressing HDR colors into the displayable 0..1 range.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tonemap {
+    /// `c / (1 + c)`, keeps hues but washes out highlights
+    Reinhard = 0,
+    /// filmic curve with more contrast and saturated highlights
+    #[default]
+    Aces = 1,
+}
+
+impl Tonemap {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Reinhard => Self::Aces,
+            Self::Aces => Self::Reinhard,
+        }
+    }
+}
+
+/// One fullscreen step of the chain. The fragment shader gets `in vec2 uv` and the image
+/// produced so far in `uniform sampler2D screen`.
+pub struct PostPass {
+    pub name: String,
+    pub enabled: bool,
+    program: ShaderProgram,
+    uniforms: HashMap<String, UniformValue>,
+}
+
+#[allow(dead_code)]
+impl PostPass {
+    pub fn new(name: &str, frag_src: &str) -> Result<Self, WrapperError> {
+        let program = ShaderProgram::from_vertex_fragment(FULLSCREEN_VERT, frag_src)?;
+        program.set_label(&format!("post.{}", name));
+
+        Ok(Self { name: name.to_string(), enabled: true, program, uniforms: HashMap::new() })
+    }
+
+    pub fn grayscale() -> Result<Self, WrapperError> {
+        Self::new("grayscale", GRAYSCALE_FRAG)
+    }
+
+    pub fn invert() -> Result<Self, WrapperError> {
+        Self::new("invert", INVERT_FRAG)
+    }
+
+    /// `strength` of 0 leaves the image untouched, 1 makes corners black.
+    pub fn vignette(strength: f32) -> Result<Self, WrapperError> {
+        Ok(Self::new("vignette", VIGNETTE_FRAG)?.with_uniform("strength", UniformValue::F32(strength)))
+    }
+
+    /// Maps the HDR scene to 0..1 after scaling it by `exposure`. Should run before the
+    /// other passes, which expect displayable colors. The output is linear, render to an sRGB
+    /// window (`SetupBuilder::srgb`) for it to be displayed correctly.
+    pub fn tonemap(curve: Tonemap, exposure: f32) -> Result<Self, WrapperError> {
+        Ok(Self::new("tonemap", TONEMAP_FRAG)?
+            .with_uniform("curve", UniformValue::I32(curve as i32))
+            .with_uniform("exposure", UniformValue::F32(exposure)))
+    }
+
+    pub fn with_uniform(mut self, name: &str, value: UniformValue) -> Self {
+        self.set_uniform(name, value);
+        self
+    }
+
+    /// Value is kept and applied every time the pass runs.
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) {
+        self.uniforms.insert(name.to_string(), value);
+    }
+
+    pub fn program(&self) -> &ShaderProgram {
+        &self.program
+    }
+
+    fn run(&self, vao: &VertexArray, input: &Framebuffer) {
+        self.program.use_program();
+        if let Some(texture) = input.color_texture(0) {
+            texture.bind_to_unit(SCREEN_UNIT);
+        }
+        self.program.set_i32("screen", SCREEN_UNIT as i32);
+
+        for (name, value) in &self.uniforms {
+            self.program.set_uniform(name, value);
+        }
+
+        draw_fullscreen(vao);
+    }
+}
+
+/// Scene is rendered into an offscreen framebuffer between `begin` and `finish`, then every
+/// enabled pass is applied in the order they were pushed, the last one drawing to the window.
+pub struct PostProcess {
+    vao: VertexArray,
+    scene: Framebuffer,
+    /// single-sample copy of a multisampled `scene`, which can't be sampled directly
+    resolved: Option<Framebuffer>,
+    ping_pong: [Framebuffer; 2],
+    copy: PostPass,
+    bloom: Option<Bloom>,
+    passes: Vec<PostPass>,
+}
+
+#[allow(dead_code)]
+impl PostProcess {
+    pub fn new(width: u32, height: u32) -> Result<Self, WrapperError> {
+        Self::with_samples(width, height, 0)
+    }
+
+    /// Scene is rendered with `samples` samples and resolved before the first pass.
+    pub fn with_samples(width: u32, height: u32, samples: u32) -> Result<Self, WrapperError> {
+        Self::with_format(width, height, samples, TextureFormat::Rgba8)
+    }
+
+    /// Scene and intermediate images are stored as `format`, a float one (`Rgba16F`) keeps
+    /// colors above 1 for a `PostPass::tonemap`.
+    pub fn with_format(width: u32, height: u32, samples: u32, format: TextureFormat) -> Result<Self, WrapperError> {
+        let target = || Framebuffer::builder(width, height).color_texture(format).build();
+
+        let (scene, resolved) = if samples > 1 {
+            let scene = Framebuffer::builder(width, height)
+                .samples(samples)
+                .color_renderbuffer(format)
+                .depth_renderbuffer(TextureFormat::Depth24Stencil8)
+                .build()?;
+            (scene, Some(target()?))
+        } else {
+            let scene = Framebuffer::builder(width, height)
+                .color_texture(format)
+                .depth_renderbuffer(TextureFormat::Depth24Stencil8)
+                .build()?;
+            (scene, None)
+        };
+
+        let post = Self {
+            vao: VertexArray::new().ok_or(WrapperError::Allocation("vertex array"))?,
+            scene,
+            resolved,
+            ping_pong: [target()?, target()?],
+            copy: PostPass::new("copy", COPY_FRAG)?,
+            bloom: None,
+            passes: Vec::new(),
+        };
+
+        post.scene.set_label("post.scene");
+        if let Some(resolved) = &post.resolved {
+            resolved.set_label("post.resolved");
+        }
+        post.ping_pong[0].set_label("post.ping");
+        post.ping_pong[1].set_label("post.pong");
+
+        Ok(post)
+    }
+
+    pub fn push(&mut self, pass: PostPass) {
+        self.passes.push(pass);
+    }
+
+    pub fn pass(&self, name: &str) -> Option<&PostPass> {
+        self.passes.iter().find(|pass| pass.name == name)
+    }
+
+    pub fn pass_mut(&mut self, name: &str) -> Option<&mut PostPass> {
+        self.passes.iter_mut().find(|pass| pass.name == name)
+    }
+
+    /// Flips `enabled` of the named pass, returns the new state.
+    pub fn toggle(&mut self, name: &str) -> Option<bool> {
+        self.pass_mut(name).map(|pass| {
+            pass.enabled = !pass.enabled;
+            pass.enabled
+        })
+    }
+
+    pub fn passes(&self) -> &[PostPass] {
+        &self.passes
+    }
+
+    /// Bloom runs on the scene before the passes, so a tonemap pass sees the glow too.
+    /// Create it with the scene size, `resize` keeps it in sync afterwards.
+    pub fn set_bloom(&mut self, bloom: Option<Bloom>) {
+        self.bloom = bloom;
+    }
+
+    pub fn bloom(&self) -> Option<&Bloom> {
+        self.bloom.as_ref()
+    }
+
+    pub fn bloom_mut(&mut self) -> Option<&mut Bloom> {
+        self.bloom.as_mut()
+    }
+
+    /// Flips `enabled` of the bloom, returns the new state.
+    pub fn toggle_bloom(&mut self) -> Option<bool> {
+        self.bloom.as_mut().map(|bloom| {
+            bloom.enabled = !bloom.enabled;
+            bloom.enabled
+        })
+    }
+
+    /// Window framebuffer size changed.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.scene.resize(width, height);
+        if let Some(resolved) = &mut self.resolved {
+            resolved.resize(width, height);
+        }
+        self.ping_pong.iter_mut().for_each(|target| target.resize(width, height));
+        if let Some(bloom) = &mut self.bloom {
+            bloom.resize(width, height);
+        }
+    }
+
+    /// Everything drawn after this call goes to the offscreen scene framebuffer.
+    pub fn begin(&self) {
+        self.scene.bind();
+    }
+
+    /// Applies the passes, `width`/`height` is the window framebuffer size.
+    pub fn finish(&self, width: i32, height: i32) {
+        let enabled: Vec<&PostPass> = self.passes.iter().filter(|pass| pass.enabled).collect();
+        let chain = if enabled.is_empty() { vec![&self.copy] } else { enabled };
+
+        // fullscreen triangles must not be rejected by whatever depth the window has,
+        // nor drawn as wireframe
+        let depth = depth_test_enabled();
+        let mode = current_polygon_mode();
+        depth_test(false);
+        polygon_mode(PolygonMode::Fill);
+
+        let mut input = match &self.resolved {
+            Some(resolved) => {
+                self.scene.resolve(resolved);
+                resolved
+            }
+            None => &self.scene,
+        };
+        // the chain starts writing into the first target, so the second one is free
+        if let Some(bloom) = self.bloom.as_ref().filter(|bloom| bloom.enabled) {
+            bloom.apply(&self.vao, input, &self.ping_pong[1]);
+            input = &self.ping_pong[1];
+        }
+        for (i, pass) in chain.iter().enumerate() {
+            let output = &self.ping_pong[i % 2];
+            if i + 1 == chain.len() {
+                bind_default_framebuffer(width, height);
+            } else {
+                output.bind();
+            }
+
+            pass.run(&self.vao, input);
+            input = output;
+        }
+
+        depth_test(depth);
+        polygon_mode(mode);
+    }
+
+    /// Storage format of the scene, `Rgba8` unless created `with_format`.
+    pub fn format(&self) -> TextureFormat {
+        self.scene.color_format(0).unwrap_or(TextureFormat::Rgba8)
+    }
+
+    pub fn scene(&self) -> &Framebuffer {
+        &self.scene
+    }
+}
@@ -0,0 +1,291 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::WrapperError;
+
+/// Shader source with all `#include "file"` directives expanded, remembering where every
+/// line came from so compile errors can point at the original file.
+#[derive(Debug, Clone, Default)]
+pub struct PreprocessedSource {
+    pub source: String,
+    files: Vec<PathBuf>,
+    /// (index into `files`, 1-based line) for every line of `source`
+    origins: Vec<(usize, usize)>,
+}
+
+#[allow(dead_code)]
+impl PreprocessedSource {
+    /// File and line in that file for the 1-based `line` of the expanded source.
+    pub fn origin(&self, line: usize) -> Option<(&Path, usize)> {
+        let &(file, line) = self.origins.get(line.checked_sub(1)?)?;
+        Some((&self.files[file], line))
+    }
+
+    /// Every file which took part in building the source, the root one first.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+}
+
+/// Include paths are relative to the including file. Including a file which is already
+/// being expanded higher up the chain is reported as an error instead of recursing forever.
+/// Includes which don't exist next to the including file fall back to the built-in shaders
+/// of `BUILTIN_SHADERS`, e.g. `#include "texture/array.glsl"`.
+pub fn preprocess_file(path: impl AsRef<Path>) -> Result<PreprocessedSource, WrapperError> {
+    let path = path.as_ref();
+    let path = std::fs::canonicalize(path).map_err(|source| WrapperError::Io { path: path.to_path_buf(), source })?;
+
+    let mut result = PreprocessedSource::default();
+    expand(&Disk, &path, &mut Vec::new(), &mut result)?;
+
+    Ok(result)
+}
+
+/// `preprocess_file` for the shaders compiled into the crate, `path` and the includes are
+/// relative to `shaders/src` and resolved against `BUILTIN_SHADERS` instead of the disk.
+pub fn preprocess_builtin(path: &str) -> Result<PreprocessedSource, WrapperError> {
+    let mut result = PreprocessedSource::default();
+    expand(&Builtin, Path::new(path), &mut Vec::new(), &mut result)?;
+
+    Ok(result)
+}
+
+/// Shaders under `shaders/src` which are preprocessed or included by the crate's renderers,
+/// by their path relative to it.
+const BUILTIN_SHADERS: &[(&str, &str)] = &[
+    ("deferred/lighting.frag", include_str!("../shaders/src/deferred/lighting.frag")),
+    ("forward_plus/lit.frag", include_str!("../shaders/src/forward_plus/lit.frag")),
+    ("forward_plus/tiled_lights.glsl", include_str!("../shaders/src/forward_plus/tiled_lights.glsl")),
+    ("ibl/brdf_lut.frag", include_str!("../shaders/src/ibl/brdf_lut.frag")),
+    ("ibl/prefilter.frag", include_str!("../shaders/src/ibl/prefilter.frag")),
+    ("ibl/sampling.glsl", include_str!("../shaders/src/ibl/sampling.glsl")),
+    ("lighting/blinn_phong.glsl", include_str!("../shaders/src/lighting/blinn_phong.glsl")),
+    ("lighting/lighting_block.glsl", include_str!("../shaders/src/lighting/lighting_block.glsl")),
+    ("lighting/lights.glsl", include_str!("../shaders/src/lighting/lights.glsl")),
+    ("lighting/lit.frag", include_str!("../shaders/src/lighting/lit.frag")),
+    ("lighting/lit_normal_mapped.frag", include_str!("../shaders/src/lighting/lit_normal_mapped.frag")),
+    ("lighting/normal_mapping.glsl", include_str!("../shaders/src/lighting/normal_mapping.glsl")),
+    ("material/pbr.frag", include_str!("../shaders/src/material/pbr.frag")),
+    ("shadow/shadow.glsl", include_str!("../shaders/src/shadow/shadow.glsl")),
+    ("texture/array.glsl", include_str!("../shaders/src/texture/array.glsl")),
+];
+
+/// Where `expand` reads files from.
+trait Sources {
+    fn read(&self, path: &Path) -> Result<String, WrapperError>;
+
+    /// `target` of an `#include` in a file of `dir`.
+    fn resolve(&self, dir: &Path, target: &str) -> Result<PathBuf, String>;
+}
+
+/// Canonical, so absolute, paths of files and relative ones of built-in shaders.
+struct Disk;
+
+impl Sources for Disk {
+    fn read(&self, path: &Path) -> Result<String, WrapperError> {
+        if path.is_relative() {
+            return Builtin.read(path);
+        }
+        std::fs::read_to_string(path).map_err(|source| WrapperError::Io { path: path.to_path_buf(), source })
+    }
+
+    fn resolve(&self, dir: &Path, target: &str) -> Result<PathBuf, String> {
+        if dir.is_relative() {
+            return Builtin.resolve(dir, target);
+        }
+        std::fs::canonicalize(dir.join(target)).or_else(|source| {
+            Builtin.resolve(Path::new(""), target).map_err(|_| format!("cannot include '{}': {}", target, source))
+        })
+    }
+}
+
+struct Builtin;
+
+impl Builtin {
+    fn find(path: &Path) -> Option<&'static str> {
+        BUILTIN_SHADERS.iter().find(|(name, _)| Path::new(name) == path).map(|&(_, src)| src)
+    }
+}
+
+impl Sources for Builtin {
+    fn read(&self, path: &Path) -> Result<String, WrapperError> {
+        Self::find(path).map(str::to_string).ok_or_else(|| WrapperError::Io {
+            path: path.to_path_buf(),
+            source: std::io::Error::new(std::io::ErrorKind::NotFound, "no built-in shader of that name"),
+        })
+    }
+
+    fn resolve(&self, dir: &Path, target: &str) -> Result<PathBuf, String> {
+        // lexically, there's no file system to canonicalize against
+        let mut path = PathBuf::new();
+        for component in dir.join(target).components() {
+            match component {
+                Component::ParentDir => {
+                    path.pop();
+                }
+                Component::Normal(part) => path.push(part),
+                _ => {}
+            }
+        }
+        Self::find(&path).map(|_| path).ok_or_else(|| format!("cannot include '{}': no built-in shader of that name", target))
+    }
+}
+
+fn expand(sources: &impl Sources, path: &Path, stack: &mut Vec<PathBuf>, result: &mut PreprocessedSource) -> Result<(), WrapperError> {
+    let src = sources.read(path)?;
+
+    let file = match result.files.iter().position(|known| known == path) {
+        Some(file) => file,
+        None => {
+            result.files.push(path.to_path_buf());
+            result.files.len() - 1
+        }
+    };
+    stack.push(path.to_path_buf());
+
+    for (i, line) in src.lines().enumerate() {
+        let line_no = i + 1;
+
+        match include_target(line) {
+            Some(Ok(target)) => {
+                let dir = path.parent().unwrap_or(Path::new(""));
+                let target = sources.resolve(dir, target).map_err(|message| preprocess_error(path, line_no, &message))?;
+
+                if stack.contains(&target) {
+                    return Err(preprocess_error(
+                        path,
+                        line_no,
+                        &format!("'{}' includes itself through a cycle", target.display()),
+                    ));
+                }
+
+                expand(sources, &target, stack, result)?;
+            }
+            Some(Err(message)) => return Err(preprocess_error(path, line_no, message)),
+            None => {
+                result.source.push_str(line);
+                result.source.push('\n');
+                result.origins.push((file, line_no));
+            }
+        }
+    }
+
+    stack.pop();
+    Ok(())
+}
+
+fn include_target(line: &str) -> Option<Result<&str, &'static str>> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start().strip_prefix("include")?.trim();
+
+    Some(
+        rest.strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+            .filter(|target| !target.is_empty())
+            .ok_or("expected #include \"file\""),
+    )
+}
+
+fn preprocess_error(file: &Path, line: usize, message: &str) -> WrapperError {
+    WrapperError::Preprocess { file: file.to_path_buf(), line, message: message.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fresh directory with `files` written into it, removed again by `Drop`.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let dir = std::env::temp_dir().join(format!("preprocessor_test_{}_{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&dir);
+            for (path, src) in files {
+                let path = dir.join(path);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, src).unwrap();
+            }
+            Self(std::fs::canonicalize(dir).unwrap())
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn builtin_shaders_can_be_included() {
+        let dir = TempDir::new("builtin", &[("sprite.frag", "#version 330 core\n#include \"texture/array.glsl\"\nvoid main() {}\n")]);
+        let result = preprocess_file(dir.0.join("sprite.frag")).unwrap();
+        assert!(result.source.contains("vec4 sample_layer("));
+        assert_eq!(result.files()[1], Path::new("texture/array.glsl"));
+        assert_eq!(result.origin(result.source.lines().count()), Some((dir.0.join("sprite.frag").as_path(), 3)));
+    }
+
+    #[test]
+    fn builtin_includes_resolve_relative_to_the_includer() {
+        let result = preprocess_builtin("lighting/lit.frag").unwrap();
+        let files: Vec<&Path> = result.files().iter().map(PathBuf::as_path).collect();
+        assert_eq!(
+            files,
+            ["lighting/lit.frag", "lighting/blinn_phong.glsl", "lighting/lighting_block.glsl", "lighting/lights.glsl", "shadow/shadow.glsl"]
+                .map(Path::new)
+        );
+        assert!(matches!(preprocess_builtin("lighting/missing.frag"), Err(WrapperError::Io { .. })));
+    }
+
+    #[test]
+    fn includes_are_expanded_with_their_origins() {
+        let dir = TempDir::new("nested", &[
+            ("main.frag", "#version 330 core\n#include \"lib/common.glsl\"\nvoid main() {}\n"),
+            ("lib/common.glsl", "  #  include \"consts.glsl\"\nfloat twice(float x) { return x * 2.0; }\n"),
+            ("lib/consts.glsl", "const float PI = 3.14159;\n"),
+        ]);
+        let result = preprocess_file(dir.0.join("main.frag")).unwrap();
+        assert_eq!(
+            result.source,
+            "#version 330 core\nconst float PI = 3.14159;\nfloat twice(float x) { return x * 2.0; }\nvoid main() {}\n"
+        );
+        assert_eq!(result.origin(1), Some((dir.0.join("main.frag").as_path(), 1)));
+        assert_eq!(result.origin(2), Some((dir.0.join("lib/consts.glsl").as_path(), 1)));
+        assert_eq!(result.origin(3), Some((dir.0.join("lib/common.glsl").as_path(), 2)));
+        assert_eq!(result.origin(4), Some((dir.0.join("main.frag").as_path(), 3)));
+        assert_eq!(result.origin(0), None);
+        assert_eq!(result.origin(5), None);
+    }
+
+    #[test]
+    fn the_same_file_can_be_included_twice() {
+        let dir = TempDir::new("twice", &[("main.frag", "#include \"a.glsl\"\n#include \"a.glsl\"\n"), ("a.glsl", "// a\n")]);
+        let result = preprocess_file(dir.0.join("main.frag")).unwrap();
+        assert_eq!(result.source, "// a\n// a\n");
+        assert_eq!(result.files().len(), 2);
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        let dir = TempDir::new("cycle", &[("a.glsl", "#include \"b.glsl\"\n"), ("b.glsl", "\n#include \"a.glsl\"\n")]);
+        match preprocess_file(dir.0.join("a.glsl")) {
+            Err(WrapperError::Preprocess { file, line, .. }) => assert_eq!((file, line), (dir.0.join("b.glsl"), 2)),
+            other => panic!("expected a preprocess error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bad_includes_are_errors() {
+        let dir = TempDir::new("bad", &[("missing.frag", "#include \"nope.glsl\"\n"), ("syntax.frag", "\n\n#include <nope.glsl>\n")]);
+        assert!(matches!(preprocess_file(dir.0.join("missing.frag")), Err(WrapperError::Preprocess { line: 1, .. })));
+        assert!(matches!(preprocess_file(dir.0.join("syntax.frag")), Err(WrapperError::Preprocess { line: 3, .. })));
+        assert!(matches!(preprocess_file(dir.0.join("nope.frag")), Err(WrapperError::Io { .. })));
+    }
+
+    #[test]
+    fn include_directive_parsing() {
+        assert_eq!(include_target("#include \"a.glsl\""), Some(Ok("a.glsl")));
+        assert_eq!(include_target("  # include   \"dir/a.glsl\"  "), Some(Ok("dir/a.glsl")));
+        assert_eq!(include_target("#include \"\""), Some(Err("expected #include \"file\"")));
+        assert_eq!(include_target("#define INCLUDE 1"), None);
+        assert_eq!(include_target("// #include \"a.glsl\""), None);
+    }
+}
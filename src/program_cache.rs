@@ -0,0 +1,137 @@
+//! Linked programs saved to disk with `glGetProgramBinary` and loaded back with
+//! `glProgramBinary` on the next start, skipping compilation. Entries are keyed by a hash of
+//! the sources and the driver string, so editing a shader or updating the driver misses.
+
+use std::path::{Path, PathBuf};
+
+use gl::types::GLenum;
+
+use crate::error::WrapperError;
+use crate::preprocessor::preprocess_file;
+use crate::wrapper::{driver_string, Shader, ShaderProgram, ShaderType};
+
+/// start of every cache file, followed by the binary format and the binary itself
+const MAGIC: &[u8; 4] = b"GLPB";
+
+/// The cache is best effort: missing, corrupt or rejected entries are compiled from source
+/// again, and an entry which can't be written only costs the compile next time.
+pub struct ProgramCache {
+    dir: PathBuf,
+    driver: String,
+    /// the driver has at least one binary format
+    supported: bool,
+}
+
+#[allow(dead_code)]
+impl ProgramCache {
+    /// Cache in `dir`, created when missing, for the driver of the current context.
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir).map_err(|source| WrapperError::Io { path: dir.clone(), source })?;
+
+        let mut formats = 0;
+        unsafe { crate::checked!(gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats)) };
+
+        Ok(Self { dir, driver: driver_string(), supported: formats > 0 })
+    }
+
+    /// Whether binaries are stored at all, otherwise every program is compiled from source.
+    pub fn is_supported(&self) -> bool {
+        self.supported
+    }
+
+    pub fn vertex_fragment(&self, vert_src: &str, frag_src: &str) -> Result<ShaderProgram, WrapperError> {
+        self.build(&[(ShaderType::Vertex, vert_src), (ShaderType::Fragment, frag_src)])
+    }
+
+    /// Reads both files with their `#include`s expanded, see `preprocessor`.
+    pub fn files(&self, vert_path: impl AsRef<Path>, frag_path: impl AsRef<Path>) -> Result<ShaderProgram, WrapperError> {
+        let vertex = preprocess_file(vert_path)?;
+        let fragment = preprocess_file(frag_path)?;
+        self.vertex_fragment(&vertex.source, &fragment.source)
+    }
+
+    /// Program of the cached binary for `stages`, or compiled and linked from them and stored.
+    pub fn build(&self, stages: &[(ShaderType, &str)]) -> Result<ShaderProgram, WrapperError> {
+        if !self.supported {
+            return ShaderProgram::from_shaders(compile(stages)?);
+        }
+
+        let path = self.entry_path(stages);
+        if let Some(program) = read_entry(&path).and_then(|(format, data)| ShaderProgram::from_binary(format, &data).ok()) {
+            return Ok(program);
+        }
+
+        let program = ShaderProgram::from_shaders_retrievable(compile(stages)?)?;
+        if let Some((format, data)) = program.binary() {
+            let _ = write_entry(&path, format, &data);
+        }
+        Ok(program)
+    }
+
+    /// Deletes every stored binary.
+    pub fn clear(&self) -> Result<(), WrapperError> {
+        let entries = std::fs::read_dir(&self.dir).map_err(|source| WrapperError::Io { path: self.dir.clone(), source })?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "bin") {
+                std::fs::remove_file(&path).map_err(|source| WrapperError::Io { path, source })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn entry_path(&self, stages: &[(ShaderType, &str)]) -> PathBuf {
+        let mut hash = Fnv1a::default();
+        hash.write(self.driver.as_bytes());
+        for &(stage, src) in stages {
+            hash.write(&(stage as GLenum).to_le_bytes());
+            hash.write(&(src.len() as u64).to_le_bytes());
+            hash.write(src.as_bytes());
+        }
+        self.dir.join(format!("{:016x}.bin", hash.0))
+    }
+}
+
+fn compile(stages: &[(ShaderType, &str)]) -> Result<Vec<Shader>, WrapperError> {
+    stages.iter().map(|&(stage, src)| Shader::from_source(stage, src)).collect()
+}
+
+fn read_entry(path: &Path) -> Option<(GLenum, Vec<u8>)> {
+    let bytes = std::fs::read(path).ok()?;
+    let rest = bytes.strip_prefix(MAGIC)?;
+    let (format, data) = rest.split_at_checked(4)?;
+    let format = GLenum::from_le_bytes(format.try_into().ok()?);
+
+    (!data.is_empty()).then(|| (format, data.to_vec()))
+}
+
+/// Written next to the entry and renamed over it, so a crash never leaves half a binary.
+fn write_entry(path: &Path, format: GLenum, data: &[u8]) -> std::io::Result<()> {
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 4 + data.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&format.to_le_bytes());
+    bytes.extend_from_slice(data);
+
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(tmp, path)
+}
+
+/// 64 bit FNV-1a, unlike `DefaultHasher` it's the same for every build, which keys stored
+/// on disk need.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
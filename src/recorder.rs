@@ -0,0 +1,197 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Sender};
+use std::thread::JoinHandle;
+
+use crate::capture::save_pixels;
+use crate::error::WrapperError;
+use crate::streaming::PixelBuffer;
+
+/// Frames in flight between the GPU and the writer: a frame is collected this many
+/// captures after it was requested, by then its readback has long completed.
+pub const RECORDER_RING: usize = 3;
+
+/// Where recorded frames end up.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecorderOutput {
+    /// `frame_00000.png`, `frame_00001.png`, ... in the directory, which is created
+    PngSequence(PathBuf),
+    /// raw frames piped into an `ffmpeg` found on `PATH`, encoding to `path` at `fps`
+    Ffmpeg { path: PathBuf, fps: u32 },
+}
+
+struct Frame {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+/// Captures every `every`th frame of the window into a ring of pixel buffers, so the GPU
+/// readback doesn't stall rendering, and hands finished frames to a writer thread which
+/// encodes them. Call `record` once per frame after drawing, before swapping buffers.
+pub struct Recorder {
+    ring: Vec<PixelBuffer>,
+    head: usize,
+    every: u32,
+    frame: u64,
+    frames: Option<Sender<Frame>>,
+    /// returns the first error of the writer, frames after it are dropped
+    writer: Option<JoinHandle<Result<(), WrapperError>>>,
+    /// directory or video file of the output
+    path: PathBuf,
+}
+
+#[allow(dead_code)]
+impl Recorder {
+    pub fn new(output: RecorderOutput, every: u32) -> Result<Self, WrapperError> {
+        if let RecorderOutput::PngSequence(dir) = &output {
+            std::fs::create_dir_all(dir).map_err(|source| WrapperError::Io { path: dir.clone(), source })?;
+        }
+
+        let ring = (0..RECORDER_RING).map(|_| PixelBuffer::new(0)).collect::<Result<Vec<_>, _>>()?;
+        for (i, pbo) in ring.iter().enumerate() {
+            pbo.set_label(&format!("recorder[{}]", i));
+        }
+
+        let path = match &output {
+            RecorderOutput::PngSequence(dir) => dir.clone(),
+            RecorderOutput::Ffmpeg { path, .. } => path.clone(),
+        };
+        let (frames, received) = channel::<Frame>();
+        let writer = std::thread::spawn(move || {
+            let mut sink = Sink::new(output);
+            let mut result = Ok(());
+            for frame in received {
+                if result.is_ok() {
+                    result = sink.write(frame);
+                }
+            }
+            sink.close();
+            result
+        });
+
+        Ok(Self { ring, head: 0, every: every.max(1), frame: 0, frames: Some(frames), writer: Some(writer), path })
+    }
+
+    /// Every frame of the window into numbered PNGs in `dir`.
+    pub fn png_sequence(dir: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        Self::new(RecorderOutput::PngSequence(dir.as_ref().to_path_buf()), 1)
+    }
+
+    /// Takes frames from the window, `width`/`height` is the window framebuffer size.
+    pub fn record(&mut self, width: u32, height: u32) {
+        self.frame += 1;
+        if !self.frame.is_multiple_of(self.every as u64) {
+            return;
+        }
+
+        let pbo = &mut self.ring[self.head];
+        if let Some((pixels, width, height)) = pbo.collect() {
+            self.send(Frame { pixels, width, height });
+        }
+
+        unsafe {
+            crate::checked!(gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0));
+            crate::checked!(gl::ReadBuffer(gl::BACK));
+        }
+        self.ring[self.head].read_pixels(0, 0, width, height);
+        self.head = (self.head + 1) % self.ring.len();
+    }
+
+    /// Collects the frames still in flight and waits until the writer is done with them.
+    /// Fails with the first frame the writer couldn't save, none after it were saved.
+    pub fn finish(mut self) -> Result<(), WrapperError> {
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), WrapperError> {
+        for i in 0..self.ring.len() {
+            let index = (self.head + i) % self.ring.len();
+            if let Some((pixels, width, height)) = self.ring[index].collect() {
+                self.send(Frame { pixels, width, height });
+            }
+        }
+
+        // closing the channel ends the writer loop
+        self.frames.take();
+        match self.writer.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => {
+                let source = std::io::Error::other("recorder writer thread panicked");
+                Err(WrapperError::Io { path: self.path.clone(), source })
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn send(&self, frame: Frame) {
+        if let Some(frames) = &self.frames {
+            let _ = frames.send(frame);
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Writer thread side of `RecorderOutput`.
+enum Sink {
+    Png { dir: PathBuf, next: u32 },
+    Ffmpeg { path: PathBuf, fps: u32, process: Option<(Child, ChildStdin)> },
+}
+
+impl Sink {
+    fn new(output: RecorderOutput) -> Self {
+        match output {
+            RecorderOutput::PngSequence(dir) => Self::Png { dir, next: 0 },
+            RecorderOutput::Ffmpeg { path, fps } => Self::Ffmpeg { path, fps, process: None },
+        }
+    }
+
+    fn write(&mut self, frame: Frame) -> Result<(), WrapperError> {
+        match self {
+            Self::Png { dir, next } => {
+                let path = dir.join(format!("frame_{:05}.png", next));
+                *next += 1;
+                save_pixels(path, frame.pixels, frame.width, frame.height)
+            }
+            Self::Ffmpeg { path, fps, process } => {
+                // frame size is only known once the first frame arrives
+                if process.is_none() {
+                    *process = Some(spawn_ffmpeg(path, *fps, frame.width, frame.height)?);
+                }
+
+                let (_, stdin) = process.as_mut().unwrap();
+                stdin.write_all(&frame.pixels).map_err(|source| WrapperError::Io { path: path.clone(), source })
+            }
+        }
+    }
+
+    fn close(self) {
+        if let Self::Ffmpeg { process: Some((mut child, stdin)), .. } = self {
+            // ffmpeg finalizes the file once its input ends
+            drop(stdin);
+            let _ = child.wait();
+        }
+    }
+}
+
+fn spawn_ffmpeg(path: &Path, fps: u32, width: u32, height: u32) -> Result<(Child, ChildStdin), WrapperError> {
+    let mut child = Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgba"])
+        .args(["-s", &format!("{}x{}", width, height), "-r", &fps.to_string(), "-i", "-"])
+        // GL rows are bottom-up
+        .args(["-vf", "vflip", "-pix_fmt", "yuv420p"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|source| WrapperError::Io { path: PathBuf::from("ffmpeg"), source })?;
+
+    let stdin = child.stdin.take().ok_or(WrapperError::Allocation("ffmpeg stdin"))?;
+    Ok((child, stdin))
+}
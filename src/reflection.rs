@@ -0,0 +1,252 @@
+//! What a linked program actually uses: active uniforms, vertex attributes and uniform
+//! blocks, read back from the driver. Unused declarations are optimized out and don't show up.
+//! Sticks to the GL 3.3 `glGetActive*` queries instead of `glGetProgramInterfaceiv` (4.3),
+//! so it works on every context the wrapper runs on.
+
+use gl::types::{GLchar, GLenum, GLint, GLsizei, GLuint};
+
+use crate::error::WrapperError;
+use crate::wrapper::{ShaderProgram, UniformValue};
+
+/// Active uniform outside of a block. Arrays are listed once, named without the `[0]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniformInfo {
+    pub name: String,
+    pub location: GLint,
+    /// GLSL type, e.g. `gl::FLOAT_VEC3` or `gl::SAMPLER_2D`
+    pub kind: GLenum,
+    /// number of elements, 1 for non-arrays
+    pub size: GLint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeInfo {
+    pub name: String,
+    pub location: GLint,
+    pub kind: GLenum,
+    pub size: GLint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniformBlockInfo {
+    pub name: String,
+    pub index: GLuint,
+    /// binding point set with `bind_uniform_block` or `layout(binding = N)`
+    pub binding: GLuint,
+    /// bytes the buffer bound to it has to hold at least
+    pub data_size: GLint,
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProgramReflection {
+    uniforms: Vec<UniformInfo>,
+    attributes: Vec<AttributeInfo>,
+    blocks: Vec<UniformBlockInfo>,
+}
+
+#[allow(dead_code)]
+impl ProgramReflection {
+    /// Reads everything from the linked `program`.
+    pub fn query(program: &ShaderProgram) -> Self {
+        let id = program.0;
+        let mut blocks = query_blocks(id);
+        let mut uniforms = Vec::new();
+
+        let max_len = program_iv(id, gl::ACTIVE_UNIFORM_MAX_LENGTH);
+        for index in 0..program_iv(id, gl::ACTIVE_UNIFORMS).max(0) as GLuint {
+            let (name, kind, size) = read_name(max_len, |capacity, len, size, kind, name| unsafe {
+                crate::checked!(gl::GetActiveUniform(id, index, capacity, len, size, kind, name))
+            });
+
+            let mut block = -1;
+            unsafe { crate::checked!(gl::GetActiveUniformsiv(id, 1, &index, gl::UNIFORM_BLOCK_INDEX, &mut block)) };
+            match usize::try_from(block).ok().and_then(|block| blocks.get_mut(block)) {
+                Some(block) => block.members.push(name),
+                None => {
+                    let name = name.strip_suffix("[0]").map(str::to_string).unwrap_or(name);
+                    let location = program.get_uniform_location(&name);
+                    uniforms.push(UniformInfo { name, location, kind, size });
+                }
+            }
+        }
+
+        let max_len = program_iv(id, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH);
+        let attributes = (0..program_iv(id, gl::ACTIVE_ATTRIBUTES).max(0) as GLuint)
+            .map(|index| {
+                let (name, kind, size) = read_name(max_len, |capacity, len, size, kind, name| unsafe {
+                    crate::checked!(gl::GetActiveAttrib(id, index, capacity, len, size, kind, name))
+                });
+                let c_name = std::ffi::CString::new(name.as_str()).unwrap_or_default();
+                let location = unsafe { crate::checked!(gl::GetAttribLocation(id, c_name.as_ptr())) };
+                AttributeInfo { name, location, kind, size }
+            })
+            .collect();
+
+        for block in &mut blocks {
+            block.members.sort();
+        }
+        Self { uniforms, attributes, blocks }
+    }
+
+    pub fn uniforms(&self) -> &[UniformInfo] {
+        &self.uniforms
+    }
+
+    /// Looks arrays up with or without the `[0]`.
+    pub fn uniform(&self, name: &str) -> Option<&UniformInfo> {
+        let name = name.strip_suffix("[0]").unwrap_or(name);
+        self.uniforms.iter().find(|uniform| uniform.name == name)
+    }
+
+    pub fn attributes(&self) -> &[AttributeInfo] {
+        &self.attributes
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&AttributeInfo> {
+        self.attributes.iter().find(|attribute| attribute.name == name)
+    }
+
+    pub fn blocks(&self) -> &[UniformBlockInfo] {
+        &self.blocks
+    }
+
+    pub fn block(&self, name: &str) -> Option<&UniformBlockInfo> {
+        self.blocks.iter().find(|block| block.name == name)
+    }
+
+    /// Uniforms of sampler types, e.g. for binding a material's textures by name.
+    pub fn samplers(&self) -> impl Iterator<Item = &UniformInfo> {
+        self.uniforms.iter().filter(|uniform| is_sampler(uniform.kind))
+    }
+
+    /// Checks that the program has the uniform and that `value` fits its type, before
+    /// `ShaderProgram::set_uniform`, which would silently do nothing or raise a GL error.
+    pub fn validate(&self, name: &str, value: &UniformValue) -> Result<(), WrapperError> {
+        let error = |message: String| WrapperError::Uniform { name: name.to_string(), message };
+        let uniform = self.uniform(name).ok_or_else(|| error("not an active uniform".to_string()))?;
+
+        let fits = match value {
+            UniformValue::I32(_) => matches!(uniform.kind, gl::INT | gl::BOOL) || is_sampler(uniform.kind),
+            UniformValue::F32(_) => uniform.kind == gl::FLOAT,
+            UniformValue::Vec2(_) => uniform.kind == gl::FLOAT_VEC2,
+            UniformValue::Vec3(_) => uniform.kind == gl::FLOAT_VEC3,
+            UniformValue::Vec4(_) => uniform.kind == gl::FLOAT_VEC4,
+            UniformValue::Mat4(_) => uniform.kind == gl::FLOAT_MAT4,
+        };
+        if fits {
+            Ok(())
+        } else {
+            Err(error(format!("{:?} doesn't fit a {}", value, type_name(uniform.kind))))
+        }
+    }
+}
+
+fn query_blocks(id: GLuint) -> Vec<UniformBlockInfo> {
+    let max_len = program_iv(id, gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH);
+
+    (0..program_iv(id, gl::ACTIVE_UNIFORM_BLOCKS).max(0) as GLuint)
+        .map(|index| {
+            let (name, ..) = read_name(max_len, |capacity, len, _, _, name| unsafe {
+                crate::checked!(gl::GetActiveUniformBlockName(id, index, capacity, len, name))
+            });
+            let block_iv = |pname| {
+                let mut value = 0;
+                unsafe { crate::checked!(gl::GetActiveUniformBlockiv(id, index, pname, &mut value)) };
+                value
+            };
+            UniformBlockInfo {
+                name,
+                index,
+                binding: block_iv(gl::UNIFORM_BLOCK_BINDING) as GLuint,
+                data_size: block_iv(gl::UNIFORM_BLOCK_DATA_SIZE),
+                members: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+fn program_iv(id: GLuint, pname: GLenum) -> GLint {
+    let mut value = 0;
+    unsafe { crate::checked!(gl::GetProgramiv(id, pname, &mut value)) };
+    value
+}
+
+/// Calls one of the `glGetActive*` functions with a name buffer of `max_len` bytes and
+/// returns the name with the size and type it wrote.
+fn read_name(
+    max_len: GLint,
+    get: impl FnOnce(GLsizei, *mut GLsizei, *mut GLint, *mut GLenum, *mut GLchar),
+) -> (String, GLenum, GLint) {
+    let mut buffer = vec![0_u8; max_len.max(1) as usize];
+    let (mut len, mut size, mut kind) = (0, 0, 0);
+    get(buffer.len() as GLsizei, &mut len, &mut size, &mut kind, buffer.as_mut_ptr().cast());
+    buffer.truncate(len.max(0) as usize);
+
+    (String::from_utf8_lossy(&buffer).into_owned(), kind, size)
+}
+
+#[allow(dead_code)]
+pub fn is_sampler(kind: GLenum) -> bool {
+    matches!(
+        kind,
+        gl::SAMPLER_1D
+            | gl::SAMPLER_2D
+            | gl::SAMPLER_3D
+            | gl::SAMPLER_CUBE
+            | gl::SAMPLER_2D_SHADOW
+            | gl::SAMPLER_CUBE_SHADOW
+            | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_2D_ARRAY_SHADOW
+            | gl::SAMPLER_2D_MULTISAMPLE
+            | gl::SAMPLER_CUBE_MAP_ARRAY
+            | gl::SAMPLER_BUFFER
+            | gl::INT_SAMPLER_2D
+            | gl::INT_SAMPLER_3D
+            | gl::INT_SAMPLER_2D_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_2D
+            | gl::UNSIGNED_INT_SAMPLER_3D
+            | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY
+    )
+}
+
+/// GLSL name of a uniform or attribute type.
+#[allow(dead_code)]
+pub fn type_name(kind: GLenum) -> &'static str {
+    match kind {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL => "bool",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_CUBE_SHADOW => "samplerCubeShadow",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_2D_ARRAY_SHADOW => "sampler2DArrayShadow",
+        gl::SAMPLER_2D_MULTISAMPLE => "sampler2DMS",
+        gl::SAMPLER_CUBE_MAP_ARRAY => "samplerCubeArray",
+        gl::SAMPLER_BUFFER => "samplerBuffer",
+        gl::INT_SAMPLER_2D => "isampler2D",
+        gl::INT_SAMPLER_3D => "isampler3D",
+        gl::INT_SAMPLER_2D_ARRAY => "isampler2DArray",
+        gl::UNSIGNED_INT_SAMPLER_2D => "usampler2D",
+        gl::UNSIGNED_INT_SAMPLER_3D => "usampler3D",
+        gl::UNSIGNED_INT_SAMPLER_2D_ARRAY => "usampler2DArray",
+        _ => "unknown type",
+    }
+}
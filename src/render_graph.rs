@@ -0,0 +1,629 @@
+//! Frame described as passes with the attachments they read and write. The graph orders the
+//! passes so attachments are written before they're read, binds every pass's framebuffer with
+//! a matching viewport, and lets attachments whose lifetimes don't overlap share one texture.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use gl::types::{GLenum, GLint, GLuint};
+
+use crate::error::WrapperError;
+use crate::framebuffer::bind_default_framebuffer;
+use crate::wrapper::{clear, object_label, Texture2D, TextureFormat};
+
+/// Attachment declared in a `RenderGraph`, only meaningful for the graph that returned it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResourceId {
+    graph: usize,
+    index: usize,
+}
+
+/// Id of the next `RenderGraph`, which its `ResourceId`s carry.
+static NEXT_GRAPH: AtomicUsize = AtomicUsize::new(0);
+
+/// Pass added to a `RenderGraph`, passed back to the callback of `execute`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PassId(usize);
+
+/// Size of an attachment, `Output` ones follow `RenderGraph::resize`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetSize {
+    Output,
+    /// fraction of the output, e.g. 0.5 for a half resolution bloom chain
+    Scaled(f32),
+    Fixed(u32, u32),
+}
+
+impl TargetSize {
+    fn resolve(self, width: u32, height: u32) -> (u32, u32) {
+        match self {
+            Self::Output => (width, height),
+            Self::Scaled(scale) => {
+                (((width as f32 * scale) as u32).max(1), ((height as f32 * scale) as u32).max(1))
+            }
+            Self::Fixed(width, height) => (width, height),
+        }
+    }
+}
+
+/// Format and size of a texture backing attachments.
+type TextureSpec = (TextureFormat, (u32, u32));
+
+struct Resource {
+    name: String,
+    format: TextureFormat,
+    size: TargetSize,
+    /// kept to itself and readable after `execute`, never shared with other attachments
+    persistent: bool,
+}
+
+/// Declaration of a pass for `RenderGraph::add_pass`:
+///
+/// ```ignore
+/// PassDesc::new("scene").read(shadow_map).color(hdr).depth(depth).clear_color([0.0; 4]).clear_depth(1.0)
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PassDesc {
+    name: String,
+    reads: Vec<ResourceId>,
+    colors: Vec<ResourceId>,
+    depth: Option<ResourceId>,
+    clear_color: Option<[f32; 4]>,
+    clear_depth: Option<f32>,
+    screen: bool,
+}
+
+#[allow(dead_code)]
+impl PassDesc {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), ..Self::default() }
+    }
+
+    /// Sampled by the pass, bound to texture units in the order of the calls from 0.
+    pub fn read(mut self, resource: ResourceId) -> Self {
+        self.reads.push(resource);
+        self
+    }
+
+    /// Written as the next color attachment, `COLOR_ATTACHMENT0` first.
+    pub fn color(mut self, resource: ResourceId) -> Self {
+        self.colors.push(resource);
+        self
+    }
+
+    pub fn depth(mut self, resource: ResourceId) -> Self {
+        self.depth = Some(resource);
+        self
+    }
+
+    /// Clears the color attachments before the pass runs. Attachments are shared between
+    /// passes, so the first writer of one has to clear it or cover every pixel.
+    pub fn clear_color(mut self, color: [f32; 4]) -> Self {
+        self.clear_color = Some(color);
+        self
+    }
+
+    pub fn clear_depth(mut self, depth: f32) -> Self {
+        self.clear_depth = Some(depth);
+        self
+    }
+
+    /// Renders into the window instead of attachments, e.g. the final tonemap.
+    pub fn screen(mut self) -> Self {
+        self.screen = true;
+        self
+    }
+
+    fn writes(&self) -> impl Iterator<Item = ResourceId> + '_ {
+        self.colors.iter().copied().chain(self.depth)
+    }
+}
+
+/// What the graph built for its passes, thrown away when they or the output size change.
+struct Compiled {
+    graph: usize,
+    /// pass indices in execution order
+    order: Vec<usize>,
+    /// index into `textures` of every resource
+    physical: Vec<usize>,
+    textures: Vec<Texture2D>,
+    /// framebuffer of every pass, 0 for the screen
+    framebuffers: Vec<GLuint>,
+    viewports: Vec<(u32, u32)>,
+}
+
+impl Compiled {
+    fn texture(&self, resource: ResourceId) -> Option<&Texture2D> {
+        if resource.graph != self.graph {
+            return None;
+        }
+        self.physical.get(resource.index).and_then(|&texture| self.textures.get(texture))
+    }
+}
+
+impl Drop for Compiled {
+    fn drop(&mut self) {
+        for framebuffer in self.framebuffers.iter().filter(|&&framebuffer| framebuffer != 0) {
+            unsafe { gl::DeleteFramebuffers(1, framebuffer) }
+        }
+    }
+}
+
+/// Given to the callback of `RenderGraph::execute` while a pass runs, its framebuffer bound,
+/// viewport set and reads bound to their texture units.
+#[allow(dead_code)]
+pub struct PassContext<'a> {
+    pub name: &'a str,
+    /// viewport size of the pass
+    pub width: u32,
+    pub height: u32,
+    reads: &'a [ResourceId],
+    compiled: &'a Compiled,
+}
+
+#[allow(dead_code)]
+impl PassContext<'_> {
+    /// Texture unit `resource` is bound to, `None` when the pass doesn't read it.
+    pub fn unit(&self, resource: ResourceId) -> Option<u32> {
+        self.reads.iter().position(|&read| read == resource).map(|unit| unit as u32)
+    }
+
+    /// `None` for attachments of another graph.
+    pub fn texture(&self, resource: ResourceId) -> Option<&Texture2D> {
+        self.compiled.texture(resource)
+    }
+}
+
+/// Shadow, scene and post passes declared once, in any order:
+///
+/// ```ignore
+/// let mut graph = RenderGraph::new(width, height);
+/// let shadow_map = graph.create_texture("shadow_map", TextureFormat::Depth32F, TargetSize::Fixed(2048, 2048));
+/// let hdr = graph.create_texture("hdr", TextureFormat::Rgba16F, TargetSize::Output);
+/// let depth = graph.create_texture("depth", TextureFormat::Depth24, TargetSize::Output);
+///
+/// let post = graph.add_pass(PassDesc::new("post").read(hdr).screen());
+/// let scene = graph.add_pass(PassDesc::new("scene").read(shadow_map).color(hdr).depth(depth).clear_depth(1.0));
+/// let shadows = graph.add_pass(PassDesc::new("shadows").depth(shadow_map).clear_depth(1.0));
+///
+/// graph.execute(|pass, ctx| match pass {
+///     pass if pass == shadows => draw_casters(),
+///     pass if pass == scene => draw_lit(ctx.unit(shadow_map)),
+///     _ => tonemap(ctx.unit(hdr)),
+/// })?;
+/// ```
+pub struct RenderGraph {
+    id: usize,
+    width: u32,
+    height: u32,
+    resources: Vec<Resource>,
+    passes: Vec<PassDesc>,
+    compiled: Option<Compiled>,
+}
+
+#[allow(dead_code)]
+impl RenderGraph {
+    /// `width`/`height` is the output size, that of the window for screen passes.
+    pub fn new(width: u32, height: u32) -> Self {
+        let id = NEXT_GRAPH.fetch_add(1, Ordering::Relaxed);
+        Self { id, width, height, resources: Vec::new(), passes: Vec::new(), compiled: None }
+    }
+
+    /// Attachment living only within the frame, it may share its texture with others of the
+    /// same format and size which are used by earlier or later passes.
+    pub fn create_texture(&mut self, name: &str, format: TextureFormat, size: TargetSize) -> ResourceId {
+        self.add_resource(name, format, size, false)
+    }
+
+    /// Attachment with a texture of its own, never shared with other attachments, so what the
+    /// passes wrote to it can be read through `texture` after `execute`. Passes can't read it
+    /// before it's written within the frame, the ordering has no notion of the last frame.
+    pub fn create_persistent_texture(&mut self, name: &str, format: TextureFormat, size: TargetSize) -> ResourceId {
+        self.add_resource(name, format, size, true)
+    }
+
+    fn add_resource(&mut self, name: &str, format: TextureFormat, size: TargetSize, persistent: bool) -> ResourceId {
+        self.resources.push(Resource { name: name.to_string(), format, size, persistent });
+        self.compiled = None;
+        ResourceId { graph: self.id, index: self.resources.len() - 1 }
+    }
+
+    pub fn add_pass(&mut self, pass: PassDesc) -> PassId {
+        self.passes.push(pass);
+        self.compiled = None;
+        PassId(self.passes.len() - 1)
+    }
+
+    /// New output size, `Output` and `Scaled` attachments are reallocated on the next `execute`.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.compiled = None;
+        }
+    }
+
+    /// Texture of `resource` once the graph was compiled. Only the contents of persistent
+    /// attachments are guaranteed to survive until after `execute`. `None` before that and
+    /// for attachments of another graph.
+    pub fn texture(&self, resource: ResourceId) -> Option<&Texture2D> {
+        self.compiled.as_ref()?.texture(resource)
+    }
+
+    /// Passes in the order `execute` runs them.
+    pub fn schedule(&mut self) -> Result<Vec<PassId>, WrapperError> {
+        self.compile()?;
+        Ok(self.compiled.as_ref().map(|compiled| compiled.order.iter().copied().map(PassId).collect()).unwrap_or_default())
+    }
+
+    /// Number of textures backing the attachments, fewer than attachments when some are shared.
+    pub fn texture_count(&self) -> usize {
+        self.compiled.as_ref().map_or(0, |compiled| compiled.textures.len())
+    }
+
+    /// Runs every pass in order: binds its framebuffer (or the window), sets the viewport,
+    /// clears what it asks for, binds its reads and calls `run` to draw.
+    pub fn execute(&mut self, mut run: impl FnMut(PassId, &PassContext)) -> Result<(), WrapperError> {
+        self.compile()?;
+        let Some(compiled) = &self.compiled else {
+            return Ok(());
+        };
+
+        for &index in &compiled.order {
+            let pass = &self.passes[index];
+            let (width, height) = compiled.viewports[index];
+            if pass.screen {
+                bind_default_framebuffer(width as GLint, height as GLint);
+            } else {
+                unsafe {
+                    crate::checked!(gl::BindFramebuffer(gl::FRAMEBUFFER, compiled.framebuffers[index]));
+                    crate::checked!(gl::Viewport(0, 0, width as GLint, height as GLint));
+                }
+            }
+            clear(pass.clear_color, pass.clear_depth);
+
+            for (unit, read) in pass.reads.iter().enumerate() {
+                compiled.textures[compiled.physical[read.index]].bind_to_unit(unit as u32);
+            }
+
+            let context = PassContext { name: &pass.name, width, height, reads: &pass.reads, compiled };
+            run(PassId(index), &context);
+        }
+
+        bind_default_framebuffer(self.width as GLint, self.height as GLint);
+        Ok(())
+    }
+
+    /// Orders the passes and allocates textures and framebuffers, done by `execute` whenever
+    /// the graph changed.
+    pub fn compile(&mut self) -> Result<(), WrapperError> {
+        if self.compiled.is_some() {
+            return Ok(());
+        }
+
+        self.validate()?;
+        let order = self.order()?;
+        let (physical, textures) = self.allocate(&order)?;
+
+        let mut compiled = Compiled {
+            graph: self.id,
+            order,
+            physical,
+            textures,
+            framebuffers: vec![0; self.passes.len()],
+            viewports: vec![(self.width, self.height); self.passes.len()],
+        };
+        for (index, pass) in self.passes.iter().enumerate() {
+            if pass.screen {
+                continue;
+            }
+            let first = pass.writes().next().ok_or_else(|| error(&pass.name, "writes nothing"))?;
+            compiled.viewports[index] = self.size_of(first);
+            compiled.framebuffers[index] = self.create_framebuffer(pass, &compiled)?;
+        }
+
+        self.compiled = Some(compiled);
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), WrapperError> {
+        for pass in &self.passes {
+            if pass.reads.iter().copied().chain(pass.writes()).any(|resource| !self.owns(resource)) {
+                return Err(error(&pass.name, "uses an attachment of another graph"));
+            }
+            if pass.screen && pass.writes().next().is_some() {
+                return Err(error(&pass.name, "renders to the screen and can't have attachments"));
+            }
+            if let Some(read) = pass.reads.iter().find(|read| pass.writes().any(|write| write == **read)) {
+                let message = format!("reads '{}' while writing it", self.resources[read.index].name);
+                return Err(error(&pass.name, &message));
+            }
+            if let Some(read) = pass.reads.iter().find(|read| !self.passes.iter().any(|other| other.writes().any(|write| write == **read))) {
+                let message = format!("reads '{}', which no pass writes", self.resources[read.index].name);
+                return Err(error(&pass.name, &message));
+            }
+
+            let mut sizes = pass.writes().map(|write| self.size_of(write));
+            if let Some(size) = sizes.next() {
+                if sizes.any(|other| other != size) {
+                    return Err(error(&pass.name, "has attachments of different sizes"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writers of the same attachment run in the order they were added. A reader runs after
+    /// the last writer added before it, or after the last writer at all when it was added
+    /// before every writer, and before the writer following the one it reads from, so
+    /// ping-pong and accumulation passes keep the contents meant for them. Otherwise passes
+    /// keep the order they were added in.
+    fn order(&self) -> Result<Vec<usize>, WrapperError> {
+        let count = self.passes.len();
+        let mut dependencies = vec![Vec::new(); count];
+        for resource in 0..self.resources.len() {
+            let resource = self.resource_id(resource);
+            let writers: Vec<usize> = (0..count).filter(|&i| self.passes[i].writes().any(|w| w == resource)).collect();
+            for pair in writers.windows(2) {
+                dependencies[pair[1]].push(pair[0]);
+            }
+            for reader in (0..count).filter(|&i| self.passes[i].reads.contains(&resource)) {
+                let source = writers.iter().rposition(|&writer| writer < reader).unwrap_or(writers.len().saturating_sub(1));
+                if let Some(&writer) = writers.get(source) {
+                    dependencies[reader].push(writer);
+                }
+                if let Some(&next) = writers.get(source + 1) {
+                    dependencies[next].push(reader);
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(count);
+        let mut scheduled = vec![false; count];
+        while order.len() < count {
+            let next = (0..count)
+                .find(|&i| !scheduled[i] && dependencies[i].iter().all(|&dependency| scheduled[dependency]))
+                .ok_or_else(|| {
+                    let stuck = (0..count).find(|&i| !scheduled[i]).map_or("", |i| self.passes[i].name.as_str());
+                    error(stuck, "depends on itself through the attachments it reads")
+                })?;
+            scheduled[next] = true;
+            order.push(next);
+        }
+        Ok(order)
+    }
+
+    /// Attachments of one format and size share a texture when the passes using one all run
+    /// before the first pass using the other.
+    fn allocate(&self, order: &[usize]) -> Result<(Vec<usize>, Vec<Texture2D>), WrapperError> {
+        let (physical, pool) = self.alias(order);
+        let textures = pool
+            .iter()
+            .enumerate()
+            .map(|(texture, &(format, (width, height)))| {
+                let created = Texture2D::empty(width, height, format)?;
+                let names: Vec<&str> = (0..self.resources.len())
+                    .filter(|&resource| physical[resource] == texture)
+                    .map(|resource| self.resources[resource].name.as_str())
+                    .collect();
+                created.set_label(&format!("render_graph.{}", names.join("+")));
+                Ok(created)
+            })
+            .collect::<Result<Vec<_>, WrapperError>>()?;
+
+        Ok((physical, textures))
+    }
+
+    /// Texture index of every resource and the format and size of every texture, for `allocate`.
+    fn alias(&self, order: &[usize]) -> (Vec<usize>, Vec<TextureSpec>) {
+        let mut position = vec![0; self.passes.len()];
+        for (step, &pass) in order.iter().enumerate() {
+            position[pass] = step;
+        }
+
+        // first and last step using every resource, unused ones get a texture all the same
+        let lifetimes: Vec<(usize, usize)> = (0..self.resources.len())
+            .map(|resource| {
+                let resource = self.resource_id(resource);
+                let steps = (0..self.passes.len())
+                    .filter(|&i| self.passes[i].reads.contains(&resource) || self.passes[i].writes().any(|w| w == resource))
+                    .map(|i| position[i]);
+                let (first, last) = steps.fold((usize::MAX, 0), |(first, last), step| (first.min(step), last.max(step)));
+                if self.resources[resource.index].persistent { (0, usize::MAX) } else { (first, last) }
+            })
+            .collect();
+
+        let mut by_first: Vec<usize> = (0..self.resources.len()).collect();
+        by_first.sort_by_key(|&resource| lifetimes[resource].0);
+
+        // (format, size, last step using it) of every texture
+        let mut pool: Vec<(TextureFormat, (u32, u32), usize)> = Vec::new();
+        let mut physical = vec![0; self.resources.len()];
+        for resource in by_first {
+            let (first, last) = lifetimes[resource];
+            let (format, size) = (self.resources[resource].format, self.size_of(self.resource_id(resource)));
+            let reusable = pool.iter().position(|&(pool_format, pool_size, busy_until)| {
+                pool_format == format && pool_size == size && busy_until < first && first != usize::MAX
+            });
+            physical[resource] = match reusable {
+                Some(texture) => {
+                    pool[texture].2 = last;
+                    texture
+                }
+                None => {
+                    pool.push((format, size, last));
+                    pool.len() - 1
+                }
+            };
+        }
+
+        (physical, pool.into_iter().map(|(format, size, _)| (format, size)).collect())
+    }
+
+    fn create_framebuffer(&self, pass: &PassDesc, compiled: &Compiled) -> Result<GLuint, WrapperError> {
+        let texture = |resource: ResourceId| &compiled.textures[compiled.physical[resource.index]];
+
+        let mut id = 0;
+        unsafe { crate::checked!(gl::GenFramebuffers(1, &mut id)) };
+        if id == 0 {
+            return Err(WrapperError::Allocation("framebuffer"));
+        }
+
+        let mut draw_buffers = Vec::with_capacity(pass.colors.len());
+        unsafe {
+            crate::checked!(gl::BindFramebuffer(gl::FRAMEBUFFER, id));
+            for (i, &color) in pass.colors.iter().enumerate() {
+                let point = gl::COLOR_ATTACHMENT0 + i as GLenum;
+                crate::checked!(gl::FramebufferTexture2D(gl::FRAMEBUFFER, point, gl::TEXTURE_2D, texture(color).0, 0));
+                draw_buffers.push(point);
+            }
+            if let Some(depth) = pass.depth {
+                let point =
+                    if self.resources[depth.index].format.has_stencil() { gl::DEPTH_STENCIL_ATTACHMENT } else { gl::DEPTH_ATTACHMENT };
+                crate::checked!(gl::FramebufferTexture2D(gl::FRAMEBUFFER, point, gl::TEXTURE_2D, texture(depth).0, 0));
+            }
+
+            if draw_buffers.is_empty() {
+                crate::checked!(gl::DrawBuffer(gl::NONE));
+                crate::checked!(gl::ReadBuffer(gl::NONE));
+            } else {
+                crate::checked!(gl::DrawBuffers(draw_buffers.len() as GLint, draw_buffers.as_ptr()));
+            }
+        }
+        object_label(gl::FRAMEBUFFER, id, &format!("render_graph.{}", pass.name));
+
+        let status = unsafe { crate::checked!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER)) };
+        unsafe { crate::checked!(gl::BindFramebuffer(gl::FRAMEBUFFER, 0)) };
+
+        if status == gl::FRAMEBUFFER_COMPLETE {
+            Ok(id)
+        } else {
+            unsafe { crate::checked!(gl::DeleteFramebuffers(1, &id)) };
+            Err(WrapperError::FramebufferIncomplete(status))
+        }
+    }
+
+    fn size_of(&self, resource: ResourceId) -> (u32, u32) {
+        self.resources[resource.index].size.resolve(self.width, self.height)
+    }
+
+    fn owns(&self, resource: ResourceId) -> bool {
+        resource.graph == self.id && resource.index < self.resources.len()
+    }
+
+    fn resource_id(&self, index: usize) -> ResourceId {
+        ResourceId { graph: self.id, index }
+    }
+}
+
+fn error(pass: &str, message: &str) -> WrapperError {
+    WrapperError::RenderGraph(format!("pass '{}' {}", pass, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(graph: &RenderGraph) -> Vec<&str> {
+        graph.order().unwrap().into_iter().map(|pass| graph.passes[pass].name.as_str()).collect()
+    }
+
+    #[test]
+    fn writers_run_before_readers_added_earlier() {
+        let mut graph = RenderGraph::new(640, 480);
+        let shadow_map = graph.create_texture("shadow_map", TextureFormat::Depth32F, TargetSize::Fixed(1024, 1024));
+        let hdr = graph.create_texture("hdr", TextureFormat::Rgba16F, TargetSize::Output);
+        graph.add_pass(PassDesc::new("post").read(hdr).screen());
+        graph.add_pass(PassDesc::new("scene").read(shadow_map).color(hdr));
+        graph.add_pass(PassDesc::new("shadows").depth(shadow_map));
+        assert_eq!(names(&graph), ["shadows", "scene", "post"]);
+    }
+
+    #[test]
+    fn ping_pong_is_no_cycle() {
+        let mut graph = RenderGraph::new(640, 480);
+        let ping = graph.create_texture("ping", TextureFormat::Rgba16F, TargetSize::Output);
+        let pong = graph.create_texture("pong", TextureFormat::Rgba16F, TargetSize::Output);
+        graph.add_pass(PassDesc::new("init").color(ping));
+        graph.add_pass(PassDesc::new("blur_x").read(ping).color(pong));
+        graph.add_pass(PassDesc::new("blur_y").read(pong).color(ping));
+        graph.add_pass(PassDesc::new("blur_x2").read(ping).color(pong));
+        graph.add_pass(PassDesc::new("present").read(pong).screen());
+        assert_eq!(names(&graph), ["init", "blur_x", "blur_y", "blur_x2", "present"]);
+    }
+
+    #[test]
+    fn readers_run_before_the_next_writer() {
+        let mut graph = RenderGraph::new(640, 480);
+        let accum = graph.create_texture("accum", TextureFormat::Rgba16F, TargetSize::Output);
+        let mask = graph.create_texture("mask", TextureFormat::R8, TargetSize::Output);
+        graph.add_pass(PassDesc::new("first").color(accum));
+        graph.add_pass(PassDesc::new("use_first").read(accum).read(mask).screen());
+        graph.add_pass(PassDesc::new("second").color(accum));
+        // added last, so "use_first" waits for it and "second" has to wait as well
+        graph.add_pass(PassDesc::new("mask").color(mask));
+        assert_eq!(names(&graph), ["first", "mask", "use_first", "second"]);
+    }
+
+    #[test]
+    fn reading_while_writing_is_rejected() {
+        let mut graph = RenderGraph::new(640, 480);
+        let hdr = graph.create_texture("hdr", TextureFormat::Rgba16F, TargetSize::Output);
+        graph.add_pass(PassDesc::new("feedback").read(hdr).color(hdr));
+        assert!(matches!(graph.validate(), Err(WrapperError::RenderGraph(_))));
+    }
+
+    #[test]
+    fn foreign_resources_are_rejected() {
+        let mut other = RenderGraph::new(640, 480);
+        other.create_texture("a", TextureFormat::Rgba8, TargetSize::Output);
+        let foreign = other.create_texture("b", TextureFormat::Rgba8, TargetSize::Output);
+
+        // same index as one of the graph's own
+        let mut graph = RenderGraph::new(640, 480);
+        graph.create_texture("c", TextureFormat::Rgba8, TargetSize::Output);
+        graph.create_texture("d", TextureFormat::Rgba8, TargetSize::Output);
+        graph.add_pass(PassDesc::new("pass").color(foreign));
+        assert!(matches!(graph.validate(), Err(WrapperError::RenderGraph(_))));
+        assert!(graph.texture(foreign).is_none());
+    }
+
+    #[test]
+    fn transient_attachments_share_textures() {
+        let mut graph = RenderGraph::new(640, 480);
+        let a = graph.create_texture("a", TextureFormat::Rgba16F, TargetSize::Output);
+        let b = graph.create_texture("b", TextureFormat::Rgba16F, TargetSize::Output);
+        let c = graph.create_texture("c", TextureFormat::Rgba16F, TargetSize::Output);
+        let half = graph.create_texture("half", TextureFormat::Rgba16F, TargetSize::Scaled(0.5));
+        graph.add_pass(PassDesc::new("write_a").color(a));
+        graph.add_pass(PassDesc::new("a_to_b").read(a).color(b));
+        graph.add_pass(PassDesc::new("b_to_c").read(b).color(c));
+        graph.add_pass(PassDesc::new("c_to_half").read(c).color(half));
+        graph.add_pass(PassDesc::new("present").read(half).screen());
+
+        let (physical, textures) = graph.alias(&graph.order().unwrap());
+        // a is done once b_to_c runs, so c takes its texture
+        assert_eq!(physical[a.index], physical[c.index]);
+        assert_ne!(physical[a.index], physical[b.index]);
+        assert_ne!(physical[half.index], physical[a.index]);
+        assert_eq!(textures.len(), 3);
+        assert_eq!(textures[physical[half.index]], (TextureFormat::Rgba16F, (320, 240)));
+    }
+
+    #[test]
+    fn persistent_attachments_are_not_shared() {
+        let mut graph = RenderGraph::new(640, 480);
+        let history = graph.create_persistent_texture("history", TextureFormat::Rgba16F, TargetSize::Output);
+        let a = graph.create_texture("a", TextureFormat::Rgba16F, TargetSize::Output);
+        let b = graph.create_texture("b", TextureFormat::Rgba16F, TargetSize::Output);
+        graph.add_pass(PassDesc::new("write_history").color(history));
+        graph.add_pass(PassDesc::new("write_a").read(history).color(a));
+        graph.add_pass(PassDesc::new("write_b").read(a).color(b));
+        graph.add_pass(PassDesc::new("present").read(b).screen());
+
+        let (physical, textures) = graph.alias(&graph.order().unwrap());
+        assert_eq!(textures.len(), 3);
+        assert_ne!(physical[history.index], physical[a.index]);
+        assert_ne!(physical[history.index], physical[b.index]);
+    }
+}
@@ -0,0 +1,268 @@
+//! Hierarchy of nodes, each with a transform relative to its parent and optionally a mesh.
+//! World matrices are cached and only recomputed below nodes whose transform changed.
+
+use std::rc::Rc;
+
+use glam::Mat4;
+
+use crate::error::WrapperError;
+use crate::mesh::Mesh;
+use crate::transform::{MODEL_UNIFORM, Transform};
+use crate::wrapper::ShaderProgram;
+
+/// Index of a node in its `Scene`, only meaningful for the scene that returned it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+struct Node {
+    name: Option<String>,
+    local: Transform,
+    world: Mat4,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    mesh: Option<Rc<Mesh>>,
+    /// `local` changed since `world` was computed
+    dirty: bool,
+}
+
+#[derive(Default)]
+pub struct Scene {
+    nodes: Vec<Node>,
+    roots: Vec<NodeId>,
+}
+
+#[allow(dead_code)]
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// New top level node.
+    pub fn add(&mut self, transform: Transform) -> NodeId {
+        self.insert(None, transform)
+    }
+
+    /// New node whose `transform` is relative to `parent`.
+    pub fn add_child(&mut self, parent: NodeId, transform: Transform) -> NodeId {
+        self.insert(Some(parent), transform)
+    }
+
+    fn insert(&mut self, parent: Option<NodeId>, local: Transform) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Node {
+            name: None,
+            local,
+            world: Mat4::IDENTITY,
+            parent,
+            children: Vec::new(),
+            mesh: None,
+            dirty: true,
+        });
+        self.siblings_mut(parent).push(id);
+        id
+    }
+
+    /// Moves `node` with its subtree under `parent`, or to the top level for `None`. The local
+    /// transform is kept, so the node moves along with its new parent.
+    /// Fails without changing anything when `parent` is `node` itself or one of its descendants.
+    pub fn set_parent(&mut self, node: NodeId, parent: Option<NodeId>) -> Result<(), WrapperError> {
+        let mut ancestor = parent;
+        while let Some(id) = ancestor {
+            if id == node {
+                let name = self.nodes[node.0].name.as_deref().unwrap_or("unnamed");
+                return Err(WrapperError::SceneHierarchy(format!("node '{}' can't become its own descendant", name)));
+            }
+            ancestor = self.nodes[id.0].parent;
+        }
+
+        let old = self.nodes[node.0].parent;
+        self.siblings_mut(old).retain(|&id| id != node);
+        self.siblings_mut(parent).push(node);
+        self.nodes[node.0].parent = parent;
+        self.nodes[node.0].dirty = true;
+        Ok(())
+    }
+
+    fn siblings_mut(&mut self, parent: Option<NodeId>) -> &mut Vec<NodeId> {
+        match parent {
+            Some(parent) => &mut self.nodes[parent.0].children,
+            None => &mut self.roots,
+        }
+    }
+
+    pub fn transform(&self, node: NodeId) -> &Transform {
+        &self.nodes[node.0].local
+    }
+
+    /// Marks the node, and with it its subtree, for recomputing on the next `update`.
+    pub fn transform_mut(&mut self, node: NodeId) -> &mut Transform {
+        let node = &mut self.nodes[node.0];
+        node.dirty = true;
+        &mut node.local
+    }
+
+    pub fn set_transform(&mut self, node: NodeId, transform: Transform) {
+        *self.transform_mut(node) = transform;
+    }
+
+    /// Model matrix of the node as of the last `update`.
+    pub fn world_matrix(&self, node: NodeId) -> Mat4 {
+        self.nodes[node.0].world
+    }
+
+    pub fn set_mesh(&mut self, node: NodeId, mesh: Option<Rc<Mesh>>) {
+        self.nodes[node.0].mesh = mesh;
+    }
+
+    pub fn mesh(&self, node: NodeId) -> Option<&Rc<Mesh>> {
+        self.nodes[node.0].mesh.as_ref()
+    }
+
+    pub fn set_name(&mut self, node: NodeId, name: impl Into<String>) {
+        self.nodes[node.0].name = Some(name.into());
+    }
+
+    pub fn name(&self, node: NodeId) -> Option<&str> {
+        self.nodes[node.0].name.as_deref()
+    }
+
+    /// First node called `name`.
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|node| node.name.as_deref() == Some(name)).map(NodeId)
+    }
+
+    pub fn parent(&self, node: NodeId) -> Option<NodeId> {
+        self.nodes[node.0].parent
+    }
+
+    pub fn children(&self, node: NodeId) -> &[NodeId] {
+        &self.nodes[node.0].children
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Recomputes the world matrices of changed nodes and everything below them.
+    pub fn update(&mut self) {
+        let mut stack: Vec<(NodeId, Mat4, bool)> =
+            self.roots.iter().rev().map(|&root| (root, Mat4::IDENTITY, false)).collect();
+
+        while let Some((id, parent_world, parent_changed)) = stack.pop() {
+            let node = &mut self.nodes[id.0];
+            let changed = parent_changed || node.dirty;
+            if changed {
+                node.world = parent_world * node.local.matrix();
+                node.dirty = false;
+            }
+            let world = node.world;
+            stack.extend(node.children.iter().rev().map(|&child| (child, world, changed)));
+        }
+    }
+
+    /// Calls `visit` with every node and its world matrix, parents before their children.
+    pub fn traverse(&self, mut visit: impl FnMut(NodeId, &Mat4)) {
+        let mut stack: Vec<NodeId> = self.roots.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            visit(id, &self.nodes[id.0].world);
+            stack.extend(self.nodes[id.0].children.iter().rev());
+        }
+    }
+
+    /// Updates the world matrices, then draws every node with a mesh, setting its world matrix
+    /// as the `model` uniform of `program`, which has to be in use.
+    pub fn draw(&mut self, program: &ShaderProgram) {
+        self.update();
+        self.traverse(|id, world| {
+            if let Some(mesh) = &self.nodes[id.0].mesh {
+                program.set_mat4(MODEL_UNIFORM, &world.to_cols_array());
+                mesh.draw();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+
+    fn at(x: f32) -> Transform {
+        Transform { translation: Vec3::new(x, 0.0, 0.0), ..Transform::default() }
+    }
+
+    fn x_of(scene: &Scene, node: NodeId) -> f32 {
+        scene.world_matrix(node).w_axis.x
+    }
+
+    #[test]
+    fn changes_propagate_to_descendants() {
+        let mut scene = Scene::new();
+        let root = scene.add(at(1.0));
+        let child = scene.add_child(root, at(2.0));
+        let grandchild = scene.add_child(child, at(3.0));
+        scene.update();
+        assert_eq!(x_of(&scene, grandchild), 6.0);
+
+        scene.transform_mut(root).translation.x = 10.0;
+        scene.update();
+        assert_eq!(x_of(&scene, child), 12.0);
+        assert_eq!(x_of(&scene, grandchild), 15.0);
+        assert!(scene.nodes.iter().all(|node| !node.dirty));
+    }
+
+    #[test]
+    fn clean_subtrees_are_left_alone() {
+        let mut scene = Scene::new();
+        let a = scene.add(at(1.0));
+        let b = scene.add(at(2.0));
+        let b_child = scene.add_child(b, at(1.0));
+        scene.update();
+
+        // only recomputed when dirty, so a stale cached value shows it wasn't touched
+        scene.nodes[b_child.0].world = Mat4::IDENTITY;
+        scene.set_transform(a, at(5.0));
+        scene.update();
+        assert_eq!(x_of(&scene, a), 5.0);
+        assert_eq!(x_of(&scene, b_child), 0.0);
+    }
+
+    #[test]
+    fn reparenting_keeps_the_local_transform() {
+        let mut scene = Scene::new();
+        let a = scene.add(at(1.0));
+        let b = scene.add(at(10.0));
+        let child = scene.add_child(a, at(2.0));
+        scene.update();
+        assert_eq!(x_of(&scene, child), 3.0);
+
+        scene.set_parent(child, Some(b)).unwrap();
+        scene.update();
+        assert_eq!(x_of(&scene, child), 12.0);
+        assert_eq!(scene.children(a), []);
+        assert_eq!(scene.children(b), [child]);
+
+        scene.set_parent(child, None).unwrap();
+        assert_eq!(scene.roots(), [a, b, child]);
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut scene = Scene::new();
+        let root = scene.add(at(0.0));
+        let child = scene.add_child(root, at(0.0));
+        assert!(matches!(scene.set_parent(root, Some(child)), Err(WrapperError::SceneHierarchy(_))));
+        assert!(matches!(scene.set_parent(root, Some(root)), Err(WrapperError::SceneHierarchy(_))));
+        assert_eq!(scene.parent(root), None);
+        assert_eq!(scene.children(root), [child]);
+    }
+}
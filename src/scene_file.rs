@@ -0,0 +1,307 @@
+//! Scene descriptions saved as RON or JSON: objects with transforms, meshes, materials and
+//! shaders, the camera and the clear color. Paths inside are relative to the file.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::camera::Camera;
+use crate::error::WrapperError;
+use crate::loader::obj;
+use crate::material::PbrMaterial;
+use crate::mesh::{MeshData, MeshVertex, Normals};
+use crate::scene::{NodeId, Scene};
+use crate::shapes;
+use crate::transform::Transform;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneFile {
+    pub clear_color: [f32; 4],
+    pub camera: Option<Camera>,
+    /// referred to by name from `SceneObject::material`
+    pub materials: BTreeMap<String, MaterialDesc>,
+    pub objects: Vec<SceneObject>,
+    /// directory of the file it was loaded from, paths are resolved against it
+    #[serde(skip)]
+    pub base: PathBuf,
+}
+
+impl Default for SceneFile {
+    fn default() -> Self {
+        Self {
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            camera: None,
+            materials: BTreeMap::new(),
+            objects: Vec::new(),
+            base: PathBuf::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneObject {
+    pub name: Option<String>,
+    /// relative to the parent object
+    pub transform: Transform,
+    pub mesh: Option<MeshSource>,
+    pub material: Option<String>,
+    pub shader: Option<ShaderRef>,
+    pub children: Vec<SceneObject>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MeshSource {
+    /// Geometry written out in the file. `lines` are edges for an outline, only the triangles
+    /// become a mesh.
+    Inline {
+        positions: Vec<[f32; 3]>,
+        #[serde(default)]
+        triangles: Vec<[u32; 3]>,
+        #[serde(default)]
+        lines: Vec<[u32; 2]>,
+    },
+    /// Wavefront .obj file
+    Obj(PathBuf),
+    Shape(Shape),
+}
+
+/// Arguments of the `shapes` generator of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Shape {
+    Cube { size: f32 },
+    Plane { width: f32, depth: f32, subdivisions: u32 },
+    UvSphere { radius: f32, segments: u32, rings: u32 },
+    IcoSphere { radius: f32, subdivisions: u32 },
+    Torus { major_radius: f32, minor_radius: f32, segments: u32, sides: u32 },
+    Cylinder { radius: f32, height: f32, segments: u32 },
+    Capsule { radius: f32, height: f32, segments: u32, rings: u32 },
+}
+
+/// Factors of a `PbrMaterial`, without maps.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialDesc {
+    pub albedo: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+}
+
+impl Default for MaterialDesc {
+    fn default() -> Self {
+        let material = PbrMaterial::default();
+        Self { albedo: material.albedo, metallic: material.metallic, roughness: material.roughness, emissive: material.emissive }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShaderRef {
+    pub vertex: PathBuf,
+    pub fragment: PathBuf,
+}
+
+#[allow(dead_code)]
+impl SceneFile {
+    /// Reads JSON for a `.json` extension, RON otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        let path = path.as_ref();
+        let src = std::fs::read_to_string(path)
+            .map_err(|source| WrapperError::Io { path: path.to_path_buf(), source })?;
+
+        let parsed = if is_json(path) {
+            serde_json::from_str::<Self>(&src).map_err(|err| err.to_string())
+        } else {
+            ron::from_str::<Self>(&src).map_err(|err| err.to_string())
+        };
+        let mut scene = parsed.map_err(|message| WrapperError::SceneFile { path: path.to_path_buf(), message })?;
+        scene.base = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(scene)
+    }
+
+    /// Writes JSON for a `.json` extension, RON otherwise, both pretty printed.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), WrapperError> {
+        let path = path.as_ref();
+        let text = if is_json(path) {
+            serde_json::to_string_pretty(self).map_err(|err| err.to_string())
+        } else {
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(|err| err.to_string())
+        };
+        let text = text.map_err(|message| WrapperError::SceneFile { path: path.to_path_buf(), message })?;
+
+        std::fs::write(path, text).map_err(|source| WrapperError::Io { path: path.to_path_buf(), source })
+    }
+
+    /// `path` from the file resolved against the directory it was loaded from.
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        self.base.join(path)
+    }
+
+    /// First object called `name`, searching children too.
+    pub fn find(&self, name: &str) -> Option<&SceneObject> {
+        let mut stack: Vec<&SceneObject> = self.objects.iter().rev().collect();
+        while let Some(object) = stack.pop() {
+            if object.name.as_deref() == Some(name) {
+                return Some(object);
+            }
+            stack.extend(object.children.iter().rev());
+        }
+        None
+    }
+
+    pub fn material(&self, name: &str) -> Option<PbrMaterial> {
+        self.materials.get(name).map(|material| material.to_pbr())
+    }
+
+    /// Uploads the meshes and builds the node hierarchy, nodes named like their objects.
+    /// Materials and shaders are left to the caller, look them up through the names.
+    pub fn instantiate(&self) -> Result<Scene, WrapperError> {
+        let mut scene = Scene::new();
+        for object in &self.objects {
+            self.add_object(&mut scene, None, object)?;
+        }
+        scene.update();
+        Ok(scene)
+    }
+
+    fn add_object(&self, scene: &mut Scene, parent: Option<NodeId>, object: &SceneObject) -> Result<(), WrapperError> {
+        let node = match parent {
+            Some(parent) => scene.add_child(parent, object.transform),
+            None => scene.add(object.transform),
+        };
+        if let Some(name) = &object.name {
+            scene.set_name(node, name.clone());
+        }
+        if let Some(mesh) = &object.mesh {
+            scene.set_mesh(node, Some(Rc::new(self.mesh_data(mesh)?.upload()?)));
+        }
+
+        for child in &object.children {
+            self.add_object(scene, Some(node), child)?;
+        }
+        Ok(())
+    }
+
+    /// Triangles of `mesh`, inline geometry gets smooth normals.
+    pub fn mesh_data(&self, mesh: &MeshSource) -> Result<MeshData, WrapperError> {
+        Ok(match mesh {
+            MeshSource::Inline { positions, triangles, .. } => {
+                let mut data = MeshData {
+                    vertices: positions.iter().map(|&position| MeshVertex { position, ..MeshVertex::default() }).collect(),
+                    indices: triangles.as_flattened().to_vec(),
+                };
+                data.compute_normals(Normals::Smooth);
+                data
+            }
+            MeshSource::Obj(path) => obj::load_data(self.resolve(path))?,
+            MeshSource::Shape(shape) => shape.generate(),
+        })
+    }
+}
+
+#[allow(dead_code)]
+impl Shape {
+    pub fn generate(&self) -> MeshData {
+        match *self {
+            Self::Cube { size } => shapes::cube(size),
+            Self::Plane { width, depth, subdivisions } => shapes::plane(width, depth, subdivisions),
+            Self::UvSphere { radius, segments, rings } => shapes::uv_sphere(radius, segments, rings),
+            Self::IcoSphere { radius, subdivisions } => shapes::ico_sphere(radius, subdivisions),
+            Self::Torus { major_radius, minor_radius, segments, sides } => {
+                shapes::torus(major_radius, minor_radius, segments, sides)
+            }
+            Self::Cylinder { radius, height, segments } => shapes::cylinder(radius, height, segments),
+            Self::Capsule { radius, height, segments, rings } => shapes::capsule(radius, height, segments, rings),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl MaterialDesc {
+    pub fn to_pbr(self) -> PbrMaterial {
+        PbrMaterial { emissive: self.emissive, ..PbrMaterial::new(self.albedo, self.metallic, self.roughness) }
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+
+    fn sample() -> SceneFile {
+        let mut materials = BTreeMap::new();
+        materials.insert("red".to_string(), MaterialDesc { albedo: [1.0, 0.0, 0.0, 1.0], ..MaterialDesc::default() });
+        let child = SceneObject {
+            name: Some("triangle".to_string()),
+            mesh: Some(MeshSource::Inline { positions: vec![[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]], triangles: vec![[0, 1, 2]], lines: vec![] }),
+            ..SceneObject::default()
+        };
+        let object = SceneObject {
+            name: Some("ball".to_string()),
+            transform: Transform { translation: Vec3::new(1.0, 2.0, 3.0), ..Transform::default() },
+            mesh: Some(MeshSource::Shape(Shape::UvSphere { radius: 0.5, segments: 8, rings: 4 })),
+            material: Some("red".to_string()),
+            shader: Some(ShaderRef { vertex: "lit.vert".into(), fragment: "lit.frag".into() }),
+            children: vec![child],
+        };
+        SceneFile { clear_color: [0.1, 0.2, 0.3, 1.0], materials, objects: vec![object], ..SceneFile::default() }
+    }
+
+    fn round_trip(extension: &str) {
+        let dir = std::env::temp_dir().join(format!("scene_file_test_{}_{}", std::process::id(), extension));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("scene.{}", extension));
+
+        let scene = sample();
+        scene.save(&path).unwrap();
+        let loaded = SceneFile::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.clear_color, scene.clear_color);
+        assert_eq!(loaded.materials, scene.materials);
+        assert_eq!(loaded.objects, scene.objects);
+        assert_eq!(loaded.base, dir);
+        assert_eq!(loaded.resolve(Path::new("lit.vert")), dir.join("lit.vert"));
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        round_trip("ron");
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip("json");
+    }
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let scene: SceneFile = ron::from_str("(objects: [(name: Some(\"empty\"))])").unwrap();
+        assert_eq!(scene.clear_color, [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(scene.find("empty"), Some(&SceneObject { name: Some("empty".to_string()), ..SceneObject::default() }));
+        assert!(scene.find("triangle").is_none());
+    }
+
+    #[test]
+    fn find_searches_children() {
+        let scene = sample();
+        assert!(scene.find("triangle").is_some());
+        assert_eq!(scene.material("red").map(|material| material.albedo), Some([1.0, 0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn inline_meshes_get_normals() {
+        let scene = sample();
+        let data = scene.mesh_data(scene.find("triangle").unwrap().mesh.as_ref().unwrap()).unwrap();
+        assert_eq!(data.indices, [0, 1, 2]);
+        assert!(data.vertices.iter().all(|vertex| vertex.normal == [0.0, 0.0, 1.0]));
+    }
+}
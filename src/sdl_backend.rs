@@ -0,0 +1,246 @@
+use sdl2::event::{Event, WindowEvent as SdlWindowEvent};
+use sdl2::keyboard::{Mod, Scancode};
+use sdl2::video::{GLContext, GLProfile, SwapInterval, Window};
+use sdl2::{EventPump, Sdl, VideoSubsystem};
+
+use crate::backend::Backend;
+use crate::error::WrapperError;
+use crate::event::{Action, Key, Modifiers, MouseButton, WindowEvent};
+use crate::gl_ext;
+use crate::wrapper::context_version;
+
+/// Window, context and events from SDL2, for applications already built around SDL. Doesn't
+/// need GLFW, build without the default `glfw` feature.
+#[allow(dead_code)]
+pub struct SdlBackend {
+    sdl: Sdl,
+    video: VideoSubsystem,
+    window: Window,
+    /// has to outlive every GL call
+    _context: GLContext,
+    events: EventPump,
+    should_close: bool,
+    /// version of the created context, may be higher than requested
+    pub gl_version: (u32, u32),
+}
+
+#[allow(dead_code)]
+impl SdlBackend {
+    pub fn new(width: u32, height: u32, title: &str) -> Result<Self, WrapperError> {
+        Self::with_version(width, height, title, (3, 3))
+    }
+
+    pub fn with_version(width: u32, height: u32, title: &str, version: (u32, u32)) -> Result<Self, WrapperError> {
+        let error = |message: &str, err: String| WrapperError::ContextCreation(format!("{}: {}", message, err));
+
+        let sdl = sdl2::init().map_err(|err| error("Could not initialize SDL", err))?;
+        let video = sdl.video().map_err(|err| error("No SDL video subsystem", err))?;
+
+        let attributes = video.gl_attr();
+        attributes.set_context_profile(GLProfile::Core);
+        attributes.set_context_version(version.0 as u8, version.1 as u8);
+
+        let window = video
+            .window(title, width, height)
+            .opengl()
+            .resizable()
+            .allow_highdpi()
+            .build()
+            .map_err(|err| error("Failed to create SDL window", err.to_string()))?;
+        let context = window.gl_create_context().map_err(|err| error("Could not create GL context", err))?;
+
+        gl::load_with(|symbol| video.gl_get_proc_address(symbol) as *const _);
+        gl_ext::load_with(|symbol| video.gl_get_proc_address(symbol) as *const _);
+        // without vsync frames are merely presented as fast as they're drawn
+        if let Err(_err) = video.gl_set_swap_interval(SwapInterval::VSync) {
+            #[cfg(feature = "debug")]
+            log::warn!("VSync unavailable: {}", _err);
+        }
+
+        let events = sdl.event_pump().map_err(|err| error("No SDL event pump", err))?;
+        Ok(Self {
+            sdl, video, window, _context: context, events, should_close: false, gl_version: context_version(),
+        })
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    pub fn sdl(&self) -> &Sdl {
+        &self.sdl
+    }
+}
+
+impl Backend for SdlBackend {
+    fn should_close(&self) -> bool {
+        self.should_close
+    }
+
+    fn set_should_close(&mut self, close: bool) {
+        self.should_close = close;
+    }
+
+    fn framebuffer_size(&self) -> (i32, i32) {
+        let (width, height) = self.window.drawable_size();
+        (width as i32, height as i32)
+    }
+
+    fn poll_events(&mut self) -> Vec<WindowEvent> {
+        let keyboard = self.sdl.keyboard();
+        let mut events = Vec::new();
+        for event in self.events.poll_iter() {
+            let event = match event {
+                Event::Quit { .. } | Event::Window { win_event: SdlWindowEvent::Close, .. } => {
+                    self.should_close = true;
+                    WindowEvent::Close
+                }
+                // reported in window units, the drawable is bigger on high DPI screens
+                Event::Window { win_event: SdlWindowEvent::SizeChanged(..), .. } => {
+                    let (width, height) = self.window.drawable_size();
+                    WindowEvent::FramebufferSize(width as i32, height as i32)
+                }
+                Event::Window { win_event: SdlWindowEvent::FocusGained, .. } => WindowEvent::Focus(true),
+                Event::Window { win_event: SdlWindowEvent::FocusLost, .. } => WindowEvent::Focus(false),
+                Event::KeyDown { scancode: Some(scancode), keymod, repeat, .. } => {
+                    let Some(key) = key_from(scancode) else {
+                        continue;
+                    };
+                    let action = if repeat { Action::Repeat } else { Action::Press };
+                    WindowEvent::Key { key, scancode: scancode as i32, action, modifiers: modifiers_from(keymod) }
+                }
+                Event::KeyUp { scancode: Some(scancode), keymod, .. } => {
+                    let Some(key) = key_from(scancode) else {
+                        continue;
+                    };
+                    let modifiers = modifiers_from(keymod);
+                    WindowEvent::Key { key, scancode: scancode as i32, action: Action::Release, modifiers }
+                }
+                Event::MouseMotion { x, y, .. } => WindowEvent::CursorPos(x as f64, y as f64),
+                Event::MouseButtonDown { mouse_btn, .. } | Event::MouseButtonUp { mouse_btn, .. } => {
+                    let Some(button) = mouse_button_from(mouse_btn) else {
+                        continue;
+                    };
+                    let action = if matches!(event, Event::MouseButtonDown { .. }) { Action::Press } else { Action::Release };
+                    WindowEvent::MouseButton { button, action, modifiers: modifiers_from(keyboard.mod_state()) }
+                }
+                Event::MouseWheel { precise_x, precise_y, .. } => WindowEvent::Scroll(precise_x as f64, precise_y as f64),
+                _ => continue,
+            };
+            events.push(event);
+        }
+        events
+    }
+
+    fn swap_buffers(&mut self) {
+        self.window.gl_swap_window();
+    }
+}
+
+fn modifiers_from(keymod: Mod) -> Modifiers {
+    let mut modifiers = Modifiers::empty();
+    modifiers.set(Modifiers::SHIFT, keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD));
+    modifiers.set(Modifiers::CONTROL, keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD));
+    modifiers.set(Modifiers::ALT, keymod.intersects(Mod::LALTMOD | Mod::RALTMOD));
+    modifiers.set(Modifiers::SUPER, keymod.intersects(Mod::LGUIMOD | Mod::RGUIMOD));
+    modifiers
+}
+
+fn mouse_button_from(button: sdl2::mouse::MouseButton) -> Option<MouseButton> {
+    match button {
+        sdl2::mouse::MouseButton::Left => Some(MouseButton::Left),
+        sdl2::mouse::MouseButton::Right => Some(MouseButton::Right),
+        sdl2::mouse::MouseButton::Middle => Some(MouseButton::Middle),
+        sdl2::mouse::MouseButton::X1 => Some(MouseButton::Back),
+        sdl2::mouse::MouseButton::X2 => Some(MouseButton::Forward),
+        sdl2::mouse::MouseButton::Unknown => None,
+    }
+}
+
+fn key_from(scancode: Scancode) -> Option<Key> {
+    Some(match scancode {
+        Scancode::A => Key::A,
+        Scancode::B => Key::B,
+        Scancode::C => Key::C,
+        Scancode::D => Key::D,
+        Scancode::E => Key::E,
+        Scancode::F => Key::F,
+        Scancode::G => Key::G,
+        Scancode::H => Key::H,
+        Scancode::I => Key::I,
+        Scancode::J => Key::J,
+        Scancode::K => Key::K,
+        Scancode::L => Key::L,
+        Scancode::M => Key::M,
+        Scancode::N => Key::N,
+        Scancode::O => Key::O,
+        Scancode::P => Key::P,
+        Scancode::Q => Key::Q,
+        Scancode::R => Key::R,
+        Scancode::S => Key::S,
+        Scancode::T => Key::T,
+        Scancode::U => Key::U,
+        Scancode::V => Key::V,
+        Scancode::W => Key::W,
+        Scancode::X => Key::X,
+        Scancode::Y => Key::Y,
+        Scancode::Z => Key::Z,
+        Scancode::Num0 => Key::Num0,
+        Scancode::Num1 => Key::Num1,
+        Scancode::Num2 => Key::Num2,
+        Scancode::Num3 => Key::Num3,
+        Scancode::Num4 => Key::Num4,
+        Scancode::Num5 => Key::Num5,
+        Scancode::Num6 => Key::Num6,
+        Scancode::Num7 => Key::Num7,
+        Scancode::Num8 => Key::Num8,
+        Scancode::Num9 => Key::Num9,
+        Scancode::F1 => Key::F1,
+        Scancode::F2 => Key::F2,
+        Scancode::F3 => Key::F3,
+        Scancode::F4 => Key::F4,
+        Scancode::F5 => Key::F5,
+        Scancode::F6 => Key::F6,
+        Scancode::F7 => Key::F7,
+        Scancode::F8 => Key::F8,
+        Scancode::F9 => Key::F9,
+        Scancode::F10 => Key::F10,
+        Scancode::F11 => Key::F11,
+        Scancode::F12 => Key::F12,
+        Scancode::Up => Key::Up,
+        Scancode::Down => Key::Down,
+        Scancode::Left => Key::Left,
+        Scancode::Right => Key::Right,
+        Scancode::Space => Key::Space,
+        Scancode::Return => Key::Enter,
+        Scancode::Escape => Key::Escape,
+        Scancode::Tab => Key::Tab,
+        Scancode::Backspace => Key::Backspace,
+        Scancode::Insert => Key::Insert,
+        Scancode::Delete => Key::Delete,
+        Scancode::Home => Key::Home,
+        Scancode::End => Key::End,
+        Scancode::PageUp => Key::PageUp,
+        Scancode::PageDown => Key::PageDown,
+        Scancode::Minus => Key::Minus,
+        Scancode::Equals => Key::Equal,
+        Scancode::LeftBracket => Key::LeftBracket,
+        Scancode::RightBracket => Key::RightBracket,
+        Scancode::Backslash => Key::Backslash,
+        Scancode::Semicolon => Key::Semicolon,
+        Scancode::Apostrophe => Key::Apostrophe,
+        Scancode::Grave => Key::GraveAccent,
+        Scancode::Comma => Key::Comma,
+        Scancode::Period => Key::Period,
+        Scancode::Slash => Key::Slash,
+        Scancode::LShift => Key::LeftShift,
+        Scancode::RShift => Key::RightShift,
+        Scancode::LCtrl => Key::LeftControl,
+        Scancode::RCtrl => Key::RightControl,
+        Scancode::LAlt => Key::LeftAlt,
+        Scancode::RAlt => Key::RightAlt,
+        Scancode::LGui => Key::LeftSuper,
+        Scancode::RGui => Key::RightSuper,
+        _ => return None,
+    })
+}
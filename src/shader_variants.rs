@@ -0,0 +1,224 @@
+//! One vertex + fragment source compiled into a program per combination of `#define`s, so
+//! materials only pay for the features they use instead of branching in one big shader.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::path::Path;
+
+use crate::error::WrapperError;
+use crate::preprocessor::preprocess_file;
+use crate::wrapper::ShaderProgram;
+
+/// Set of `#define NAME value` lines selecting a variant. Order of insertion doesn't matter,
+/// two sets with the same names and values pick the same program.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Defines(BTreeMap<String, String>);
+
+#[allow(dead_code)]
+impl Defines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `#define name`, for `#ifdef` checks.
+    pub fn flag(mut self, name: impl Into<String>) -> Self {
+        self.0.insert(name.into(), String::new());
+        self
+    }
+
+    /// `flag` only when `enabled`.
+    pub fn flag_if(self, name: impl Into<String>, enabled: bool) -> Self {
+        if enabled {
+            self.flag(name)
+        } else {
+            self
+        }
+    }
+
+    /// `#define name value`, e.g. `NUM_LIGHTS 4`.
+    pub fn value(mut self, name: impl Into<String>, value: impl Display) -> Self {
+        self.0.insert(name.into(), value.to_string());
+        self
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.0.remove(name);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The `#define` lines, one per entry.
+    pub fn to_glsl(&self) -> String {
+        self.0
+            .iter()
+            .map(|(name, value)| if value.is_empty() { format!("#define {}\n", name) } else { format!("#define {} {}\n", name, value) })
+            .collect()
+    }
+
+    /// `src` with the defines inserted right after its `#version` line, or in front of
+    /// everything when it has none.
+    pub fn apply(&self, src: &str) -> String {
+        let defines = self.to_glsl();
+        let version_end = src
+            .lines()
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len() + 1;
+                Some((start, line))
+            })
+            .find(|(_, line)| line.trim_start().starts_with("#version"))
+            .map(|(start, line)| (start + line.len() + 1).min(src.len()));
+
+        match version_end {
+            Some(end) => {
+                let (version, rest) = src.split_at(end);
+                let newline = if version.ends_with('\n') { "" } else { "\n" };
+                format!("{}{}{}{}", version, newline, defines, rest)
+            }
+            None => format!("{}{}", defines, src),
+        }
+    }
+}
+
+/// Runs on every newly built variant.
+type BuildHook = Box<dyn Fn(&ShaderProgram)>;
+
+/// Builds and caches the variants of one shader:
+///
+/// ```ignore
+/// let mut variants = ShaderVariants::new(vert_src, frag_src)
+///     .label("material.pbr")
+///     .on_build(|program| lighting.bind_block(program));
+///
+/// let defines = Defines::new().flag_if("HAS_NORMAL_MAP", material.normal_map.is_some()).value("NUM_LIGHTS", 4);
+/// variants.get(&defines)?.use_program();
+/// ```
+pub struct ShaderVariants {
+    vertex: String,
+    fragment: String,
+    label: Option<String>,
+    /// run once on every new program, e.g. to bind uniform blocks or sampler units
+    on_build: Option<BuildHook>,
+    programs: HashMap<Defines, ShaderProgram>,
+}
+
+#[allow(dead_code)]
+impl ShaderVariants {
+    pub fn new(vertex: impl Into<String>, fragment: impl Into<String>) -> Self {
+        Self {
+            vertex: vertex.into(),
+            fragment: fragment.into(),
+            label: None,
+            on_build: None,
+            programs: HashMap::new(),
+        }
+    }
+
+    /// Reads both files with their `#include`s expanded, see `preprocessor`.
+    pub fn from_files(vert_path: impl AsRef<Path>, frag_path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        Ok(Self::new(preprocess_file(vert_path)?.source, preprocess_file(frag_path)?.source))
+    }
+
+    /// Debug label of the programs, each suffixed with its defines.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn on_build(mut self, setup: impl Fn(&ShaderProgram) + 'static) -> Self {
+        self.on_build = Some(Box::new(setup));
+        self
+    }
+
+    /// Program for `defines`, compiled on first use and cached after that.
+    pub fn get(&mut self, defines: &Defines) -> Result<&ShaderProgram, WrapperError> {
+        if !self.programs.contains_key(defines) {
+            let program = self.build(defines)?;
+            self.programs.insert(defines.clone(), program);
+        }
+        Ok(&self.programs[defines])
+    }
+
+    /// Compiles the variants ahead of time, to avoid hitches the first time each is drawn.
+    pub fn precompile<'a>(&mut self, variants: impl IntoIterator<Item = &'a Defines>) -> Result<(), WrapperError> {
+        for defines in variants {
+            self.get(defines)?;
+        }
+        Ok(())
+    }
+
+    /// Already compiled program for `defines`.
+    pub fn cached(&self, defines: &Defines) -> Option<&ShaderProgram> {
+        self.programs.get(defines)
+    }
+
+    /// Number of compiled variants.
+    pub fn count(&self) -> usize {
+        self.programs.len()
+    }
+
+    /// Drops every compiled program, they get rebuilt on the next `get`.
+    pub fn clear(&mut self) {
+        self.programs.clear();
+    }
+
+    /// Swaps in new sources, e.g. after a file changed, and drops the compiled programs.
+    pub fn set_sources(&mut self, vertex: impl Into<String>, fragment: impl Into<String>) {
+        self.vertex = vertex.into();
+        self.fragment = fragment.into();
+        self.clear();
+    }
+
+    fn build(&self, defines: &Defines) -> Result<ShaderProgram, WrapperError> {
+        let program = ShaderProgram::from_vertex_fragment(&defines.apply(&self.vertex), &defines.apply(&self.fragment))?;
+
+        if let Some(label) = &self.label {
+            let names: Vec<_> = defines
+                .0
+                .iter()
+                .map(|(name, value)| if value.is_empty() { name.clone() } else { format!("{}={}", name, value) })
+                .collect();
+            program.set_label(&if names.is_empty() { label.clone() } else { format!("{}[{}]", label, names.join(",")) });
+        }
+        if let Some(setup) = &self.on_build {
+            setup(&program);
+        }
+        Ok(program)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defines_go_after_the_version_line() {
+        let defines = Defines::new().flag("SHADOWS").value("NUM_LIGHTS", 4);
+        assert_eq!(
+            defines.apply("// header\n#version 330 core\nvoid main() {}\n"),
+            "// header\n#version 330 core\n#define NUM_LIGHTS 4\n#define SHADOWS\nvoid main() {}\n"
+        );
+        assert_eq!(defines.apply("#version 330 core"), "#version 330 core\n#define NUM_LIGHTS 4\n#define SHADOWS\n");
+        assert_eq!(defines.apply("void main() {}\n"), "#define NUM_LIGHTS 4\n#define SHADOWS\nvoid main() {}\n");
+        assert_eq!(Defines::new().apply("#version 330 core\n"), "#version 330 core\n");
+    }
+
+    #[test]
+    fn insertion_order_doesnt_matter() {
+        let a = Defines::new().flag("A").value("B", 2);
+        let b = Defines::new().value("B", 2).flag("A");
+        assert_eq!(a, b);
+        assert_eq!(a.to_glsl(), b.to_glsl());
+
+        let mut c = b.clone().flag_if("C", false).value("B", 3);
+        assert_ne!(a, c);
+        c.remove("B");
+        assert!(c.contains("A") && !c.contains("B") && !c.contains("C"));
+    }
+}
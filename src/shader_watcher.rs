@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::error::WrapperError;
+use crate::preprocessor::preprocess_file;
+use crate::wrapper::{Shader, ShaderProgram, ShaderType};
+
+/// Owns a program built from shader files and rebuilds it whenever one of them (or a file
+/// they `#include`) changes on disk. Recompilation happens in `poll`, so GL is only touched
+/// from the thread which calls it.
+pub struct ShaderWatcher {
+    program: ShaderProgram,
+    stages: Vec<(ShaderType, PathBuf)>,
+    watched: HashSet<PathBuf>,
+    watched_dirs: HashSet<PathBuf>,
+    watcher: RecommendedWatcher,
+    changes: Receiver<notify::Result<Event>>,
+}
+
+#[allow(dead_code)]
+impl ShaderWatcher {
+    pub fn new(vert_path: impl AsRef<Path>, frag_path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        Self::from_stages(vec![
+            (ShaderType::Vertex, vert_path.as_ref().to_path_buf()),
+            (ShaderType::Fragment, frag_path.as_ref().to_path_buf()),
+        ])
+    }
+
+    pub fn from_stages(stages: Vec<(ShaderType, PathBuf)>) -> Result<Self, WrapperError> {
+        let stages = stages
+            .into_iter()
+            .map(|(stage, path)| {
+                std::fs::canonicalize(&path)
+                    .map(|path| (stage, path))
+                    .map_err(|source| WrapperError::Io { path, source })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (program, files) = build_program(&stages)?;
+
+        let (sender, changes) = channel();
+        let watcher = notify::recommended_watcher(sender)?;
+
+        let mut shader_watcher = Self {
+            program,
+            stages,
+            watched: HashSet::new(),
+            watched_dirs: HashSet::new(),
+            watcher,
+            changes,
+        };
+        shader_watcher.watch(files)?;
+
+        Ok(shader_watcher)
+    }
+
+    pub fn program(&self) -> &ShaderProgram {
+        &self.program
+    }
+
+    /// Should be called once per frame. Returns `Ok(true)` when the program was replaced.
+    /// On compile or link failure the previous program is kept and the error is returned.
+    pub fn poll(&mut self) -> Result<bool, WrapperError> {
+        let mut changed = false;
+
+        for event in self.changes.try_iter() {
+            let event = event?;
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event.paths.iter().any(|path| self.watched.contains(path))
+            {
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return Ok(false);
+        }
+
+        let (program, files) = build_program(&self.stages)?;
+        self.program = program;
+        self.watch(files)?;
+
+        Ok(true)
+    }
+
+    /// Editors often save by replacing the file, which silently ends a watch on the file
+    /// itself, so the containing directories are watched instead.
+    fn watch(&mut self, files: Vec<PathBuf>) -> Result<(), WrapperError> {
+        for file in files {
+            if let Some(dir) = file.parent() {
+                if !self.watched_dirs.contains(dir) {
+                    self.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+                    self.watched_dirs.insert(dir.to_path_buf());
+                }
+            }
+            self.watched.insert(file);
+        }
+
+        Ok(())
+    }
+}
+
+/// Program of the shader files, returned together with every file it was built from, includes
+/// too, which are the ones to watch for changes.
+pub fn build_program(stages: &[(ShaderType, PathBuf)]) -> Result<(ShaderProgram, Vec<PathBuf>), WrapperError> {
+    let mut shaders = Vec::new();
+    let mut files = Vec::new();
+
+    for (stage, path) in stages {
+        let src = preprocess_file(path)?;
+        files.extend_from_slice(src.files());
+        shaders.push(Shader::from_preprocessed(*stage, &src)?);
+    }
+
+    Ok((ShaderProgram::from_shaders(shaders)?, files))
+}
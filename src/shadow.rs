@@ -0,0 +1,582 @@
+use std::mem::offset_of;
+
+use gl::types::GLuint;
+use glam::{Mat4, Vec3, Vec4Swizzles};
+
+use crate::camera::Camera;
+use crate::error::WrapperError;
+use crate::framebuffer::Framebuffer;
+use crate::lighting::{DirectionalLight, PointLight};
+use crate::wrapper::{
+    clear, CompareFunc, Cubemap, depth_test, ShaderProgram, Std140, Texture2D, TextureFilter, TextureFormat,
+    TextureWrap, UniformBuffer, verify_std140, viewport,
+};
+
+const DEPTH_VERT: &str = include_str!("../shaders/src/shadow/depth.vert");
+const DEPTH_FRAG: &str = include_str!("../shaders/src/shadow/depth.frag");
+const CUBE_DEPTH_VERT: &str = include_str!("../shaders/src/shadow/cube_depth.vert");
+const CUBE_DEPTH_GEOM: &str = include_str!("../shaders/src/shadow/cube_depth.geom");
+const CUBE_DEPTH_FRAG: &str = include_str!("../shaders/src/shadow/cube_depth.frag");
+
+/// Texture unit of `shadow_map`, above the units `PbrMaterial` binds its maps to.
+pub const SHADOW_UNIT: u32 = 5;
+/// Texture unit of `point_shadow_map`.
+pub const POINT_SHADOW_UNIT: u32 = 6;
+
+/// `shadow_mode` values of `shadow.glsl`.
+const SHADOWS_OFF: i32 = 0;
+const SHADOWS_SINGLE: i32 = 1;
+const SHADOWS_CASCADED: i32 = 2;
+
+/// Size of the cascade arrays of the `ShadowCascades` block.
+pub const MAX_CASCADES: usize = 4;
+pub const CASCADES_BLOCK: &str = "ShadowCascades";
+/// Uniform buffer binding point of `ShadowCascades`, after the `Lighting` block.
+pub const CASCADES_BINDING: GLuint = 3;
+
+/// Shadows of a directional light: the scene's depth as seen from the light is rendered
+/// into a square depth texture, lit shaders then compare against it, filtered over a few
+/// texels (PCF) for soft edges. The light looks at a sphere of the scene set with `fit`,
+/// everything casting shadows into it has to be inside.
+///
+/// ```ignore
+/// shadows.fit(&lights.directional[0], Vec3::ZERO, 20.0);
+/// shadows.begin();
+/// for (mesh, transform) in &scene { transform.upload(shadows.depth_program()); mesh.draw(); }
+/// post.begin();
+/// lighting.begin(&camera);
+/// shadows.bind(lighting.program());
+/// ```
+pub struct ShadowMap {
+    /// depth offset against acne, in 0..1 depth of the light's view
+    pub bias: f32,
+    /// world space offset along the normal against acne on surfaces facing away from the light
+    pub normal_bias: f32,
+    /// texels sampled to each side of a pixel, 0 is a single hardware filtered tap
+    pub pcf_radius: u32,
+    framebuffer: Framebuffer,
+    depth: ShaderProgram,
+    light_space: Mat4,
+}
+
+#[allow(dead_code)]
+impl ShadowMap {
+    pub const DEFAULT_RESOLUTION: u32 = 2048;
+
+    pub fn new() -> Result<Self, WrapperError> {
+        Self::with_resolution(Self::DEFAULT_RESOLUTION)
+    }
+
+    /// `resolution` is the width and height of the depth texture.
+    pub fn with_resolution(resolution: u32) -> Result<Self, WrapperError> {
+        let framebuffer = Framebuffer::builder(resolution, resolution).depth_texture(TextureFormat::Depth32F).build()?;
+        framebuffer.set_label("shadow_map");
+        if let Some(texture) = framebuffer.depth_texture() {
+            configure(texture);
+        }
+
+        let depth = ShaderProgram::from_vertex_fragment(DEPTH_VERT, DEPTH_FRAG)?;
+        depth.set_label("shadow.depth");
+
+        Ok(Self {
+            bias: 0.005,
+            normal_bias: 0.02,
+            pcf_radius: 1,
+            framebuffer,
+            depth,
+            light_space: Mat4::IDENTITY,
+        })
+    }
+
+    pub fn resolution(&self) -> u32 {
+        self.framebuffer.width()
+    }
+
+    /// Reallocates the depth texture, it has to be rendered again.
+    pub fn set_resolution(&mut self, resolution: u32) {
+        self.framebuffer.resize(resolution, resolution);
+    }
+
+    /// Orthographic view along `direction` enclosing the sphere at `center` with `radius`.
+    pub fn light_space_matrix(direction: Vec3, center: Vec3, radius: f32) -> Mat4 {
+        let direction = direction.try_normalize().unwrap_or(Vec3::NEG_Y);
+        let up = if direction.cross(Vec3::Y).length_squared() < 1e-6 { Vec3::Z } else { Vec3::Y };
+        // back off by a radius further, so casters just outside the sphere still get in
+        let view = Mat4::look_at_rh(center - direction * radius * 2.0, center, up);
+        let projection = Mat4::orthographic_rh_gl(-radius, radius, -radius, radius, radius, radius * 3.0);
+        projection * view
+    }
+
+    /// Aims the map at the sphere of the scene at `center` with `radius` along `light`.
+    pub fn fit(&mut self, light: &DirectionalLight, center: Vec3, radius: f32) {
+        self.light_space = Self::light_space_matrix(Vec3::from_array(light.direction), center, radius);
+    }
+
+    pub fn light_space(&self) -> Mat4 {
+        self.light_space
+    }
+
+    pub fn set_light_space(&mut self, light_space: Mat4) {
+        self.light_space = light_space;
+    }
+
+    /// Binds and clears the map, turns depth testing on and uses the depth program with
+    /// the light's matrix. Bind the next target before drawing the lit scene.
+    pub fn begin(&self) {
+        self.framebuffer.bind();
+        clear(None, Some(1.0));
+        depth_test(true);
+        self.depth.use_program();
+        self.depth.set_mat4("light_space", &self.light_space.to_cols_array());
+    }
+
+    /// Program of the depth pass for vertices with positions at location 0, set `model`
+    /// per object.
+    pub fn depth_program(&self) -> &ShaderProgram {
+        &self.depth
+    }
+
+    /// Depth texture, set up for a `sampler2DShadow`.
+    pub fn texture(&self) -> &Texture2D {
+        self.framebuffer.depth_texture().expect("shadow map has a depth texture")
+    }
+
+    /// Binds the map to `SHADOW_UNIT` and sets the `shadow.glsl` uniforms of `program`,
+    /// which has to be in use.
+    pub fn bind(&self, program: &ShaderProgram) {
+        self.texture().bind_to_unit(SHADOW_UNIT);
+        program.set_i32("shadow_map", SHADOW_UNIT as i32);
+        program.set_mat4("light_space", &self.light_space.to_cols_array());
+        program.set_i32("shadow_mode", SHADOWS_SINGLE);
+        program.set_f32("shadow_bias", self.bias);
+        program.set_f32("shadow_normal_bias", self.normal_bias);
+        program.set_i32("shadow_pcf", self.pcf_radius as i32);
+    }
+
+    /// Lights everything in `program` again, which has to be in use.
+    pub fn unbind(program: &ShaderProgram) {
+        program.set_i32("shadow_mode", SHADOWS_OFF);
+    }
+}
+
+/// std140 mirror of the `ShadowCascades` block of `shadow.glsl`.
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CascadeUniforms {
+    /// view matrix of the camera the cascades were split for
+    pub view: [f32; 16],
+    pub light_space: [[f32; 16]; MAX_CASCADES],
+    /// view space distance where each cascade ends
+    pub splits: [f32; MAX_CASCADES],
+    /// cascades used, 1 to tint by cascade, zw unused
+    pub info: [u32; 4],
+}
+
+#[allow(dead_code)]
+impl CascadeUniforms {
+    pub fn verify_layout() -> Result<(), WrapperError> {
+        verify_std140::<Self>(&[
+            ("view", offset_of!(Self, view), Std140::Mat4),
+            ("light_space", offset_of!(Self, light_space), Std140::Array(&Std140::Mat4, MAX_CASCADES)),
+            ("splits", offset_of!(Self, splits), Std140::Vec4),
+            ("info", offset_of!(Self, info), Std140::Vec4),
+        ])
+    }
+}
+
+/// Shadows of a directional light over a large view: the camera frustum is cut along its
+/// depth into up to `MAX_CASCADES` slices, each with its own shadow map, so close shadows
+/// get as many texels as distant ones covering far more ground. All cascades share one
+/// depth texture, side by side, and the lit shaders pick theirs by view distance.
+///
+/// ```ignore
+/// cascades.update(&lights.directional[0], &camera);
+/// cascades.render(|program| {
+///     for (mesh, transform) in &scene { transform.upload(program); mesh.draw(); }
+/// });
+/// post.begin();
+/// lighting.begin(&camera);
+/// cascades.bind(lighting.program());
+/// ```
+pub struct CascadedShadowMap {
+    /// depth offset against acne of the first cascade, later ones scale it up
+    pub bias: f32,
+    /// world space offset along the normal of the first cascade, later ones scale it up
+    pub normal_bias: f32,
+    /// texels sampled to each side of a pixel, 0 is a single hardware filtered tap
+    pub pcf_radius: u32,
+    /// 0 splits the depth evenly, 1 logarithmically, which suits perspective best
+    pub split_lambda: f32,
+    /// view distance the shadows reach, capped by the camera's far plane
+    pub max_distance: f32,
+    /// tints the lit output by cascade, to see where the boundaries fall
+    pub debug: bool,
+    cascades: usize,
+    resolution: u32,
+    framebuffer: Framebuffer,
+    depth: ShaderProgram,
+    buffer: UniformBuffer<CascadeUniforms>,
+    uniforms: CascadeUniforms,
+}
+
+#[allow(dead_code)]
+impl CascadedShadowMap {
+    pub const DEFAULT_RESOLUTION: u32 = 2048;
+
+    pub fn new(cascades: usize) -> Result<Self, WrapperError> {
+        Self::with_resolution(cascades, Self::DEFAULT_RESOLUTION)
+    }
+
+    /// `cascades` is clamped to 1..=`MAX_CASCADES`, `resolution` is the size of each one.
+    pub fn with_resolution(cascades: usize, resolution: u32) -> Result<Self, WrapperError> {
+        CascadeUniforms::verify_layout()?;
+        let cascades = cascades.clamp(1, MAX_CASCADES);
+
+        let framebuffer = Framebuffer::builder(resolution * cascades as u32, resolution)
+            .depth_texture(TextureFormat::Depth32F)
+            .build()?;
+        framebuffer.set_label("cascaded_shadow_map");
+        if let Some(texture) = framebuffer.depth_texture() {
+            configure(texture);
+        }
+
+        let depth = ShaderProgram::from_vertex_fragment(DEPTH_VERT, DEPTH_FRAG)?;
+        depth.set_label("shadow.cascade_depth");
+
+        let uniforms: CascadeUniforms = bytemuck::Zeroable::zeroed();
+        let buffer = UniformBuffer::new(CASCADES_BINDING, &uniforms)?;
+        buffer.set_label("shadow.cascades");
+
+        Ok(Self {
+            bias: 0.002,
+            normal_bias: 0.02,
+            pcf_radius: 1,
+            split_lambda: 0.75,
+            max_distance: 100.0,
+            debug: false,
+            cascades,
+            resolution,
+            framebuffer,
+            depth,
+            buffer,
+            uniforms,
+        })
+    }
+
+    pub fn cascade_count(&self) -> usize {
+        self.cascades
+    }
+
+    pub fn resolution(&self) -> u32 {
+        self.resolution
+    }
+
+    /// View space distance where each cascade ends, as of the last `update`.
+    pub fn splits(&self) -> &[f32] {
+        &self.uniforms.splits[..self.cascades]
+    }
+
+    /// Light matrix of `cascade` as of the last `update`.
+    pub fn light_space(&self, cascade: usize) -> Mat4 {
+        Mat4::from_cols_array(&self.uniforms.light_space[cascade])
+    }
+
+    /// Splits `camera`'s frustum, fits a light matrix around every slice and uploads them.
+    /// Call whenever the camera or the light moved.
+    pub fn update(&mut self, light: &DirectionalLight, camera: &Camera) {
+        let direction = Vec3::from_array(light.direction);
+        let inverse_view = camera.view_matrix().inverse();
+        let tan_y = (camera.fov.to_radians() * 0.5).tan();
+        let tan_x = tan_y * camera.aspect;
+
+        let splits = cascade_splits(camera.near, camera.far.min(self.max_distance), self.cascades, self.split_lambda);
+        let mut slice_near = camera.near;
+        for (i, slice_far) in splits.into_iter().enumerate() {
+            // bounding sphere of the slice, its size doesn't change as the camera turns, so
+            // the shadows don't swim
+            let corners = [slice_near, slice_far].into_iter().flat_map(|depth| {
+                [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+                    .map(|(x, y)| inverse_view.transform_point3(Vec3::new(x * tan_x * depth, y * tan_y * depth, -depth)))
+            });
+            let corners: Vec<Vec3> = corners.collect();
+            let center = corners.iter().copied().sum::<Vec3>() / corners.len() as f32;
+            let radius = corners.iter().map(|corner| corner.distance(center)).fold(0.0, f32::max);
+
+            let matrix = ShadowMap::light_space_matrix(direction, center, radius.max(1e-3));
+            self.uniforms.light_space[i] = snap_to_texels(matrix, self.resolution).to_cols_array();
+            self.uniforms.splits[i] = slice_far;
+            slice_near = slice_far;
+        }
+
+        self.uniforms.view = camera.view_matrix().to_cols_array();
+        self.uniforms.info = [self.cascades as u32, self.debug as u32, 0, 0];
+        self.buffer.update(&self.uniforms);
+    }
+
+    /// Renders every cascade, `draw` is called once per cascade with the depth program in use
+    /// and should draw all shadow casters, setting `model` per object. Bind the next target
+    /// before drawing the lit scene.
+    pub fn render(&self, mut draw: impl FnMut(&ShaderProgram)) {
+        self.framebuffer.bind();
+        clear(None, Some(1.0));
+        depth_test(true);
+        self.depth.use_program();
+
+        // casters between the light and a slice's sphere get clamped onto the near plane
+        // instead of clipped, so they still shadow it
+        unsafe { crate::checked!(gl::Enable(gl::DEPTH_CLAMP)) };
+        let size = self.resolution as i32;
+        for cascade in 0..self.cascades {
+            viewport(size * cascade as i32, 0, size, size);
+            self.depth.set_mat4("light_space", &self.uniforms.light_space[cascade]);
+            draw(&self.depth);
+        }
+        unsafe { crate::checked!(gl::Disable(gl::DEPTH_CLAMP)) };
+        viewport(0, 0, self.framebuffer.width() as i32, self.framebuffer.height() as i32);
+    }
+
+    /// All cascades side by side, set up for a `sampler2DShadow`.
+    pub fn texture(&self) -> &Texture2D {
+        self.framebuffer.depth_texture().expect("shadow map has a depth texture")
+    }
+
+    /// Binds the cascades to `SHADOW_UNIT` and sets the `shadow.glsl` uniforms of `program`,
+    /// which has to be in use and set up by `init_shadow_uniforms`.
+    pub fn bind(&self, program: &ShaderProgram) {
+        self.texture().bind_to_unit(SHADOW_UNIT);
+        program.set_i32("shadow_map", SHADOW_UNIT as i32);
+        program.set_i32("shadow_mode", SHADOWS_CASCADED);
+        program.set_f32("shadow_bias", self.bias);
+        program.set_f32("shadow_normal_bias", self.normal_bias);
+        program.set_i32("shadow_pcf", self.pcf_radius as i32);
+    }
+}
+
+/// Shadows of one point light: the distance to the nearest surface in every direction is
+/// rendered into a depth cubemap in a single pass, a geometry shader sending every triangle
+/// to all six faces. Lit shaders shadow the point light picked at `bind` with it.
+///
+/// ```ignore
+/// omni.fit(&lights.point[0]);
+/// omni.begin();
+/// for (mesh, transform) in &scene { transform.upload(omni.depth_program()); mesh.draw(); }
+/// post.begin();
+/// lighting.begin(&camera);
+/// omni.bind(lighting.program(), 0);
+/// ```
+pub struct OmniShadowMap {
+    /// distance offset against acne, relative to the light's radius
+    pub bias: f32,
+    /// world space width of the soft edge at the light's radius, 0 is hard
+    pub softness: f32,
+    /// closest distance to the light that casts shadows
+    pub near: f32,
+    framebuffer: Framebuffer,
+    depth: ShaderProgram,
+    position: Vec3,
+    far: f32,
+}
+
+#[allow(dead_code)]
+impl OmniShadowMap {
+    pub const DEFAULT_RESOLUTION: u32 = 1024;
+
+    pub fn new() -> Result<Self, WrapperError> {
+        Self::with_resolution(Self::DEFAULT_RESOLUTION)
+    }
+
+    /// `resolution` is the width and height of every face.
+    pub fn with_resolution(resolution: u32) -> Result<Self, WrapperError> {
+        let framebuffer = Framebuffer::builder(resolution, resolution).depth_cubemap(TextureFormat::Depth32F).build()?;
+        framebuffer.set_label("omni_shadow_map");
+        if let Some(cubemap) = framebuffer.depth_cubemap() {
+            cubemap.set_filter(TextureFilter::Linear, TextureFilter::Linear);
+            cubemap.set_compare(Some(CompareFunc::LessEqual));
+        }
+
+        let depth = ShaderProgram::from_stages()
+            .vertex(CUBE_DEPTH_VERT)
+            .geometry(CUBE_DEPTH_GEOM)
+            .fragment(CUBE_DEPTH_FRAG)
+            .build()?;
+        depth.set_label("shadow.cube_depth");
+
+        Ok(Self {
+            bias: 0.01,
+            softness: 0.05,
+            near: 0.05,
+            framebuffer,
+            depth,
+            position: Vec3::ZERO,
+            far: 1.0,
+        })
+    }
+
+    pub fn resolution(&self) -> u32 {
+        self.framebuffer.width()
+    }
+
+    /// Reallocates the cubemap, it has to be rendered again.
+    pub fn set_resolution(&mut self, resolution: u32) {
+        self.framebuffer.resize(resolution, resolution);
+    }
+
+    /// Follows `light`, shadows reach as far as its light does.
+    pub fn fit(&mut self, light: &PointLight) {
+        self.position = Vec3::from_array(light.position);
+        self.far = light.radius.max(self.near * 2.0);
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn far(&self) -> f32 {
+        self.far
+    }
+
+    /// View projection of every face seen from `position`, in the order of `CUBE_FACES`.
+    pub fn face_matrices(position: Vec3, near: f32, far: f32) -> [Mat4; 6] {
+        let projection = Mat4::perspective_rh_gl(90f32.to_radians(), 1.0, near, far);
+        // cubemap faces are looked at from the inside with y pointing down, except +-y
+        [
+            (Vec3::X, Vec3::NEG_Y),
+            (Vec3::NEG_X, Vec3::NEG_Y),
+            (Vec3::Y, Vec3::Z),
+            (Vec3::NEG_Y, Vec3::NEG_Z),
+            (Vec3::Z, Vec3::NEG_Y),
+            (Vec3::NEG_Z, Vec3::NEG_Y),
+        ]
+        .map(|(direction, up)| projection * Mat4::look_at_rh(position, position + direction, up))
+    }
+
+    /// Binds and clears the cubemap, turns depth testing on and uses the depth program set
+    /// up for the light. Bind the next target before drawing the lit scene.
+    pub fn begin(&self) {
+        self.framebuffer.bind();
+        clear(None, Some(1.0));
+        depth_test(true);
+        self.depth.use_program();
+        for (i, face) in Self::face_matrices(self.position, self.near, self.far).iter().enumerate() {
+            self.depth.set_mat4(&format!("faces[{}]", i), &face.to_cols_array());
+        }
+        self.depth.set_vec3("light_position", self.position.to_array());
+        self.depth.set_f32("far_plane", self.far);
+    }
+
+    /// Program of the depth pass for vertices with positions at location 0, set `model`
+    /// per object.
+    pub fn depth_program(&self) -> &ShaderProgram {
+        &self.depth
+    }
+
+    /// Distance cubemap, set up for a `samplerCubeShadow`.
+    pub fn cubemap(&self) -> &Cubemap {
+        self.framebuffer.depth_cubemap().expect("omni shadow map has a depth cubemap")
+    }
+
+    /// Binds the cubemap to `POINT_SHADOW_UNIT` and shadows point light `light` (its index
+    /// in `Lights::point`) of `program`, which has to be in use.
+    pub fn bind(&self, program: &ShaderProgram, light: usize) {
+        self.cubemap().bind_to_unit(POINT_SHADOW_UNIT);
+        program.set_i32("point_shadow_map", POINT_SHADOW_UNIT as i32);
+        program.set_i32("point_shadow_light", light as i32);
+        program.set_vec3("point_shadow_position", self.position.to_array());
+        program.set_f32("point_shadow_far", self.far);
+        program.set_f32("point_shadow_bias", self.bias);
+        program.set_f32("point_shadow_softness", self.softness);
+    }
+
+    /// Lights every point light of `program` again, which has to be in use.
+    pub fn unbind(program: &ShaderProgram) {
+        program.set_i32("point_shadow_light", -1);
+    }
+}
+
+/// View space distance where each of `cascades` slices of `near..far` ends, blending
+/// logarithmic splits (`lambda` 1) with uniform ones (`lambda` 0). `far` is raised to `near`
+/// at the least.
+fn cascade_splits(near: f32, far: f32, cascades: usize, lambda: f32) -> Vec<f32> {
+    let far = far.max(near);
+    (1..=cascades)
+        .map(|i| {
+            let t = i as f32 / cascades as f32;
+            let log = near * (far / near).powf(t);
+            let uniform = near + (far - near) * t;
+            lambda * log + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+/// Moves `light_space` by less than a texel so the map's texel grid stays fixed in world
+/// space, otherwise shadow edges crawl as the fitted sphere follows the camera.
+fn snap_to_texels(light_space: Mat4, resolution: u32) -> Mat4 {
+    let half = resolution as f32 * 0.5;
+    let origin = (light_space * Vec3::ZERO.extend(1.0)).xy() * half;
+    let offset = (origin.round() - origin) / half;
+    Mat4::from_translation(offset.extend(0.0)) * light_space
+}
+
+/// Points the shadow samplers of a program including `shadow.glsl` at `SHADOW_UNIT` and
+/// `POINT_SHADOW_UNIT`, so they never share a unit with a regular sampler while no map is
+/// bound, and its `ShadowCascades` block at `CASCADES_BINDING`. `program` has to be in use.
+pub fn init_shadow_uniforms(program: &ShaderProgram) {
+    program.set_i32("shadow_map", SHADOW_UNIT as i32);
+    program.set_i32("shadow_mode", SHADOWS_OFF);
+    program.set_i32("point_shadow_map", POINT_SHADOW_UNIT as i32);
+    program.set_i32("point_shadow_light", -1);
+    program.bind_uniform_block(CASCADES_BLOCK, CASCADES_BINDING);
+}
+
+/// Linear comparison filtering gives 2x2 PCF per tap for free, outside the map is lit.
+fn configure(texture: &Texture2D) {
+    texture.set_filter(TextureFilter::Linear, TextureFilter::Linear);
+    texture.set_wrap(TextureWrap::ClampToBorder, TextureWrap::ClampToBorder);
+    texture.set_border_color([1.0, 1.0, 1.0, 1.0]);
+    texture.set_compare(Some(CompareFunc::LessEqual));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-3 * expected.max(1.0), "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn splits_increase_up_to_far() {
+        for lambda in [0.0, 0.5, 0.9, 1.0] {
+            let splits = cascade_splits(0.1, 100.0, 4, lambda);
+            assert!(splits.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", splits);
+            assert!(splits[0] > 0.1);
+            assert_close(&splits[3..], &[100.0]);
+        }
+    }
+
+    #[test]
+    fn lambda_blends_uniform_and_log_splits() {
+        assert_close(&cascade_splits(1.0, 1000.0, 3, 0.0), &[334.0, 667.0, 1000.0]);
+        assert_close(&cascade_splits(1.0, 1000.0, 3, 1.0), &[10.0, 100.0, 1000.0]);
+        assert_close(&cascade_splits(1.0, 1000.0, 3, 0.5), &[172.0, 383.5, 1000.0]);
+    }
+
+    #[test]
+    fn splits_stop_at_near() {
+        assert_close(&cascade_splits(5.0, 1.0, 2, 0.5), &[5.0, 5.0]);
+    }
+
+    #[test]
+    fn snapping_moves_the_origin_onto_a_texel() {
+        let light_space = ShadowMap::light_space_matrix(Vec3::new(-0.3, -1.0, 0.2), Vec3::new(3.7, 0.0, -1.3), 12.5);
+        let snapped = snap_to_texels(light_space, 1024);
+        let texels = |matrix: Mat4| (matrix * Vec3::ZERO.extend(1.0)).xy() * 512.0;
+        let (before, after) = (texels(light_space), texels(snapped));
+        assert!((after - after.round()).abs().max_element() < 1e-3, "{}", after);
+        assert!((after - before).abs().max_element() <= 0.5 + 1e-3);
+    }
+}
@@ -0,0 +1,338 @@
+//! Primitive meshes generated in code, centered on the origin with normals, UVs and tangents
+//! and counter-clockwise front faces. Upload the result with `MeshData::upload`.
+
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use glam::{Vec2, Vec3};
+
+use crate::mesh::{MeshData, MeshVertex};
+
+/// Point of a profile curve revolved around the y axis: distance from the axis, height,
+/// outward normal in the radial and y direction and the v texture coordinate.
+#[derive(Debug, Clone, Copy)]
+struct ProfilePoint {
+    radius: f32,
+    y: f32,
+    normal: Vec2,
+    v: f32,
+}
+
+/// Cube with edges of `size`, every face with its own four vertices and the full texture.
+#[allow(dead_code)]
+pub fn cube(size: f32) -> MeshData {
+    let half = size * 0.5;
+    // outward normal and the direction u grows in, v grows along normal x u
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z),
+        (Vec3::NEG_X, Vec3::Z),
+        (Vec3::Y, Vec3::X),
+        (Vec3::NEG_Y, Vec3::X),
+        (Vec3::Z, Vec3::X),
+        (Vec3::NEG_Z, Vec3::NEG_X),
+    ];
+
+    let mut data = MeshData::default();
+    for (normal, u) in faces {
+        let v = normal.cross(u);
+        let first = data.vertices.len() as u32;
+        for (du, dv) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            data.vertices.push(vertex(
+                (normal + u * du + v * dv) * half,
+                normal,
+                [(du + 1.0) * 0.5, (dv + 1.0) * 0.5],
+                u,
+            ));
+        }
+        data.indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+    data
+}
+
+/// `width` along x by `depth` along z facing +y, split into `subdivisions`² quads. u grows
+/// along +x and v along -z.
+#[allow(dead_code)]
+pub fn plane(width: f32, depth: f32, subdivisions: u32) -> MeshData {
+    let cells = subdivisions.max(1);
+    let mut data = MeshData::default();
+    for j in 0..=cells {
+        for i in 0..=cells {
+            let (u, v) = (i as f32 / cells as f32, j as f32 / cells as f32);
+            let position = Vec3::new((u - 0.5) * width, 0.0, (0.5 - v) * depth);
+            data.vertices.push(vertex(position, Vec3::Y, [u, v], Vec3::X));
+        }
+    }
+    grid_indices(&mut data.indices, 0, cells, cells);
+    data
+}
+
+/// Sphere of `segments` around by `rings` from pole to pole, u around the y axis and v from
+/// the bottom pole to the top one.
+#[allow(dead_code)]
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> MeshData {
+    let rings = rings.max(2);
+    let profile: Vec<ProfilePoint> = (0..=rings)
+        .map(|j| {
+            let v = j as f32 / rings as f32;
+            let latitude = v * PI - FRAC_PI_2;
+            let normal = Vec2::new(latitude.cos(), latitude.sin());
+            ProfilePoint { radius: normal.x * radius, y: normal.y * radius, normal, v }
+        })
+        .collect();
+    revolve(&profile, segments)
+}
+
+/// Sphere from an icosahedron with every triangle split into four `subdivisions` times,
+/// evenly sized triangles without the crowded poles of `uv_sphere`. UVs are spherical like
+/// there, vertices along the seam are doubled so no triangle wraps around.
+#[allow(dead_code)]
+pub fn ico_sphere(radius: f32, subdivisions: u32) -> MeshData {
+    let t = (1.0 + 5f32.sqrt()) * 0.5;
+    let mut points: Vec<Vec3> = [
+        [-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+        [0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+        [t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0],
+    ]
+    .into_iter()
+    .map(|point| Vec3::from_array(point).normalize())
+    .collect();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| -> u32 {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                points.push(((points[a as usize] + points[b as usize]) * 0.5).normalize());
+                points.len() as u32 - 1
+            })
+        };
+        triangles = triangles
+            .into_iter()
+            .flat_map(|[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let vertices = points.iter().map(|&point| spherical_vertex(point, radius)).collect();
+    let mut data = MeshData { vertices, indices: Vec::with_capacity(triangles.len() * 3) };
+    for mut triangle in triangles {
+        let [a, b, c] = triangle.map(|i| points[i as usize]);
+        if (b - a).cross(c - a).dot(a) < 0.0 {
+            triangle.swap(1, 2);
+        }
+        // a triangle across the seam has corners near u = 1 and near u = 0, move the latter on
+        let us = triangle.map(|i| data.vertices[i as usize].uv[0]);
+        if us.iter().copied().fold(f32::MIN, f32::max) - us.iter().copied().fold(f32::MAX, f32::min) > 0.5 {
+            for (corner, u) in triangle.iter_mut().zip(us) {
+                if u < 0.5 {
+                    let mut copy = data.vertices[*corner as usize];
+                    copy.uv[0] += 1.0;
+                    data.vertices.push(copy);
+                    *corner = data.vertices.len() as u32 - 1;
+                }
+            }
+        }
+        data.indices.extend_from_slice(&triangle);
+    }
+    data
+}
+
+/// Ring around the y axis, `major_radius` to the middle of the tube of `minor_radius`.
+/// u goes around the ring in `segments` steps, v around the tube in `sides` steps.
+#[allow(dead_code)]
+pub fn torus(major_radius: f32, minor_radius: f32, segments: u32, sides: u32) -> MeshData {
+    let sides = sides.max(3);
+    let profile: Vec<ProfilePoint> = (0..=sides)
+        .map(|j| {
+            let v = j as f32 / sides as f32;
+            let angle = v * TAU;
+            let normal = Vec2::new(angle.cos(), angle.sin());
+            ProfilePoint { radius: major_radius + normal.x * minor_radius, y: normal.y * minor_radius, normal, v }
+        })
+        .collect();
+    revolve(&profile, segments)
+}
+
+/// Closed cylinder along the y axis. The side wraps the texture once around, the caps show
+/// it as a disc seen from outside.
+#[allow(dead_code)]
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> MeshData {
+    let half = height * 0.5;
+    let side = [-half, half].map(|y| ProfilePoint { radius, y, normal: Vec2::X, v: (y + half) / height.max(f32::EPSILON) });
+    let mut data = revolve(&side, segments);
+
+    let segments = segments.max(3);
+    for (y, normal) in [(half, Vec3::Y), (-half, Vec3::NEG_Y)] {
+        let center = data.vertices.len() as u32;
+        // v of the disc grows along normal x tangent, -z on top and +z below
+        let v_axis = normal.cross(Vec3::X);
+        let planar = |position: Vec3| [position.x / (2.0 * radius) + 0.5, position.dot(v_axis) / (2.0 * radius) + 0.5];
+        data.vertices.push(vertex(Vec3::new(0.0, y, 0.0), normal, [0.5, 0.5], Vec3::X));
+        for i in 0..=segments {
+            let angle = i as f32 / segments as f32 * TAU;
+            let position = Vec3::new(angle.sin() * radius, y, angle.cos() * radius);
+            data.vertices.push(vertex(position, normal, planar(position), Vec3::X));
+        }
+        for i in 0..segments {
+            let (a, b) = (center + 1 + i, center + 2 + i);
+            // the ring runs counter-clockwise seen from above
+            if normal.y > 0.0 {
+                data.indices.extend_from_slice(&[center, a, b]);
+            } else {
+                data.indices.extend_from_slice(&[center, b, a]);
+            }
+        }
+    }
+    data
+}
+
+/// Cylinder of `height` along the y axis capped by hemispheres of `radius`, `height +
+/// 2 * radius` tall in total. `rings` per hemisphere, v runs along the outline from bottom
+/// to top in proportion to its length.
+#[allow(dead_code)]
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> MeshData {
+    let rings = rings.max(1);
+    let half = height * 0.5;
+    let length = PI * radius + height;
+
+    let mut profile = Vec::with_capacity(2 * rings as usize + 2);
+    for (offset, start) in [(-half, -FRAC_PI_2), (half, 0.0)] {
+        for j in 0..=rings {
+            let latitude = start + j as f32 / rings as f32 * FRAC_PI_2;
+            let normal = Vec2::new(latitude.cos(), latitude.sin());
+            // arc length from the bottom pole
+            let along = (latitude + FRAC_PI_2) * radius + if offset > 0.0 { height } else { 0.0 };
+            profile.push(ProfilePoint { radius: normal.x * radius, y: offset + normal.y * radius, normal, v: along / length });
+        }
+    }
+    revolve(&profile, segments)
+}
+
+/// Surface of revolution of `profile`, listed from the bottom up, in `segments` steps around
+/// the y axis starting at +z. u goes once around.
+fn revolve(profile: &[ProfilePoint], segments: u32) -> MeshData {
+    let segments = segments.max(3);
+    let mut data = MeshData::default();
+    for point in profile {
+        for i in 0..=segments {
+            let u = i as f32 / segments as f32;
+            let (sin, cos) = (u * TAU).sin_cos();
+            let position = Vec3::new(sin * point.radius, point.y, cos * point.radius);
+            let normal = Vec3::new(sin * point.normal.x, point.normal.y, cos * point.normal.x).normalize_or_zero();
+            data.vertices.push(vertex(position, normal, [u, point.v], Vec3::new(cos, 0.0, -sin)));
+        }
+    }
+    grid_indices(&mut data.indices, 0, segments, profile.len() as u32 - 1);
+    data
+}
+
+/// Two triangles per cell of a grid of `(columns + 1) * (rows + 1)` vertices from `first`,
+/// row by row, counter-clockwise when u and v go right and up.
+fn grid_indices(indices: &mut Vec<u32>, first: u32, columns: u32, rows: u32) {
+    let stride = columns + 1;
+    for j in 0..rows {
+        for i in 0..columns {
+            let a = first + j * stride + i;
+            let (b, c, d) = (a + 1, a + stride + 1, a + stride);
+            indices.extend_from_slice(&[a, b, c, a, c, d]);
+        }
+    }
+}
+
+/// Point on the unit sphere scaled to `radius`, UVs and tangent as on `uv_sphere`.
+fn spherical_vertex(point: Vec3, radius: f32) -> MeshVertex {
+    let longitude = point.x.atan2(point.z).rem_euclid(TAU);
+    let uv = [longitude / TAU, point.y.clamp(-1.0, 1.0).asin() / PI + 0.5];
+    vertex(point * radius, point, uv, Vec3::new(longitude.cos(), 0.0, -longitude.sin()))
+}
+
+fn vertex(position: Vec3, normal: Vec3, uv: [f32; 2], tangent: Vec3) -> MeshVertex {
+    MeshVertex { position: position.to_array(), normal: normal.to_array(), uv, tangent: tangent.extend(1.0).to_array() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all() -> Vec<(&'static str, MeshData)> {
+        vec![
+            ("cube", cube(2.0)),
+            ("plane", plane(2.0, 3.0, 4)),
+            ("uv_sphere", uv_sphere(1.5, 16, 8)),
+            ("ico_sphere", ico_sphere(1.5, 2)),
+            ("torus", torus(2.0, 0.5, 16, 8)),
+            ("cylinder", cylinder(1.0, 2.0, 12)),
+            ("capsule", capsule(0.5, 1.0, 12, 4)),
+        ]
+    }
+
+    #[test]
+    fn indices_form_valid_triangles() {
+        for (name, data) in all() {
+            assert!(!data.indices.is_empty(), "{}", name);
+            assert_eq!(data.indices.len() % 3, 0, "{}", name);
+            assert!(data.indices.iter().all(|&i| (i as usize) < data.vertices.len()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn normals_and_tangents_are_unit_and_orthogonal() {
+        for (name, data) in all() {
+            for vertex in &data.vertices {
+                let normal = Vec3::from_array(vertex.normal);
+                let tangent = Vec3::from_array([vertex.tangent[0], vertex.tangent[1], vertex.tangent[2]]);
+                assert!((normal.length() - 1.0).abs() < 1e-4, "{}: {:?}", name, vertex);
+                assert!((tangent.length() - 1.0).abs() < 1e-4, "{}: {:?}", name, vertex);
+                assert!(normal.dot(tangent).abs() < 1e-4, "{}: {:?}", name, vertex);
+            }
+        }
+    }
+
+    #[test]
+    fn faces_wind_counter_clockwise_outwards() {
+        // the triangle normal from the winding agrees with the vertex normals
+        for (name, data) in all() {
+            for triangle in data.indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| data.vertices[triangle[i] as usize]);
+                let [pa, pb, pc] = [a, b, c].map(|vertex| Vec3::from_array(vertex.position));
+                let face = (pb - pa).cross(pc - pa);
+                if face.length_squared() < 1e-10 {
+                    continue;
+                }
+                let normals = Vec3::from_array(a.normal) + Vec3::from_array(b.normal) + Vec3::from_array(c.normal);
+                assert!(face.dot(normals) > 0.0, "{}: {:?}", name, triangle);
+            }
+        }
+    }
+
+    #[test]
+    fn sizes_and_counts() {
+        let cube = cube(2.0);
+        assert_eq!((cube.vertices.len(), cube.indices.len()), (24, 36));
+        assert!(cube.vertices.iter().all(|vertex| vertex.position.iter().all(|p| p.abs() == 1.0)));
+
+        let plane = plane(2.0, 3.0, 4);
+        assert_eq!((plane.vertices.len(), plane.indices.len()), (25, 4 * 4 * 6));
+
+        let sphere = uv_sphere(1.5, 16, 8);
+        assert_eq!(sphere.vertices.len(), 17 * 9);
+        assert!(sphere.vertices.iter().all(|vertex| (Vec3::from_array(vertex.position).length() - 1.5).abs() < 1e-4));
+
+        // 20 * 4^n triangles
+        let ico = ico_sphere(1.5, 2);
+        assert_eq!(ico.indices.len(), 20 * 16 * 3);
+        assert!(ico.vertices.iter().all(|vertex| (Vec3::from_array(vertex.position).length() - 1.5).abs() < 1e-4));
+
+        let capsule = capsule(0.5, 1.0, 12, 4);
+        let heights = capsule.vertices.iter().map(|vertex| vertex.position[1]);
+        let (low, high) = heights.fold((f32::MAX, f32::MIN), |(low, high), y| (low.min(y), high.max(y)));
+        assert!((low + 1.0).abs() < 1e-5 && (high - 1.0).abs() < 1e-5);
+    }
+}
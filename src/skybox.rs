@@ -0,0 +1,165 @@
+use std::path::Path;
+
+use gl::types::GLint;
+
+use crate::camera::Camera;
+use crate::error::WrapperError;
+use crate::postprocess::{draw_fullscreen, FULLSCREEN_VERT};
+use crate::wrapper::{
+    ColorSpace, CompareFunc, Cubemap, cull_face, current_cull_face, current_depth_func, depth_func, depth_test,
+    depth_test_enabled, depth_write, depth_write_enabled, ShaderProgram, Texture2D, TextureFormat, VertexArray,
+    viewport,
+};
+
+const SKYBOX_VERT: &str = include_str!("../shaders/src/skybox/skybox.vert");
+const SKYBOX_FRAG: &str = include_str!("../shaders/src/skybox/skybox.frag");
+/// Sends `FULLSCREEN_VERT`'s triangle to all six layers with the `direction` of every pixel.
+pub const CUBE_LAYERS_GEOM: &str = include_str!("../shaders/src/skybox/cube_layers.geom");
+const EQUIRECT_FRAG: &str = include_str!("../shaders/src/skybox/equirect.frag");
+
+/// Program drawing `frag_src`, which reads `in vec3 direction`, into all faces of a cubemap
+/// at once, for `render_into_cubemap`.
+pub fn cube_layers_program(label: &str, frag_src: &str) -> Result<ShaderProgram, WrapperError> {
+    let program = ShaderProgram::from_stages()
+        .vertex(FULLSCREEN_VERT)
+        .geometry(CUBE_LAYERS_GEOM)
+        .fragment(frag_src)
+        .build()?;
+    program.set_label(label);
+    Ok(program)
+}
+
+/// Renders `program` of `cube_layers_program` into mip `level` of every face of `target`,
+/// `setup` sets its uniforms and textures with the program in use. Leaves the default
+/// framebuffer bound, reset the viewport before drawing on.
+pub fn render_into_cubemap(
+    target: &Cubemap,
+    level: u32,
+    size: u32,
+    program: &ShaderProgram,
+    setup: impl FnOnce(&ShaderProgram),
+) -> Result<(), WrapperError> {
+    let mut fbo = 0;
+    unsafe { crate::checked!(gl::GenFramebuffers(1, &mut fbo)) };
+    if fbo == 0 {
+        return Err(WrapperError::Allocation("framebuffer"));
+    }
+
+    let vao = VertexArray::new().ok_or(WrapperError::Allocation("vertex array"))?;
+    let depth_enabled = depth_test_enabled();
+    depth_test(false);
+
+    let status = unsafe {
+        crate::checked!(gl::BindFramebuffer(gl::FRAMEBUFFER, fbo));
+        crate::checked!(gl::FramebufferTexture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, target.0, level as GLint));
+        crate::checked!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER))
+    };
+    if status == gl::FRAMEBUFFER_COMPLETE {
+        viewport(0, 0, size as i32, size as i32);
+        program.use_program();
+        setup(program);
+        draw_fullscreen(&vao);
+    }
+
+    depth_test(depth_enabled);
+    unsafe {
+        crate::checked!(gl::BindFramebuffer(gl::FRAMEBUFFER, 0));
+        crate::checked!(gl::DeleteFramebuffers(1, &fbo));
+    }
+
+    if status == gl::FRAMEBUFFER_COMPLETE {
+        Ok(())
+    } else {
+        Err(WrapperError::FramebufferIncomplete(status))
+    }
+}
+
+/// Renders a fragment shader reading `in vec3 direction` into every face of a new cubemap,
+/// like `render_into_cubemap`.
+pub fn render_cubemap(
+    label: &str,
+    frag_src: &str,
+    size: u32,
+    format: TextureFormat,
+    setup: impl FnOnce(&ShaderProgram),
+) -> Result<Cubemap, WrapperError> {
+    let program = cube_layers_program(label, frag_src)?;
+    let cubemap = Cubemap::empty(size, format)?;
+    cubemap.set_label(label);
+    render_into_cubemap(&cubemap, 0, size, &program, setup)?;
+    Ok(cubemap)
+}
+
+/// Resamples an equirectangular panorama into a cubemap with faces of `size`².
+pub fn equirect_to_cubemap(panorama: &Texture2D, size: u32) -> Result<Cubemap, WrapperError> {
+    render_cubemap("skybox.equirect", EQUIRECT_FRAG, size, TextureFormat::Rgba16F, |program| {
+        panorama.bind_to_unit(0);
+        program.set_i32("panorama", 0);
+    })
+}
+
+/// Background drawn behind everything: a cubemap looked at from its center, following only
+/// the camera's rotation. Draw it after the opaque geometry, it sits on the far plane and only
+/// fills pixels nothing else was drawn to.
+pub struct Skybox {
+    /// multiplies the colors, to balance HDR environments against the lights
+    pub intensity: f32,
+    cubemap: Cubemap,
+    program: ShaderProgram,
+    vao: VertexArray,
+}
+
+#[allow(dead_code)]
+impl Skybox {
+    pub fn new(cubemap: Cubemap) -> Result<Self, WrapperError> {
+        let program = ShaderProgram::from_vertex_fragment(SKYBOX_VERT, SKYBOX_FRAG)?;
+        program.set_label("skybox");
+
+        Ok(Self {
+            intensity: 1.0,
+            cubemap,
+            program,
+            vao: VertexArray::new().ok_or(WrapperError::Allocation("vertex array"))?,
+        })
+    }
+
+    /// Six images in the order of `CUBE_FACES`, usually sRGB.
+    pub fn from_files<P: AsRef<Path>>(paths: [P; 6], color_space: ColorSpace) -> Result<Self, WrapperError> {
+        Self::new(Cubemap::from_files(paths, color_space)?)
+    }
+
+    /// Equirectangular HDR panorama, converted to faces of `size`².
+    pub fn from_equirect(path: impl AsRef<Path>, size: u32) -> Result<Self, WrapperError> {
+        let panorama = Texture2D::from_hdr_file(path)?;
+        Self::new(equirect_to_cubemap(&panorama, size)?)
+    }
+
+    pub fn cubemap(&self) -> &Cubemap {
+        &self.cubemap
+    }
+
+    pub fn set_cubemap(&mut self, cubemap: Cubemap) {
+        self.cubemap = cubemap;
+    }
+
+    /// Draws the sky where the depth buffer is still cleared to 1. Depth writes and culling
+    /// are off meanwhile, they and the depth function are restored afterwards.
+    pub fn draw(&self, camera: &Camera) {
+        let (cull, func, write) = (current_cull_face(), current_depth_func(), depth_write_enabled());
+        cull_face(None);
+        depth_func(CompareFunc::LessEqual);
+        depth_write(false);
+
+        self.program.use_program();
+        camera.upload(&self.program);
+        self.cubemap.bind_to_unit(0);
+        self.program.set_i32("sky", 0);
+        self.program.set_f32("intensity", self.intensity);
+        self.vao.bind();
+        unsafe { crate::checked!(gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 14)) };
+
+        depth_write(write);
+        depth_func(func);
+        cull_face(cull);
+    }
+}
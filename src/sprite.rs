@@ -0,0 +1,251 @@
+use gl::types::GLuint;
+use glam::{Mat4, Vec2};
+
+use crate::atlas::{Atlas, UvRect};
+use crate::camera::{Camera, Ortho2D, Origin, PROJECTION_UNIFORM, VIEW_UNIFORM};
+use crate::error::WrapperError;
+use crate::wrapper::{
+    ArrayBuffer, Attribute, BlendState, BufferType, buffer_data, buffer_vertices, depth_test, depth_test_enabled,
+    draw_elements_from, PrimitiveMode, ShaderProgram, Texture2D, VertexArray, VertexLike,
+};
+
+const SPRITE_VERT: &str = include_str!("../shaders/src/sprite/sprite.vert");
+pub const SPRITE_FRAG: &str = include_str!("../shaders/src/sprite/sprite.frag");
+
+/// Color leaving sprites as they are.
+#[allow(dead_code)]
+pub const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpriteVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl VertexLike for SpriteVertex {
+    const ATTRIBUTES: &'static [Attribute] = &[Attribute::vec2(0), Attribute::vec2(1), Attribute::vec4(2)];
+}
+
+/// Consecutive quads sharing a texture, drawn with one call.
+#[derive(Debug, Clone, Copy)]
+struct Run {
+    texture: GLuint,
+    first_quad: usize,
+    quads: usize,
+}
+
+/// Immediate-style 2D drawing: sprites queued between `begin` and `end` go into one dynamic
+/// vertex buffer, uploaded once per flush and drawn with a single call per run of sprites
+/// sharing a texture. Sprites from one `Atlas` therefore cost a single draw however many
+/// there are. Later sprites are drawn over earlier ones, so switching textures back and
+/// forth splits the runs.
+///
+/// ```ignore
+/// batch.begin(&ortho);
+/// batch.draw_sprite(atlas.texture(), atlas.uv("coin").unwrap(), [x, y, 32.0, 32.0], 0.0, WHITE);
+/// batch.draw_rect([10.0, 10.0, 200.0, 4.0], [1.0, 0.0, 0.0, 1.0]);
+/// batch.end();
+/// ```
+pub struct SpriteBatch {
+    vertices: Vec<SpriteVertex>,
+    runs: Vec<Run>,
+    view: Mat4,
+    projection: Mat4,
+    /// y grows downwards, rects are given by their top edge
+    y_down: bool,
+    program: ShaderProgram,
+    vao: VertexArray,
+    vbo: ArrayBuffer,
+    ebo: ArrayBuffer,
+    /// quads the element buffer has indices for
+    index_capacity: usize,
+    /// 1x1 white texture for plain colored quads
+    white: Texture2D,
+    draw_calls: usize,
+}
+
+#[allow(dead_code)]
+impl SpriteBatch {
+    pub fn new() -> Result<Self, WrapperError> {
+        Self::with_fragment("sprite", SPRITE_FRAG)
+    }
+
+    /// Draws with another fragment shader reading `in vec2 uv`, `in vec4 color` and the
+    /// `sprite` sampler, its other uniforms are set through `program`.
+    pub fn with_fragment(label: &str, frag_src: &str) -> Result<Self, WrapperError> {
+        let program = ShaderProgram::from_vertex_fragment(SPRITE_VERT, frag_src)?;
+        program.set_label(label);
+
+        let vao = VertexArray::new().ok_or(WrapperError::Allocation("vertex array"))?;
+        let vbo = ArrayBuffer::new().ok_or(WrapperError::Allocation("vertex buffer"))?;
+        let ebo = ArrayBuffer::new().ok_or(WrapperError::Allocation("element buffer"))?;
+        vao.bind();
+        vbo.bind(BufferType::Array);
+        vao.set_vertex_layout::<SpriteVertex>();
+        ebo.bind(BufferType::ElementArray);
+        vao.set_label(label);
+
+        let white = Texture2D::from_rgba(1, 1, &[255; 4])?;
+        white.set_label("sprite.white");
+
+        Ok(Self {
+            vertices: Vec::new(),
+            runs: Vec::new(),
+            view: Mat4::IDENTITY,
+            projection: Mat4::IDENTITY,
+            y_down: false,
+            program,
+            vao,
+            vbo,
+            ebo,
+            index_capacity: 0,
+            white,
+            draw_calls: 0,
+        })
+    }
+
+    /// Starts a new batch drawn in the pixel space of `projection`.
+    pub fn begin(&mut self, projection: &Ortho2D) {
+        self.start(Mat4::IDENTITY, projection.projection_matrix(), projection.origin == Origin::TopLeft);
+    }
+
+    /// Starts a new batch drawn on the world's z = 0 plane as seen by `camera`, rects in
+    /// world units from their bottom left corner.
+    pub fn begin_camera(&mut self, camera: &Camera) {
+        self.start(camera.view_matrix(), camera.projection_matrix(), false);
+    }
+
+    fn start(&mut self, view: Mat4, projection: Mat4, y_down: bool) {
+        self.view = view;
+        self.projection = projection;
+        self.y_down = y_down;
+        self.vertices.clear();
+        self.runs.clear();
+        self.draw_calls = 0;
+    }
+
+    /// Queues `uv` of `texture` stretched over the pixel rectangle `[x, y, width, height]`,
+    /// turned by `rotation` radians around its center and multiplied by `color`.
+    pub fn draw_sprite(&mut self, texture: &Texture2D, uv: UvRect, rect: [f32; 4], rotation: f32, color: [f32; 4]) {
+        self.push_quad(texture.0, uv, rect, rotation, color);
+    }
+
+    /// Queues the image `name` of `atlas`, false when the atlas has no such image.
+    pub fn draw_atlas(&mut self, atlas: &Atlas, name: &str, rect: [f32; 4], rotation: f32, color: [f32; 4]) -> bool {
+        match atlas.uv(name) {
+            Some(uv) => {
+                self.draw_sprite(atlas.texture(), uv, rect, rotation, color);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Queues a plain colored rectangle.
+    pub fn draw_rect(&mut self, rect: [f32; 4], color: [f32; 4]) {
+        self.push_quad(self.white.0, UvRect::FULL, rect, 0.0, color);
+    }
+
+    /// Sprites queued since `begin` or the last flush.
+    pub fn sprite_count(&self) -> usize {
+        self.vertices.len() / 4
+    }
+
+    /// Draw calls issued since `begin`.
+    pub fn draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+
+    /// Whether the batch was started with y growing downwards.
+    pub fn y_down(&self) -> bool {
+        self.y_down
+    }
+
+    pub fn program(&self) -> &ShaderProgram {
+        &self.program
+    }
+
+    fn push_quad(&mut self, texture: GLuint, uv: UvRect, rect: [f32; 4], rotation: f32, color: [f32; 4]) {
+        let [x, y, width, height] = rect;
+        let half = Vec2::new(width, height) * 0.5;
+        let center = Vec2::new(x, y) + half;
+        let turn = Vec2::from_angle(rotation);
+
+        // corners counter-clockwise from min uv, the bottom of the image; with y growing
+        // downwards that corner belongs at the bottom of the rectangle, at +y
+        let corners = [
+            (Vec2::new(-half.x, -half.y), [uv.min[0], uv.min[1]]),
+            (Vec2::new(half.x, -half.y), [uv.max[0], uv.min[1]]),
+            (Vec2::new(half.x, half.y), [uv.max[0], uv.max[1]]),
+            (Vec2::new(-half.x, half.y), [uv.min[0], uv.max[1]]),
+        ];
+        let flip = self.y_down;
+        for (offset, [u, v]) in corners {
+            let offset = if flip { Vec2::new(offset.x, -offset.y) } else { offset };
+            let position = center + turn.rotate(offset);
+            self.vertices.push(SpriteVertex { position: position.to_array(), uv: [u, v], color });
+        }
+
+        match self.runs.last_mut() {
+            Some(run) if run.texture == texture => run.quads += 1,
+            _ => self.runs.push(Run { texture, first_quad: self.vertices.len() / 4 - 1, quads: 1 }),
+        }
+    }
+
+    /// Draws what is queued so far, e.g. before drawing something else in between.
+    /// Alpha blending is on and depth testing off while drawing.
+    pub fn flush(&mut self) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        self.vao.bind();
+        self.vbo.bind(BufferType::Array);
+        buffer_vertices(BufferType::Array, &self.vertices, gl::STREAM_DRAW);
+        self.ensure_indices(self.sprite_count());
+
+        let depth_enabled = depth_test_enabled();
+        depth_test(false);
+        BlendState::alpha().apply();
+
+        self.program.use_program();
+        self.program.set_mat4(VIEW_UNIFORM, &self.view.to_cols_array());
+        self.program.set_mat4(PROJECTION_UNIFORM, &self.projection.to_cols_array());
+        self.program.set_i32("sprite", 0);
+        for run in &self.runs {
+            unsafe {
+                crate::checked!(gl::ActiveTexture(gl::TEXTURE0));
+                crate::checked!(gl::BindTexture(gl::TEXTURE_2D, run.texture));
+            }
+            draw_elements_from(PrimitiveMode::Triangles, (run.quads * 6) as i32, run.first_quad * 6);
+            self.draw_calls += 1;
+        }
+
+        BlendState::disable();
+        depth_test(depth_enabled);
+        self.vertices.clear();
+        self.runs.clear();
+    }
+
+    /// Flushes the rest of the batch.
+    pub fn end(&mut self) {
+        self.flush();
+    }
+
+    /// Grows the static quad indices to at least `quads`, doubling to keep regrowth rare.
+    fn ensure_indices(&mut self, quads: usize) {
+        self.ebo.bind(BufferType::ElementArray);
+        if quads <= self.index_capacity {
+            return;
+        }
+
+        let capacity = quads.next_power_of_two().max(64);
+        let indices: Vec<u32> = (0..capacity as u32)
+            .flat_map(|quad| [0, 1, 2, 2, 3, 0].map(|corner| quad * 4 + corner))
+            .collect();
+        buffer_data(BufferType::ElementArray, bytemuck::cast_slice(&indices), gl::STATIC_DRAW);
+        self.index_capacity = capacity;
+    }
+}
@@ -0,0 +1,171 @@
+use glam::{Mat4, Vec3};
+
+use crate::error::WrapperError;
+use crate::framebuffer::Framebuffer;
+use crate::postprocess::{draw_fullscreen, FULLSCREEN_VERT};
+use crate::wrapper::{
+    depth_test, depth_test_enabled, ShaderProgram, Texture2D, TextureFilter, TextureFormat, TextureWrap, VertexArray,
+};
+
+const OCCLUSION_FRAG: &str = include_str!("../shaders/src/ssao/occlusion.frag");
+const BLUR_FRAG: &str = include_str!("../shaders/src/ssao/blur.frag");
+
+const DEPTH_UNIT: u32 = 0;
+const NORMAL_UNIT: u32 = 1;
+const NOISE_UNIT: u32 = 2;
+/// noise tile is `NOISE_SIZE`² texels, the blur is as wide
+const NOISE_SIZE: u32 = 4;
+/// `MAX_KERNEL` of the occlusion shader
+const MAX_KERNEL: usize = 64;
+
+/// Screen-space ambient occlusion: darkens creases and contact points by testing how many
+/// points of a hemisphere around each pixel lie behind the depth buffer. Runs on the depth
+/// and view-space normals of a G-buffer, the result is a single channel texture (1 is
+/// unoccluded) to multiply the ambient light with.
+pub struct Ssao {
+    /// view-space distance the samples reach, roughly the size of the creases darkened
+    pub radius: f32,
+    /// depth difference below which the surface doesn't occlude itself, fights acne
+    pub bias: f32,
+    kernel_size: usize,
+    noise: Texture2D,
+    occlusion: Framebuffer,
+    blurred: Framebuffer,
+    occlusion_program: ShaderProgram,
+    blur_program: ShaderProgram,
+    vao: VertexArray,
+}
+
+#[allow(dead_code)]
+impl Ssao {
+    pub const DEFAULT_KERNEL_SIZE: usize = 32;
+
+    /// `width`/`height` is the size of the G-buffer, the occlusion is computed at that size.
+    pub fn new(width: u32, height: u32) -> Result<Self, WrapperError> {
+        Self::with_kernel_size(width, height, Self::DEFAULT_KERNEL_SIZE)
+    }
+
+    /// More samples give smoother occlusion at a higher cost, at most 64.
+    pub fn with_kernel_size(width: u32, height: u32, kernel_size: usize) -> Result<Self, WrapperError> {
+        let kernel_size = kernel_size.clamp(1, MAX_KERNEL);
+        let mut random = Random(0x2545_f491);
+
+        let occlusion_program = ShaderProgram::from_vertex_fragment(FULLSCREEN_VERT, OCCLUSION_FRAG)?;
+        occlusion_program.set_label("ssao.occlusion");
+        occlusion_program.use_program();
+        for (i, sample) in hemisphere_kernel(kernel_size, &mut random).iter().enumerate() {
+            occlusion_program.set_vec3(&format!("samples[{}]", i), sample.to_array());
+        }
+        occlusion_program.set_i32("kernel_size", kernel_size as i32);
+
+        let blur_program = ShaderProgram::from_vertex_fragment(FULLSCREEN_VERT, BLUR_FRAG)?;
+        blur_program.set_label("ssao.blur");
+
+        // random rotations around z, tiled over the screen so neighbouring pixels sample
+        // differently and the banding turns into noise the blur removes
+        let noise_pixels: Vec<u8> = (0..NOISE_SIZE * NOISE_SIZE)
+            .flat_map(|_| {
+                let [x, y] = [random.next() * 2.0 - 1.0, random.next() * 2.0 - 1.0];
+                [x, y, 0.0].map(|value| ((value * 0.5 + 0.5) * 255.0).round() as u8).into_iter().chain([255])
+            })
+            .collect();
+        let noise = Texture2D::from_rgba(NOISE_SIZE, NOISE_SIZE, &noise_pixels)?;
+        noise.set_filter(TextureFilter::Nearest, TextureFilter::Nearest);
+        noise.set_wrap(TextureWrap::Repeat, TextureWrap::Repeat);
+        noise.set_label("ssao.noise");
+
+        let target = |label: &str| -> Result<Framebuffer, WrapperError> {
+            let target = Framebuffer::builder(width, height).color_texture(TextureFormat::R8).build()?;
+            target.set_label(label);
+            Ok(target)
+        };
+
+        Ok(Self {
+            radius: 0.5,
+            bias: 0.025,
+            kernel_size,
+            noise,
+            occlusion: target("ssao.occlusion")?,
+            blurred: target("ssao.blurred")?,
+            occlusion_program,
+            blur_program,
+            vao: VertexArray::new().ok_or(WrapperError::Allocation("vertex array"))?,
+        })
+    }
+
+    pub fn kernel_size(&self) -> usize {
+        self.kernel_size
+    }
+
+    /// G-buffer size changed.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.occlusion.resize(width, height);
+        self.blurred.resize(width, height);
+    }
+
+    /// Computes and blurs the occlusion of the scene in `depth` and `normals`, seen through
+    /// `projection`. Leaves the blurred target bound, bind the next one before drawing on.
+    pub fn apply(&self, depth: &Texture2D, normals: &Texture2D, projection: Mat4) -> &Texture2D {
+        let depth_enabled = depth_test_enabled();
+        depth_test(false);
+
+        self.occlusion.bind();
+        self.occlusion_program.use_program();
+        depth.bind_to_unit(DEPTH_UNIT);
+        normals.bind_to_unit(NORMAL_UNIT);
+        self.noise.bind_to_unit(NOISE_UNIT);
+        self.occlusion_program.set_i32("depth", DEPTH_UNIT as i32);
+        self.occlusion_program.set_i32("normals", NORMAL_UNIT as i32);
+        self.occlusion_program.set_i32("noise", NOISE_UNIT as i32);
+        self.occlusion_program.set_mat4("projection", &projection.to_cols_array());
+        self.occlusion_program.set_mat4("inverse_projection", &projection.inverse().to_cols_array());
+        let (width, height) = (self.occlusion.width() as f32, self.occlusion.height() as f32);
+        self.occlusion_program.set_vec2("noise_scale", [width / NOISE_SIZE as f32, height / NOISE_SIZE as f32]);
+        self.occlusion_program.set_f32("radius", self.radius);
+        self.occlusion_program.set_f32("bias", self.bias);
+        draw_fullscreen(&self.vao);
+
+        self.blurred.bind();
+        self.blur_program.use_program();
+        if let Some(texture) = self.occlusion.color_texture(0) {
+            texture.bind_to_unit(0);
+        }
+        self.blur_program.set_i32("screen", 0);
+        self.blur_program.set_vec2("texel", [1.0 / width, 1.0 / height]);
+        draw_fullscreen(&self.vao);
+
+        depth_test(depth_enabled);
+        self.texture()
+    }
+
+    /// Blurred occlusion of the last `apply`.
+    pub fn texture(&self) -> &Texture2D {
+        self.blurred.color_texture(0).expect("ssao target has a color texture")
+    }
+}
+
+/// Points in the +z hemisphere, denser near the center so close geometry weighs more.
+fn hemisphere_kernel(size: usize, random: &mut Random) -> Vec<Vec3> {
+    (0..size)
+        .map(|i| {
+            let direction = Vec3::new(random.next() * 2.0 - 1.0, random.next() * 2.0 - 1.0, random.next())
+                .try_normalize()
+                .unwrap_or(Vec3::Z);
+            let t = i as f32 / size as f32;
+            direction * random.next() * (0.1 + 0.9 * t * t)
+        })
+        .collect()
+}
+
+/// xorshift32, the kernel and noise only need to look random, not be unpredictable.
+struct Random(u32);
+
+impl Random {
+    /// Uniform in 0..1.
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+}
@@ -0,0 +1,380 @@
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+use gl::types::{GLbitfield, GLenum, GLint, GLintptr, GLsizei, GLsizeiptr, GLuint};
+
+use crate::error::WrapperError;
+use crate::wrapper::{
+    clear_buffer_binding, context_version, update_buffer_data, ArrayBuffer, Attribute, BufferType, Fence, MapRead,
+    Texture2D, VertexArray,
+};
+
+/// Regions of a `PersistentBuffer`: the CPU writes one while the GPU may still read the
+/// two written in the previous frames.
+pub const PERSISTENT_REGIONS: usize = 3;
+
+/// How long `PersistentBuffer::write` waits for the GPU before giving up on a region, 1s.
+const FENCE_TIMEOUT_NS: u64 = 1_000_000_000;
+
+/// Buffer mapped once for its whole life (`glBufferStorage` with persistent, coherent mapping)
+/// and split into `PERSISTENT_REGIONS` regions of `len` elements written round-robin.
+/// Per-frame data is written straight into GPU visible memory without `glBufferSubData`
+/// stalls, a fence per region makes sure the GPU is done reading before it is reused:
+/// ```ignore
+/// let mut stream = PersistentBuffer::<Vertex>::new(BufferType::Array, vertices.len())?;
+/// // every frame
+/// stream.write().copy_from_slice(&vertices);
+/// stream.bind();
+/// gl::DrawArrays(gl::TRIANGLES, stream.first_element() as i32, vertices.len() as i32);
+/// stream.finish();
+/// ```
+/// Needs GL 4.4+.
+pub struct PersistentBuffer<T: bytemuck::Pod> {
+    buffer: ArrayBuffer,
+    buf_type: BufferType,
+    ptr: *mut T,
+    len: usize,
+    head: usize,
+    fences: [Option<Fence>; PERSISTENT_REGIONS],
+    _data: PhantomData<T>,
+}
+
+#[allow(dead_code)]
+impl<T: bytemuck::Pod> PersistentBuffer<T> {
+    pub fn new(buf_type: BufferType, len: usize) -> Result<Self, WrapperError> {
+        if context_version() < (4, 4) || !gl::BufferStorage::is_loaded() {
+            return Err(WrapperError::Unsupported("persistent mapping needs OpenGL 4.4"));
+        }
+
+        let buffer = ArrayBuffer::new().ok_or(WrapperError::Allocation("persistent buffer"))?;
+        buffer.bind(buf_type);
+
+        let size = (len * PERSISTENT_REGIONS * size_of::<T>()) as GLsizeiptr;
+        let flags: GLbitfield = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+        let ptr = unsafe {
+            crate::checked!(gl::BufferStorage(buf_type as GLenum, size, std::ptr::null(), flags));
+            crate::checked!(gl::MapBufferRange(buf_type as GLenum, 0, size, flags))
+        };
+
+        if ptr.is_null() {
+            return Err(WrapperError::Allocation("persistent buffer mapping"));
+        }
+
+        Ok(Self {
+            buffer,
+            buf_type,
+            ptr: ptr.cast(),
+            len,
+            head: 0,
+            fences: Default::default(),
+            _data: PhantomData,
+        })
+    }
+
+    /// Current region, waiting first until the GPU is done with what was written there
+    /// `PERSISTENT_REGIONS` frames ago.
+    pub fn write(&mut self) -> &mut [T] {
+        if let Some(fence) = self.fences[self.head].take() {
+            // after the timeout the region is overwritten anyway rather than hanging the frame
+            if !fence.wait(FENCE_TIMEOUT_NS) {
+                #[cfg(feature = "debug")]
+                log::warn!("PersistentBuffer: GPU is still reading region {} after 1s", self.head);
+            }
+        }
+
+        // the mapping stays valid until the buffer is deleted and regions never overlap,
+        // the returned borrow of `self` keeps anyone from getting a second slice
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.add(self.head * self.len), self.len) }
+    }
+
+    /// Must be called after the draws reading the current region were issued, fences the
+    /// region and moves to the next one.
+    pub fn finish(&mut self) {
+        self.fences[self.head] = Fence::new();
+        self.head = (self.head + 1) % PERSISTENT_REGIONS;
+    }
+
+    pub fn bind(&self) {
+        self.buffer.bind(self.buf_type);
+    }
+
+    /// Binds just the current region to an indexed target of a uniform or storage buffer.
+    pub fn bind_range(&self, binding: GLuint) {
+        let size = self.len * size_of::<T>();
+        unsafe {
+            crate::checked!(gl::BindBufferRange(
+                self.buf_type as GLenum,
+                binding,
+                self.buffer.0,
+                (self.head * size) as GLintptr,
+                size as GLsizeiptr,
+            ));
+        }
+    }
+
+    /// Index of the first element of the current region in the whole buffer, e.g. the
+    /// `first` of `glDrawArrays` or the base vertex of an indexed draw.
+    pub fn first_element(&self) -> usize {
+        self.head * self.len
+    }
+
+    /// Byte offset of the current region.
+    pub fn offset(&self) -> usize {
+        self.first_element() * size_of::<T>()
+    }
+
+    /// Elements per region.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn buffer(&self) -> &ArrayBuffer {
+        &self.buffer
+    }
+}
+
+/// How `DynamicBuffer` avoids waiting for the GPU to finish reading the previous contents.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamStrategy {
+    /// re-specifies the storage with `glBufferData(NULL)` before every upload, letting the
+    /// driver hand out fresh memory while the old one is still in use
+    Orphan,
+    /// cycles through this many buffers, each upload goes to the least recently used one
+    Rotate(usize),
+}
+
+/// Buffer for data replaced every frame, works on any GL 3.3 context unlike `PersistentBuffer`.
+/// With `StreamStrategy::Rotate` the current buffer changes on every upload, so vertex
+/// arrays sourcing it have to be re-pointed with `attach`.
+pub struct DynamicBuffer<T: bytemuck::Pod> {
+    buffers: Vec<ArrayBuffer>,
+    buf_type: BufferType,
+    strategy: StreamStrategy,
+    current: usize,
+    /// elements the storage of every buffer can hold
+    capacity: usize,
+    len: usize,
+    _data: PhantomData<T>,
+}
+
+#[allow(dead_code)]
+impl<T: bytemuck::Pod> DynamicBuffer<T> {
+    pub fn new(buf_type: BufferType, capacity: usize, strategy: StreamStrategy) -> Result<Self, WrapperError> {
+        let count = match strategy {
+            StreamStrategy::Orphan => 1,
+            StreamStrategy::Rotate(count) => count.max(1),
+        };
+
+        let buffers = (0..count)
+            .map(|_| {
+                let buffer = ArrayBuffer::new().ok_or(WrapperError::Allocation("dynamic buffer"))?;
+                buffer.bind(buf_type);
+                Self::allocate(buf_type, capacity);
+                Ok(buffer)
+            })
+            .collect::<Result<Vec<_>, WrapperError>>()?;
+
+        Ok(Self { buffers, buf_type, strategy, current: 0, capacity, len: 0, _data: PhantomData })
+    }
+
+    /// Replaces the contents, growing the storage when `data` doesn't fit.
+    pub fn upload(&mut self, data: &[T]) {
+        if self.strategy != StreamStrategy::Orphan {
+            self.current = (self.current + 1) % self.buffers.len();
+        }
+
+        if data.len() > self.capacity {
+            self.capacity = data.len().next_power_of_two();
+            for buffer in &self.buffers {
+                buffer.bind(self.buf_type);
+                Self::allocate(self.buf_type, self.capacity);
+            }
+        } else if self.strategy == StreamStrategy::Orphan {
+            self.bind();
+            Self::allocate(self.buf_type, self.capacity);
+        }
+
+        self.bind();
+        update_buffer_data(self.buf_type, bytemuck::cast_slice(data));
+        self.len = data.len();
+    }
+
+    fn allocate(buf_type: BufferType, capacity: usize) {
+        unsafe {
+            crate::checked!(gl::BufferData(
+                buf_type as GLenum,
+                (capacity * size_of::<T>()) as GLsizeiptr,
+                std::ptr::null(),
+                gl::STREAM_DRAW,
+            ));
+        }
+    }
+
+    pub fn bind(&self) {
+        self.current().bind(self.buf_type);
+    }
+
+    /// Points `attributes` of `vao` at the current buffer, needed after every upload
+    /// with `StreamStrategy::Rotate`.
+    pub fn attach(&self, vao: &VertexArray, attributes: &[Attribute]) {
+        vao.attach_buffer(self.current(), attributes);
+    }
+
+    /// Buffer holding the last upload.
+    pub fn current(&self) -> &ArrayBuffer {
+        &self.buffers[self.current]
+    }
+
+    /// Elements of the last upload.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn strategy(&self) -> StreamStrategy {
+        self.strategy
+    }
+
+    pub fn set_label(&self, label: &str) {
+        for (i, buffer) in self.buffers.iter().enumerate() {
+            buffer.set_label(&format!("{}[{}]", label, i));
+        }
+    }
+}
+
+/// Readback requested by `PixelBuffer::read_pixels` which may not have completed yet.
+struct PendingRead {
+    fence: Option<Fence>,
+    width: u32,
+    height: u32,
+}
+
+/// Pixel buffer object moving pixels between the CPU and textures or framebuffers without
+/// stalling the CPU: uploads return once the pixels are copied into the buffer and readbacks
+/// are collected a few frames later, once their fence is signaled. Pixels are RGBA8 rows,
+/// bottom row first like GL lays them out.
+pub struct PixelBuffer {
+    buffer: ArrayBuffer,
+    /// bytes the storage can hold
+    capacity: usize,
+    pending: Option<PendingRead>,
+}
+
+#[allow(dead_code)]
+impl PixelBuffer {
+    pub fn new(capacity: usize) -> Result<Self, WrapperError> {
+        let buffer = ArrayBuffer::new().ok_or(WrapperError::Allocation("pixel buffer"))?;
+        buffer.bind(BufferType::PixelPack);
+        Self::allocate(BufferType::PixelPack, capacity, gl::STREAM_READ);
+        clear_buffer_binding(BufferType::PixelPack);
+
+        Ok(Self { buffer, capacity, pending: None })
+    }
+
+    fn allocate(buf_type: BufferType, capacity: usize, usage: GLenum) {
+        unsafe { crate::checked!(gl::BufferData(buf_type as GLenum, capacity as GLsizeiptr, std::ptr::null(), usage)) }
+    }
+
+    /// Replaces the `width`x`height` RGBA8 area at (`x`, `y`) of `texture`. The GPU copies
+    /// from the buffer into the texture on its own time.
+    pub fn upload(&mut self, texture: &Texture2D, x: u32, y: u32, width: u32, height: u32, pixels: &[u8]) {
+        let size = (width * height * 4) as usize;
+        assert_eq!(pixels.len(), size, "pixel data doesn't match the upload area");
+
+        self.buffer.bind(BufferType::PixelUnpack);
+        // orphan, an upload still reading the old contents keeps its own copy
+        self.capacity = self.capacity.max(size);
+        Self::allocate(BufferType::PixelUnpack, self.capacity, gl::STREAM_DRAW);
+        update_buffer_data(BufferType::PixelUnpack, pixels);
+
+        texture.bind();
+        unsafe {
+            crate::checked!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1));
+            crate::checked!(gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                x as GLint,
+                y as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            ));
+        }
+        clear_buffer_binding(BufferType::PixelUnpack);
+    }
+
+    /// Starts reading the area of the bound read framebuffer into the buffer, replacing a
+    /// readback which wasn't collected yet. Returns immediately.
+    pub fn read_pixels(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        let size = (width * height * 4) as usize;
+
+        self.buffer.bind(BufferType::PixelPack);
+        if size > self.capacity {
+            self.capacity = size;
+        }
+        Self::allocate(BufferType::PixelPack, self.capacity, gl::STREAM_READ);
+
+        unsafe {
+            crate::checked!(gl::PixelStorei(gl::PACK_ALIGNMENT, 1));
+            crate::checked!(gl::ReadPixels(x, y, width as GLsizei, height as GLsizei, gl::RGBA, gl::UNSIGNED_BYTE, std::ptr::null_mut()));
+        }
+        clear_buffer_binding(BufferType::PixelPack);
+
+        self.pending = Some(PendingRead { fence: Fence::new(), width, height });
+    }
+
+    /// Whether a readback was started and not collected yet.
+    pub fn is_reading(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Pixels of the last `read_pixels` with their width and height, or `None` when the GPU
+    /// hasn't finished yet (or nothing was requested). Never blocks.
+    pub fn try_collect(&mut self) -> Option<(Vec<u8>, u32, u32)> {
+        let ready = match &self.pending {
+            Some(PendingRead { fence: Some(fence), .. }) => fence.is_signaled(),
+            Some(PendingRead { fence: None, .. }) => true,
+            None => false,
+        };
+
+        if ready {
+            self.collect()
+        } else {
+            None
+        }
+    }
+
+    /// Like `try_collect`, but waits for the GPU.
+    pub fn collect(&mut self) -> Option<(Vec<u8>, u32, u32)> {
+        let PendingRead { width, height, .. } = self.pending.take()?;
+        let size = (width * height * 4) as usize;
+
+        // mapping waits for the read to complete if the fence wasn't signaled yet
+        let pixels = self.buffer.map_range::<u8, _>(BufferType::PixelPack, 0, size, MapRead).ok()?.to_vec();
+        clear_buffer_binding(BufferType::PixelPack);
+
+        Some((pixels, width, height))
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_label(&self, label: &str) {
+        self.buffer.set_label(label);
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use glam::Vec2;
+
+use crate::atlas::{Atlas, UvRect};
+use crate::camera::{Camera, Ortho2D};
+use crate::error::WrapperError;
+use crate::sprite::{SpriteBatch, SPRITE_FRAG};
+use crate::wrapper::{ColorSpace, Texture2D};
+
+const SDF_TEXT_FRAG: &str = include_str!("../shaders/src/text/sdf_text.frag");
+
+/// Placement of one rasterized glyph, in pixels of the font's raster size.
+#[derive(Debug, Clone, Copy)]
+struct Glyph {
+    uv: UvRect,
+    /// left edge and bottom edge of the bitmap relative to the pen on the baseline
+    offset: [f32; 2],
+    size: [f32; 2],
+    advance: f32,
+}
+
+/// A TrueType/OpenType font rasterized once at `raster_size` pixels into a glyph atlas.
+/// Coverage bitmaps blur when drawn much larger than the raster size, so pick it near the
+/// largest size they're drawn at. Signed distance fields from `Font::sdf` stay sharp at any
+/// size instead, for labels that get zoomed.
+pub struct Font {
+    font: fontdue::Font,
+    raster_size: f32,
+    /// raster pixels the distance field reaches on either side of an edge, `None` for
+    /// coverage bitmaps
+    spread: Option<u32>,
+    atlas: Atlas,
+    glyphs: HashMap<char, Glyph>,
+    ascent: f32,
+    line_height: f32,
+}
+
+#[allow(dead_code)]
+impl Font {
+    pub const DEFAULT_RASTER_SIZE: f32 = 32.0;
+    /// Distance fields hold shapes more faithfully from a larger raster.
+    pub const SDF_RASTER_SIZE: f32 = 48.0;
+    pub const SDF_SPREAD: u32 = 6;
+
+    /// Printable ASCII, what `from_file` and `from_bytes` rasterize.
+    pub fn ascii() -> impl Iterator<Item = char> {
+        ' '..='~'
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| WrapperError::Io { path: path.to_path_buf(), source })?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WrapperError> {
+        Self::with_chars(bytes, Self::DEFAULT_RASTER_SIZE, Self::ascii())
+    }
+
+    /// Rasterizes `chars` at `raster_size` pixels. Characters drawn later outside of them
+    /// show up as '?', or nothing when that is missing too.
+    pub fn with_chars(bytes: &[u8], raster_size: f32, chars: impl IntoIterator<Item = char>) -> Result<Self, WrapperError> {
+        Self::rasterize(bytes, raster_size, None, chars)
+    }
+
+    /// Printable ASCII as distance fields, see `sdf`.
+    pub fn sdf_from_file(path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| WrapperError::Io { path: path.to_path_buf(), source })?;
+        Self::sdf(&bytes, Self::SDF_RASTER_SIZE, Self::SDF_SPREAD, Self::ascii())
+    }
+
+    /// Like `with_chars` but stores the signed distance to the glyph outline, reaching
+    /// `spread` raster pixels inside and outside. The spread also bounds how wide outlines
+    /// and how far shadows of `SdfStyle` can go.
+    pub fn sdf(bytes: &[u8], raster_size: f32, spread: u32, chars: impl IntoIterator<Item = char>) -> Result<Self, WrapperError> {
+        Self::rasterize(bytes, raster_size, Some(spread.max(1)), chars)
+    }
+
+    fn rasterize(
+        bytes: &[u8],
+        raster_size: f32,
+        spread: Option<u32>,
+        chars: impl IntoIterator<Item = char>,
+    ) -> Result<Self, WrapperError> {
+        let settings = fontdue::FontSettings { scale: raster_size, ..Default::default() };
+        let font = fontdue::Font::from_bytes(bytes, settings).map_err(WrapperError::Font)?;
+        let line = font
+            .horizontal_line_metrics(raster_size)
+            .ok_or(WrapperError::Font("no horizontal line metrics"))?;
+
+        let mut builder = Atlas::builder().color_space(ColorSpace::Linear);
+        let mut metrics = Vec::new();
+        for c in chars {
+            let (mut glyph, mut coverage) = font.rasterize(c, raster_size);
+            if glyph.width > 0 && glyph.height > 0 {
+                if let Some(spread) = spread {
+                    coverage = signed_distance_field(&coverage, glyph.width, glyph.height, spread);
+                    glyph.xmin -= spread as i32;
+                    glyph.ymin -= spread as i32;
+                    glyph.width += 2 * spread as usize;
+                    glyph.height += 2 * spread as usize;
+                }
+                // white with the coverage as alpha, bottom row first like the atlas expects
+                let pixels: Vec<u8> = coverage
+                    .chunks_exact(glyph.width)
+                    .rev()
+                    .flatten()
+                    .flat_map(|&alpha| [255, 255, 255, alpha])
+                    .collect();
+                builder = builder.image(&c.to_string(), glyph.width as u32, glyph.height as u32, &pixels)?;
+            }
+            metrics.push((c, glyph));
+        }
+        let atlas = builder.build()?;
+        atlas.texture().set_label("font");
+
+        let glyphs = metrics
+            .into_iter()
+            .map(|(c, glyph)| {
+                let uv = atlas.uv(&c.to_string()).unwrap_or(UvRect { min: [0.0; 2], max: [0.0; 2] });
+                let glyph = Glyph {
+                    uv,
+                    offset: [glyph.xmin as f32, glyph.ymin as f32],
+                    size: [glyph.width as f32, glyph.height as f32],
+                    advance: glyph.advance_width,
+                };
+                (c, glyph)
+            })
+            .collect();
+
+        Ok(Self { font, raster_size, spread, atlas, glyphs, ascent: line.ascent, line_height: line.new_line_size })
+    }
+
+    pub fn raster_size(&self) -> f32 {
+        self.raster_size
+    }
+
+    pub fn is_sdf(&self) -> bool {
+        self.spread.is_some()
+    }
+
+    pub fn spread(&self) -> Option<u32> {
+        self.spread
+    }
+
+    pub fn atlas(&self) -> &Atlas {
+        &self.atlas
+    }
+
+    pub fn texture(&self) -> &Texture2D {
+        self.atlas.texture()
+    }
+
+    /// Distance between baselines of consecutive lines at `size` pixels.
+    pub fn line_height(&self, size: f32) -> f32 {
+        self.line_height * size / self.raster_size
+    }
+
+    /// Width of the widest line and height of all lines of `text` at `size` pixels.
+    pub fn measure(&self, text: &str, size: f32) -> Vec2 {
+        let scale = size / self.raster_size;
+        let mut lines = 0;
+        let mut widest: f32 = 0.0;
+        for line in text.split('\n') {
+            let mut pen = 0.0;
+            self.layout_line(line, |_, _, after| pen = after);
+            widest = widest.max(pen);
+            lines += 1;
+        }
+        Vec2::new(widest * scale, lines as f32 * self.line_height(size))
+    }
+
+    fn glyph(&self, c: char) -> Option<(char, &Glyph)> {
+        self.glyphs.get_key_value(&c).or_else(|| self.glyphs.get_key_value(&'?')).map(|(&c, glyph)| (c, glyph))
+    }
+
+    /// Walks the glyphs of one line at the raster size, calling `place` with every glyph, the
+    /// pen position it is drawn at and the pen position after it.
+    fn layout_line(&self, line: &str, mut place: impl FnMut(&Glyph, f32, f32)) {
+        let mut pen = 0.0;
+        let mut previous = None;
+        for c in line.chars() {
+            let Some((c, glyph)) = self.glyph(c) else {
+                continue;
+            };
+            if let Some(previous) = previous {
+                pen += self.font.horizontal_kern(previous, c, self.raster_size).unwrap_or(0.0);
+            }
+            let at = pen;
+            pen += glyph.advance;
+            place(glyph, at, pen);
+            previous = Some(c);
+        }
+    }
+}
+
+/// Look of distance field text beyond its fill color. Widths and offsets are in raster
+/// pixels of the font and scale with the text, they reach at most the font's spread.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SdfStyle {
+    pub outline_width: f32,
+    pub outline_color: [f32; 4],
+    /// right and down
+    pub shadow_offset: [f32; 2],
+    pub shadow_color: [f32; 4],
+    /// extra blur of the shadow edge, in distance units of 0 to 0.5
+    pub shadow_softness: f32,
+    /// extra blur of every edge, 0 keeps them one screen pixel wide
+    pub softness: f32,
+}
+
+impl Default for SdfStyle {
+    fn default() -> Self {
+        Self {
+            outline_width: 0.0,
+            outline_color: [0.0; 4],
+            shadow_offset: [0.0; 2],
+            shadow_color: [0.0; 4],
+            shadow_softness: 0.0,
+            softness: 0.0,
+        }
+    }
+}
+
+/// Draws strings in pixel coordinates on top of whatever was rendered, for instructions,
+/// counters and other overlays. Every glyph is a sprite of the font's atlas, so a whole
+/// overlay is a single draw call. With an SDF font `style` adds outlines and drop shadows,
+/// and `begin_camera` places labels in the world that stay crisp while zooming in.
+///
+/// ```ignore
+/// text.begin(&ortho);
+/// text.draw_text(&format!("{:.0} fps", stats.fps()), 10.0, 10.0, 16.0, [1.0; 4]);
+/// text.end();
+/// ```
+pub struct TextRenderer {
+    /// only used with distance field fonts
+    pub style: SdfStyle,
+    font: Font,
+    batch: SpriteBatch,
+}
+
+#[allow(dead_code)]
+impl TextRenderer {
+    pub fn new(font: Font) -> Result<Self, WrapperError> {
+        Ok(Self { style: SdfStyle::default(), batch: Self::batch_for(&font)?, font })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        Self::new(Font::from_file(path)?)
+    }
+
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    /// Switches fonts, between bitmap and distance field fonts that needs a new program.
+    pub fn set_font(&mut self, font: Font) -> Result<(), WrapperError> {
+        if font.is_sdf() != self.font.is_sdf() {
+            self.batch = Self::batch_for(&font)?;
+        }
+        self.font = font;
+        Ok(())
+    }
+
+    pub fn begin(&mut self, projection: &Ortho2D) {
+        self.batch.begin(projection);
+    }
+
+    /// Text on the world's z = 0 plane, in world units, see `SpriteBatch::begin_camera`.
+    pub fn begin_camera(&mut self, camera: &Camera) {
+        self.batch.begin_camera(camera);
+    }
+
+    /// Queues `text` with the top left corner of its first line at `x, y`, whatever the
+    /// origin of the projection. `size` is the font size in pixels, `\n` starts a new line
+    /// further down the screen.
+    pub fn draw_text(&mut self, text: &str, x: f32, y: f32, size: f32, color: [f32; 4]) {
+        let scale = size / self.font.raster_size;
+        // pixels towards the next line
+        let down = if self.batch.y_down() { 1.0 } else { -1.0 };
+
+        let mut baseline = y + down * self.font.ascent * scale;
+        for line in text.split('\n') {
+            let (font, batch) = (&self.font, &mut self.batch);
+            font.layout_line(line, |glyph, pen, _| {
+                if glyph.size[0] == 0.0 {
+                    return;
+                }
+                let [width, height] = [glyph.size[0] * scale, glyph.size[1] * scale];
+                let left = x + (pen + glyph.offset[0]) * scale;
+                let bottom = baseline - down * glyph.offset[1] * scale;
+                // rect y is the edge the projection measures from: the top with y growing downwards
+                let top = if down > 0.0 { bottom - height } else { bottom };
+                batch.draw_sprite(font.texture(), glyph.uv, [left, top, width, height], 0.0, color);
+            });
+            baseline += down * self.font.line_height(size);
+        }
+    }
+
+    /// Draws everything queued since `begin`.
+    pub fn end(&mut self) {
+        if let Some(spread) = self.font.spread {
+            let program = self.batch.program();
+            // distance units span 0.5 over the spread
+            let to_distance = 0.5 / spread as f32;
+            let style = &self.style;
+            program.use_program();
+            program.set_vec2("texel", [1.0 / self.font.atlas.width() as f32, 1.0 / self.font.atlas.height() as f32]);
+            program.set_f32("outline_width", (style.outline_width * to_distance).min(0.5));
+            program.set_vec4("outline_color", style.outline_color);
+            program.set_vec2("shadow_offset", [style.shadow_offset[0], -style.shadow_offset[1]]);
+            program.set_vec4("shadow_color", style.shadow_color);
+            program.set_f32("shadow_softness", style.shadow_softness);
+            program.set_f32("softness", style.softness);
+        }
+        self.batch.end();
+    }
+
+    fn batch_for(font: &Font) -> Result<SpriteBatch, WrapperError> {
+        if font.is_sdf() {
+            SpriteBatch::with_fragment("text.sdf", SDF_TEXT_FRAG)
+        } else {
+            SpriteBatch::with_fragment("text", SPRITE_FRAG)
+        }
+    }
+}
+
+/// Signed distance of every pixel to the outline of a coverage bitmap, in a bitmap grown by
+/// `spread` on every side: 128 on the edge, growing inside up to 255 at `spread` pixels.
+/// Vector propagation in two passes (8SSEDT), exact enough for glyph shapes.
+fn signed_distance_field(coverage: &[u8], width: usize, height: usize, spread: u32) -> Vec<u8> {
+    let pad = spread as usize;
+    let (w, h) = (width + 2 * pad, height + 2 * pad);
+    let inside: Vec<bool> = (0..w * h)
+        .map(|i| {
+            let (x, y) = (i % w, i / w);
+            x >= pad && y >= pad && x < pad + width && y < pad + height && coverage[(y - pad) * width + x - pad] >= 128
+        })
+        .collect();
+
+    let to_inside = distance_transform(&inside, w, h);
+    let outside: Vec<bool> = inside.iter().map(|inside| !inside).collect();
+    let to_outside = distance_transform(&outside, w, h);
+
+    (0..w * h)
+        .map(|i| {
+            // pixel centers lie half a pixel from the edge between them
+            let distance = if inside[i] { to_outside[i] - 0.5 } else { 0.5 - to_inside[i] };
+            ((0.5 + distance / (2.0 * spread as f32)).clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect()
+}
+
+/// Distance from every pixel to the nearest pixel set in `seeds`.
+fn distance_transform(seeds: &[bool], w: usize, h: usize) -> Vec<f32> {
+    const FAR: (i32, i32) = (i32::MAX / 4, i32::MAX / 4);
+    let mut grid: Vec<(i32, i32)> = seeds.iter().map(|&seed| if seed { (0, 0) } else { FAR }).collect();
+    let len = |(dx, dy): (i32, i32)| dx as i64 * dx as i64 + dy as i64 * dy as i64;
+
+    let compare = |grid: &mut [(i32, i32)], x: usize, y: usize, ox: i32, oy: i32| {
+        let (nx, ny) = (x as i32 + ox, y as i32 + oy);
+        if nx < 0 || ny < 0 || nx >= w as i32 || ny >= h as i32 {
+            return;
+        }
+        let other = grid[ny as usize * w + nx as usize];
+        if other == FAR {
+            return;
+        }
+        let candidate = (other.0 + ox, other.1 + oy);
+        if len(candidate) < len(grid[y * w + x]) {
+            grid[y * w + x] = candidate;
+        }
+    };
+
+    for y in 0..h {
+        for x in 0..w {
+            for (ox, oy) in [(-1, 0), (0, -1), (-1, -1), (1, -1)] {
+                compare(&mut grid, x, y, ox, oy);
+            }
+        }
+        for x in (0..w).rev() {
+            compare(&mut grid, x, y, 1, 0);
+        }
+    }
+    for y in (0..h).rev() {
+        for x in (0..w).rev() {
+            for (ox, oy) in [(1, 0), (0, 1), (-1, 1), (1, 1)] {
+                compare(&mut grid, x, y, ox, oy);
+            }
+        }
+        for x in 0..w {
+            compare(&mut grid, x, y, -1, 0);
+        }
+    }
+
+    grid.into_iter().map(|offset| if offset == FAR { f32::MAX } else { (len(offset) as f64).sqrt() as f32 }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(seeds: &[bool], w: usize, h: usize) -> Vec<f32> {
+        (0..w * h)
+            .map(|i| {
+                let (x, y) = ((i % w) as f32, (i / w) as f32);
+                (0..w * h)
+                    .filter(|&j| seeds[j])
+                    .map(|j| ((x - (j % w) as f32).powi(2) + (y - (j / w) as f32).powi(2)).sqrt())
+                    .fold(f32::MAX, f32::min)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn distance_transform_matches_brute_force() {
+        let (w, h): (usize, usize) = (24, 17);
+        // scattered seeds from a small LCG, plus a blob
+        let mut state = 12345u32;
+        let seeds: Vec<bool> = (0..w * h)
+            .map(|i| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                (state >> 16).is_multiple_of(29) || ((i % w).abs_diff(6) < 3 && (i / w).abs_diff(8) < 4)
+            })
+            .collect();
+
+        let fast = distance_transform(&seeds, w, h);
+        for (fast, exact) in fast.iter().zip(brute_force(&seeds, w, h)) {
+            assert!((fast - exact).abs() < 0.1, "{} vs {}", fast, exact);
+        }
+    }
+
+    #[test]
+    fn no_seeds_is_infinitely_far() {
+        assert!(distance_transform(&[false; 6], 3, 2).iter().all(|&distance| distance == f32::MAX));
+    }
+
+    #[test]
+    fn sdf_is_centered_on_the_outline() {
+        // 4x4 square in an 8x8 bitmap
+        let coverage: Vec<u8> = (0..64).map(|i| if (2..6).contains(&(i % 8)) && (2..6).contains(&(i / 8)) { 255 } else { 0 }).collect();
+        let spread = 4;
+        let field = signed_distance_field(&coverage, 8, 8, spread);
+        let w = 8 + 2 * spread as usize;
+        assert_eq!(field.len(), w * w);
+
+        let at = |x: usize, y: usize| field[(y + spread as usize) * w + x + spread as usize];
+        // pixels on either side of the edge are half a pixel from it
+        assert!(at(2, 4) > 128 && at(1, 4) < 128);
+        assert_eq!(at(2, 4) as i32 - 128, 127 - at(1, 4) as i32);
+        assert!(at(3, 4) > at(2, 4));
+        assert_eq!(field[0], 0);
+    }
+}
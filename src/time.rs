@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Instant;
+
+/// Frame timing for the main loop, `tick` once at the start of every frame.
+#[derive(Debug, Clone)]
+pub struct Time {
+    start: Instant,
+    last: Instant,
+    /// seconds since the previous tick
+    pub delta: f32,
+    /// seconds since creation
+    pub elapsed: f32,
+    /// ticks so far
+    pub frame: u64,
+}
+
+#[allow(dead_code)]
+impl Time {
+    /// Longest `delta` reported, so a hitch (window drag, breakpoint) doesn't teleport
+    /// everything moving by `speed * delta`.
+    pub const MAX_DELTA: f32 = 0.25;
+
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self { start: now, last: now, delta: 0.0, elapsed: 0.0, frame: 0 }
+    }
+
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        self.delta = (now - self.last).as_secs_f32().min(Self::MAX_DELTA);
+        self.elapsed = (now - self.start).as_secs_f32();
+        self.last = now;
+        self.frame += 1;
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Frame time statistics over the last `window` frames, feed it `Time::delta` every frame.
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    frames: VecDeque<f32>,
+    window: usize,
+    sum: f32,
+    /// prefix of the window title the statistics are appended to, `None` leaves the title alone
+    title: Option<String>,
+    since_title: f32,
+}
+
+#[allow(dead_code)]
+impl FrameStats {
+    /// Seconds between title updates, any faster and the numbers are unreadable.
+    pub const TITLE_INTERVAL: f32 = 0.5;
+
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self { frames: VecDeque::with_capacity(window), window, sum: 0.0, title: None, since_title: 0.0 }
+    }
+
+    /// Shows the statistics in the window title after `title`, see `update_title`.
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    pub fn record(&mut self, delta: f32) {
+        if self.frames.len() == self.window {
+            if let Some(oldest) = self.frames.pop_front() {
+                self.sum -= oldest;
+            }
+        }
+        self.frames.push_back(delta);
+        self.sum += delta;
+        self.since_title += delta;
+    }
+
+    /// Frames currently in the window, fewer than `window` right after start.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Shortest frame time in seconds.
+    pub fn min(&self) -> f32 {
+        self.frames.iter().copied().reduce(f32::min).unwrap_or(0.0)
+    }
+
+    /// Average frame time in seconds.
+    pub fn avg(&self) -> f32 {
+        if self.frames.is_empty() {
+            return 0.0;
+        }
+        self.sum / self.frames.len() as f32
+    }
+
+    /// Longest frame time in seconds.
+    pub fn max(&self) -> f32 {
+        self.frames.iter().copied().reduce(f32::max).unwrap_or(0.0)
+    }
+
+    /// Frames per second from the average frame time.
+    pub fn fps(&self) -> f32 {
+        let avg = self.avg();
+        if avg > 0.0 { 1.0 / avg } else { 0.0 }
+    }
+
+    /// Passes the title with the statistics to `set_title`, which writes it into the window,
+    /// at most every `TITLE_INTERVAL` seconds. Does nothing unless created `with_title`.
+    pub fn update_title(&mut self, set_title: impl FnOnce(&str)) {
+        let Some(title) = &self.title else {
+            return;
+        };
+        if self.since_title < Self::TITLE_INTERVAL {
+            return;
+        }
+        self.since_title = 0.0;
+        set_title(&format!("{} - {}", title, self));
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.0} fps, {:.2}/{:.2}/{:.2} ms",
+            self.fps(), self.min() * 1000.0, self.avg() * 1000.0, self.max() * 1000.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oldest_frames_leave_the_window() {
+        let mut stats = FrameStats::new(3);
+        for delta in [0.1, 0.02, 0.03, 0.04] {
+            stats.record(delta);
+        }
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.min(), 0.02);
+        assert_eq!(stats.max(), 0.04);
+        assert!((stats.avg() - 0.03).abs() < 1e-6);
+        assert!((stats.fps() - 1.0 / 0.03).abs() < 1e-2);
+    }
+
+    #[test]
+    fn empty_stats_are_zero() {
+        let stats = FrameStats::new(0);
+        assert!(stats.is_empty());
+        assert_eq!([stats.min(), stats.avg(), stats.max(), stats.fps()], [0.0; 4]);
+    }
+
+    #[test]
+    fn title_updates_are_throttled() {
+        let mut updates = Vec::new();
+        let mut stats = FrameStats::new(10).with_title("demo");
+        for _ in 0..12 {
+            stats.record(0.1);
+            stats.update_title(|title| updates.push(title.to_string()));
+        }
+        // once after about 0.5 s and once after about 1 s
+        assert_eq!(updates.len(), 2);
+        assert!(updates[0].starts_with("demo - 10 fps"), "{}", updates[0]);
+
+        let mut untitled = FrameStats::new(10);
+        untitled.record(1.0);
+        untitled.update_title(|_| panic!("no title to update"));
+    }
+}
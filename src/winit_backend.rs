@@ -0,0 +1,358 @@
+use std::ffi::CString;
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+use glutin::config::ConfigTemplateBuilder;
+use glutin::context::{ContextApi, ContextAttributesBuilder, GlProfile, PossiblyCurrentContext, Version};
+use glutin::display::{Display, DisplayApiPreference, GlDisplay};
+use glutin::prelude::{GlSurface, NotCurrentGlContext};
+use glutin::surface::{Surface, SurfaceAttributesBuilder, SwapInterval, WindowSurface};
+use glutin_winit::{GlWindow, GlutinEventLoop};
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use winit::application::ApplicationHandler;
+use winit::dpi::LogicalSize;
+use winit::event::{ElementState, MouseScrollDelta, WindowEvent as WinitEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
+use winit::platform::pump_events::{EventLoopExtPumpEvents, PumpStatus};
+use winit::window::{Window, WindowId};
+
+use crate::backend::Backend;
+use crate::error::WrapperError;
+use crate::event::{Action, Key, Modifiers, MouseButton, WindowEvent};
+use crate::gl_ext;
+use crate::wrapper::{context_version, context_versions, FALLBACK_VERSIONS};
+
+/// Pixels a line of `MouseScrollDelta::PixelDelta` scrolling counts as, to report it in the
+/// wheel steps `WindowEvent::Scroll` uses.
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// Window and context from winit and glutin, for builds that can't link GLFW's C library
+/// (build without the default `glfw` feature).
+#[allow(dead_code)]
+pub struct WinitBackend {
+    // fields drop in order: the context goes before the surface, both before their window
+    context: PossiblyCurrentContext,
+    surface: Surface<WindowSurface>,
+    window: Window,
+    event_loop: EventLoop<()>,
+    state: PumpState,
+    /// version of the created context, may be higher than requested
+    pub gl_version: (u32, u32),
+}
+
+#[allow(dead_code)]
+impl WinitBackend {
+    /// Context of the highest version in `FALLBACK_VERSIONS` the driver has, like `Setup::new`.
+    pub fn new(width: u32, height: u32, title: &str) -> Result<Self, WrapperError> {
+        Self::with_version(width, height, title, FALLBACK_VERSIONS[0])
+    }
+
+    /// Core profile context of `version` or, failing that, the lower `FALLBACK_VERSIONS`.
+    /// `gl_version` tells which one was obtained.
+    pub fn with_version(width: u32, height: u32, title: &str, version: (u32, u32)) -> Result<Self, WrapperError> {
+        let error = |message: &str, err: &dyn std::fmt::Display| WrapperError::ContextCreation(format!("{}: {}", message, err));
+
+        let event_loop = EventLoop::new().map_err(|err| error("Could not create event loop", &err))?;
+        let attributes = Window::default_attributes()
+            .with_title(title)
+            .with_inner_size(LogicalSize::new(width, height));
+
+        // WGL only finds pixel formats for an existing window, elsewhere the window is
+        // created for the config, e.g. with its X11 visual
+        let early_window = if cfg!(windows) {
+            let window = GlutinEventLoop::create_window(&event_loop, attributes.clone());
+            Some(window.map_err(|err| error("Failed to create winit window", &err))?)
+        } else {
+            None
+        };
+        let raw_window = match &early_window {
+            Some(window) => Some(window.window_handle().map_err(|err| error("No window handle", &err))?.as_raw()),
+            None => None,
+        };
+
+        let display_handle = event_loop.glutin_display_handle().map_err(|err| error("No display handle", &err))?.as_raw();
+        let display = unsafe { Display::new(display_handle, display_preference(raw_window)) }
+            .map_err(|err| error("Could not create GL display", &err))?;
+
+        let mut template = ConfigTemplateBuilder::new();
+        if let Some(raw_window) = raw_window {
+            template = template.compatible_with_native_window(raw_window);
+        }
+        // the first config is the display's preferred one
+        let config = unsafe { display.find_configs(template.build()) }
+            .map_err(|err| error("Could not list GL configs", &err))?
+            .next()
+            .ok_or_else(|| WrapperError::ContextCreation("No GL config matches the display.".to_string()))?;
+
+        let window = match early_window {
+            Some(window) => window,
+            None => glutin_winit::finalize_window(&event_loop, attributes, &config)
+                .map_err(|err| error("Failed to create winit window", &err))?,
+        };
+
+        let handle = window.window_handle().map_err(|err| error("No window handle", &err))?.as_raw();
+        let context = context_versions(version, true)
+            .find_map(|(major, minor)| {
+                let context_attributes = ContextAttributesBuilder::new()
+                    .with_context_api(ContextApi::OpenGl(Some(Version::new(major as u8, minor as u8))))
+                    .with_profile(GlProfile::Core)
+                    .build(Some(handle));
+                unsafe { display.create_context(&config, &context_attributes) }.ok()
+            })
+            .ok_or_else(|| {
+                WrapperError::ContextCreation(format!("Could not create GL context with GL {}.{} or lower.", version.0, version.1))
+            })?;
+
+        let surface_attributes = window
+            .build_surface_attributes(SurfaceAttributesBuilder::new())
+            .map_err(|err| error("No window handle", &err))?;
+        let surface = unsafe { display.create_window_surface(&config, &surface_attributes) }
+            .map_err(|err| error("Could not create window surface", &err))?;
+        let context = context.make_current(&surface).map_err(|err| error("Could not make context current", &err))?;
+
+        // without vsync frames are merely presented as fast as they're drawn
+        if let Err(_err) = surface.set_swap_interval(&context, SwapInterval::Wait(NonZeroU32::MIN)) {
+            #[cfg(feature = "debug")]
+            log::warn!("VSync unavailable: {}", _err);
+        }
+
+        gl::load_with(|symbol| match CString::new(symbol) {
+            Ok(symbol) => display.get_proc_address(&symbol),
+            Err(_) => std::ptr::null(),
+        });
+        gl_ext::load_with(|symbol| match CString::new(symbol) {
+            Ok(symbol) => display.get_proc_address(&symbol),
+            Err(_) => std::ptr::null(),
+        });
+
+        Ok(Self {
+            context, surface, window, event_loop, state: PumpState::default(), gl_version: context_version(),
+        })
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+}
+
+impl Backend for WinitBackend {
+    fn should_close(&self) -> bool {
+        self.state.should_close
+    }
+
+    fn set_should_close(&mut self, close: bool) {
+        self.state.should_close = close;
+    }
+
+    fn framebuffer_size(&self) -> (i32, i32) {
+        let size = self.window.inner_size();
+        (size.width as i32, size.height as i32)
+    }
+
+    fn poll_events(&mut self) -> Vec<WindowEvent> {
+        if let PumpStatus::Exit(_) = self.event_loop.pump_app_events(Some(Duration::ZERO), &mut self.state) {
+            self.state.should_close = true;
+        }
+
+        if std::mem::take(&mut self.state.resized) {
+            self.window.resize_surface(&self.surface, &self.context);
+        }
+        std::mem::take(&mut self.state.events)
+    }
+
+    /// A failed swap only loses the frame, the next one is tried all the same.
+    fn swap_buffers(&mut self) {
+        if let Err(_err) = self.surface.swap_buffers(&self.context) {
+            #[cfg(feature = "debug")]
+            log::error!("Swapping buffers failed: {}", _err);
+        }
+    }
+}
+
+/// The display APIs glutin-winit's `DisplayBuilder` would try, in the same order.
+fn display_preference(_raw_window: Option<RawWindowHandle>) -> DisplayApiPreference {
+    #[cfg(windows)]
+    let preference = DisplayApiPreference::WglThenEgl(_raw_window);
+    #[cfg(target_os = "macos")]
+    let preference = DisplayApiPreference::Cgl;
+    #[cfg(target_os = "android")]
+    let preference = DisplayApiPreference::Egl;
+    #[cfg(all(unix, not(any(target_os = "macos", target_os = "android"))))]
+    let preference = DisplayApiPreference::EglThenGlx(Box::new(winit::platform::x11::register_xlib_error_hook));
+    preference
+}
+
+/// Collects translated events while the event loop is pumped.
+struct PumpState {
+    events: Vec<WindowEvent>,
+    modifiers: Modifiers,
+    should_close: bool,
+    resized: bool,
+}
+
+impl Default for PumpState {
+    fn default() -> Self {
+        Self { events: Vec::new(), modifiers: Modifiers::empty(), should_close: false, resized: false }
+    }
+}
+
+impl ApplicationHandler for PumpState {
+    fn resumed(&mut self, _event_loop: &ActiveEventLoop) {}
+
+    fn window_event(&mut self, _event_loop: &ActiveEventLoop, _window: WindowId, event: WinitEvent) {
+        let event = match event {
+            WinitEvent::CloseRequested => {
+                self.should_close = true;
+                WindowEvent::Close
+            }
+            WinitEvent::Resized(size) => {
+                self.resized = true;
+                WindowEvent::FramebufferSize(size.width as i32, size.height as i32)
+            }
+            WinitEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers_from(modifiers.state());
+                return;
+            }
+            WinitEvent::KeyboardInput { event, .. } => {
+                let PhysicalKey::Code(code) = event.physical_key else {
+                    return;
+                };
+                let Some(key) = key_from(code) else {
+                    return;
+                };
+                let action = match (event.state, event.repeat) {
+                    (ElementState::Pressed, false) => Action::Press,
+                    (ElementState::Pressed, true) => Action::Repeat,
+                    (ElementState::Released, _) => Action::Release,
+                };
+                WindowEvent::Key { key, scancode: 0, action, modifiers: self.modifiers }
+            }
+            WinitEvent::CursorMoved { position, .. } => WindowEvent::CursorPos(position.x, position.y),
+            WinitEvent::MouseInput { state, button, .. } => {
+                let Some(button) = mouse_button_from(button) else {
+                    return;
+                };
+                let action = if state == ElementState::Pressed { Action::Press } else { Action::Release };
+                WindowEvent::MouseButton { button, action, modifiers: self.modifiers }
+            }
+            WinitEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(x, y) => WindowEvent::Scroll(x as f64, y as f64),
+                MouseScrollDelta::PixelDelta(delta) => {
+                    WindowEvent::Scroll(delta.x / PIXELS_PER_LINE, delta.y / PIXELS_PER_LINE)
+                }
+            },
+            WinitEvent::Focused(focused) => WindowEvent::Focus(focused),
+            _ => return,
+        };
+        self.events.push(event);
+    }
+}
+
+fn modifiers_from(state: ModifiersState) -> Modifiers {
+    let mut modifiers = Modifiers::empty();
+    modifiers.set(Modifiers::SHIFT, state.shift_key());
+    modifiers.set(Modifiers::CONTROL, state.control_key());
+    modifiers.set(Modifiers::ALT, state.alt_key());
+    modifiers.set(Modifiers::SUPER, state.super_key());
+    modifiers
+}
+
+fn mouse_button_from(button: winit::event::MouseButton) -> Option<MouseButton> {
+    match button {
+        winit::event::MouseButton::Left => Some(MouseButton::Left),
+        winit::event::MouseButton::Right => Some(MouseButton::Right),
+        winit::event::MouseButton::Middle => Some(MouseButton::Middle),
+        winit::event::MouseButton::Back => Some(MouseButton::Back),
+        winit::event::MouseButton::Forward => Some(MouseButton::Forward),
+        winit::event::MouseButton::Other(_) => None,
+    }
+}
+
+fn key_from(code: KeyCode) -> Option<Key> {
+    Some(match code {
+        KeyCode::KeyA => Key::A,
+        KeyCode::KeyB => Key::B,
+        KeyCode::KeyC => Key::C,
+        KeyCode::KeyD => Key::D,
+        KeyCode::KeyE => Key::E,
+        KeyCode::KeyF => Key::F,
+        KeyCode::KeyG => Key::G,
+        KeyCode::KeyH => Key::H,
+        KeyCode::KeyI => Key::I,
+        KeyCode::KeyJ => Key::J,
+        KeyCode::KeyK => Key::K,
+        KeyCode::KeyL => Key::L,
+        KeyCode::KeyM => Key::M,
+        KeyCode::KeyN => Key::N,
+        KeyCode::KeyO => Key::O,
+        KeyCode::KeyP => Key::P,
+        KeyCode::KeyQ => Key::Q,
+        KeyCode::KeyR => Key::R,
+        KeyCode::KeyS => Key::S,
+        KeyCode::KeyT => Key::T,
+        KeyCode::KeyU => Key::U,
+        KeyCode::KeyV => Key::V,
+        KeyCode::KeyW => Key::W,
+        KeyCode::KeyX => Key::X,
+        KeyCode::KeyY => Key::Y,
+        KeyCode::KeyZ => Key::Z,
+        KeyCode::Digit0 => Key::Num0,
+        KeyCode::Digit1 => Key::Num1,
+        KeyCode::Digit2 => Key::Num2,
+        KeyCode::Digit3 => Key::Num3,
+        KeyCode::Digit4 => Key::Num4,
+        KeyCode::Digit5 => Key::Num5,
+        KeyCode::Digit6 => Key::Num6,
+        KeyCode::Digit7 => Key::Num7,
+        KeyCode::Digit8 => Key::Num8,
+        KeyCode::Digit9 => Key::Num9,
+        KeyCode::F1 => Key::F1,
+        KeyCode::F2 => Key::F2,
+        KeyCode::F3 => Key::F3,
+        KeyCode::F4 => Key::F4,
+        KeyCode::F5 => Key::F5,
+        KeyCode::F6 => Key::F6,
+        KeyCode::F7 => Key::F7,
+        KeyCode::F8 => Key::F8,
+        KeyCode::F9 => Key::F9,
+        KeyCode::F10 => Key::F10,
+        KeyCode::F11 => Key::F11,
+        KeyCode::F12 => Key::F12,
+        KeyCode::ArrowUp => Key::Up,
+        KeyCode::ArrowDown => Key::Down,
+        KeyCode::ArrowLeft => Key::Left,
+        KeyCode::ArrowRight => Key::Right,
+        KeyCode::Space => Key::Space,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Escape => Key::Escape,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::Minus => Key::Minus,
+        KeyCode::Equal => Key::Equal,
+        KeyCode::BracketLeft => Key::LeftBracket,
+        KeyCode::BracketRight => Key::RightBracket,
+        KeyCode::Backslash => Key::Backslash,
+        KeyCode::Semicolon => Key::Semicolon,
+        KeyCode::Quote => Key::Apostrophe,
+        KeyCode::Backquote => Key::GraveAccent,
+        KeyCode::Comma => Key::Comma,
+        KeyCode::Period => Key::Period,
+        KeyCode::Slash => Key::Slash,
+        KeyCode::ShiftLeft => Key::LeftShift,
+        KeyCode::ShiftRight => Key::RightShift,
+        KeyCode::ControlLeft => Key::LeftControl,
+        KeyCode::ControlRight => Key::RightControl,
+        KeyCode::AltLeft => Key::LeftAlt,
+        KeyCode::AltRight => Key::RightAlt,
+        KeyCode::SuperLeft => Key::LeftSuper,
+        KeyCode::SuperRight => Key::RightSuper,
+        _ => return None,
+    })
+}
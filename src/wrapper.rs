@@ -1,155 +1,2839 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "glfw")]
 use std::sync::mpsc::Receiver;
 
-use gl::types::{GLbitfield, GLenum, GLsizei, GLuint};
-use glfw::{Action, Context, fail_on_errors, Key, SwapInterval, WindowType};
+use gl::types::{GLbitfield, GLenum, GLint, GLsizei, GLsync, GLuint};
+#[cfg(feature = "glfw")]
+use glfw::{Context, SwapInterval, WindowType};
+
+use crate::error::WrapperError;
+use crate::gl_ext;
+use crate::input::InputMap;
+use crate::preprocessor::{preprocess_file, PreprocessedSource};
+use crate::reflection::ProgramReflection;
 
 pub type Vertex = [f32; 3];
 pub type BiIndices = [u32; 2];
 pub type TriIndices = [u32; 3];
 
-pub const VAO_LOAD_ERROR: &str = "Could not make the VAO";
-pub const VBO_LOAD_ERROR: &str = "Could not make the VBO";
-pub const EBO_LOAD_ERROR: &str = "Could not make the EBO";
+/// Runs a GL call and, in debug builds, panics if it raised a GL error, naming the
+/// function and the place of the call. Every GL call in this module goes through it, except
+/// the `glDelete*` calls of `Drop` impls, which may run while unwinding. The rest of the
+/// crate uses it for all of its GL calls the same way. Has to be used in an unsafe block:
+/// ```ignore
+/// unsafe { checked!(gl::DrawArrays(gl::TRIANGLES, 0, 3)) };
+/// ```
+#[macro_export]
+macro_rules! checked {
+    ($($function:ident)::+ ($($arg:expr),* $(,)?)) => {{
+        let result = $($function)::+($($arg),*);
+        $crate::wrapper::check_errors(concat!($(stringify!($function)),+));
+        result
+    }};
+}
+
+/// Drains the GL error flags and panics if any was set. Does nothing in release builds,
+/// as `glGetError` stalls the pipeline.
+#[track_caller]
+pub fn check_errors(function: &str) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+
+    let mut errors = Vec::new();
+    loop {
+        let error = unsafe { gl::GetError() };
+        if error == gl::NO_ERROR || errors.len() >= 8 {
+            break;
+        }
+        errors.push(crate::error::gl_error_name(error));
+    }
+
+    if !errors.is_empty() {
+        panic!("{} raised {}", function, errors.join(", "));
+    }
+}
 
 // useful functions wrappers
 
+#[allow(dead_code)]
 pub fn clear_color(r: f32, g: f32, b: f32, a: f32) {
-    unsafe { gl::ClearColor(r, g, b, a) }
+    unsafe { checked!(gl::ClearColor(r, g, b, a)) }
 }
 
 pub fn buffer_data(buf_type: BufferType, data: &[u8], usage: GLenum) {
     unsafe {
-        gl::BufferData(
+        checked!(gl::BufferData(
             buf_type as GLenum,
             data.len().try_into().unwrap(),
             data.as_ptr().cast(),
             usage,
-        );
+        ));
     }
 }
 
+pub fn buffer_vertices<V: VertexLike>(buf_type: BufferType, vertices: &[V], usage: GLenum) {
+    buffer_data(buf_type, bytemuck::cast_slice(vertices), usage);
+}
+
+#[allow(dead_code)]
 pub fn update_buffer_data(buf_type: BufferType, data: &[u8]) {
     unsafe {
-        gl::BufferSubData(
+        checked!(gl::BufferSubData(
             buf_type as GLenum,
             0,
             data.len().try_into().unwrap(),
             data.as_ptr().cast(),
-        )
+        ));
     }
 }
 
 #[allow(dead_code)]
 pub fn clear_buffer_binding(buf_type: BufferType) {
-    unsafe { gl::BindBuffer(buf_type as GLenum, 0) }
+    unsafe { checked!(gl::BindBuffer(buf_type as GLenum, 0)) }
 }
 
 #[allow(dead_code)]
 pub fn clear_array_binding() {
-    unsafe { gl::BindVertexArray(0) }
+    unsafe { checked!(gl::BindVertexArray(0)) }
+}
+
+#[allow(dead_code)]
+pub fn clear_shaders() { unsafe { checked!(gl::UseProgram(0)) } }
+
+/// Orders incoherent shader writes (SSBO, images) before the accesses named by `barriers`,
+/// e.g. `gl::SHADER_STORAGE_BARRIER_BIT`. Needs GL 4.2+.
+#[allow(dead_code)]
+pub fn memory_barrier(barriers: GLbitfield) {
+    unsafe { checked!(gl::MemoryBarrier(barriers)) }
+}
+
+/// SSBO writes of a dispatch become visible to following shader reads and buffer readbacks.
+#[allow(dead_code)]
+pub fn storage_barrier() {
+    memory_barrier(gl::SHADER_STORAGE_BARRIER_BIT | gl::BUFFER_UPDATE_BARRIER_BIT);
+}
+
+/// `imageStore` writes of a dispatch become visible to image loads and texture sampling.
+#[allow(dead_code)]
+pub fn image_barrier() {
+    memory_barrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT | gl::TEXTURE_FETCH_BARRIER_BIT);
+}
+
+/// Version of the current context as reported by the driver.
+pub fn context_version() -> (u32, u32) {
+    let (mut major, mut minor) = (0, 0);
+    unsafe {
+        checked!(gl::GetIntegerv(gl::MAJOR_VERSION, &mut major));
+        checked!(gl::GetIntegerv(gl::MINOR_VERSION, &mut minor));
+    }
+    (major as u32, minor as u32)
+}
+
+/// Vendor, renderer and version strings of the current context on one line, e.g. to tell
+/// whether cached program binaries still match the driver.
+#[allow(dead_code)]
+pub fn driver_string() -> String {
+    let string = |name| {
+        let ptr = unsafe { checked!(gl::GetString(name)) };
+        if ptr.is_null() {
+            String::new()
+        } else {
+            unsafe { CStr::from_ptr(ptr as *const _) }.to_string_lossy().into_owned()
+        }
+    };
+    format!("{} | {} | {}", string(gl::VENDOR), string(gl::RENDERER), string(gl::VERSION))
+}
+
+/// Tried from the first when creating a `Setup`, highest first: 4.6 is the latest, 4.3 adds
+/// compute shaders and debug output, 3.3 is the minimum the wrapper needs.
+pub const FALLBACK_VERSIONS: [(u32, u32); 3] = [(4, 6), (4, 3), (3, 3)];
+
+/// Versions to try creating a context with: `highest`, then with `fallback` the lower
+/// `FALLBACK_VERSIONS`.
+pub fn context_versions(highest: (u32, u32), fallback: bool) -> impl Iterator<Item = (u32, u32)> {
+    std::iter::once(highest).chain(FALLBACK_VERSIONS.into_iter().filter(move |version| fallback && *version < highest))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlProfile {
+    Core,
+    Compatibility,
+}
+
+/// Profile of the current context. Contexts before 3.2 have no profiles and report
+/// `Compatibility`.
+pub fn context_profile() -> GlProfile {
+    let mut mask = 0;
+    unsafe { checked!(gl::GetIntegerv(gl::CONTEXT_PROFILE_MASK, &mut mask)) };
+    if mask as GLenum & gl::CONTEXT_CORE_PROFILE_BIT != 0 {
+        GlProfile::Core
+    } else {
+        GlProfile::Compatibility
+    }
+}
+
+/// Extensions of a context, read once since `glGetStringi` over all of them isn't cheap.
+/// The convenience checks also hold when the feature is core in the context's version.
+#[derive(Debug, Clone, Default)]
+pub struct Extensions {
+    names: HashSet<String>,
+    version: (u32, u32),
+}
+
+#[allow(dead_code)]
+impl Extensions {
+    /// Reads the extensions of the current context.
+    pub fn query() -> Self {
+        let mut count = 0;
+        unsafe { checked!(gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count)) };
+
+        let names = (0..count.max(0) as GLuint)
+            .filter_map(|i| {
+                let name = unsafe { checked!(gl::GetStringi(gl::EXTENSIONS, i)) };
+                (!name.is_null()).then(|| unsafe { CStr::from_ptr(name as *const _) }.to_string_lossy().into_owned())
+            })
+            .collect();
+        Self { names, version: context_version() }
+    }
+
+    /// Full name including the prefix, e.g. "GL_ARB_direct_state_access".
+    pub fn has(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// `glCreate*` and `glNamed*` functions, core in 4.5.
+    pub fn direct_state_access(&self) -> bool {
+        self.version >= (4, 5) || self.has("GL_ARB_direct_state_access")
+    }
+
+    /// Immutable buffer storage and persistent mapping, core in 4.4.
+    pub fn buffer_storage(&self) -> bool {
+        self.version >= (4, 4) || self.has("GL_ARB_buffer_storage")
+    }
+
+    /// Debug output and object labels, core in 4.3.
+    pub fn debug(&self) -> bool {
+        self.version >= (4, 3) || self.has("GL_KHR_debug")
+    }
+
+    /// SPIR-V shader modules, core in 4.6.
+    pub fn spirv(&self) -> bool {
+        self.version >= (4, 6) || self.has("GL_ARB_gl_spirv")
+    }
+
+    /// `TEXTURE_MAX_ANISOTROPY`, core in 4.6.
+    pub fn anisotropic_filtering(&self) -> bool {
+        self.version >= (4, 6)
+            || self.has("GL_ARB_texture_filter_anisotropic")
+            || self.has("GL_EXT_texture_filter_anisotropic")
+    }
+
+    /// BC1-3 (DXT) compressed textures, never core but on every desktop driver.
+    pub fn texture_compression_s3tc(&self) -> bool {
+        self.has("GL_EXT_texture_compression_s3tc")
+    }
+
+    /// sRGB variants of the S3TC formats.
+    pub fn texture_compression_s3tc_srgb(&self) -> bool {
+        self.texture_compression_s3tc()
+            && (self.has("GL_EXT_texture_sRGB") || self.has("GL_EXT_texture_compression_s3tc_srgb"))
+    }
+
+    /// BC6H/BC7 compressed textures, core in 4.2.
+    pub fn texture_compression_bptc(&self) -> bool {
+        self.version >= (4, 2) || self.has("GL_ARB_texture_compression_bptc")
+    }
+
+    /// ETC2/EAC compressed textures, core in 4.3.
+    pub fn texture_compression_etc2(&self) -> bool {
+        self.version >= (4, 3) || self.has("GL_ARB_ES3_compatibility")
+    }
+}
+
+/// Names a GL object for debuggers and KHR_debug messages, does nothing when `glObjectLabel`
+/// isn't available (GL < 4.3 without KHR_debug). The object must have been bound once,
+/// names from `glGen*` alone aren't objects yet.
+#[allow(dead_code)]
+pub fn object_label(identifier: GLenum, id: GLuint, label: &str) {
+    if !gl::ObjectLabel::is_loaded() {
+        return;
+    }
+
+    unsafe { checked!(gl::ObjectLabel(identifier, id, label.len() as GLsizei, label.as_ptr() as *const _)) }
+}
+
+/// Turns linear to sRGB encoding of writes to sRGB render targets on or off. Targets with
+/// linear formats are never affected.
+#[allow(dead_code)]
+pub fn framebuffer_srgb(enable: bool) {
+    unsafe {
+        if enable {
+            checked!(gl::Enable(gl::FRAMEBUFFER_SRGB))
+        } else {
+            checked!(gl::Disable(gl::FRAMEBUFFER_SRGB))
+        }
+    }
+}
+
+fn default_framebuffer_is_srgb() -> bool {
+    let mut encoding = 0;
+    unsafe {
+        checked!(gl::GetFramebufferAttachmentParameteriv(
+            gl::FRAMEBUFFER, gl::BACK_LEFT, gl::FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING, &mut encoding,
+        ))
+    };
+    encoding as GLenum == gl::SRGB
+}
+
+/// Turns multisample rasterization on or off for multisampled render targets, the default
+/// framebuffer included when the window was created with samples.
+#[allow(dead_code)]
+pub fn multisample(enable: bool) {
+    unsafe {
+        if enable {
+            checked!(gl::Enable(gl::MULTISAMPLE))
+        } else {
+            checked!(gl::Disable(gl::MULTISAMPLE))
+        }
+    }
 }
 
+/// Highest sample count renderbuffers can be allocated with.
 #[allow(dead_code)]
-pub fn clear_shaders() { unsafe { gl::UseProgram(0) } }
+pub fn max_samples() -> u32 {
+    let mut samples = 0;
+    unsafe { checked!(gl::GetIntegerv(gl::MAX_SAMPLES, &mut samples)) };
+    samples.max(0) as u32
+}
 
 pub fn clear_mask(mask: GLbitfield) {
-    unsafe { gl::Clear(mask) }
+    unsafe { checked!(gl::Clear(mask)) };
 }
 
-pub fn draw_triangles(vertices_count: GLsizei) {
-    draw(gl::TRIANGLES, vertices_count);
+/// Clears the color and depth buffers of the bound framebuffer to the given values,
+/// `None` leaves that buffer untouched.
+#[allow(dead_code)]
+pub fn clear(color: Option<[f32; 4]>, depth: Option<f32>) {
+    let mut mask = 0;
+    unsafe {
+        if let Some([r, g, b, a]) = color {
+            checked!(gl::ClearColor(r, g, b, a));
+            mask |= gl::COLOR_BUFFER_BIT;
+        }
+        if let Some(depth) = depth {
+            checked!(gl::ClearDepth(depth as f64));
+            mask |= gl::DEPTH_BUFFER_BIT;
+        }
+    }
+    if mask != 0 {
+        clear_mask(mask);
+    }
 }
 
-pub fn draw_lines(vertices_count: GLsizei) {
-    draw(gl::LINES, vertices_count);
+/// Clears the stencil buffer of the bound framebuffer. The stencil write mask applies, so
+/// `StencilState::default().apply()` first when a state with a partial mask is active.
+#[allow(dead_code)]
+pub fn clear_stencil(value: i32) {
+    unsafe { checked!(gl::ClearStencil(value)) };
+    clear_mask(gl::STENCIL_BUFFER_BIT);
+}
+
+/// `None` disables culling.
+#[allow(dead_code)]
+pub fn cull_face(face: Option<CullFace>) {
+    unsafe {
+        match face {
+            Some(face) => {
+                checked!(gl::Enable(gl::CULL_FACE));
+                checked!(gl::CullFace(face as GLenum));
+            }
+            None => checked!(gl::Disable(gl::CULL_FACE)),
+        }
+    }
 }
 
-fn draw(mode: GLenum, v_count: GLsizei) {
-    unsafe { gl::DrawElements(mode, v_count, gl::UNSIGNED_INT, 0 as *const _); }
+/// Limits drawing and clearing to the `[x, y, width, height]` pixel rectangle, `None` disables it.
+#[allow(dead_code)]
+pub fn scissor(rect: Option<[i32; 4]>) {
+    unsafe {
+        match rect {
+            Some([x, y, width, height]) => {
+                checked!(gl::Enable(gl::SCISSOR_TEST));
+                checked!(gl::Scissor(x, y, width, height));
+            }
+            None => checked!(gl::Disable(gl::SCISSOR_TEST)),
+        }
+    }
 }
 
-// Structs begin here
+/// Winding order of the vertices of a front facing triangle as seen on screen.
+#[allow(dead_code)]
+pub fn front_face(winding: Winding) {
+    unsafe { checked!(gl::FrontFace(winding as GLenum)) }
+}
 
-pub struct VertexArray(pub GLuint);
+/// Applies to both faces, core profile has no separate front/back modes.
+pub fn polygon_mode(mode: PolygonMode) {
+    unsafe { checked!(gl::PolygonMode(gl::FRONT_AND_BACK, mode as GLenum)) }
+}
 
+/// Face culled right now, `None` when culling is off.
 #[allow(dead_code)]
-impl VertexArray {
-    pub fn new() -> Option<Self> {
-        let mut vao = 0;
-        unsafe {
-            gl::GenVertexArrays(1, &mut vao);
+pub fn current_cull_face() -> Option<CullFace> {
+    let mut face = 0;
+    unsafe {
+        if checked!(gl::IsEnabled(gl::CULL_FACE)) != gl::TRUE {
+            return None;
         }
+        checked!(gl::GetIntegerv(gl::CULL_FACE_MODE, &mut face));
+    }
+    match face as GLenum {
+        gl::FRONT => Some(CullFace::Front),
+        gl::FRONT_AND_BACK => Some(CullFace::FrontAndBack),
+        _ => Some(CullFace::Back),
+    }
+}
 
-        if vao != 0 {
-            Some(Self(vao))
+#[allow(dead_code)]
+pub fn current_polygon_mode() -> PolygonMode {
+    // some drivers still report front and back separately
+    let mut modes = [0; 2];
+    unsafe { checked!(gl::GetIntegerv(gl::POLYGON_MODE, modes.as_mut_ptr())) };
+    match modes[0] as GLenum {
+        gl::LINE => PolygonMode::Line,
+        gl::POINT => PolygonMode::Point,
+        _ => PolygonMode::Fill,
+    }
+}
+
+/// Pixel rectangle of the bound framebuffer drawn into, e.g. one tile of an atlas.
+#[allow(dead_code)]
+pub fn viewport(x: i32, y: i32, width: i32, height: i32) {
+    unsafe { checked!(gl::Viewport(x, y, width, height)) }
+}
+
+#[allow(dead_code)]
+pub fn depth_test(enable: bool) {
+    unsafe {
+        if enable {
+            checked!(gl::Enable(gl::DEPTH_TEST))
         } else {
-            None
+            checked!(gl::Disable(gl::DEPTH_TEST))
         }
     }
+}
 
-    pub fn bind(&self) {
-        unsafe { gl::BindVertexArray(self.0) }
+#[allow(dead_code)]
+pub fn depth_test_enabled() -> bool {
+    unsafe { checked!(gl::IsEnabled(gl::DEPTH_TEST)) == gl::TRUE }
+}
+
+#[allow(dead_code)]
+pub fn depth_func(func: CompareFunc) {
+    unsafe { checked!(gl::DepthFunc(func as GLenum)) }
+}
+
+#[allow(dead_code)]
+pub fn current_depth_func() -> CompareFunc {
+    let mut func = 0;
+    unsafe { checked!(gl::GetIntegerv(gl::DEPTH_FUNC, &mut func)) };
+    match func as GLenum {
+        gl::NEVER => CompareFunc::Never,
+        gl::EQUAL => CompareFunc::Equal,
+        gl::LEQUAL => CompareFunc::LessEqual,
+        gl::GREATER => CompareFunc::Greater,
+        gl::NOTEQUAL => CompareFunc::NotEqual,
+        gl::GEQUAL => CompareFunc::GreaterEqual,
+        gl::ALWAYS => CompareFunc::Always,
+        _ => CompareFunc::Less,
     }
+}
+
+/// With writes off fragments are still depth tested but don't update the depth buffer,
+/// e.g. for transparent geometry drawn after the opaque one.
+#[allow(dead_code)]
+pub fn depth_write(enable: bool) {
+    unsafe { checked!(gl::DepthMask(if enable { gl::TRUE } else { gl::FALSE })) }
+}
 
-    pub fn delete(&self) { unsafe { gl::DeleteVertexArrays(1, &self.0) } }
+#[allow(dead_code)]
+pub fn depth_write_enabled() -> bool {
+    let mut enabled = 0;
+    unsafe { checked!(gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut enabled)) };
+    enabled == gl::TRUE
+}
+
+#[allow(dead_code)]
+pub fn draw_triangles(vertices_count: GLsizei) {
+    draw_elements(PrimitiveMode::Triangles, vertices_count);
+}
+
+#[allow(dead_code)]
+pub fn draw_lines(vertices_count: GLsizei) {
+    draw_elements(PrimitiveMode::Lines, vertices_count);
+}
+
+pub fn draw_elements(mode: PrimitiveMode, v_count: GLsizei) {
+    unsafe { checked!(gl::DrawElements(mode as GLenum, v_count, gl::UNSIGNED_INT, std::ptr::null())) };
+}
+
+/// Draws `v_count` indices starting at index `first` of the bound element buffer.
+#[allow(dead_code)]
+pub fn draw_elements_from(mode: PrimitiveMode, v_count: GLsizei, first: usize) {
+    let offset = (first * size_of::<GLuint>()) as *const std::ffi::c_void;
+    unsafe { checked!(gl::DrawElements(mode as GLenum, v_count, gl::UNSIGNED_INT, offset)) };
+}
+
+/// Draws `count` vertices of the bound vertex array starting at `first`, without indices.
+#[allow(dead_code)]
+pub fn draw_arrays(mode: PrimitiveMode, first: GLint, count: GLsizei) {
+    unsafe { checked!(gl::DrawArrays(mode as GLenum, first, count)) };
+}
+
+/// Draws the bound vertex array `instances` times, shaders tell copies apart by
+/// `gl_InstanceID` or by per-instance attributes.
+pub fn draw_elements_instanced(mode: PrimitiveMode, v_count: GLsizei, instances: GLsizei) {
+    unsafe {
+        checked!(gl::DrawElementsInstanced(mode as GLenum, v_count, gl::UNSIGNED_INT, std::ptr::null(), instances))
+    };
+}
+
+#[allow(dead_code)]
+pub fn draw_triangles_instanced(vertices_count: GLsizei, instances: GLsizei) {
+    draw_elements_instanced(PrimitiveMode::Triangles, vertices_count, instances);
+}
+
+/// Feeds the bound vertex array to the tessellation stages in groups of `patch_size` vertices.
+/// Needs a GL 4.0+ context and a program with tessellation stages.
+#[allow(dead_code)]
+pub fn draw_patches(vertex_count: GLsizei, patch_size: GLint) {
+    unsafe {
+        checked!(gl::PatchParameteri(gl::PATCH_VERTICES, patch_size));
+        checked!(gl::DrawArrays(gl::PATCHES, 0, vertex_count));
+    }
 }
 
+// Structs begin here
+
+/// Comparison of the incoming value against the stored one, for depth `Less` passes when the
+/// new fragment is closer.
+#[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum BufferType {
-    Array = gl::ARRAY_BUFFER as isize,
-    ElementArray = gl::ELEMENT_ARRAY_BUFFER as isize,
+pub enum CompareFunc {
+    Never = gl::NEVER as isize,
+    Less = gl::LESS as isize,
+    Equal = gl::EQUAL as isize,
+    LessEqual = gl::LEQUAL as isize,
+    Greater = gl::GREATER as isize,
+    NotEqual = gl::NOTEQUAL as isize,
+    GreaterEqual = gl::GEQUAL as isize,
+    Always = gl::ALWAYS as isize,
 }
 
-pub struct ArrayBuffer(pub GLuint);
+/// What happens to the stored stencil value, `Replace` writes the reference value.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StencilOp {
+    Keep = gl::KEEP as isize,
+    Zero = gl::ZERO as isize,
+    Replace = gl::REPLACE as isize,
+    Increment = gl::INCR as isize,
+    IncrementWrap = gl::INCR_WRAP as isize,
+    Decrement = gl::DECR as isize,
+    DecrementWrap = gl::DECR_WRAP as isize,
+    Invert = gl::INVERT as isize,
+}
 
+/// Whole stencil configuration, set at once with `apply`. The test passes when
+/// `reference & read_mask` compared by `func` to `stored & read_mask` holds.
 #[allow(dead_code)]
-impl ArrayBuffer {
-    pub fn new() -> Option<Self> {
-        let mut vbo = 0;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilState {
+    pub func: CompareFunc,
+    pub reference: i32,
+    pub read_mask: u32,
+    pub write_mask: u32,
+    /// stencil test failed
+    pub stencil_fail: StencilOp,
+    /// stencil test passed, depth test failed
+    pub depth_fail: StencilOp,
+    /// both tests passed
+    pub pass: StencilOp,
+}
+
+impl Default for StencilState {
+    /// GL's initial state: everything passes, nothing is written.
+    fn default() -> Self {
+        Self {
+            func: CompareFunc::Always,
+            reference: 0,
+            read_mask: !0,
+            write_mask: !0,
+            stencil_fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl StencilState {
+    /// Marks every drawn fragment with `reference`, e.g. the object to be outlined
+    /// or the shape of a mirror.
+    pub fn write(reference: i32) -> Self {
+        Self { reference, pass: StencilOp::Replace, ..Self::default() }
+    }
+
+    /// Draws only where the stored value equals `reference`, leaving the buffer untouched.
+    pub fn equal(reference: i32) -> Self {
+        Self { func: CompareFunc::Equal, reference, write_mask: 0, ..Self::default() }
+    }
+
+    /// Draws only where the stored value differs from `reference`, e.g. the scaled up
+    /// outline around an object marked by `write`.
+    pub fn not_equal(reference: i32) -> Self {
+        Self { func: CompareFunc::NotEqual, reference, write_mask: 0, ..Self::default() }
+    }
+
+    /// Enables the stencil test with this configuration.
+    pub fn apply(&self) {
         unsafe {
-            gl::GenBuffers(1, &mut vbo);
+            checked!(gl::Enable(gl::STENCIL_TEST));
+            checked!(gl::StencilFunc(self.func as GLenum, self.reference, self.read_mask));
+            checked!(gl::StencilMask(self.write_mask));
+            checked!(gl::StencilOp(self.stencil_fail as GLenum, self.depth_fail as GLenum, self.pass as GLenum));
         }
+    }
 
-        if vbo != 0 {
-            Some(Self(vbo))
-        } else {
-            None
+    /// Disables the stencil test, the stored configuration is kept for the next `apply`.
+    pub fn disable() {
+        unsafe { checked!(gl::Disable(gl::STENCIL_TEST)) }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CullFace {
+    Front = gl::FRONT as isize,
+    #[default]
+    Back = gl::BACK as isize,
+    FrontAndBack = gl::FRONT_AND_BACK as isize,
+}
+
+/// `Line` draws triangle edges only, handy to inspect triangulation.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PolygonMode {
+    #[default]
+    Fill = gl::FILL as isize,
+    Line = gl::LINE as isize,
+    Point = gl::POINT as isize,
+}
+
+impl PolygonMode {
+    /// Fill -> Line -> Point -> Fill
+    pub fn next(self) -> Self {
+        match self {
+            Self::Fill => Self::Line,
+            Self::Line => Self::Point,
+            Self::Point => Self::Fill,
         }
     }
+}
 
-    pub fn bind(&self, buf_type: BufferType) {
-        unsafe { gl::BindBuffer(buf_type as GLenum, self.0) }
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Winding {
+    #[default]
+    CounterClockwise = gl::CCW as isize,
+    Clockwise = gl::CW as isize,
+}
+
+/// Rasterizer configuration, set at once with `apply`. The default matches GL's initial
+/// state: nothing is culled and counter-clockwise triangles face the viewer.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RasterState {
+    pub cull: Option<CullFace>,
+    pub front_face: Winding,
+    pub polygon_mode: PolygonMode,
+}
+
+#[allow(dead_code)]
+impl RasterState {
+    /// Culls back faces of counter-clockwise wound meshes, the usual setup for closed 3D models.
+    pub fn back_face_culling() -> Self {
+        Self { cull: Some(CullFace::Back), ..Self::default() }
     }
 
-    pub fn delete(&self) {
-        unsafe { gl::DeleteBuffers(1, &self.0) }
+    pub fn with_cull(mut self, cull: Option<CullFace>) -> Self {
+        self.cull = cull;
+        self
+    }
+
+    pub fn with_front_face(mut self, winding: Winding) -> Self {
+        self.front_face = winding;
+        self
+    }
+
+    pub fn with_polygon_mode(mut self, mode: PolygonMode) -> Self {
+        self.polygon_mode = mode;
+        self
+    }
+
+    pub fn apply(&self) {
+        cull_face(self.cull);
+        front_face(self.front_face);
+        polygon_mode(self.polygon_mode);
     }
 }
 
-pub enum ShaderType {
-    Vertex = gl::VERTEX_SHADER as isize,
-    Fragment = gl::FRAGMENT_SHADER as isize,
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero = gl::ZERO as isize,
+    One = gl::ONE as isize,
+    SrcColor = gl::SRC_COLOR as isize,
+    OneMinusSrcColor = gl::ONE_MINUS_SRC_COLOR as isize,
+    DstColor = gl::DST_COLOR as isize,
+    OneMinusDstColor = gl::ONE_MINUS_DST_COLOR as isize,
+    SrcAlpha = gl::SRC_ALPHA as isize,
+    OneMinusSrcAlpha = gl::ONE_MINUS_SRC_ALPHA as isize,
+    DstAlpha = gl::DST_ALPHA as isize,
+    OneMinusDstAlpha = gl::ONE_MINUS_DST_ALPHA as isize,
+    ConstantColor = gl::CONSTANT_COLOR as isize,
+    OneMinusConstantColor = gl::ONE_MINUS_CONSTANT_COLOR as isize,
+    SrcAlphaSaturate = gl::SRC_ALPHA_SATURATE as isize,
 }
 
-pub struct Shader(pub GLuint);
+/// How the weighted source and destination are combined, `Subtract` is `src - dst`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendEquation {
+    Add = gl::FUNC_ADD as isize,
+    Subtract = gl::FUNC_SUBTRACT as isize,
+    ReverseSubtract = gl::FUNC_REVERSE_SUBTRACT as isize,
+    Min = gl::MIN as isize,
+    Max = gl::MAX as isize,
+}
 
-impl Shader {
-    pub fn from_source(shader_type: ShaderType, src: &str) -> Result<Self, String> {
-        let shader = Self::new(shader_type).ok_or_else(|| "Could not allocate shader".to_string())?;
+/// Blending of color and alpha, set at once with `apply` (or `apply_to` for a single
+/// draw buffer of a framebuffer with several color attachments).
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlendState {
+    pub src_color: BlendFactor,
+    pub dst_color: BlendFactor,
+    pub color_equation: BlendEquation,
+    pub src_alpha: BlendFactor,
+    pub dst_alpha: BlendFactor,
+    pub alpha_equation: BlendEquation,
+}
+
+#[allow(dead_code)]
+impl BlendState {
+    pub const fn new(src: BlendFactor, dst: BlendFactor, equation: BlendEquation) -> Self {
+        Self {
+            src_color: src,
+            dst_color: dst,
+            color_equation: equation,
+            src_alpha: src,
+            dst_alpha: dst,
+            alpha_equation: equation,
+        }
+    }
+
+    /// Classic transparency, `src * a + dst * (1 - a)`.
+    pub const fn alpha() -> Self {
+        Self::new(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha, BlendEquation::Add)
+    }
+
+    /// For colors already multiplied by their alpha.
+    pub const fn premultiplied() -> Self {
+        Self::new(BlendFactor::One, BlendFactor::OneMinusSrcAlpha, BlendEquation::Add)
+    }
+
+    /// Light-like accumulation for particles and glows, order independent.
+    pub const fn additive() -> Self {
+        Self::new(BlendFactor::SrcAlpha, BlendFactor::One, BlendEquation::Add)
+    }
+
+    pub const fn multiply() -> Self {
+        Self::new(BlendFactor::DstColor, BlendFactor::Zero, BlendEquation::Add)
+    }
+
+    /// Uses different factors for alpha than for color.
+    pub const fn with_alpha(mut self, src: BlendFactor, dst: BlendFactor, equation: BlendEquation) -> Self {
+        self.src_alpha = src;
+        self.dst_alpha = dst;
+        self.alpha_equation = equation;
+        self
+    }
+
+    /// Enables blending with this configuration for every draw buffer.
+    pub fn apply(&self) {
+        unsafe {
+            checked!(gl::Enable(gl::BLEND));
+            checked!(gl::BlendFuncSeparate(
+                self.src_color as GLenum,
+                self.dst_color as GLenum,
+                self.src_alpha as GLenum,
+                self.dst_alpha as GLenum,
+            ));
+            checked!(gl::BlendEquationSeparate(self.color_equation as GLenum, self.alpha_equation as GLenum));
+        }
+    }
+
+    /// Enables blending with this configuration only for draw buffer `index`, i.e.
+    /// `COLOR_ATTACHMENT0 + index` of a `Framebuffer`. Needs GL 4.0+.
+    pub fn apply_to(&self, index: u32) -> Result<(), WrapperError> {
+        if context_version() < (4, 0) {
+            return Err(WrapperError::Unsupported("per-attachment blending needs OpenGL 4.0"));
+        }
+
+        unsafe {
+            checked!(gl::Enablei(gl::BLEND, index));
+            checked!(gl::BlendFuncSeparatei(
+                index,
+                self.src_color as GLenum,
+                self.dst_color as GLenum,
+                self.src_alpha as GLenum,
+                self.dst_alpha as GLenum,
+            ));
+            checked!(gl::BlendEquationSeparatei(index, self.color_equation as GLenum, self.alpha_equation as GLenum));
+        }
+        Ok(())
+    }
+
+    pub fn disable() {
+        unsafe { checked!(gl::Disable(gl::BLEND)) }
+    }
+
+    pub fn disable_for(index: u32) {
+        unsafe { checked!(gl::Disablei(gl::BLEND, index)) }
+    }
+
+    /// Color used by the `ConstantColor` factors.
+    pub fn set_constant(color: [f32; 4]) {
+        unsafe { checked!(gl::BlendColor(color[0], color[1], color[2], color[3])) }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveMode {
+    Points = gl::POINTS as isize,
+    Lines = gl::LINES as isize,
+    LineStrip = gl::LINE_STRIP as isize,
+    LineLoop = gl::LINE_LOOP as isize,
+    Triangles = gl::TRIANGLES as isize,
+    TriangleStrip = gl::TRIANGLE_STRIP as isize,
+    TriangleFan = gl::TRIANGLE_FAN as isize,
+    /// lines with the vertex before and after each, for geometry shaders joining them
+    LinesAdjacency = gl::LINES_ADJACENCY as isize,
+}
+
+pub struct VertexArray(pub GLuint);
+
+#[allow(dead_code)]
+impl VertexArray {
+    pub fn new() -> Option<Self> {
+        let mut vao = 0;
+        unsafe {
+            checked!(gl::GenVertexArrays(1, &mut vao));
+        }
+
+        if vao != 0 {
+            Some(Self(vao))
+        } else {
+            None
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe { checked!(gl::BindVertexArray(self.0)) }
+    }
+
+    /// Describes the vertices of currently bound `BufferType::Array` buffer.
+    /// Attributes are expected to be tightly packed in the given order, so stride and offsets
+    /// are computed from their sizes.
+    pub fn set_vertex_layout<V: VertexLike>(&self) {
+        debug_assert_eq!(
+            V::ATTRIBUTES.iter().map(Attribute::size).sum::<usize>(),
+            size_of::<V>(),
+            "Attributes of a vertex type must cover all of its fields"
+        );
+        self.set_layout(V::ATTRIBUTES);
+    }
+
+    pub fn set_layout(&self, attributes: &[Attribute]) {
+        self.bind();
+
+        let stride: usize = attributes.iter().map(Attribute::size).sum();
+        let mut offset = 0;
+
+        for attribute in attributes {
+            attribute.enable(stride, offset);
+            offset += attribute.size();
+        }
+    }
+
+    /// Sources `attributes` from `buffer` instead of the vertex buffer, typically
+    /// per-instance data next to the mesh vertices.
+    pub fn attach_buffer(&self, buffer: &ArrayBuffer, attributes: &[Attribute]) {
+        self.bind();
+        buffer.bind(BufferType::Array);
+        self.set_layout(attributes);
+    }
+
+    /// Name shown for the object by debuggers like RenderDoc or apitrace.
+    pub fn set_label(&self, label: &str) {
+        object_label(gl::VERTEX_ARRAY, self.0, label);
+    }
+
+    /// Gives up ownership of the GL object: it won't be deleted on drop.
+    pub fn leak(self) -> GLuint {
+        let id = self.0;
+        std::mem::forget(self);
+        id
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) { unsafe { gl::DeleteVertexArrays(1, &self.0) } }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeType {
+    Float = gl::FLOAT as isize,
+    Int = gl::INT as isize,
+    UnsignedInt = gl::UNSIGNED_INT as isize,
+    UnsignedByte = gl::UNSIGNED_BYTE as isize,
+}
+
+impl AttributeType {
+    pub fn size(self) -> usize {
+        match self {
+            Self::Float => size_of::<f32>(),
+            Self::Int => size_of::<i32>(),
+            Self::UnsignedInt => size_of::<u32>(),
+            Self::UnsignedByte => size_of::<u8>(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attribute {
+    pub location: GLuint,
+    pub components: GLint,
+    pub kind: AttributeType,
+    /// integer data is read as floats in [0, 1] (or [-1, 1]) instead of as is
+    pub normalized: bool,
+    /// 0 advances per vertex, n advances once every n instances
+    pub divisor: GLuint,
+}
+
+#[allow(dead_code)]
+impl Attribute {
+    pub const fn new(location: GLuint, components: GLint, kind: AttributeType) -> Self {
+        Self { location, components, kind, normalized: false, divisor: 0 }
+    }
+
+    pub const fn float(location: GLuint) -> Self {
+        Self::new(location, 1, AttributeType::Float)
+    }
+
+    pub const fn vec2(location: GLuint) -> Self {
+        Self::new(location, 2, AttributeType::Float)
+    }
+
+    pub const fn vec3(location: GLuint) -> Self {
+        Self::new(location, 3, AttributeType::Float)
+    }
+
+    pub const fn vec4(location: GLuint) -> Self {
+        Self::new(location, 4, AttributeType::Float)
+    }
+
+    pub const fn normalized(mut self) -> Self {
+        self.normalized = true;
+        self
+    }
+
+    /// Advances once per instance instead of per vertex.
+    pub const fn per_instance(self) -> Self {
+        self.with_divisor(1)
+    }
+
+    pub const fn with_divisor(mut self, divisor: GLuint) -> Self {
+        self.divisor = divisor;
+        self
+    }
+
+    /// A mat4 attribute takes 4 consecutive locations, one per column.
+    pub const fn mat4_columns(location: GLuint) -> [Self; 4] {
+        [Self::vec4(location), Self::vec4(location + 1), Self::vec4(location + 2), Self::vec4(location + 3)]
+    }
+
+    pub fn size(&self) -> usize {
+        self.components as usize * self.kind.size()
+    }
+
+    fn enable(&self, stride: usize, offset: usize) {
+        let stride = stride.try_into().unwrap();
+        let offset = offset as *const _;
+
+        unsafe {
+            match self.kind {
+                AttributeType::Int | AttributeType::UnsignedInt if !self.normalized => {
+                    checked!(gl::VertexAttribIPointer(self.location, self.components, self.kind as GLenum, stride, offset))
+                }
+                _ => checked!(gl::VertexAttribPointer(
+                    self.location,
+                    self.components,
+                    self.kind as GLenum,
+                    if self.normalized { gl::TRUE } else { gl::FALSE },
+                    stride,
+                    offset,
+                )),
+            }
+            checked!(gl::EnableVertexAttribArray(self.location));
+            checked!(gl::VertexAttribDivisor(self.location, self.divisor));
+        }
+    }
+}
+
+/// Vertex struct which knows its own attribute layout, e.g.
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+/// struct MyVertex { pos: [f32; 3], color: [f32; 3], uv: [f32; 2] }
+///
+/// impl VertexLike for MyVertex {
+///     const ATTRIBUTES: &'static [Attribute] = &[Attribute::vec3(0), Attribute::vec3(1), Attribute::vec2(2)];
+/// }
+/// ```
+///
+/// Attributes must be listed in the order of struct fields.
+pub trait VertexLike: bytemuck::Pod {
+    const ATTRIBUTES: &'static [Attribute];
+}
+
+impl VertexLike for Vertex {
+    const ATTRIBUTES: &'static [Attribute] = &[Attribute::vec3(0)];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferType {
+    Array = gl::ARRAY_BUFFER as isize,
+    ElementArray = gl::ELEMENT_ARRAY_BUFFER as isize,
+    Uniform = gl::UNIFORM_BUFFER as isize,
+    ShaderStorage = gl::SHADER_STORAGE_BUFFER as isize,
+    DrawIndirect = gl::DRAW_INDIRECT_BUFFER as isize,
+    /// source of `glTexImage*` uploads when bound
+    PixelUnpack = gl::PIXEL_UNPACK_BUFFER as isize,
+    /// target of `glReadPixels` when bound
+    PixelPack = gl::PIXEL_PACK_BUFFER as isize,
+}
+
+pub struct ArrayBuffer(pub GLuint);
+
+#[allow(dead_code)]
+impl ArrayBuffer {
+    pub fn new() -> Option<Self> {
+        let mut vbo = 0;
+        unsafe {
+            checked!(gl::GenBuffers(1, &mut vbo));
+        }
+
+        if vbo != 0 {
+            Some(Self(vbo))
+        } else {
+            None
+        }
+    }
+
+    pub fn bind(&self, buf_type: BufferType) {
+        unsafe { checked!(gl::BindBuffer(buf_type as GLenum, self.0)) }
+    }
+
+    /// Size of the data store in bytes, binds the buffer to `buf_type`.
+    pub fn size(&self, buf_type: BufferType) -> usize {
+        let mut size = 0;
+        self.bind(buf_type);
+        unsafe { checked!(gl::GetBufferParameteri64v(buf_type as GLenum, gl::BUFFER_SIZE, &mut size)) };
+        size as usize
+    }
+
+    /// Maps `len` elements of `T` starting at element `first` into client memory. The buffer
+    /// is unmapped when the returned guard is dropped, it must not be used by draws until then.
+    pub fn map_range<T: bytemuck::Pod, A: MapAccess>(
+        &self,
+        buf_type: BufferType,
+        first: usize,
+        len: usize,
+        _access: A,
+    ) -> Result<BufferMapping<'_, T, A>, WrapperError> {
+        let (offset, size) = mapped_range(first, len, size_of::<T>(), self.size(buf_type))
+            .ok_or(WrapperError::Unsupported("buffer mappings past the end of the buffer"))?;
+
+        let mapping = |ptr: *mut T| BufferMapping { buffer: self, buf_type, ptr, len, _access: PhantomData };
+        if size == 0 {
+            return Ok(mapping(std::ptr::NonNull::dangling().as_ptr()));
+        }
+
+        // both fit the buffer size, which GL reports as an i64
+        let ptr = unsafe { checked!(gl::MapBufferRange(buf_type as GLenum, offset as isize, size as isize, A::FLAGS)) };
+        if ptr.is_null() {
+            return Err(WrapperError::Allocation("buffer mapping"));
+        }
+
+        Ok(mapping(ptr.cast()))
+    }
+
+    /// Maps the whole buffer as elements of `T`, any trailing bytes are left out.
+    pub fn map<T: bytemuck::Pod, A: MapAccess>(&self, buf_type: BufferType, access: A) -> Result<BufferMapping<'_, T, A>, WrapperError> {
+        let len = self.size(buf_type) / size_of::<T>();
+        self.map_range(buf_type, 0, len, access)
+    }
+
+    /// Name shown for the object by debuggers like RenderDoc or apitrace.
+    pub fn set_label(&self, label: &str) {
+        object_label(gl::BUFFER, self.0, label);
+    }
+
+    /// Gives up ownership of the GL object: it won't be deleted on drop.
+    pub fn leak(self) -> GLuint {
+        let id = self.0;
+        std::mem::forget(self);
+        id
+    }
+}
+
+impl Drop for ArrayBuffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteBuffers(1, &self.0) }
+    }
+}
+
+/// Byte offset and size of `len` elements of `element_size` bytes from element `first`,
+/// `None` unless they lie within `buffer_size` bytes.
+fn mapped_range(first: usize, len: usize, element_size: usize, buffer_size: usize) -> Option<(usize, usize)> {
+    let offset = first.checked_mul(element_size)?;
+    let size = len.checked_mul(element_size)?;
+    (offset.checked_add(size)? <= buffer_size).then_some((offset, size))
+}
+
+/// How a buffer range gets mapped, one of `MapRead`, `MapWrite`, `MapWriteDiscard` and
+/// `MapReadWrite`.
+pub trait MapAccess {
+    const FLAGS: GLbitfield;
+}
+
+/// Access which allows writing, only mappings with it can be written through.
+pub trait MapWritable: MapAccess {}
+
+/// Read back, e.g. results of a compute shader or transform feedback.
+#[derive(Debug, Clone, Copy)]
+pub struct MapRead;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub struct MapWrite;
+
+/// Previous contents of the range are discarded, avoids waiting for the GPU to finish with them.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub struct MapWriteDiscard;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub struct MapReadWrite;
+
+impl MapAccess for MapRead {
+    const FLAGS: GLbitfield = gl::MAP_READ_BIT;
+}
+
+impl MapAccess for MapWrite {
+    const FLAGS: GLbitfield = gl::MAP_WRITE_BIT;
+}
+
+impl MapAccess for MapWriteDiscard {
+    const FLAGS: GLbitfield = gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_RANGE_BIT;
+}
+
+impl MapAccess for MapReadWrite {
+    const FLAGS: GLbitfield = gl::MAP_READ_BIT | gl::MAP_WRITE_BIT;
+}
+
+impl MapWritable for MapWrite {}
+impl MapWritable for MapWriteDiscard {}
+impl MapWritable for MapReadWrite {}
+
+/// Mapped range of an `ArrayBuffer`, derefs to the mapped elements, mutably for the writable
+/// accesses. Reading a write-only mapping gives unspecified values.
+pub struct BufferMapping<'a, T: bytemuck::Pod, A: MapAccess> {
+    buffer: &'a ArrayBuffer,
+    buf_type: BufferType,
+    ptr: *mut T,
+    len: usize,
+    _access: PhantomData<A>,
+}
+
+#[allow(dead_code)]
+impl<T: bytemuck::Pod, A: MapAccess> BufferMapping<'_, T, A> {
+    /// Unmaps right away. Returns false when the contents got corrupted meanwhile
+    /// (e.g. by a display mode change) and have to be uploaded again.
+    pub fn unmap(mut self) -> bool {
+        let intact = self.release();
+        std::mem::forget(self);
+        intact
+    }
+
+    fn release(&mut self) -> bool {
+        if self.len == 0 {
+            return true;
+        }
+
+        self.buffer.bind(self.buf_type);
+        unsafe { checked!(gl::UnmapBuffer(self.buf_type as GLenum)) == gl::TRUE }
+    }
+}
+
+impl<T: bytemuck::Pod, A: MapAccess> std::ops::Deref for BufferMapping<'_, T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // valid until unmapped, which only happens when the guard goes away
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<T: bytemuck::Pod, A: MapWritable> std::ops::DerefMut for BufferMapping<'_, T, A> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl<T: bytemuck::Pod, A: MapAccess> Drop for BufferMapping<'_, T, A> {
+    fn drop(&mut self) {
+        // use `unmap` to find out about lost contents
+        if !self.release() {
+            #[cfg(feature = "debug")]
+            log::warn!("Buffer {} contents were lost while mapped", self.buffer.0);
+        }
+    }
+}
+
+/// UBO holding one `T`, which must be laid out by std140 rules (see `verify_std140`).
+/// Programs find it through `ShaderProgram::bind_uniform_block` with the same binding point.
+pub struct UniformBuffer<T: bytemuck::Pod> {
+    buffer: ArrayBuffer,
+    binding: GLuint,
+    _data: PhantomData<T>,
+}
+
+#[allow(dead_code)]
+impl<T: bytemuck::Pod> UniformBuffer<T> {
+    pub fn new(binding: GLuint, data: &T) -> Result<Self, WrapperError> {
+        let buffer = ArrayBuffer::new().ok_or(WrapperError::Allocation("uniform buffer"))?;
+        buffer.bind(BufferType::Uniform);
+        buffer_data(BufferType::Uniform, bytemuck::bytes_of(data), gl::DYNAMIC_DRAW);
+
+        let ubo = Self { buffer, binding, _data: PhantomData };
+        ubo.bind_base();
+
+        Ok(ubo)
+    }
+
+    pub fn update(&self, data: &T) {
+        self.buffer.bind(BufferType::Uniform);
+        update_buffer_data(BufferType::Uniform, bytemuck::bytes_of(data));
+    }
+
+    pub fn binding(&self) -> GLuint {
+        self.binding
+    }
+
+    pub fn set_label(&self, label: &str) {
+        self.buffer.set_label(label);
+    }
+
+    pub fn set_binding(&mut self, binding: GLuint) {
+        self.binding = binding;
+        self.bind_base();
+    }
+
+    fn bind_base(&self) {
+        unsafe { checked!(gl::BindBufferBase(gl::UNIFORM_BUFFER, self.binding, self.buffer.0)) }
+    }
+}
+
+/// Shader storage buffer holding an array of `T`, which must follow std430 layout rules.
+/// Programs find it through `ShaderProgram::bind_storage_block`. Needs GL 4.3+.
+pub struct SsboBuffer<T: bytemuck::Pod> {
+    buffer: ArrayBuffer,
+    binding: GLuint,
+    len: usize,
+    _data: PhantomData<T>,
+}
+
+#[allow(dead_code)]
+impl<T: bytemuck::Pod> SsboBuffer<T> {
+    pub fn new(binding: GLuint, data: &[T], usage: GLenum) -> Result<Self, WrapperError> {
+        if context_version() < (4, 3) {
+            return Err(WrapperError::Unsupported("shader storage buffers need OpenGL 4.3"));
+        }
+
+        let buffer = ArrayBuffer::new().ok_or(WrapperError::Allocation("shader storage buffer"))?;
+        buffer.bind(BufferType::ShaderStorage);
+        buffer_data(BufferType::ShaderStorage, bytemuck::cast_slice(data), usage);
+
+        let ssbo = Self { buffer, binding, len: data.len(), _data: PhantomData };
+        ssbo.bind_base();
+
+        Ok(ssbo)
+    }
+
+    /// Zero-filled buffer for `len` elements, e.g. as compute shader output.
+    pub fn zeroed(binding: GLuint, len: usize, usage: GLenum) -> Result<Self, WrapperError> {
+        Self::new(binding, &vec![T::zeroed(); len], usage)
+    }
+
+    /// Replaces the whole contents, reallocating the storage when the length changes.
+    pub fn upload(&mut self, data: &[T], usage: GLenum) {
+        self.buffer.bind(BufferType::ShaderStorage);
+        if data.len() == self.len {
+            update_buffer_data(BufferType::ShaderStorage, bytemuck::cast_slice(data));
+        } else {
+            buffer_data(BufferType::ShaderStorage, bytemuck::cast_slice(data), usage);
+            self.len = data.len();
+        }
+    }
+
+    /// Overwrites elements starting at element `first`.
+    pub fn update(&self, first: usize, data: &[T]) -> Result<(), WrapperError> {
+        if first.checked_add(data.len()).is_none_or(|end| end > self.len) {
+            return Err(WrapperError::Unsupported("storage buffer updates past the last element"));
+        }
+
+        self.buffer.bind(BufferType::ShaderStorage);
+        unsafe {
+            checked!(gl::BufferSubData(
+                gl::SHADER_STORAGE_BUFFER,
+                (first * size_of::<T>()).try_into().unwrap(),
+                size_of_val(data).try_into().unwrap(),
+                data.as_ptr().cast(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Copies the contents back to the CPU. Writes made by shaders have to be made visible
+    /// first with `barrier` (or `memory_barrier(gl::BUFFER_UPDATE_BARRIER_BIT)`).
+    pub fn read(&self) -> Vec<T> {
+        let mut data = vec![T::zeroed(); self.len];
+
+        self.buffer.bind(BufferType::ShaderStorage);
+        unsafe {
+            checked!(gl::GetBufferSubData(
+                gl::SHADER_STORAGE_BUFFER,
+                0,
+                (self.len * size_of::<T>()).try_into().unwrap(),
+                data.as_mut_ptr().cast(),
+            ));
+        }
+
+        data
+    }
+
+    /// Maps the elements for direct access instead of copying them like `read`/`update`.
+    /// Shader writes need a `barrier` first, like for `read`.
+    pub fn map<A: MapAccess>(&self, access: A) -> Result<BufferMapping<'_, T, A>, WrapperError> {
+        self.buffer.map_range(BufferType::ShaderStorage, 0, self.len, access)
+    }
+
+    /// Makes shader writes to storage buffers visible to later shader reads and to `read`.
+    pub fn barrier(&self) {
+        storage_barrier();
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn binding(&self) -> GLuint {
+        self.binding
+    }
+
+    pub fn set_label(&self, label: &str) {
+        self.buffer.set_label(label);
+    }
+
+    pub fn set_binding(&mut self, binding: GLuint) {
+        self.binding = binding;
+        self.bind_base();
+    }
+
+    fn bind_base(&self) {
+        unsafe { checked!(gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, self.binding, self.buffer.0)) }
+    }
+}
+
+/// GPU fence: signaled once every command issued before its creation has completed.
+pub struct Fence(pub GLsync);
+
+#[allow(dead_code)]
+impl Fence {
+    pub fn new() -> Option<Self> {
+        let sync = unsafe { checked!(gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0)) };
+
+        if !sync.is_null() {
+            Some(Self(sync))
+        } else {
+            None
+        }
+    }
+
+    /// Blocks until the fence is signaled or `timeout_ns` passes, returns whether it was signaled.
+    pub fn wait(&self, timeout_ns: u64) -> bool {
+        let status = unsafe { checked!(gl::ClientWaitSync(self.0, gl::SYNC_FLUSH_COMMANDS_BIT, timeout_ns)) };
+        status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+    }
+
+    /// Checks without blocking.
+    pub fn is_signaled(&self) -> bool {
+        let status = unsafe { checked!(gl::ClientWaitSync(self.0, 0, 0)) };
+        status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteSync(self.0) }
+    }
+}
+
+/// Query object, counts or times the commands between `begin` and `end`.
+pub struct Query(pub GLuint);
+
+#[allow(dead_code)]
+impl Query {
+    pub fn new() -> Option<Self> {
+        let mut query = 0;
+        unsafe {
+            checked!(gl::GenQueries(1, &mut query));
+        }
+
+        if query != 0 {
+            Some(Self(query))
+        } else {
+            None
+        }
+    }
+
+    /// Only one query per `target` can be active at a time.
+    pub fn begin(&self, target: GLenum) {
+        unsafe { checked!(gl::BeginQuery(target, self.0)) }
+    }
+
+    pub fn end(target: GLenum) {
+        unsafe { checked!(gl::EndQuery(target)) }
+    }
+
+    /// Records the GPU time once every command issued so far has completed, read it by `result`.
+    pub fn timestamp(&self) {
+        unsafe { checked!(gl::QueryCounter(self.0, gl::TIMESTAMP)) }
+    }
+
+    /// Whether `result` would return without waiting for the GPU.
+    pub fn is_available(&self) -> bool {
+        let mut available = 0;
+        unsafe { checked!(gl::GetQueryObjectiv(self.0, gl::QUERY_RESULT_AVAILABLE, &mut available)) };
+        available != 0
+    }
+
+    /// Blocks until the result is available, nanoseconds for time queries.
+    pub fn result(&self) -> u64 {
+        let mut result = 0;
+        unsafe { checked!(gl::GetQueryObjectui64v(self.0, gl::QUERY_RESULT, &mut result)) };
+        result
+    }
+
+    /// Name shown for the object by debuggers like RenderDoc or apitrace.
+    pub fn set_label(&self, label: &str) {
+        object_label(gl::QUERY, self.0, label);
+    }
+
+    /// Gives up ownership of the GL object: it won't be deleted on drop.
+    pub fn leak(self) -> GLuint {
+        let id = self.0;
+        std::mem::forget(self);
+        id
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) { unsafe { gl::DeleteQueries(1, &self.0) } }
+}
+
+/// One draw of `multi_draw_elements_indirect`, laid out as GL expects it in the buffer.
+/// Indices and vertices are looked up in the element and vertex buffers of the bound
+/// vertex array, so many meshes packed in shared buffers can be drawn at once.
+#[allow(dead_code)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DrawElementsIndirectCommand {
+    pub count: GLuint,
+    pub instance_count: GLuint,
+    pub first_index: GLuint,
+    /// added to every index before fetching the vertex
+    pub base_vertex: GLint,
+    /// first instance for per-instance attributes, needs GL 4.2+ to be non-zero
+    pub base_instance: GLuint,
+}
+
+#[allow(dead_code)]
+impl DrawElementsIndirectCommand {
+    /// Single instance of `count` indices starting at `first_index`.
+    pub const fn new(count: GLuint, first_index: GLuint, base_vertex: GLint) -> Self {
+        Self { count, instance_count: 1, first_index, base_vertex, base_instance: 0 }
+    }
+
+    pub const fn instanced(mut self, instance_count: GLuint, base_instance: GLuint) -> Self {
+        self.instance_count = instance_count;
+        self.base_instance = base_instance;
+        self
+    }
+}
+
+/// `DRAW_INDIRECT_BUFFER` of draw commands, which may also be written by compute shaders
+/// for GPU driven culling.
+pub struct IndirectBuffer {
+    buffer: ArrayBuffer,
+    len: usize,
+}
+
+#[allow(dead_code)]
+impl IndirectBuffer {
+    pub fn new(commands: &[DrawElementsIndirectCommand], usage: GLenum) -> Result<Self, WrapperError> {
+        let buffer = ArrayBuffer::new().ok_or(WrapperError::Allocation("indirect buffer"))?;
+        buffer.bind(BufferType::DrawIndirect);
+        buffer_data(BufferType::DrawIndirect, bytemuck::cast_slice(commands), usage);
+
+        Ok(Self { buffer, len: commands.len() })
+    }
+
+    /// Replaces the commands, reallocating the storage when their count changes.
+    pub fn upload(&mut self, commands: &[DrawElementsIndirectCommand], usage: GLenum) {
+        self.buffer.bind(BufferType::DrawIndirect);
+        if commands.len() == self.len {
+            update_buffer_data(BufferType::DrawIndirect, bytemuck::cast_slice(commands));
+        } else {
+            buffer_data(BufferType::DrawIndirect, bytemuck::cast_slice(commands), usage);
+            self.len = commands.len();
+        }
+    }
+
+    pub fn bind(&self) {
+        self.buffer.bind(BufferType::DrawIndirect);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn set_label(&self, label: &str) {
+        self.buffer.set_label(label);
+    }
+
+    /// The underlying buffer, e.g. to bind it as an SSBO for a compute pass filling it.
+    pub fn buffer(&self) -> &ArrayBuffer {
+        &self.buffer
+    }
+}
+
+/// Issues every command of `commands` with one call, using the bound vertex array and its
+/// `u32` element buffer. Needs GL 4.3+.
+#[allow(dead_code)]
+pub fn multi_draw_elements_indirect(mode: PrimitiveMode, commands: &IndirectBuffer) -> Result<(), WrapperError> {
+    if context_version() < (4, 3) {
+        return Err(WrapperError::Unsupported("multi-draw indirect needs OpenGL 4.3"));
+    }
+
+    commands.bind();
+    unsafe {
+        checked!(gl::MultiDrawElementsIndirect(
+            mode as GLenum,
+            gl::UNSIGNED_INT,
+            std::ptr::null(),
+            commands.len().try_into().unwrap(),
+            0,
+        ));
+    }
+    Ok(())
+}
+
+/// GLSL types as seen by std140 layout rules.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Std140 {
+    Float,
+    Int,
+    UInt,
+    Vec2,
+    Vec3,
+    Vec4,
+    Mat3,
+    Mat4,
+    Array(&'static Std140, usize),
+    Struct(&'static [Std140]),
+}
+
+#[allow(dead_code)]
+impl Std140 {
+    pub fn align(self) -> usize {
+        match self {
+            Self::Float | Self::Int | Self::UInt => 4,
+            Self::Vec2 => 8,
+            Self::Vec3 | Self::Vec4 | Self::Mat3 | Self::Mat4 => 16,
+            // array elements and structs are rounded up to vec4 alignment
+            Self::Array(element, _) => round_up(element.align(), 16),
+            Self::Struct(fields) => round_up(fields.iter().map(|f| f.align()).max().unwrap_or(0), 16),
+        }
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            Self::Float | Self::Int | Self::UInt => 4,
+            Self::Vec2 => 8,
+            Self::Vec3 => 12,
+            Self::Vec4 => 16,
+            // every column takes a whole vec4
+            Self::Mat3 => 48,
+            Self::Mat4 => 64,
+            Self::Array(element, len) => round_up(element.size(), self.align()) * len,
+            Self::Struct(fields) => round_up(std140_offsets(fields).1, self.align()),
+        }
+    }
+}
+
+fn round_up(value: usize, align: usize) -> usize {
+    value.div_ceil(align) * align
+}
+
+/// Offsets std140 assigns to consecutive block members, and the end of the last one.
+pub fn std140_offsets(fields: &[Std140]) -> (Vec<usize>, usize) {
+    let mut offsets = Vec::with_capacity(fields.len());
+    let mut end = 0;
+
+    for field in fields {
+        let offset = round_up(end, field.align());
+        offsets.push(offset);
+        end = offset + field.size();
+    }
+
+    (offsets, end)
+}
+
+/// Compares real field offsets of `T` (use `std::mem::offset_of!`) with the ones std140
+/// expects, so a wrongly padded struct is caught before it silently garbles uniforms.
+#[allow(dead_code)]
+pub fn verify_std140<T>(fields: &[(&'static str, usize, Std140)]) -> Result<(), WrapperError> {
+    let types: Vec<Std140> = fields.iter().map(|&(_, _, kind)| kind).collect();
+    let (expected, end) = std140_offsets(&types);
+
+    for (&(field, actual, _), expected) in fields.iter().zip(expected) {
+        if actual != expected {
+            return Err(WrapperError::Std140Layout { field, expected, actual });
+        }
+    }
+
+    if size_of::<T>() < end {
+        return Err(WrapperError::Std140Layout { field: "<size>", expected: end, actual: size_of::<T>() });
+    }
+
+    Ok(())
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFilter {
+    Nearest = gl::NEAREST as isize,
+    Linear = gl::LINEAR as isize,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureWrap {
+    Repeat = gl::REPEAT as isize,
+    MirroredRepeat = gl::MIRRORED_REPEAT as isize,
+    ClampToEdge = gl::CLAMP_TO_EDGE as isize,
+    ClampToBorder = gl::CLAMP_TO_BORDER as isize,
+}
+
+/// `TEXTURE_MIN_FILTER` of a mipmapped texture: `min` within a level, `mip` between the two
+/// closest levels, `(Linear, Linear)` being trilinear filtering.
+#[allow(dead_code)]
+pub fn mipmap_min_filter(min: TextureFilter, mip: TextureFilter) -> GLenum {
+    match (min, mip) {
+        (TextureFilter::Nearest, TextureFilter::Nearest) => gl::NEAREST_MIPMAP_NEAREST,
+        (TextureFilter::Linear, TextureFilter::Nearest) => gl::LINEAR_MIPMAP_NEAREST,
+        (TextureFilter::Nearest, TextureFilter::Linear) => gl::NEAREST_MIPMAP_LINEAR,
+        (TextureFilter::Linear, TextureFilter::Linear) => gl::LINEAR_MIPMAP_LINEAR,
+    }
+}
+
+/// Highest anisotropy texture filtering can use, 1 (plain filtering) when the context has no
+/// anisotropic filtering.
+#[allow(dead_code)]
+pub fn max_anisotropy() -> f32 {
+    // core in 4.6, only older contexts need the slower extension check
+    if context_version() < (4, 6) && !Extensions::query().anisotropic_filtering() {
+        return 1.0;
+    }
+
+    let mut max = 1.0;
+    unsafe { checked!(gl::GetFloatv(gl_ext::MAX_TEXTURE_MAX_ANISOTROPY, &mut max)) };
+    max
+}
+
+/// Number of values in pixel data with `components` per texel and `size` texels along each
+/// axis, `None` when that overflows.
+fn pixel_data_len(size: &[u32], components: usize) -> Option<usize> {
+    size.iter().try_fold(components, |len, &extent| len.checked_mul(extent as usize))
+}
+
+/// A texture width, height or depth as GL takes it.
+fn gl_size(extent: u32) -> Result<GLsizei, WrapperError> {
+    extent.try_into().map_err(|_| WrapperError::Unsupported("texture sizes beyond i32::MAX"))
+}
+
+/// Storage formats for textures and renderbuffers which get rendered into.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    R8,
+    Rgba8,
+    /// RGBA8 holding sRGB encoded colors, decoded to linear when sampled
+    Srgb8Alpha8,
+    /// two half floats, e.g. lookup tables
+    Rg16F,
+    Rgba16F,
+    Rgba32F,
+    /// packed unsigned floats without alpha, half the size of `Rgba16F` for HDR color
+    R11fG11fB10f,
+    Depth24,
+    Depth32F,
+    Depth24Stencil8,
+}
+
+#[allow(dead_code)]
+impl TextureFormat {
+    pub fn internal_format(self) -> GLenum {
+        match self {
+            Self::R8 => gl::R8,
+            Self::Rgba8 => gl::RGBA8,
+            Self::Srgb8Alpha8 => gl::SRGB8_ALPHA8,
+            Self::Rg16F => gl::RG16F,
+            Self::Rgba16F => gl::RGBA16F,
+            Self::Rgba32F => gl::RGBA32F,
+            Self::R11fG11fB10f => gl::R11F_G11F_B10F,
+            Self::Depth24 => gl::DEPTH_COMPONENT24,
+            Self::Depth32F => gl::DEPTH_COMPONENT32F,
+            Self::Depth24Stencil8 => gl::DEPTH24_STENCIL8,
+        }
+    }
+
+    /// `format` and `type` arguments of `glTexImage2D` compatible with the internal format.
+    pub fn pixel_format(self) -> (GLenum, GLenum) {
+        match self {
+            Self::R8 => (gl::RED, gl::UNSIGNED_BYTE),
+            Self::Rgba8 | Self::Srgb8Alpha8 => (gl::RGBA, gl::UNSIGNED_BYTE),
+            Self::Rg16F => (gl::RG, gl::FLOAT),
+            Self::Rgba16F | Self::Rgba32F => (gl::RGBA, gl::FLOAT),
+            Self::R11fG11fB10f => (gl::RGB, gl::FLOAT),
+            Self::Depth24 | Self::Depth32F => (gl::DEPTH_COMPONENT, gl::FLOAT),
+            Self::Depth24Stencil8 => (gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8),
+        }
+    }
+
+    /// Color formats holding values outside 0..1, for HDR render targets.
+    pub fn is_float(self) -> bool {
+        matches!(self, Self::Rg16F | Self::Rgba16F | Self::Rgba32F | Self::R11fG11fB10f)
+    }
+
+    pub fn is_depth(self) -> bool {
+        matches!(self, Self::Depth24 | Self::Depth32F | Self::Depth24Stencil8)
+    }
+
+    pub fn has_stencil(self) -> bool {
+        self == Self::Depth24Stencil8
+    }
+}
+
+/// How the bytes of an 8-bit image are to be read. Colors painted or photographed (albedo,
+/// UI) are sRGB, data (normal maps, roughness, masks) is linear.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorSpace {
+    #[default]
+    Linear,
+    Srgb,
+}
+
+impl ColorSpace {
+    pub fn rgba_format(self) -> TextureFormat {
+        match self {
+            Self::Linear => TextureFormat::Rgba8,
+            Self::Srgb => TextureFormat::Srgb8Alpha8,
+        }
+    }
+}
+
+pub struct Texture2D(pub GLuint);
+
+#[allow(dead_code)]
+impl Texture2D {
+    /// Loads PNG/JPEG image from disk and uploads it as RGBA8 texture.
+    /// Image is flipped vertically, because OpenGL expects the first row to be the bottom one.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        Self::from_file_as(path, ColorSpace::Linear)
+    }
+
+    /// `from_file` for images in `color_space`, sRGB ones get decoded to linear when sampled.
+    pub fn from_file_as(path: impl AsRef<Path>, color_space: ColorSpace) -> Result<Self, WrapperError> {
+        let img = Self::decode_file(path)?;
+        Self::from_rgba_as(img.width(), img.height(), img.as_raw(), color_space)
+    }
+
+    /// Reads and flips the image the way `from_file` does without touching GL, so it can
+    /// run on another thread.
+    pub fn decode_file(path: impl AsRef<Path>) -> Result<image::RgbaImage, WrapperError> {
+        let path = path.as_ref();
+        Ok(image::open(path)
+            .map_err(|source| WrapperError::Image { path: path.to_path_buf(), source })?
+            .flipv()
+            .into_rgba8())
+    }
+
+    pub fn from_rgba(width: u32, height: u32, pixels: &[u8]) -> Result<Self, WrapperError> {
+        Self::from_rgba_as(width, height, pixels, ColorSpace::Linear)
+    }
+
+    /// `pixels` has to hold exactly `width * height` RGBA8 pixels, bottom row first.
+    pub fn from_rgba_as(width: u32, height: u32, pixels: &[u8], color_space: ColorSpace) -> Result<Self, WrapperError> {
+        if pixel_data_len(&[width, height], 4) != Some(pixels.len()) {
+            return Err(WrapperError::Unsupported("RGBA8 pixels other than width * height of them"));
+        }
+        let (gl_width, gl_height) = (gl_size(width)?, gl_size(height)?);
+
+        let texture = Self::new().ok_or(WrapperError::Allocation("texture"))?;
+        texture.bind();
+
+        unsafe {
+            checked!(gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                color_space.rgba_format().internal_format() as GLint,
+                gl_width,
+                gl_height,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr().cast(),
+            ));
+        }
+
+        texture.set_filter(TextureFilter::Linear, TextureFilter::Linear);
+        texture.set_wrap(TextureWrap::Repeat, TextureWrap::Repeat);
+
+        Ok(texture)
+    }
+
+    /// Loads a Radiance HDR image, e.g. an equirectangular environment, as RGBA32F texture
+    /// holding linear values beyond 1. Flipped like `from_file`.
+    pub fn from_hdr_file(path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        let path = path.as_ref();
+        let img = image::open(path)
+            .map_err(|source| WrapperError::Image { path: path.to_path_buf(), source })?
+            .flipv()
+            .into_rgba32f();
+
+        Self::from_rgba_f32(img.width(), img.height(), img.as_raw())
+    }
+
+    /// RGBA32F texture from 4 floats per pixel, bottom row first.
+    pub fn from_rgba_f32(width: u32, height: u32, pixels: &[f32]) -> Result<Self, WrapperError> {
+        if pixel_data_len(&[width, height], 4) != Some(pixels.len()) {
+            return Err(WrapperError::Unsupported("RGBA32F pixels other than width * height of them"));
+        }
+        let (gl_width, gl_height) = (gl_size(width)?, gl_size(height)?);
+
+        let texture = Self::new().ok_or(WrapperError::Allocation("texture"))?;
+        texture.bind();
+
+        unsafe {
+            checked!(gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA32F as GLint,
+                gl_width,
+                gl_height,
+                0,
+                gl::RGBA,
+                gl::FLOAT,
+                pixels.as_ptr().cast(),
+            ));
+        }
+
+        texture.set_filter(TextureFilter::Linear, TextureFilter::Linear);
+        texture.set_wrap(TextureWrap::Repeat, TextureWrap::ClampToEdge);
+
+        Ok(texture)
+    }
+
+    /// Texture without contents, e.g. to be used as framebuffer attachment.
+    pub fn empty(width: u32, height: u32, format: TextureFormat) -> Result<Self, WrapperError> {
+        let texture = Self::new().ok_or(WrapperError::Allocation("texture"))?;
+        texture.allocate(width, height, format);
+
+        texture.set_filter(TextureFilter::Linear, TextureFilter::Linear);
+        texture.set_wrap(TextureWrap::ClampToEdge, TextureWrap::ClampToEdge);
+
+        Ok(texture)
+    }
+
+    /// (Re)creates storage of level 0, previous contents are lost.
+    pub fn allocate(&self, width: u32, height: u32, format: TextureFormat) {
+        let (pixel_format, pixel_type) = format.pixel_format();
+
+        self.bind();
+        unsafe {
+            checked!(gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                format.internal_format() as GLint,
+                width.try_into().unwrap(),
+                height.try_into().unwrap(),
+                0,
+                pixel_format,
+                pixel_type,
+                std::ptr::null(),
+            ));
+        }
+    }
+
+    pub fn new() -> Option<Self> {
+        let mut texture = 0;
+        unsafe {
+            checked!(gl::GenTextures(1, &mut texture));
+        }
+
+        if texture != 0 {
+            Some(Self(texture))
+        } else {
+            None
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe { checked!(gl::BindTexture(gl::TEXTURE_2D, self.0)) }
+    }
+
+    /// Makes the texture visible to samplers with `unit` value (`layout(binding = unit)`
+    /// or uniform set to `unit`).
+    pub fn bind_to_unit(&self, unit: u32) {
+        unsafe { checked!(gl::ActiveTexture(gl::TEXTURE0 + unit)) }
+        self.bind();
+    }
+
+    pub fn set_filter(&self, min: TextureFilter, mag: TextureFilter) {
+        self.bind();
+        unsafe {
+            checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min as GLint));
+            checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag as GLint));
+        }
+    }
+
+    /// Fills the mip chain from level 0, call again after changing level 0. Sampling only uses
+    /// the levels with a mipmap filter from `set_mipmap_filter`, which keeps the texture from
+    /// shimmering when it covers fewer pixels than it has texels.
+    pub fn generate_mipmaps(&self) {
+        self.bind();
+        unsafe { checked!(gl::GenerateMipmap(gl::TEXTURE_2D)) }
+    }
+
+    /// Filters between mip levels too, see `mipmap_min_filter`. Needs `generate_mipmaps` first,
+    /// textures without the levels read as black.
+    pub fn set_mipmap_filter(&self, min: TextureFilter, mip: TextureFilter, mag: TextureFilter) {
+        self.bind();
+        unsafe {
+            checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, mipmap_min_filter(min, mip) as GLint));
+            checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag as GLint));
+        }
+    }
+
+    /// Anisotropic filtering with up to `anisotropy` samples, keeping surfaces seen at grazing
+    /// angles sharp. Clamped to `max_anisotropy`, 1 turns it off; does nothing when the context
+    /// has no anisotropic filtering.
+    pub fn set_anisotropy(&self, anisotropy: f32) {
+        let max = max_anisotropy();
+        if max > 1.0 {
+            self.bind();
+            unsafe { checked!(gl::TexParameterf(gl::TEXTURE_2D, gl_ext::TEXTURE_MAX_ANISOTROPY, anisotropy.clamp(1.0, max))) };
+        }
+    }
+
+    pub fn set_wrap(&self, s: TextureWrap, t: TextureWrap) {
+        self.bind();
+        unsafe {
+            checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, s as GLint));
+            checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, t as GLint));
+        }
+    }
+
+    /// Color sampled outside the texture with `TextureWrap::ClampToBorder`.
+    pub fn set_border_color(&self, color: [f32; 4]) {
+        self.bind();
+        unsafe { checked!(gl::TexParameterfv(gl::TEXTURE_2D, gl::TEXTURE_BORDER_COLOR, color.as_ptr())) }
+    }
+
+    /// Depth textures only: with `Some(func)` a `sampler2DShadow` returns how much of the
+    /// filtered texels pass `func` against the reference depth instead of the depth itself.
+    pub fn set_compare(&self, func: Option<CompareFunc>) {
+        self.bind();
+        unsafe {
+            match func {
+                Some(func) => {
+                    checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as GLint));
+                    checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_FUNC, func as GLint));
+                }
+                None => checked!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_MODE, gl::NONE as GLint)),
+            }
+        }
+    }
+
+    /// Name shown for the object by debuggers like RenderDoc or apitrace.
+    pub fn set_label(&self, label: &str) {
+        object_label(gl::TEXTURE, self.0, label);
+    }
+
+    /// Gives up ownership of the GL object: it won't be deleted on drop.
+    pub fn leak(self) -> GLuint {
+        let id = self.0;
+        std::mem::forget(self);
+        id
+    }
+}
+
+impl Drop for Texture2D {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTextures(1, &self.0) }
+    }
+}
+
+/// Six square faces sampled by direction, in the order of `CUBE_FACES`.
+pub struct Cubemap(pub GLuint);
+
+/// Face targets of a cubemap, the order of layers when rendering into all of them at once.
+#[allow(dead_code)]
+pub const CUBE_FACES: [GLenum; 6] = [
+    gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+    gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+    gl::TEXTURE_CUBE_MAP_POSITIVE_Y,
+    gl::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+    gl::TEXTURE_CUBE_MAP_POSITIVE_Z,
+    gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+];
+
+#[allow(dead_code)]
+impl Cubemap {
+    pub fn new() -> Option<Self> {
+        let mut texture = 0;
+        unsafe {
+            checked!(gl::GenTextures(1, &mut texture));
+        }
+
+        if texture != 0 {
+            Some(Self(texture))
+        } else {
+            None
+        }
+    }
+
+    /// Loads six PNG/JPEG faces in the order of `CUBE_FACES` (+x, -x, +y, -y, +z, -z). Unlike
+    /// 2D textures they aren't flipped, cubemaps expect the top row first.
+    pub fn from_files<P: AsRef<Path>>(paths: [P; 6], color_space: ColorSpace) -> Result<Self, WrapperError> {
+        let mut faces = Vec::with_capacity(6);
+        for path in &paths {
+            let path = path.as_ref();
+            let img = image::open(path)
+                .map_err(|source| WrapperError::Image { path: path.to_path_buf(), source })?
+                .into_rgba8();
+            faces.push(img);
+        }
+
+        let size = faces[0].width();
+        if faces.iter().any(|face| face.width() != size || face.height() != size) {
+            return Err(WrapperError::Unsupported("cubemap faces which aren't squares of one size"));
+        }
+
+        let pixels: Vec<&[u8]> = faces.iter().map(|face| face.as_raw().as_slice()).collect();
+        Self::from_faces(size, &pixels, color_space)
+    }
+
+    /// Cubemap from six faces of RGBA8 pixels, `size`² each, top row first.
+    pub fn from_faces(size: u32, faces: &[&[u8]], color_space: ColorSpace) -> Result<Self, WrapperError> {
+        let face_len = pixel_data_len(&[size, size], 4);
+        if faces.len() != 6 || faces.iter().any(|face| Some(face.len()) != face_len) {
+            return Err(WrapperError::Unsupported("cubemaps other than six RGBA8 faces of size²"));
+        }
+
+        let cubemap = Self::empty(size, color_space.rgba_format())?;
+        for (face, pixels) in CUBE_FACES.into_iter().zip(faces) {
+            unsafe {
+                checked!(gl::TexSubImage2D(
+                    face,
+                    0,
+                    0,
+                    0,
+                    size.try_into().unwrap(),
+                    size.try_into().unwrap(),
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_ptr().cast(),
+                ));
+            }
+        }
+
+        Ok(cubemap)
+    }
+
+    /// Cubemap without contents, `size` is the width and height of every face.
+    pub fn empty(size: u32, format: TextureFormat) -> Result<Self, WrapperError> {
+        let cubemap = Self::new().ok_or(WrapperError::Allocation("cubemap"))?;
+        cubemap.allocate(size, format);
+
+        cubemap.set_filter(TextureFilter::Linear, TextureFilter::Linear);
+        cubemap.bind();
+        unsafe {
+            for wrap in [gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T, gl::TEXTURE_WRAP_R] {
+                checked!(gl::TexParameteri(gl::TEXTURE_CUBE_MAP, wrap, gl::CLAMP_TO_EDGE as GLint));
+            }
+        }
+
+        Ok(cubemap)
+    }
+
+    /// (Re)creates storage of level 0 of every face, previous contents are lost.
+    pub fn allocate(&self, size: u32, format: TextureFormat) {
+        let (pixel_format, pixel_type) = format.pixel_format();
+
+        self.bind();
+        for face in CUBE_FACES {
+            unsafe {
+                checked!(gl::TexImage2D(
+                    face,
+                    0,
+                    format.internal_format() as GLint,
+                    size.try_into().unwrap(),
+                    size.try_into().unwrap(),
+                    0,
+                    pixel_format,
+                    pixel_type,
+                    std::ptr::null(),
+                ));
+            }
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe { checked!(gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.0)) }
+    }
+
+    /// Makes the cubemap visible to `samplerCube`s with `unit` value.
+    pub fn bind_to_unit(&self, unit: u32) {
+        unsafe { checked!(gl::ActiveTexture(gl::TEXTURE0 + unit)) }
+        self.bind();
+    }
+
+    pub fn set_filter(&self, min: TextureFilter, mag: TextureFilter) {
+        self.bind();
+        unsafe {
+            checked!(gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, min as GLint));
+            checked!(gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, mag as GLint));
+        }
+    }
+
+    /// Fills the mip chain from level 0 and samples it trilinearly, e.g. so rough reflections
+    /// can read blurred levels.
+    pub fn generate_mipmaps(&self) {
+        self.bind();
+        unsafe {
+            checked!(gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP));
+            checked!(gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as GLint));
+        }
+    }
+
+    /// Depth cubemaps only: with `Some(func)` a `samplerCubeShadow` compares against the
+    /// reference depth, like `Texture2D::set_compare`.
+    pub fn set_compare(&self, func: Option<CompareFunc>) {
+        self.bind();
+        unsafe {
+            match func {
+                Some(func) => {
+                    checked!(gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as GLint));
+                    checked!(gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_COMPARE_FUNC, func as GLint));
+                }
+                None => checked!(gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_COMPARE_MODE, gl::NONE as GLint)),
+            }
+        }
+    }
+
+    pub fn set_label(&self, label: &str) {
+        object_label(gl::TEXTURE, self.0, label);
+    }
+
+    /// Gives up ownership of the GL object: it won't be deleted on drop.
+    pub fn leak(self) -> GLuint {
+        let id = self.0;
+        std::mem::forget(self);
+        id
+    }
+}
+
+impl Drop for Cubemap {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTextures(1, &self.0) }
+    }
+}
+
+/// Layers of one size and format behind a single `sampler2DArray`, e.g. animation frames or
+/// shadow cascades, picked in the shader by index without rebinding textures. Shaders get
+/// `sample_layer` and `sample_layer_blend` with `#include "texture/array.glsl"`, see
+/// `preprocess_file`.
+pub struct Texture2DArray(pub GLuint);
+
+#[allow(dead_code)]
+impl Texture2DArray {
+    pub fn new() -> Option<Self> {
+        let mut texture = 0;
+        unsafe {
+            checked!(gl::GenTextures(1, &mut texture));
+        }
+
+        if texture != 0 {
+            Some(Self(texture))
+        } else {
+            None
+        }
+    }
+
+    /// Loads PNG/JPEG images as consecutive layers, flipped like `Texture2D::from_file`.
+    pub fn from_files<P: AsRef<Path>>(paths: &[P], color_space: ColorSpace) -> Result<Self, WrapperError> {
+        let layers = paths.iter().map(Texture2D::decode_file).collect::<Result<Vec<_>, _>>()?;
+        let Some(first) = layers.first() else {
+            return Err(WrapperError::Unsupported("texture arrays without layers"));
+        };
+
+        let (width, height) = first.dimensions();
+        if layers.iter().any(|layer| layer.dimensions() != (width, height)) {
+            return Err(WrapperError::Unsupported("texture array layers of different sizes"));
+        }
+
+        let pixels: Vec<&[u8]> = layers.iter().map(|layer| layer.as_raw().as_slice()).collect();
+        Self::from_layers(width, height, &pixels, color_space)
+    }
+
+    /// One layer per slice of RGBA8 pixels, `width * height` each, bottom row first. Mipmaps
+    /// are generated and sampled trilinearly.
+    pub fn from_layers(width: u32, height: u32, layers: &[&[u8]], color_space: ColorSpace) -> Result<Self, WrapperError> {
+        let layer_len = pixel_data_len(&[width, height], 4);
+        if layers.is_empty() || layers.iter().any(|layer| Some(layer.len()) != layer_len) {
+            return Err(WrapperError::Unsupported("texture array layers other than RGBA8 of width * height"));
+        }
+
+        let texture = Self::empty(width, height, layers.len() as u32, color_space.rgba_format())?;
+        for (layer, pixels) in layers.iter().enumerate() {
+            texture.upload_layer(layer as u32, width, height, pixels)?;
+        }
+        texture.generate_mipmaps();
+        texture.set_mipmap_filter(TextureFilter::Linear, TextureFilter::Linear, TextureFilter::Linear);
+        texture.set_wrap(TextureWrap::Repeat, TextureWrap::Repeat);
+
+        Ok(texture)
+    }
+
+    /// Array without contents, e.g. for rendering into its layers with `attach_layer`.
+    pub fn empty(width: u32, height: u32, layers: u32, format: TextureFormat) -> Result<Self, WrapperError> {
+        let texture = Self::new().ok_or(WrapperError::Allocation("texture array"))?;
+        texture.allocate(width, height, layers, format);
+
+        texture.set_filter(TextureFilter::Linear, TextureFilter::Linear);
+        texture.set_wrap(TextureWrap::ClampToEdge, TextureWrap::ClampToEdge);
+
+        Ok(texture)
+    }
+
+    /// (Re)creates storage of level 0 for all layers, previous contents are lost.
+    pub fn allocate(&self, width: u32, height: u32, layers: u32, format: TextureFormat) {
+        let (pixel_format, pixel_type) = format.pixel_format();
+
+        self.bind();
+        unsafe {
+            checked!(gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                format.internal_format() as GLint,
+                width.try_into().unwrap(),
+                height.try_into().unwrap(),
+                layers.try_into().unwrap(),
+                0,
+                pixel_format,
+                pixel_type,
+                std::ptr::null(),
+            ));
+        }
+    }
+
+    /// Replaces level 0 of `layer` with RGBA8 pixels, `width`/`height` being the array's size.
+    /// Fails when `pixels` holds fewer than `width * height` of them.
+    pub fn upload_layer(&self, layer: u32, width: u32, height: u32, pixels: &[u8]) -> Result<(), WrapperError> {
+        if pixel_data_len(&[width, height], 4).is_none_or(|len| pixels.len() < len) {
+            return Err(WrapperError::Unsupported("texture array layers with fewer than width * height RGBA8 pixels"));
+        }
+
+        self.bind();
+        unsafe {
+            checked!(gl::TexSubImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                0,
+                0,
+                layer.try_into().unwrap(),
+                width.try_into().unwrap(),
+                height.try_into().unwrap(),
+                1,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr().cast(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fills the mip chain of every layer from level 0, `set_mipmap_filter` samples it.
+    pub fn generate_mipmaps(&self) {
+        self.bind();
+        unsafe { checked!(gl::GenerateMipmap(gl::TEXTURE_2D_ARRAY)) }
+    }
+
+    pub fn bind(&self) {
+        unsafe { checked!(gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.0)) }
+    }
+
+    /// Makes the array visible to `sampler2DArray`s with `unit` value.
+    pub fn bind_to_unit(&self, unit: u32) {
+        unsafe { checked!(gl::ActiveTexture(gl::TEXTURE0 + unit)) }
+        self.bind();
+    }
+
+    /// Renders into `layer` through `point` (e.g. `gl::DEPTH_ATTACHMENT`) of the bound
+    /// framebuffer, one layer at a time.
+    pub fn attach_layer(&self, point: GLenum, layer: u32) {
+        unsafe { checked!(gl::FramebufferTextureLayer(gl::FRAMEBUFFER, point, self.0, 0, layer as GLint)) };
+    }
+
+    pub fn set_filter(&self, min: TextureFilter, mag: TextureFilter) {
+        self.bind();
+        unsafe {
+            checked!(gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, min as GLint));
+            checked!(gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, mag as GLint));
+        }
+    }
+
+    /// Filters between mip levels too, like `Texture2D::set_mipmap_filter`.
+    pub fn set_mipmap_filter(&self, min: TextureFilter, mip: TextureFilter, mag: TextureFilter) {
+        self.bind();
+        unsafe {
+            checked!(gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, mipmap_min_filter(min, mip) as GLint));
+            checked!(gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, mag as GLint));
+        }
+    }
+
+    pub fn set_wrap(&self, s: TextureWrap, t: TextureWrap) {
+        self.bind();
+        unsafe {
+            checked!(gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, s as GLint));
+            checked!(gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, t as GLint));
+        }
+    }
+
+    /// Depth arrays only: with `Some(func)` a `sampler2DArrayShadow` compares against the
+    /// reference depth, like `Texture2D::set_compare`.
+    pub fn set_compare(&self, func: Option<CompareFunc>) {
+        self.bind();
+        unsafe {
+            match func {
+                Some(func) => {
+                    checked!(gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as GLint));
+                    checked!(gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_COMPARE_FUNC, func as GLint));
+                }
+                None => checked!(gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_COMPARE_MODE, gl::NONE as GLint)),
+            }
+        }
+    }
+
+    pub fn set_label(&self, label: &str) {
+        object_label(gl::TEXTURE, self.0, label);
+    }
+
+    /// Gives up ownership of the GL object: it won't be deleted on drop.
+    pub fn leak(self) -> GLuint {
+        let id = self.0;
+        std::mem::forget(self);
+        id
+    }
+}
+
+impl Drop for Texture2DArray {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTextures(1, &self.0) }
+    }
+}
+
+/// Volume of texels sampled with 3D coordinates by a `sampler3D`, e.g. density fields or color
+/// grading lookup tables. Clamps at the edges on every axis unless told otherwise.
+pub struct Texture3D(pub GLuint);
+
+#[allow(dead_code)]
+impl Texture3D {
+    pub fn new() -> Option<Self> {
+        let mut texture = 0;
+        unsafe {
+            checked!(gl::GenTextures(1, &mut texture));
+        }
+
+        if texture != 0 {
+            Some(Self(texture))
+        } else {
+            None
+        }
+    }
+
+    /// RGBA8 texels, x fastest, then y, then z.
+    pub fn from_rgba(width: u32, height: u32, depth: u32, pixels: &[u8], color_space: ColorSpace) -> Result<Self, WrapperError> {
+        if pixel_data_len(&[width, height, depth], 4) != Some(pixels.len()) {
+            return Err(WrapperError::Unsupported("3D textures other than RGBA8 of width * height * depth"));
+        }
+
+        let texture = Self::empty(width, height, depth, color_space.rgba_format())?;
+        texture.upload(width, height, depth, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_ptr().cast());
+        Ok(texture)
+    }
+
+    /// RGBA32F texels, laid out like `from_rgba`.
+    pub fn from_rgba_f32(width: u32, height: u32, depth: u32, pixels: &[f32]) -> Result<Self, WrapperError> {
+        if pixel_data_len(&[width, height, depth], 4) != Some(pixels.len()) {
+            return Err(WrapperError::Unsupported("3D textures other than RGBA32F of width * height * depth"));
+        }
+
+        let texture = Self::empty(width, height, depth, TextureFormat::Rgba32F)?;
+        texture.upload(width, height, depth, gl::RGBA, gl::FLOAT, pixels.as_ptr().cast());
+        Ok(texture)
+    }
+
+    /// Single channel 8-bit texels, e.g. a density volume, read as `.r`.
+    pub fn from_r8(width: u32, height: u32, depth: u32, texels: &[u8]) -> Result<Self, WrapperError> {
+        if pixel_data_len(&[width, height, depth], 1) != Some(texels.len()) {
+            return Err(WrapperError::Unsupported("3D textures other than R8 of width * height * depth"));
+        }
+
+        let texture = Self::empty(width, height, depth, TextureFormat::R8)?;
+        unsafe { checked!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1)) };
+        texture.upload(width, height, depth, gl::RED, gl::UNSIGNED_BYTE, texels.as_ptr().cast());
+        unsafe { checked!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4)) };
+        Ok(texture)
+    }
+
+    /// `size`³ lookup table mapping every color to itself, the starting point for color
+    /// grading: sample it with the color as coordinate, edit its texels to change the mapping.
+    pub fn identity_lut(size: u32) -> Result<Self, WrapperError> {
+        let scale = 255.0 / (size.max(2) - 1) as f32;
+        let len = pixel_data_len(&[size; 3], 4).ok_or(WrapperError::Unsupported("lookup tables with more texels than fit in memory"))?;
+        let mut pixels = Vec::with_capacity(len);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    pixels.extend([r, g, b].map(|c| (c as f32 * scale).round() as u8));
+                    pixels.push(255);
+                }
+            }
+        }
+
+        Self::from_rgba(size, size, size, &pixels, ColorSpace::Linear)
+    }
+
+    /// Texture without contents, e.g. to be written by a compute shader.
+    pub fn empty(width: u32, height: u32, depth: u32, format: TextureFormat) -> Result<Self, WrapperError> {
+        let texture = Self::new().ok_or(WrapperError::Allocation("3D texture"))?;
+        texture.allocate(width, height, depth, format);
+
+        texture.set_filter(TextureFilter::Linear, TextureFilter::Linear);
+        texture.set_wrap(TextureWrap::ClampToEdge, TextureWrap::ClampToEdge, TextureWrap::ClampToEdge);
+
+        Ok(texture)
+    }
+
+    /// (Re)creates storage of level 0, previous contents are lost.
+    pub fn allocate(&self, width: u32, height: u32, depth: u32, format: TextureFormat) {
+        let (pixel_format, pixel_type) = format.pixel_format();
+
+        self.bind();
+        unsafe {
+            checked!(gl::TexImage3D(
+                gl::TEXTURE_3D,
+                0,
+                format.internal_format() as GLint,
+                width.try_into().unwrap(),
+                height.try_into().unwrap(),
+                depth.try_into().unwrap(),
+                0,
+                pixel_format,
+                pixel_type,
+                std::ptr::null(),
+            ));
+        }
+    }
+
+    fn upload(&self, width: u32, height: u32, depth: u32, format: GLenum, kind: GLenum, pixels: *const std::ffi::c_void) {
+        self.bind();
+        unsafe {
+            checked!(gl::TexSubImage3D(
+                gl::TEXTURE_3D,
+                0,
+                0,
+                0,
+                0,
+                width.try_into().unwrap(),
+                height.try_into().unwrap(),
+                depth.try_into().unwrap(),
+                format,
+                kind,
+                pixels,
+            ));
+        }
+    }
+
+    pub fn bind(&self) {
+        unsafe { checked!(gl::BindTexture(gl::TEXTURE_3D, self.0)) }
+    }
+
+    /// Makes the texture visible to `sampler3D`s with `unit` value.
+    pub fn bind_to_unit(&self, unit: u32) {
+        unsafe { checked!(gl::ActiveTexture(gl::TEXTURE0 + unit)) }
+        self.bind();
+    }
+
+    pub fn set_filter(&self, min: TextureFilter, mag: TextureFilter) {
+        self.bind();
+        unsafe {
+            checked!(gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MIN_FILTER, min as GLint));
+            checked!(gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MAG_FILTER, mag as GLint));
+        }
+    }
+
+    pub fn set_wrap(&self, s: TextureWrap, t: TextureWrap, r: TextureWrap) {
+        self.bind();
+        unsafe {
+            checked!(gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_S, s as GLint));
+            checked!(gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_T, t as GLint));
+            checked!(gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_WRAP_R, r as GLint));
+        }
+    }
+
+    pub fn set_label(&self, label: &str) {
+        object_label(gl::TEXTURE, self.0, label);
+    }
+
+    /// Gives up ownership of the GL object: it won't be deleted on drop.
+    pub fn leak(self) -> GLuint {
+        let id = self.0;
+        std::mem::forget(self);
+        id
+    }
+}
+
+impl Drop for Texture3D {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTextures(1, &self.0) }
+    }
+}
+
+/// Filtering, wrapping and LOD state apart from any texture. Bound to a unit it takes over
+/// from the parameters of the texture bound there, so one texture can be read differently by
+/// different passes. Binding no sampler goes back to the texture's own parameters.
+pub struct Sampler(pub GLuint);
+
+#[allow(dead_code)]
+impl Sampler {
+    pub fn new() -> Option<Self> {
+        let mut sampler = 0;
+        unsafe { checked!(gl::GenSamplers(1, &mut sampler)) };
+
+        if sampler != 0 {
+            Some(Self(sampler))
+        } else {
+            None
+        }
+    }
+
+    /// Sampler with the given filter and wrap mode on all axes, without mipmaps.
+    pub fn with(filter: TextureFilter, wrap: TextureWrap) -> Result<Self, WrapperError> {
+        let sampler = Self::new().ok_or(WrapperError::Allocation("sampler"))?;
+        sampler.set_filter(filter, filter);
+        sampler.set_wrap(wrap, wrap, wrap);
+        Ok(sampler)
+    }
+
+    /// Used for every texture sampled through `unit` until `unbind` or another sampler.
+    pub fn bind(&self, unit: u32) {
+        unsafe { checked!(gl::BindSampler(unit, self.0)) }
+    }
+
+    pub fn unbind(unit: u32) {
+        unsafe { checked!(gl::BindSampler(unit, 0)) }
+    }
+
+    pub fn set_filter(&self, min: TextureFilter, mag: TextureFilter) {
+        self.set_i32(gl::TEXTURE_MIN_FILTER, min as GLint);
+        self.set_i32(gl::TEXTURE_MAG_FILTER, mag as GLint);
+    }
+
+    /// Filters between mip levels too, see `mipmap_min_filter`. Textures without mipmaps
+    /// sampled like this are incomplete and read as black.
+    pub fn set_mipmap_filter(&self, min: TextureFilter, mip: TextureFilter, mag: TextureFilter) {
+        self.set_i32(gl::TEXTURE_MIN_FILTER, mipmap_min_filter(min, mip) as GLint);
+        self.set_i32(gl::TEXTURE_MAG_FILTER, mag as GLint);
+    }
+
+    pub fn set_wrap(&self, s: TextureWrap, t: TextureWrap, r: TextureWrap) {
+        self.set_i32(gl::TEXTURE_WRAP_S, s as GLint);
+        self.set_i32(gl::TEXTURE_WRAP_T, t as GLint);
+        self.set_i32(gl::TEXTURE_WRAP_R, r as GLint);
+    }
+
+    /// Color sampled outside the texture with `TextureWrap::ClampToBorder`.
+    pub fn set_border_color(&self, color: [f32; 4]) {
+        unsafe { checked!(gl::SamplerParameterfv(self.0, gl::TEXTURE_BORDER_COLOR, color.as_ptr())) }
+    }
+
+    /// Mip levels that can be sampled, level 0 being the full size one.
+    pub fn set_lod_range(&self, min: f32, max: f32) {
+        self.set_f32(gl::TEXTURE_MIN_LOD, min);
+        self.set_f32(gl::TEXTURE_MAX_LOD, max);
+    }
+
+    /// Added to the computed level, positive values blur.
+    pub fn set_lod_bias(&self, bias: f32) {
+        self.set_f32(gl::TEXTURE_LOD_BIAS, bias);
+    }
+
+    /// Anisotropic filtering with up to `anisotropy` samples, clamped to `max_anisotropy`;
+    /// 1 turns it off. Does nothing when the context has no anisotropic filtering.
+    pub fn set_anisotropy(&self, anisotropy: f32) {
+        let max = max_anisotropy();
+        if max > 1.0 {
+            self.set_f32(gl_ext::TEXTURE_MAX_ANISOTROPY, anisotropy.clamp(1.0, max));
+        }
+    }
+
+    /// Depth textures only, like `Texture2D::set_compare`.
+    pub fn set_compare(&self, func: Option<CompareFunc>) {
+        match func {
+            Some(func) => {
+                self.set_i32(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as GLint);
+                self.set_i32(gl::TEXTURE_COMPARE_FUNC, func as GLint);
+            }
+            None => self.set_i32(gl::TEXTURE_COMPARE_MODE, gl::NONE as GLint),
+        }
+    }
+
+    fn set_i32(&self, pname: GLenum, value: GLint) {
+        unsafe { checked!(gl::SamplerParameteri(self.0, pname, value)) };
+    }
+
+    fn set_f32(&self, pname: GLenum, value: f32) {
+        unsafe { checked!(gl::SamplerParameterf(self.0, pname, value)) };
+    }
+
+    /// Name shown for the object by debuggers, only after the sampler was bound once.
+    pub fn set_label(&self, label: &str) {
+        object_label(gl::SAMPLER, self.0, label);
+    }
+
+    /// Gives up ownership of the GL object: it won't be deleted on drop.
+    pub fn leak(self) -> GLuint {
+        let id = self.0;
+        std::mem::forget(self);
+        id
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteSamplers(1, &self.0) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum ShaderType {
+    Vertex = gl::VERTEX_SHADER as isize,
+    Fragment = gl::FRAGMENT_SHADER as isize,
+    Geometry = gl::GEOMETRY_SHADER as isize,
+    /// GL 4.0+
+    TessControl = gl::TESS_CONTROL_SHADER as isize,
+    /// GL 4.0+
+    TessEvaluation = gl::TESS_EVALUATION_SHADER as isize,
+    /// GL 4.3+
+    Compute = gl::COMPUTE_SHADER as isize,
+}
+
+pub struct Shader(pub GLuint);
+
+/// first word of every SPIR-V module
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+/// Whether the current context can load SPIR-V shaders, core in 4.6.
+#[allow(dead_code)]
+pub fn spirv_supported() -> bool {
+    gl_ext::SpecializeShader::is_loaded() && Extensions::query().spirv()
+}
+
+#[allow(dead_code)]
+impl Shader {
+    pub fn from_source(shader_type: ShaderType, src: &str) -> Result<Self, WrapperError> {
+        let shader = Self::new(shader_type).ok_or(WrapperError::Allocation("shader"))?;
         shader.set_source(src);
         shader.compile();
 
         if shader.compile_success() {
             Ok(shader)
         } else {
-            let msg = shader.info_log();
-            shader.delete();
-            Err(msg)
+            Err(WrapperError::ShaderCompile { stage: shader_type, log: shader.info_log(), file: None })
+        }
+    }
+
+    /// Reads the file with its `#include`s expanded, see `preprocessor`.
+    pub fn from_file(shader_type: ShaderType, path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        Self::from_preprocessed(shader_type, &preprocess_file(path)?)
+    }
+
+    pub fn from_preprocessed(shader_type: ShaderType, src: &PreprocessedSource) -> Result<Self, WrapperError> {
+        Self::from_source(shader_type, &src.source).map_err(|err| match err {
+            WrapperError::ShaderCompile { stage, log, .. } => WrapperError::ShaderCompile {
+                stage,
+                log: annotate_compile_log(&log, src),
+                file: src.files().first().cloned(),
+            },
+            other => other,
+        })
+    }
+
+    /// Precompiled SPIR-V module, e.g. from `glslangValidator -G`, specialized with `entry_point`
+    /// and `(constant_id, value)` pairs for its specialization constants, values as raw bits.
+    /// Needs GL 4.6 or ARB_gl_spirv, see `spirv_supported`.
+    pub fn from_spirv(
+        shader_type: ShaderType,
+        binary: &[u8],
+        entry_point: &str,
+        constants: &[(GLuint, GLuint)],
+    ) -> Result<Self, WrapperError> {
+        if !spirv_supported() {
+            return Err(WrapperError::Unsupported("SPIR-V shaders need GL 4.6 or ARB_gl_spirv"));
+        }
+        if !binary.len().is_multiple_of(4) || binary.get(..4) != Some(&SPIRV_MAGIC.to_le_bytes()[..]) {
+            return Err(WrapperError::ShaderCompile {
+                stage: shader_type,
+                log: "not a little endian SPIR-V module".to_string(),
+                file: None,
+            });
+        }
+
+        let entry_point = CString::new(entry_point)
+            .map_err(|_| WrapperError::Unsupported("SPIR-V entry points containing NUL bytes"))?;
+
+        let shader = Self::new(shader_type).ok_or(WrapperError::Allocation("shader"))?;
+        let (indices, values): (Vec<GLuint>, Vec<GLuint>) = constants.iter().copied().unzip();
+        unsafe {
+            checked!(gl::ShaderBinary(
+                1,
+                &shader.0,
+                gl_ext::SHADER_BINARY_FORMAT_SPIR_V,
+                binary.as_ptr().cast(),
+                binary.len() as GLsizei,
+            ));
+            checked!(gl_ext::SpecializeShader(
+                shader.0,
+                entry_point.as_ptr(),
+                constants.len() as GLuint,
+                indices.as_ptr(),
+                values.as_ptr(),
+            ));
+        }
+
+        if shader.compile_success() {
+            Ok(shader)
+        } else {
+            Err(WrapperError::ShaderCompile { stage: shader_type, log: shader.info_log(), file: None })
         }
     }
 
+    /// `.spv` file with a `main` entry point and default specialization constants.
+    pub fn from_spirv_file(shader_type: ShaderType, path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        let path = path.as_ref();
+        let binary = std::fs::read(path).map_err(|source| WrapperError::Io { path: path.to_path_buf(), source })?;
+
+        Self::from_spirv(shader_type, &binary, "main", &[]).map_err(|err| match err {
+            WrapperError::ShaderCompile { stage, log, .. } => {
+                WrapperError::ShaderCompile { stage, log, file: Some(path.to_path_buf()) }
+            }
+            other => other,
+        })
+    }
+
     pub fn new(shader_type: ShaderType) -> Option<Self> {
-        let shader = unsafe { gl::CreateShader(shader_type as GLenum) };
+        let shader = unsafe { checked!(gl::CreateShader(shader_type as GLenum)) };
         if shader != 0 {
             Some(Self(shader))
         } else {
@@ -157,80 +2841,254 @@ impl Shader {
         }
     }
 
-    pub fn delete(&self) {
-        unsafe { gl::DeleteShader(self.0) }
+    /// Name shown for the object by debuggers like RenderDoc or apitrace.
+    pub fn set_label(&self, label: &str) {
+        object_label(gl::SHADER, self.0, label);
+    }
+
+    /// Gives up ownership of the GL object: it won't be deleted on drop.
+    pub fn leak(self) -> GLuint {
+        let id = self.0;
+        std::mem::forget(self);
+        id
     }
 
     pub fn set_source(&self, src: &str) {
         unsafe {
-            gl::ShaderSource(
+            checked!(gl::ShaderSource(
                 self.0,
                 1,
                 &(src.as_bytes().as_ptr().cast()),
                 &(src.len().try_into().unwrap()),
-            );
+            ));
         }
     }
 
     pub fn compile(&self) {
-        unsafe { gl::CompileShader(self.0); }
+        unsafe { checked!(gl::CompileShader(self.0)); }
     }
 
     pub fn compile_success(&self) -> bool {
         let mut compiled = 0;
-        unsafe { gl::GetShaderiv(self.0, gl::COMPILE_STATUS, &mut compiled) };
+        unsafe { checked!(gl::GetShaderiv(self.0, gl::COMPILE_STATUS, &mut compiled)) };
         compiled == i32::from(gl::TRUE)
     }
 
     pub fn info_log(&self) -> String {
         let mut needed_len = 0;
-        unsafe { gl::GetShaderiv(self.0, gl::INFO_LOG_LENGTH, &mut needed_len) };
+        unsafe { checked!(gl::GetShaderiv(self.0, gl::INFO_LOG_LENGTH, &mut needed_len)) };
 
         let mut vec: Vec<u8> = Vec::with_capacity(needed_len.try_into().unwrap());
         let mut len_written = 0_i32;
 
         unsafe {
-            gl::GetShaderInfoLog(
+            checked!(gl::GetShaderInfoLog(
                 self.0,
                 vec.capacity().try_into().unwrap(),
                 &mut len_written,
                 vec.as_mut_ptr().cast(),
-            );
+            ));
             vec.set_len(len_written.try_into().unwrap());
         }
         String::from_utf8_lossy(&vec).into_owned()
     }
 }
 
-pub struct ShaderProgram(pub GLuint);
+/// Prefixes every log line which mentions a source line with `path:line:` of the file the line
+/// originally came from, so compile errors point straight to it. Understands Mesa `0:12(5):`,
+/// NVIDIA `0(12) :` and AMD/Intel `ERROR: 0:12:` styles; lines in other formats are kept as they are.
+fn annotate_compile_log(log: &str, src: &PreprocessedSource) -> String {
+    log.lines()
+        .map(|line| match compile_log_line_number(line).and_then(|line_no| src.origin(line_no)) {
+            Some((path, line_no)) => format!("{}:{}: {}", path.display(), line_no, line.trim()),
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn compile_log_line_number(line: &str) -> Option<usize> {
+    let line = line.trim_start();
+    let rest = ["ERROR: ", "WARNING: "]
+        .iter()
+        .find_map(|prefix| line.strip_prefix(prefix))
+        .unwrap_or(line);
+
+    // skip the source string index
+    let rest = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    let rest = rest.strip_prefix(':').or_else(|| rest.strip_prefix('('))?;
+
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+impl Drop for Shader {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteShader(self.0) }
+    }
+}
+
+/// Uniform value which can be stored and applied later with `ShaderProgram::set_uniform`.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniformValue {
+    I32(i32),
+    F32(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Mat4([f32; 16]),
+}
+
+pub struct ShaderProgram(pub GLuint, RefCell<HashMap<String, GLint>>);
 
+#[allow(dead_code)]
 impl ShaderProgram {
-    pub fn from_vertex_fragment(vert_src: &str, frag_src: &str) -> Result<Self, String> {
-        let p_id = Self::new().ok_or_else(|| "Could not allocate a program".to_string())?;
+    pub fn from_vertex_fragment(vert_src: &str, frag_src: &str) -> Result<Self, WrapperError> {
+        let vertex = Shader::from_source(ShaderType::Vertex, vert_src)?;
+        let fragment = Shader::from_source(ShaderType::Fragment, frag_src)?;
+
+        Self::from_shaders([vertex, fragment])
+    }
+
+    pub fn from_files(vert_path: impl AsRef<Path>, frag_path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        let vertex = Shader::from_file(ShaderType::Vertex, vert_path)?;
+        let fragment = Shader::from_file(ShaderType::Fragment, frag_path)?;
+
+        Self::from_shaders([vertex, fragment])
+    }
+
+    /// Program of precompiled SPIR-V stages, see `Shader::from_spirv_file`.
+    pub fn from_spirv_files(vert_path: impl AsRef<Path>, frag_path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        let vertex = Shader::from_spirv_file(ShaderType::Vertex, vert_path)?;
+        let fragment = Shader::from_spirv_file(ShaderType::Fragment, frag_path)?;
+
+        Self::from_shaders([vertex, fragment])
+    }
+
+    /// The SPIR-V stages when the context can load them, the GLSL sources they were
+    /// compiled from otherwise.
+    pub fn from_spirv_or_glsl(
+        spirv: (impl AsRef<Path>, impl AsRef<Path>),
+        glsl: (impl AsRef<Path>, impl AsRef<Path>),
+    ) -> Result<Self, WrapperError> {
+        if spirv_supported() {
+            Self::from_spirv_files(spirv.0, spirv.1)
+        } else {
+            Self::from_files(glsl.0, glsl.1)
+        }
+    }
+
+    /// Needs a GL 4.3 context, see `Setup::with_version`.
+    pub fn from_compute(src: &str) -> Result<Self, WrapperError> {
+        if context_version() < (4, 3) {
+            return Err(WrapperError::Unsupported("compute shaders need a GL 4.3 context"));
+        }
+
+        Self::from_shaders([Shader::from_source(ShaderType::Compute, src)?])
+    }
+
+    pub fn from_compute_file(path: impl AsRef<Path>) -> Result<Self, WrapperError> {
+        if context_version() < (4, 3) {
+            return Err(WrapperError::Unsupported("compute shaders need a GL 4.3 context"));
+        }
+
+        Self::from_shaders([Shader::from_file(ShaderType::Compute, path)?])
+    }
+
+    /// Runs the compute program over `x * y * z` work groups. Results written to buffers or
+    /// images are only guaranteed to be visible after `storage_barrier`/`image_barrier`.
+    pub fn dispatch(&self, x: GLuint, y: GLuint, z: GLuint) {
+        self.use_program();
+        unsafe { checked!(gl::DispatchCompute(x, y, z)) };
+    }
+
+    /// Builder for programs with stages beyond vertex + fragment:
+    ///
+    /// ```ignore
+    /// let program = ShaderProgram::from_stages()
+    ///     .vertex(vert_src)
+    ///     .geometry(geom_src)
+    ///     .fragment(frag_src)
+    ///     .build()?;
+    /// ```
+    pub fn from_stages() -> ProgramBuilder {
+        ProgramBuilder::default()
+    }
+
+    pub fn from_shaders(shaders: impl IntoIterator<Item = Shader>) -> Result<Self, WrapperError> {
+        Self::link_shaders(shaders, false)
+    }
 
-        let vertex = Shader::from_source(ShaderType::Vertex, vert_src)
-            .map_err(|e| format!("Vertex Compile Error: {}", e))?;
-        let fragment = Shader::from_source(ShaderType::Fragment, frag_src)
-            .map_err(|e| format!("Fragment Compile Error: {}", e))?;
+    /// Like `from_shaders`, hinting the driver that `binary` will be called on the program.
+    pub fn from_shaders_retrievable(shaders: impl IntoIterator<Item = Shader>) -> Result<Self, WrapperError> {
+        Self::link_shaders(shaders, true)
+    }
+
+    fn link_shaders(shaders: impl IntoIterator<Item = Shader>, retrievable: bool) -> Result<Self, WrapperError> {
+        let p_id = Self::new().ok_or(WrapperError::Allocation("program"))?;
+        if retrievable {
+            unsafe { checked!(gl::ProgramParameteri(p_id.0, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, GLint::from(gl::TRUE))) };
+        }
 
-        p_id.attach_shader(vertex);
-        p_id.attach_shader(fragment);
+        for shader in shaders {
+            p_id.attach_shader(shader);
+        }
         p_id.link_program();
 
         if p_id.link_successful() {
             Ok(p_id)
         } else {
-            let msg = format!("Program Link Error: {}", p_id.info_log());
-            p_id.delete();
-            Err(msg)
+            Err(WrapperError::ProgramLink(p_id.info_log()))
+        }
+    }
+
+    /// Program from a binary `binary` returned earlier. Drivers reject binaries of other
+    /// drivers or versions, which fails like a link error.
+    pub fn from_binary(format: GLenum, data: &[u8]) -> Result<Self, WrapperError> {
+        // formats the driver doesn't list raise INVALID_ENUM instead of failing the link
+        let mut count = 0;
+        unsafe { checked!(gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut count)) };
+        let mut formats = vec![0; count.max(0) as usize];
+        if !formats.is_empty() {
+            unsafe { checked!(gl::GetIntegerv(gl::PROGRAM_BINARY_FORMATS, formats.as_mut_ptr())) };
+        }
+        if !formats.contains(&(format as GLint)) {
+            return Err(WrapperError::ProgramLink(format!("unknown program binary format {:#x}", format)));
+        }
+
+        let p_id = Self::new().ok_or(WrapperError::Allocation("program"))?;
+        unsafe { checked!(gl::ProgramBinary(p_id.0, format, data.as_ptr().cast(), data.len() as GLsizei)) };
+
+        if p_id.link_successful() {
+            Ok(p_id)
+        } else {
+            Err(WrapperError::ProgramLink(p_id.info_log()))
+        }
+    }
+
+    /// Format and contents of the linked program, `None` when the driver has no binary
+    /// formats (needs GL 4.1 or ARB_get_program_binary).
+    pub fn binary(&self) -> Option<(GLenum, Vec<u8>)> {
+        let mut len = 0;
+        unsafe { checked!(gl::GetProgramiv(self.0, gl::PROGRAM_BINARY_LENGTH, &mut len)) };
+        if len <= 0 {
+            return None;
         }
+
+        let mut data = vec![0_u8; len as usize];
+        let (mut written, mut format) = (0, 0);
+        unsafe { checked!(gl::GetProgramBinary(self.0, len, &mut written, &mut format, data.as_mut_ptr().cast())) };
+        data.truncate(written.max(0) as usize);
+
+        (!data.is_empty()).then_some((format, data))
     }
 
     pub fn new() -> Option<Self> {
         unsafe {
-            let id = gl::CreateProgram();
+            let id = checked!(gl::CreateProgram());
             if id != 0 {
-                Some(Self(id))
+                Some(Self(id, RefCell::default()))
             } else {
                 None
             }
@@ -238,131 +3096,711 @@ impl ShaderProgram {
     }
 
     pub fn attach_shader(&self, shader: Shader) {
-        unsafe { gl::AttachShader(self.0, shader.0) }
+        unsafe { checked!(gl::AttachShader(self.0, shader.0)) }
     }
 
     pub fn link_program(&self) {
-        unsafe { gl::LinkProgram(self.0) }
+        unsafe { checked!(gl::LinkProgram(self.0)) }
     }
 
     pub fn link_successful(&self) -> bool {
         let mut linked = 0;
         unsafe {
-            gl::GetProgramiv(self.0, gl::LINK_STATUS, &mut linked);
+            checked!(gl::GetProgramiv(self.0, gl::LINK_STATUS, &mut linked));
         }
         linked == i32::from(gl::TRUE)
     }
 
     pub fn info_log(&self) -> String {
         let mut needed_len = 0;
-        unsafe { gl::GetProgramiv(self.0, gl::INFO_LOG_LENGTH, &mut needed_len) };
+        unsafe { checked!(gl::GetProgramiv(self.0, gl::INFO_LOG_LENGTH, &mut needed_len)) };
 
         let mut vec: Vec<u8> = Vec::with_capacity(needed_len.try_into().unwrap());
         let mut len_written = 0_i32;
 
         unsafe {
-            gl::GetProgramInfoLog(
+            checked!(gl::GetProgramInfoLog(
                 self.0,
                 vec.capacity().try_into().unwrap(),
                 &mut len_written,
                 vec.as_mut_ptr().cast(),
-            );
+            ));
             vec.set_len(len_written.try_into().unwrap());
         }
         String::from_utf8_lossy(&vec).into_owned()
     }
 
     pub fn use_program(&self) {
-        unsafe { gl::UseProgram(self.0) }
+        unsafe { checked!(gl::UseProgram(self.0)) }
+    }
+
+    /// Connects `uniform <name> { ... }` block to the UBO binding point (GL 3.3 has no
+    /// `layout(binding = N)` for blocks).
+    pub fn bind_uniform_block(&self, name: &str, binding: GLuint) {
+        let c_name = CString::new(name).expect("Block name must not contain NUL bytes");
+        unsafe {
+            let index = checked!(gl::GetUniformBlockIndex(self.0, c_name.as_ptr()));
+            if index != gl::INVALID_INDEX {
+                checked!(gl::UniformBlockBinding(self.0, index, binding));
+            }
+        }
+    }
+
+    /// Connects `buffer <name> { ... }` block to the SSBO binding point, for shaders
+    /// without `layout(binding = N)`. Needs GL 4.3+.
+    pub fn bind_storage_block(&self, name: &str, binding: GLuint) -> Result<(), WrapperError> {
+        if context_version() < (4, 3) {
+            return Err(WrapperError::Unsupported("shader storage blocks need OpenGL 4.3"));
+        }
+
+        let c_name = CString::new(name).expect("Block name must not contain NUL bytes");
+        unsafe {
+            let index = checked!(gl::GetProgramResourceIndex(self.0, gl::SHADER_STORAGE_BLOCK, c_name.as_ptr()));
+            if index != gl::INVALID_INDEX {
+                checked!(gl::ShaderStorageBlockBinding(self.0, index, binding));
+            }
+        }
+        Ok(())
+    }
+
+    /// Looks the uniform up once and remembers its location (`-1` for unknown names,
+    /// which GL silently ignores in `glUniform*`).
+    pub fn get_uniform_location(&self, name: &str) -> GLint {
+        if let Some(&location) = self.1.borrow().get(name) {
+            return location;
+        }
+
+        let c_name = CString::new(name).expect("Uniform name must not contain NUL bytes");
+        let location = unsafe { checked!(gl::GetUniformLocation(self.0, c_name.as_ptr())) };
+        self.1.borrow_mut().insert(name.to_string(), location);
+
+        location
+    }
+
+    // uniform setters apply to the program, so it has to be in use at the moment of call
+
+    pub fn set_i32(&self, name: &str, value: i32) {
+        unsafe { checked!(gl::Uniform1i(self.get_uniform_location(name), value)) };
+    }
+
+    pub fn set_u32(&self, name: &str, value: u32) {
+        unsafe { checked!(gl::Uniform1ui(self.get_uniform_location(name), value)) };
+    }
+
+    pub fn set_f32(&self, name: &str, value: f32) {
+        unsafe { checked!(gl::Uniform1f(self.get_uniform_location(name), value)) };
+    }
+
+    pub fn set_vec2(&self, name: &str, value: [f32; 2]) {
+        unsafe { checked!(gl::Uniform2f(self.get_uniform_location(name), value[0], value[1])) };
+    }
+
+    pub fn set_vec3(&self, name: &str, value: [f32; 3]) {
+        unsafe { checked!(gl::Uniform3f(self.get_uniform_location(name), value[0], value[1], value[2])) };
+    }
+
+    pub fn set_vec4(&self, name: &str, value: [f32; 4]) {
+        unsafe { checked!(gl::Uniform4f(self.get_uniform_location(name), value[0], value[1], value[2], value[3])) };
+    }
+
+    /// `value` is expected in column-major order, as GLSL stores it.
+    pub fn set_mat4(&self, name: &str, value: &[f32; 16]) {
+        unsafe { checked!(gl::UniformMatrix4fv(self.get_uniform_location(name), 1, gl::FALSE, value.as_ptr())) };
+    }
+
+    pub fn set_uniform(&self, name: &str, value: &UniformValue) {
+        match value {
+            UniformValue::I32(v) => self.set_i32(name, *v),
+            UniformValue::F32(v) => self.set_f32(name, *v),
+            UniformValue::Vec2(v) => self.set_vec2(name, *v),
+            UniformValue::Vec3(v) => self.set_vec3(name, *v),
+            UniformValue::Vec4(v) => self.set_vec4(name, *v),
+            UniformValue::Mat4(v) => self.set_mat4(name, v),
+        }
+    }
+
+    /// Active uniforms, attributes and uniform blocks of the linked program.
+    pub fn reflect(&self) -> ProgramReflection {
+        ProgramReflection::query(self)
+    }
+
+    /// Name shown for the object by debuggers like RenderDoc or apitrace.
+    pub fn set_label(&self, label: &str) {
+        object_label(gl::PROGRAM, self.0, label);
+    }
+
+    /// Gives up ownership of the GL object: it won't be deleted on drop.
+    pub fn leak(self) -> GLuint {
+        let id = self.0;
+        std::mem::forget(self);
+        id
+    }
+}
+
+enum StageSource {
+    Source(String),
+    File(PathBuf),
+}
+
+/// Collects stage sources and compiles them all at once in `build`.
+#[derive(Default)]
+pub struct ProgramBuilder {
+    stages: Vec<(ShaderType, StageSource)>,
+}
+
+#[allow(dead_code)]
+impl ProgramBuilder {
+    pub fn stage(mut self, shader_type: ShaderType, src: &str) -> Self {
+        self.stages.push((shader_type, StageSource::Source(src.to_string())));
+        self
+    }
+
+    pub fn stage_file(mut self, shader_type: ShaderType, path: impl AsRef<Path>) -> Self {
+        self.stages.push((shader_type, StageSource::File(path.as_ref().to_path_buf())));
+        self
+    }
+
+    pub fn vertex(self, src: &str) -> Self {
+        self.stage(ShaderType::Vertex, src)
+    }
+
+    pub fn geometry(self, src: &str) -> Self {
+        self.stage(ShaderType::Geometry, src)
+    }
+
+    pub fn fragment(self, src: &str) -> Self {
+        self.stage(ShaderType::Fragment, src)
+    }
+
+    pub fn tess_control(self, src: &str) -> Self {
+        self.stage(ShaderType::TessControl, src)
+    }
+
+    pub fn tess_evaluation(self, src: &str) -> Self {
+        self.stage(ShaderType::TessEvaluation, src)
     }
 
-    pub fn delete(&self) {
+    pub fn vertex_file(self, path: impl AsRef<Path>) -> Self {
+        self.stage_file(ShaderType::Vertex, path)
+    }
+
+    pub fn geometry_file(self, path: impl AsRef<Path>) -> Self {
+        self.stage_file(ShaderType::Geometry, path)
+    }
+
+    pub fn fragment_file(self, path: impl AsRef<Path>) -> Self {
+        self.stage_file(ShaderType::Fragment, path)
+    }
+
+    pub fn tess_control_file(self, path: impl AsRef<Path>) -> Self {
+        self.stage_file(ShaderType::TessControl, path)
+    }
+
+    pub fn tess_evaluation_file(self, path: impl AsRef<Path>) -> Self {
+        self.stage_file(ShaderType::TessEvaluation, path)
+    }
+
+    pub fn build(self) -> Result<ShaderProgram, WrapperError> {
+        let shaders = self
+            .stages
+            .into_iter()
+            .map(|(shader_type, src)| match src {
+                StageSource::Source(src) => Shader::from_source(shader_type, &src),
+                StageSource::File(path) => Shader::from_file(shader_type, path),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        ShaderProgram::from_shaders(shaders)
+    }
+}
+
+impl Drop for ShaderProgram {
+    fn drop(&mut self) {
         unsafe { gl::DeleteProgram(self.0) }
     }
 }
 
+#[cfg(feature = "glfw")]
 pub struct Setup {
     pub window: WindowType,
     pub events: Receiver<(f64, glfw::WindowEvent)>,
+    /// version of the created context, may differ from the requested one both ways
+    pub gl_version: (u32, u32),
+    pub profile: GlProfile,
+    pub extensions: Extensions,
+    /// samples of the default framebuffer, 0 when it isn't multisampled
+    pub samples: u32,
+    /// whether writes to the default framebuffer are encoded to sRGB
+    pub srgb: bool,
+    fullscreen: FullscreenMode,
+    /// window position and size to go back to when leaving fullscreen
+    windowed: [i32; 4],
+}
+
+/// How the window covers the screen. Monitors are indices into the connected ones, 0 is
+/// the primary monitor.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FullscreenMode {
+    #[default]
+    Windowed,
+    /// the monitor is taken over in its current video mode
+    Exclusive(usize),
+    /// undecorated window covering the monitor, switching away from it is instant
+    Borderless(usize),
 }
 
+#[cfg(feature = "glfw")]
+#[allow(dead_code)]
 impl Setup {
-    pub fn new(width: u32, height: u32, title: &str) -> Self {
-        let mut glfw = glfw::init(fail_on_errors!()).expect("Could not initialize glfw");
+    pub fn new(width: u32, height: u32, title: &str) -> Result<Self, WrapperError> {
+        Self::builder(width, height, title).build()
+    }
+
+    /// Core profile context of the given version, e.g. `(4, 3)` for compute shaders
+    /// and storage buffers.
+    pub fn with_version(width: u32, height: u32, title: &str, version: (u32, u32)) -> Result<Self, WrapperError> {
+        Self::builder(width, height, title).version(version).build()
+    }
+
+    /// ```ignore
+    /// let setup = Setup::builder(800, 600, "MSAA").version((4, 3)).samples(4).build()?;
+    /// ```
+    pub fn builder(width: u32, height: u32, title: &str) -> SetupBuilder {
+        SetupBuilder {
+            width,
+            height,
+            title: title.to_string(),
+            version: FALLBACK_VERSIONS[0],
+            fallback: true,
+            samples: 0,
+            srgb: false,
+            visible: true,
+            #[cfg(feature = "debug")]
+            debug_severity: crate::debug::DebugSeverity::Low,
+        }
+    }
+
+    /// Context with an invisible window, for rendering into framebuffers and reading the
+    /// result back (see `Framebuffer::read_pixels`) without showing anything. Still needs a
+    /// display server, GLFW can't create a context without one.
+    pub fn new_headless(width: u32, height: u32) -> Result<Self, WrapperError> {
+        Self::builder(width, height, "headless").visible(false).build()
+    }
+
+    /// Whether the context is at least `version`, for features newer than 3.3. Constructors of
+    /// such features check on their own and fail with `WrapperError::Unsupported`.
+    pub fn supports(&self, version: (u32, u32)) -> bool {
+        self.gl_version >= version
+    }
+
+    /// Whether the context has the extension, e.g. "GL_ARB_bindless_texture".
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.has(name)
+    }
+
+    pub fn supports_compute(&self) -> bool {
+        self.supports((4, 3))
+    }
+
+    pub fn is_multisampled(&self) -> bool {
+        self.samples > 1
+    }
+
+    pub fn fullscreen(&self) -> FullscreenMode {
+        self.fullscreen
+    }
+
+    /// Switches between windowed and fullscreen, going back to `Windowed` restores the window's
+    /// previous position and size. Fails for a monitor index that isn't connected.
+    pub fn set_fullscreen(&mut self, mode: FullscreenMode) -> Result<(), WrapperError> {
+        if mode == self.fullscreen {
+            return Ok(());
+        }
+        if self.fullscreen == FullscreenMode::Windowed {
+            let ((x, y), (width, height)) = (self.window.get_pos(), self.window.get_size());
+            self.windowed = [x, y, width, height];
+        }
+
+        let window = &mut self.window;
+        match mode {
+            FullscreenMode::Windowed => {
+                let [x, y, width, height] = self.windowed;
+                window.set_decorated(true);
+                window.set_monitor(glfw::WindowMode::Windowed, x, y, width as u32, height as u32, None);
+            }
+            FullscreenMode::Exclusive(index) | FullscreenMode::Borderless(index) => {
+                let mut glfw = window.glfw.clone();
+                glfw.with_connected_monitors(|_, monitors| -> Result<(), WrapperError> {
+                    let monitor = monitors.get(index).ok_or(WrapperError::Unsupported("monitor"))?;
+                    let video = monitor.get_video_mode().ok_or(WrapperError::Unsupported("monitor video mode"))?;
+
+                    if let FullscreenMode::Exclusive(_) = mode {
+                        window.set_monitor(
+                            glfw::WindowMode::FullScreen(monitor), 0, 0, video.width, video.height,
+                            Some(video.refresh_rate),
+                        );
+                    } else {
+                        let (x, y) = monitor.get_pos();
+                        window.set_decorated(false);
+                        window.set_monitor(glfw::WindowMode::Windowed, x, y, video.width, video.height, None);
+                    }
+                    Ok(())
+                })?;
+            }
+        }
+
+        self.fullscreen = mode;
+        Ok(())
+    }
+
+    /// Opens another window whose context shares GL objects with this one, see `SharedWindow`.
+    /// It's created with the hints of this one: same version, samples and visibility.
+    pub fn create_window(&self, width: u32, height: u32, title: &str) -> Result<SharedWindow, WrapperError> {
+        let (mut window, events) = self
+            .window
+            .create_shared(width, height, title, glfw::WindowMode::Windowed)
+            .ok_or_else(|| WrapperError::ContextCreation("Failed to create shared GLFW window.".to_string()))?;
+        enable_polling(&mut window);
+
+        let mut shared = SharedWindow { window, events };
+        {
+            let mut current = shared.make_current();
+            current.glfw.set_swap_interval(SwapInterval::Sync(1));
+        }
+        Ok(shared)
+    }
+
+    /// Makes the main window's context current again, for drawing after a `SharedWindow`.
+    pub fn make_current(&mut self) -> ContextGuard<'_> {
+        ContextGuard::new(&mut self.window)
+    }
+
+    /// Hides the cursor and locks it to the window, its position then moves without bounds and
+    /// only the deltas are meaningful, as a first person camera needs them. Raw motion is used
+    /// where the platform has it. `false` gives the cursor back.
+    pub fn capture_cursor(&mut self, capture: bool) {
+        let mode = if capture { glfw::CursorMode::Disabled } else { glfw::CursorMode::Normal };
+        self.window.set_cursor_mode(mode);
+        self.set_raw_mouse_motion(capture);
+    }
+
+    pub fn is_cursor_captured(&self) -> bool {
+        self.window.get_cursor_mode() == glfw::CursorMode::Disabled
+    }
+
+    /// Hides the cursor while it's over the window, it still moves freely.
+    pub fn hide_cursor(&mut self, hide: bool) {
+        let mode = if hide { glfw::CursorMode::Hidden } else { glfw::CursorMode::Normal };
+        self.window.set_cursor_mode(mode);
+    }
+
+    /// Unscaled, unaccelerated motion for a captured cursor. Returns whether the platform
+    /// supports it, nothing changes if not.
+    pub fn set_raw_mouse_motion(&mut self, raw: bool) -> bool {
+        if !self.window.glfw.supports_raw_motion() {
+            return false;
+        }
+        self.window.set_raw_mouse_motion(raw);
+        true
+    }
+}
+
+#[cfg(feature = "glfw")]
+pub struct SetupBuilder {
+    width: u32,
+    height: u32,
+    title: String,
+    version: (u32, u32),
+    fallback: bool,
+    samples: u32,
+    srgb: bool,
+    visible: bool,
+    #[cfg(feature = "debug")]
+    debug_severity: crate::debug::DebugSeverity,
+}
+
+#[cfg(feature = "glfw")]
+#[allow(dead_code)]
+impl SetupBuilder {
+    /// Highest version to ask for, 4.6 by default. Lower versions are tried next unless
+    /// `fallback` is off, check `Setup::gl_version` for what was obtained.
+    pub fn version(mut self, version: (u32, u32)) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Whether a context below the requested version is acceptable, tried in the order of
+    /// `FALLBACK_VERSIONS`. On by default.
+    pub fn fallback(mut self, fallback: bool) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Invisible windows never show up on screen, their default framebuffer may not be
+    /// rendered at all, so render to a `Framebuffer` instead.
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+
+    /// Multisampled default framebuffer, 0 or 1 turns it off.
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// sRGB-capable default framebuffer with `GL_FRAMEBUFFER_SRGB` enabled: shaders output
+    /// linear colors and writes get encoded to sRGB. Load color textures as `ColorSpace::Srgb`
+    /// then, or they end up decoded twice.
+    pub fn srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Least severe GL debug message which is still logged, `Low` by default.
+    #[cfg(feature = "debug")]
+    pub fn debug_severity(mut self, min_severity: crate::debug::DebugSeverity) -> Self {
+        self.debug_severity = min_severity;
+        self
+    }
+
+    pub fn build(self) -> Result<Setup, WrapperError> {
+        // drivers report versions they lack as errors, those are expected while falling back
+        let mut glfw = glfw::init(|error, description| {
+            if error != glfw::Error::VersionUnavailable {
+                glfw::fail_on_errors(error, description);
+            }
+        })
+        .map_err(|e| WrapperError::ContextCreation(format!("Could not initialize glfw: {:?}", e)))?;
 
-        glfw.window_hint(glfw::WindowHint::ContextVersion(3, 3));
         glfw.window_hint(glfw::WindowHint::OpenGlProfile(
             glfw::OpenGlProfileHint::Core,
         ));
-
-        let (mut window, events) = glfw
-            .create_window(width, height, title, glfw::WindowMode::Windowed)
-            .expect("Failed to create GLFW window.");
+        glfw.window_hint(glfw::WindowHint::Samples((self.samples > 1).then_some(self.samples)));
+        glfw.window_hint(glfw::WindowHint::SRgbCapable(self.srgb));
+        glfw.window_hint(glfw::WindowHint::Visible(self.visible));
+        #[cfg(feature = "debug")]
+        glfw.window_hint(glfw::WindowHint::OpenGlDebugContext(true));
+
+        let mut created = None;
+        for version in context_versions(self.version, self.fallback) {
+            glfw.window_hint(glfw::WindowHint::ContextVersion(version.0, version.1));
+            created = glfw.create_window(self.width, self.height, &self.title, glfw::WindowMode::Windowed);
+            if created.is_some() {
+                break;
+            }
+        }
+        let (mut window, events) = created.ok_or_else(|| {
+            WrapperError::ContextCreation(format!("Failed to create GLFW window with GL {}.{} or lower.", self.version.0, self.version.1))
+        })?;
 
         window.make_current();
-        window.set_key_polling(true);
-        window.set_framebuffer_size_polling(true);
+        enable_polling(&mut window);
         window.glfw.set_swap_interval(SwapInterval::Sync(1));
 
         gl::load_with(|s| window.get_proc_address(s) as *const _);
+        gl_ext::load_with(|s| window.get_proc_address(s) as *const _);
+
+        #[cfg(feature = "debug")]
+        if let Err(err) = crate::debug::install(self.debug_severity) {
+            log::warn!("GL debug output unavailable: {}", err);
+        }
+
+        // the driver may pick a different count than hinted
+        let mut samples = 0;
+        unsafe { checked!(gl::GetIntegerv(gl::SAMPLES, &mut samples)) };
+        if samples > 1 {
+            multisample(true);
+        }
+
+        // not every driver honors the hint, only claim sRGB when the back buffer really is
+        let srgb = self.srgb && default_framebuffer_is_srgb();
+        framebuffer_srgb(srgb);
+
+        let ((x, y), (width, height)) = (window.get_pos(), window.get_size());
+        Ok(Setup {
+            window, events, gl_version: context_version(), profile: context_profile(), extensions: Extensions::query(),
+            samples: samples.max(0) as u32, srgb,
+            fullscreen: FullscreenMode::Windowed, windowed: [x, y, width, height],
+        })
+    }
+}
+
+#[cfg(feature = "glfw")]
+/// Events `Setup` windows deliver.
+fn enable_polling(window: &mut WindowType) {
+    window.set_key_polling(true);
+    window.set_framebuffer_size_polling(true);
+    window.set_mouse_button_polling(true);
+    window.set_cursor_pos_polling(true);
+    window.set_scroll_polling(true);
+}
+
+#[cfg(feature = "glfw")]
+/// Additional window from `Setup::create_window`. Its context shares buffers, textures,
+/// shaders and programs with the main one, vertex arrays and framebuffers aren't shared
+/// and have to be created for each context. Drawing needs its context current.
+#[allow(dead_code)]
+pub struct SharedWindow {
+    pub window: WindowType,
+    pub events: Receiver<(f64, glfw::WindowEvent)>,
+}
+
+#[cfg(feature = "glfw")]
+#[allow(dead_code)]
+impl SharedWindow {
+    pub fn make_current(&mut self) -> ContextGuard<'_> {
+        ContextGuard::new(&mut self.window)
+    }
+}
+
+#[cfg(feature = "glfw")]
+/// Keeps a window's context current, the previously current one is restored on drop.
+/// Derefs to the window, to `swap_buffers` while it's current.
+pub struct ContextGuard<'a> {
+    window: &'a mut WindowType,
+    previous: *mut glfw::ffi::GLFWwindow,
+}
+
+#[cfg(feature = "glfw")]
+impl<'a> ContextGuard<'a> {
+    fn new(window: &'a mut WindowType) -> Self {
+        let previous = unsafe { glfw::ffi::glfwGetCurrentContext() };
+        window.make_current();
+        Self { window, previous }
+    }
+}
 
-        Self { window, events }
+#[cfg(feature = "glfw")]
+impl std::ops::Deref for ContextGuard<'_> {
+    type Target = WindowType;
+
+    fn deref(&self) -> &Self::Target {
+        self.window
+    }
+}
+
+#[cfg(feature = "glfw")]
+impl std::ops::DerefMut for ContextGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.window
+    }
+}
+
+#[cfg(feature = "glfw")]
+impl Drop for ContextGuard<'_> {
+    fn drop(&mut self) {
+        unsafe { glfw::ffi::glfwMakeContextCurrent(self.previous) }
     }
 }
 
 #[derive(Clone)]
 pub struct Settings{
     pub landslide: [f32; 2],
-    pub delta: f32,
-    iteration: f32,
+    /// units per second while a movement key is held, grows the longer it's held
+    pub speed: f32,
+    /// seconds the movement keys have been held
+    held: f32,
+    /// scale of the shape, changed by `zoom_by`
+    pub zoom: f32,
+    pub msaa: bool,
+    pub polygon_mode: PolygonMode,
+    pub instanced: bool,
+    /// set for one frame when a screenshot was requested
+    pub screenshot: bool,
+    pub recording: bool,
 }
 
 impl Settings {
+    /// units per second when a movement key is first pressed
+    pub const BASE_SPEED: f32 = 0.5;
+    /// speed doubles every second of holding, up to this many times the base speed
+    pub const MAX_BOOST: f32 = 8.0;
+    /// zoom factor of one scroll wheel step
+    pub const ZOOM_STEP: f32 = 1.1;
+    pub const MIN_ZOOM: f32 = 0.1;
+    pub const MAX_ZOOM: f32 = 10.0;
+
     pub fn new() -> Self {
         Self {
-            landslide: [0.0, 0.0], delta: 0.01, iteration: 0.0
+            landslide: [0.0, 0.0], speed: Self::BASE_SPEED, held: 0.0, zoom: 1.0, msaa: true, polygon_mode: PolygonMode::Fill,
+            instanced: false, screenshot: false, recording: false
         }
     }
 
-    pub fn move_img(&mut self, window: &WindowType) {
-
-        if window.get_key(Key::W) == Action::Press {
-            self.move_vert(Key::W)
-        } else if window.get_key(Key::S) == Action::Press {
-            self.move_vert(Key::S);
+    /// Moves by the held "move_*" actions or else `stick`, a gamepad stick with y pointing down,
+    /// `delta` is the time step in seconds. Speed resets once nothing moves the shape anymore.
+    pub fn move_img(&mut self, input: &InputMap, stick: [f32; 2], delta: f32) {
+        let mut direction = [input.axis("move_left", "move_right"), input.axis("move_down", "move_up")];
+        if direction == [0.0, 0.0] {
+            direction = [stick[0], -stick[1]];
         }
 
-        if window.get_key(Key::A) == Action::Press {
-            self.move_horiz(Key::A)
-        } else if window.get_key(Key::D) == Action::Press {
-            self.move_horiz(Key::D);
+        if direction == [0.0, 0.0] {
+            self.reset_params();
+            return;
         }
-    }
 
-    fn move_horiz(&mut self, key: Key) {
-        self.iteration += 1.0;
-        if self.iteration as i32 % 10 == 0 { self.delta *= 2.0; }
+        self.held += delta;
+        self.speed = Self::BASE_SPEED * 2f32.powf(self.held).min(Self::MAX_BOOST);
 
-        match key {
-            Key::A => self.landslide[0] -= self.delta,
-            Key::D => self.landslide[0] += self.delta,
-            _ => ()
-        };
+        self.landslide[0] += direction[0] * self.speed * delta;
+        self.landslide[1] += direction[1] * self.speed * delta;
     }
 
-    fn move_vert(&mut self, key: Key) {
-        self.iteration += 1.0;
-        if self.iteration as i32 % 10 == 0 { self.delta *= 2.0; }
+    /// Moves by `offset` right away, for dragging with the mouse.
+    pub fn drag(&mut self, offset: [f32; 2]) {
+        self.landslide[0] += offset[0];
+        self.landslide[1] += offset[1];
+    }
 
-        match key {
-            Key::W => self.landslide[1] += self.delta,
-            Key::S => self.landslide[1] -= self.delta,
-            _ => ()
-        };
+    /// Zooms in by `steps` scroll wheel steps, out when negative.
+    pub fn zoom_by(&mut self, steps: f32) {
+        self.zoom = (self.zoom * Self::ZOOM_STEP.powf(steps)).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
     }
 
     pub fn reset_params(&mut self) {
-        self.delta = 0.01;
-        self.iteration = 0.0;
+        self.speed = Self::BASE_SPEED;
+        self.held = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_version_candidates() {
+        assert_eq!(context_versions((4, 6), true).collect::<Vec<_>>(), FALLBACK_VERSIONS);
+        assert_eq!(context_versions((4, 5), true).collect::<Vec<_>>(), [(4, 5), (4, 3), (3, 3)]);
+        assert_eq!(context_versions((4, 3), false).collect::<Vec<_>>(), [(4, 3)]);
+    }
+
+    #[test]
+    fn mapped_ranges() {
+        assert_eq!(mapped_range(2, 3, 4, 20), Some((8, 12)));
+        assert_eq!(mapped_range(0, 0, 4, 0), Some((0, 0)));
+        assert_eq!(mapped_range(2, 4, 4, 20), None);
+        assert_eq!(mapped_range(usize::MAX / 2, 1, 4, 20), None);
+        assert_eq!(mapped_range(1, usize::MAX, 1, usize::MAX), None);
+    }
+
+    #[test]
+    fn pixel_data_lengths() {
+        assert_eq!(pixel_data_len(&[3, 2], 4), Some(24));
+        assert_eq!(pixel_data_len(&[0, 2], 4), Some(0));
+        assert_eq!(pixel_data_len(&[u32::MAX, u32::MAX, u32::MAX], 4), None);
+        assert!(gl_size(i32::MAX as u32).is_ok());
+        assert!(gl_size(i32::MAX as u32 + 1).is_err());
+    }
+
+    #[test]
+    fn compile_log_line_numbers() {
+        assert_eq!(compile_log_line_number("0:12(5): error: `x' undeclared"), Some(12));
+        assert_eq!(compile_log_line_number("0(7) : error C1008: undefined variable \"x\""), Some(7));
+        assert_eq!(compile_log_line_number("ERROR: 0:3: 'x' : undeclared identifier"), Some(3));
+        assert_eq!(compile_log_line_number("WARNING: 0:9: extension not supported"), Some(9));
+        assert_eq!(compile_log_line_number("error: linking failed"), None);
     }
-}
\ No newline at end of file
+}